@@ -1,4 +1,10 @@
-use crate::proto::Color;
+use std::f64::consts::{PI, TAU};
+
+use crate::proto::{Color, ColorInterpolation};
+
+/// Below this Oklab chroma a color is treated as gray and has no hue of its
+/// own.
+const ACHROMATIC_CHROMA: f64 = 1e-4;
 
 impl Color {
     /// Linearly interpolate between two colors. (0.0 is self)
@@ -16,4 +22,232 @@ impl Color {
             },
         }
     }
+
+    /// Interpolate between two colors through `space`. (0.0 is self)
+    #[must_use]
+    pub fn interpolate(&self, other: &Color, t: f64, space: ColorInterpolation) -> Color {
+        let oklab = |c: &Color| linear_srgb_to_oklab([c.red, c.green, c.blue].map(srgb_to_linear));
+        let lab = match space {
+            ColorInterpolation::Rgb => return self.lerp(other, t),
+            ColorInterpolation::Oklab => {
+                let (a, b) = (oklab(self), oklab(other));
+                [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+            }
+            ColorInterpolation::OklchShorterHue => {
+                interpolate_oklch(oklab(self), oklab(other), t, false)
+            }
+            ColorInterpolation::OklchLongerHue => {
+                interpolate_oklch(oklab(self), oklab(other), t, true)
+            }
+        };
+        let [red, green, blue] =
+            oklab_to_linear_srgb(lab).map(|c| linear_to_srgb(c.clamp(0.0, 1.0)));
+
+        Color {
+            red,
+            green,
+            blue,
+            white: self.lerp(other, t).white,
+        }
+    }
+
+    /// Converts a CIE 1931 xy chromaticity into the brightest sRGB color with
+    /// that chromaticity.
+    #[must_use]
+    #[allow(clippy::many_single_char_names)]
+    pub fn from_cie_xy(x: f64, y: f64) -> Color {
+        if y <= 0.0 {
+            return Color::default();
+        }
+
+        let big_x = x / y;
+        let big_z = (1.0 - x - y) / y;
+
+        let r = (3.240_454_2 * big_x - 1.537_138_5 - 0.498_531_4 * big_z).max(0.0);
+        let g = (-0.969_266_0 * big_x + 1.876_010_8 + 0.041_556_0 * big_z).max(0.0);
+        let b = (0.055_643_4 * big_x - 0.204_025_9 + 1.057_225_2 * big_z).max(0.0);
+
+        let max = r.max(g).max(b);
+        if max <= 0.0 {
+            return Color::default();
+        }
+
+        Color {
+            red: linear_to_srgb(r / max),
+            green: linear_to_srgb(g / max),
+            blue: linear_to_srgb(b / max),
+            white: None,
+        }
+    }
+}
+
+/// Apply sRGB gamma correction to linear RGB value
+pub(crate) fn linear_to_srgb(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Remove sRGB gamma correction from an encoded RGB value
+pub(crate) fn srgb_to_linear(encoded: f64) -> f64 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts linear sRGB into Oklab `[L, a, b]`.
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn linear_srgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = 0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b;
+    let m = 0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b;
+    let s = 0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b;
+
+    let l_ = l.cbrt();
+    let m_ = m.cbrt();
+    let s_ = s.cbrt();
+
+    [
+        0.210_454_255_3 * l_ + 0.793_617_785_0 * m_ - 0.004_072_046_8 * s_,
+        1.977_998_495_1 * l_ - 2.428_592_205_0 * m_ + 0.450_593_709_9 * s_,
+        0.025_904_037_1 * l_ + 0.782_771_766_2 * m_ - 0.808_675_766_0 * s_,
+    ]
+}
+
+/// Converts Oklab `[L, a, b]` into linear sRGB, which may be out of gamut.
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn oklab_to_linear_srgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
+    let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
+    let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
+
+    // Cube to get LMS
+    let l_lms = l_ * l_ * l_;
+    let m_lms = m_ * m_ * m_;
+    let s_lms = s_ * s_ * s_;
+
+    [
+        4.076_741_662_1 * l_lms - 3.307_711_591_3 * m_lms + 0.230_969_929_2 * s_lms,
+        -1.268_438_004_6 * l_lms + 2.609_757_401_1 * m_lms - 0.341_319_396_5 * s_lms,
+        -0.004_196_086_3 * l_lms - 0.703_418_614_7 * m_lms + 1.707_614_701_0 * s_lms,
+    ]
+}
+
+/// Interpolates between two Oklab colors around the Oklch hue circle, the
+/// long way round if `longer`.
+fn interpolate_oklch(
+    [l_a, a_a, b_a]: [f64; 3],
+    [l_b, a_b, b_b]: [f64; 3],
+    t: f64,
+    longer: bool,
+) -> [f64; 3] {
+    let (chroma_a, chroma_b) = (a_a.hypot(b_a), a_b.hypot(b_b));
+    let (hue_a, hue_b) = (b_a.atan2(a_a), b_b.atan2(a_b));
+    // A gray has no hue of its own, so the fade keeps the other color's hue.
+    let (hue, delta) = match (chroma_a < ACHROMATIC_CHROMA, chroma_b < ACHROMATIC_CHROMA) {
+        (true, false) => (hue_b, 0.0),
+        (_, true) => (hue_a, 0.0),
+        (false, false) => {
+            let mut delta = (hue_b - hue_a).rem_euclid(TAU);
+            if delta > PI {
+                delta -= TAU;
+            }
+            if longer {
+                delta -= TAU.copysign(delta);
+            }
+            (hue_a, delta)
+        }
+    };
+
+    let chroma = chroma_a + (chroma_b - chroma_a) * t;
+    let hue = hue + delta * t;
+    [
+        l_a + (l_b - l_a) * t,
+        chroma * hue.cos(),
+        chroma * hue.sin(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oklab_round_trips() {
+        for rgb in [[1.0, 0.0, 0.0], [0.2, 0.5, 0.9], [1.0, 1.0, 1.0]] {
+            let back = oklab_to_linear_srgb(linear_srgb_to_oklab(rgb));
+            for (a, b) in rgb.iter().zip(back) {
+                assert!((a - b).abs() < 1e-6, "{rgb:?} became {back:?}");
+            }
+        }
+
+        let [l, a, b] = linear_srgb_to_oklab([1.0, 1.0, 1.0]);
+        assert!((l - 1.0).abs() < 1e-6 && a.abs() < 1e-6 && b.abs() < 1e-6);
+    }
+
+    fn rgb(red: f64, green: f64, blue: f64) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            white: None,
+        }
+    }
+
+    fn chroma(color: &Color) -> f64 {
+        let [_, a, b] =
+            linear_srgb_to_oklab([color.red, color.green, color.blue].map(srgb_to_linear));
+        a.hypot(b)
+    }
+
+    #[test]
+    fn perceptual_fades_keep_their_saturation() {
+        let red = rgb(1.0, 0.0, 0.0);
+        let cyan = rgb(0.0, 1.0, 1.0);
+
+        let muddy = red.interpolate(&cyan, 0.5, ColorInterpolation::Rgb);
+        assert!(chroma(&muddy) < 1e-6, "{muddy:?}");
+
+        // The short way from red to cyan passes through yellow and green and
+        // the long way through magenta and blue.
+        let shorter = red.interpolate(&cyan, 0.5, ColorInterpolation::OklchShorterHue);
+        assert!(chroma(&shorter) > 0.1, "{shorter:?}");
+        assert!(shorter.green > shorter.blue, "{shorter:?}");
+        let longer = red.interpolate(&cyan, 0.5, ColorInterpolation::OklchLongerHue);
+        assert!(chroma(&longer) > 0.1, "{longer:?}");
+        assert!(longer.blue > longer.green, "{longer:?}");
+
+        for space in [
+            ColorInterpolation::Oklab,
+            ColorInterpolation::OklchShorterHue,
+            ColorInterpolation::OklchLongerHue,
+        ] {
+            for (t, expected) in [(0.0, red), (1.0, cyan)] {
+                let actual = red.interpolate(&cyan, t, space);
+                for (a, b) in [
+                    (actual.red, expected.red),
+                    (actual.green, expected.green),
+                    (actual.blue, expected.blue),
+                ] {
+                    assert!((a - b).abs() < 1e-4, "{space:?} at {t} gave {actual:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fades_from_gray_keep_the_hue_of_the_color() {
+        let white = rgb(1.0, 1.0, 1.0);
+        let blue = rgb(0.0, 0.0, 1.0);
+        let halfway = white.interpolate(&blue, 0.5, ColorInterpolation::OklchLongerHue);
+        // Without borrowing blue's hue the long way round would swing through
+        // every other hue on the way.
+        assert!(
+            halfway.blue > halfway.red && halfway.blue > halfway.green,
+            "{halfway:?}"
+        );
+    }
 }
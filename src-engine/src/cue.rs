@@ -0,0 +1,267 @@
+//! GO, BACK and GOTO for cue lists.
+
+use crate::proto::{
+    CueList, Project,
+    cue_list::{Cue, InterruptedFade},
+};
+
+/// Where a cue list stands at a moment, after any follows due by then have
+/// fired. Cues are referred to by their index in the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuePosition {
+    pub previous: Option<usize>,
+    pub active: Option<usize>,
+    /// The `system_t` at which `active` went.
+    pub go_t: u64,
+}
+
+/// How many interrupted fades are remembered. A fade interrupted before them
+/// starts from the look of its previous cue instead.
+const MAX_INTERRUPTED_FADES: usize = 8;
+
+fn cue_index(cue_list: &CueList, cue_id: u64) -> Option<usize> {
+    if cue_id == 0 {
+        return None;
+    }
+    cue_list.cues.iter().position(|c| c.id == cue_id)
+}
+
+/// Time from a cue going until its fade completes.
+#[must_use]
+pub fn fade_end_ms(cue: &Cue) -> u64 {
+    u64::from(cue.delay_ms) + u64::from(cue.up_ms.max(cue.down_ms))
+}
+
+/// Returns where `cue_list` stands at `t`. Follows are not stored when they
+/// fire; they are worked out from the last cue that was gone to by hand.
+#[must_use]
+pub fn position(cue_list: &CueList, t: u64) -> CuePosition {
+    let mut position = CuePosition {
+        previous: cue_index(cue_list, cue_list.previous_cue),
+        active: cue_index(cue_list, cue_list.active_cue),
+        go_t: cue_list.go_t,
+    };
+
+    while let Some(index) = position.active
+        && let Some(follow_ms) = cue_list.cues[index].follow_ms
+        && index + 1 < cue_list.cues.len()
+    {
+        let next_go_t = position.go_t + fade_end_ms(&cue_list.cues[index]) + u64::from(follow_ms);
+        if next_go_t > t {
+            break;
+        }
+        position = CuePosition {
+            previous: Some(index),
+            active: Some(index + 1),
+            go_t: next_go_t,
+        };
+    }
+
+    position
+}
+
+/// Returns the fades `cue_list` is running at `t`, oldest first. The first
+/// starts from the look of its previous cue, and each after it from where the
+/// one before had got to when it went. The last is `position(cue_list, t)`.
+#[must_use]
+pub fn fades(cue_list: &CueList, t: u64) -> Vec<CuePosition> {
+    let position = position(cue_list, t);
+    // Follows only go once a fade has completed.
+    if position.go_t != cue_list.go_t {
+        return vec![position];
+    }
+    cue_list
+        .interrupted_fades
+        .iter()
+        .map(|fade| CuePosition {
+            previous: cue_index(cue_list, fade.previous_cue),
+            active: cue_index(cue_list, fade.active_cue),
+            go_t: fade.go_t,
+        })
+        .filter(|fade| fade.active.is_some())
+        .chain([position])
+        .collect()
+}
+
+fn cue_list_mut(project: &mut Project, cue_list_id: u64) -> Result<&mut CueList, String> {
+    project
+        .cue_lists
+        .get_mut(&cue_list_id)
+        .ok_or_else(|| format!("Cue list {cue_list_id} not found"))
+}
+
+/// Fades from wherever the list stands at `t` to the cue at `index`.
+fn fade_to(cue_list: &mut CueList, index: usize, t: u64) {
+    let from = position(cue_list, t);
+    // A follow since the last GO went from a completed fade.
+    if from.go_t != cue_list.go_t {
+        cue_list.interrupted_fades.clear();
+    }
+    match from.active {
+        Some(active) if t < from.go_t + fade_end_ms(&cue_list.cues[active]) => {
+            cue_list.interrupted_fades.push(InterruptedFade {
+                previous_cue: from.previous.map_or(0, |i| cue_list.cues[i].id),
+                active_cue: cue_list.cues[active].id,
+                go_t: from.go_t,
+            });
+            if cue_list.interrupted_fades.len() > MAX_INTERRUPTED_FADES {
+                cue_list.interrupted_fades.remove(0);
+            }
+        }
+        _ => cue_list.interrupted_fades.clear(),
+    }
+    cue_list.previous_cue = from.active.map_or(0, |i| cue_list.cues[i].id);
+    cue_list.active_cue = cue_list.cues[index].id;
+    cue_list.go_t = t;
+}
+
+/// Goes to the cue after the active one, or the first cue if none is active.
+/// Returns whether a cue went; GO on the last cue does nothing.
+pub fn go(project: &mut Project, cue_list_id: u64, t: u64) -> Result<bool, String> {
+    let cue_list = cue_list_mut(project, cue_list_id)?;
+    let next = position(cue_list, t).active.map_or(0, |i| i + 1);
+    if next >= cue_list.cues.len() {
+        return Ok(false);
+    }
+    fade_to(cue_list, next, t);
+    Ok(true)
+}
+
+/// Goes back to the cue before the active one, fading with that cue's times.
+/// Returns whether a cue went; BACK on the first cue does nothing.
+pub fn back(project: &mut Project, cue_list_id: u64, t: u64) -> Result<bool, String> {
+    let cue_list = cue_list_mut(project, cue_list_id)?;
+    let Some(previous) = position(cue_list, t).active.and_then(|i| i.checked_sub(1)) else {
+        return Ok(false);
+    };
+    fade_to(cue_list, previous, t);
+    Ok(true)
+}
+
+/// Goes straight to the cue with id `cue_id`, fading with its times.
+pub fn goto(project: &mut Project, cue_list_id: u64, cue_id: u64, t: u64) -> Result<(), String> {
+    let cue_list = cue_list_mut(project, cue_list_id)?;
+    let index = cue_index(cue_list, cue_id)
+        .ok_or_else(|| format!("Cue {cue_id} not found in cue list {cue_list_id}"))?;
+    fade_to(cue_list, index, t);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn cue(id: u64, follow_ms: Option<u32>) -> Cue {
+        Cue {
+            id,
+            name: format!("Cue {id}"),
+            up_ms: 1_000,
+            down_ms: 500,
+            delay_ms: 100,
+            follow_ms,
+            ..Default::default()
+        }
+    }
+
+    fn project(cues: Vec<Cue>) -> Project {
+        Project {
+            cue_lists: HashMap::from([(
+                1,
+                CueList {
+                    name: "Main".to_string(),
+                    cues,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn active_cue(project: &Project, t: u64) -> Option<u64> {
+        let cue_list = &project.cue_lists[&1];
+        position(cue_list, t).active.map(|i| cue_list.cues[i].id)
+    }
+
+    #[test]
+    fn go_and_back_step_through_the_list() {
+        let mut project = project(vec![cue(10, None), cue(20, None), cue(30, None)]);
+        assert_eq!(active_cue(&project, 0), None);
+        assert_eq!(back(&mut project, 1, 0), Ok(false));
+
+        assert_eq!(go(&mut project, 1, 1_000), Ok(true));
+        assert_eq!(go(&mut project, 1, 2_000), Ok(true));
+        assert_eq!(active_cue(&project, 2_000), Some(20));
+        assert_eq!(project.cue_lists[&1].previous_cue, 10);
+        assert_eq!(project.cue_lists[&1].go_t, 2_000);
+
+        assert_eq!(back(&mut project, 1, 3_000), Ok(true));
+        assert_eq!(active_cue(&project, 3_000), Some(10));
+        assert_eq!(project.cue_lists[&1].previous_cue, 20);
+
+        goto(&mut project, 1, 30, 4_000).unwrap();
+        assert_eq!(active_cue(&project, 4_000), Some(30));
+        assert_eq!(go(&mut project, 1, 5_000), Ok(false));
+        assert!(goto(&mut project, 1, 99, 5_000).is_err());
+        assert!(go(&mut project, 2, 5_000).is_err());
+    }
+
+    #[test]
+    fn follows_go_after_the_fade_completes() {
+        let mut project = project(vec![cue(10, Some(2_000)), cue(20, Some(0)), cue(30, None)]);
+        go(&mut project, 1, 1_000).unwrap();
+
+        // Cue 10 completes at 2100 and follows 2000ms later.
+        assert_eq!(active_cue(&project, 4_099), Some(10));
+        assert_eq!(active_cue(&project, 4_100), Some(20));
+        // Cue 20 follows as soon as it completes, 1100ms after it went.
+        assert_eq!(active_cue(&project, 5_199), Some(20));
+        let cue_list = &project.cue_lists[&1];
+        assert_eq!(
+            position(cue_list, 5_200),
+            CuePosition {
+                previous: Some(1),
+                active: Some(2),
+                go_t: 5_200,
+            }
+        );
+
+        // GO continues from wherever follows have taken the list.
+        assert_eq!(go(&mut project, 1, 4_500), Ok(true));
+        assert_eq!(project.cue_lists[&1].previous_cue, 20);
+        assert_eq!(project.cue_lists[&1].active_cue, 30);
+    }
+
+    #[test]
+    fn go_during_a_fade_remembers_it() {
+        let mut project = project(vec![cue(10, None), cue(20, None), cue(30, None)]);
+        go(&mut project, 1, 1_000).unwrap();
+        // Cue 10 completes at 2100.
+        go(&mut project, 1, 2_100).unwrap();
+        assert!(project.cue_lists[&1].interrupted_fades.is_empty());
+
+        // GO while cue 20 fades in.
+        go(&mut project, 1, 2_500).unwrap();
+        let cue_list = &project.cue_lists[&1];
+        assert_eq!(
+            fades(cue_list, 2_600),
+            [
+                CuePosition {
+                    previous: Some(0),
+                    active: Some(1),
+                    go_t: 2_100,
+                },
+                CuePosition {
+                    previous: Some(1),
+                    active: Some(2),
+                    go_t: 2_500,
+                },
+            ]
+        );
+
+        // BACK once cue 30 has completed starts afresh.
+        back(&mut project, 1, 4_000).unwrap();
+        assert!(project.cue_lists[&1].interrupted_fades.is_empty());
+    }
+}
@@ -0,0 +1,529 @@
+//! DMX received from an external console over sACN or Art-Net.
+//!
+//! The host keeps a [`DmxInputState`] and hands it every received universe.
+//! The values are then passed through by the `DmxInput` render mode, merged
+//! into the show render as the project's `DmxInput.merge` says, and drive
+//! input bindings like a MIDI controller named [`DMX_INPUT_CONTROLLER`] whose
+//! channels are named `"<universe>/<channel>"`.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::midi::{ActionResult, BindingContext, ControlCommandType, find_binding, perform_action};
+use crate::project;
+use crate::proto::{
+    DmxInput, InputType, Project, RenderMode, dmx_input::Merge, output::Output as ProtoOutput,
+    render_mode::Mode,
+};
+use crate::render::dmx_render_target::{DmxRenderTarget, UNIVERSE_SIZE};
+use crate::render::render_target::RenderTarget;
+use crate::render::util::dmx_universes;
+
+/// The controller name input bindings are looked up under.
+pub const DMX_INPUT_CONTROLLER: &str = "DMX input";
+
+/// The binding channel of `channel`, starting at 1, of `universe`.
+#[must_use]
+pub fn channel_name(universe: u16, channel: u16) -> String {
+    format!("{universe}/{channel}")
+}
+
+/// The protocol a universe is received over. Each has its own universes, so
+/// universe 1 of sACN and of Art-Net are kept apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Sacn,
+    Artnet,
+}
+
+/// The values of a universe and the `t` at which each channel last changed.
+#[derive(Clone)]
+struct Tracked {
+    values: [u8; UNIVERSE_SIZE],
+    changed_t: [u64; UNIVERSE_SIZE],
+}
+
+impl Tracked {
+    fn new(values: &[u8; UNIVERSE_SIZE], t: u64) -> Self {
+        Tracked {
+            values: *values,
+            changed_t: [t; UNIVERSE_SIZE],
+        }
+    }
+
+    /// Takes on `values`, returning the indices of the channels that changed.
+    fn update(&mut self, values: &[u8; UNIVERSE_SIZE], t: u64) -> Vec<usize> {
+        let mut changed = Vec::new();
+        for (index, (value, new_value)) in self.values.iter_mut().zip(values).enumerate() {
+            if value != new_value {
+                *value = *new_value;
+                self.changed_t[index] = t;
+                changed.push(index);
+            }
+        }
+        changed
+    }
+}
+
+/// The channels of a universe that changed when it was received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Changes {
+    /// The channels, starting at 1, whose values changed along with their
+    /// new values.
+    pub channels: Vec<(u16, u8)>,
+    /// Whether the universe was not being received before, in which case
+    /// every channel is reported though none of them were moved.
+    pub first: bool,
+}
+
+/// The received universes, and what merging needs to remember between
+/// frames. The host owns it and hands it to the renders of its outputs, so
+/// offline renders and previews are not affected by whatever is arriving.
+#[derive(Default)]
+pub struct DmxInputState {
+    /// The last values received on each universe of each protocol.
+    received: HashMap<(Protocol, u16), Tracked>,
+    /// The last render of each universe of each output, keyed by output ID
+    /// and index within the output, which LTP merging weighs the input
+    /// against.
+    rendered: HashMap<(u64, usize), Tracked>,
+}
+
+impl DmxInputState {
+    /// Stores the values received on `universe` at `t`. Returns the channels
+    /// that changed; the first time a universe is received every channel has.
+    pub fn receive(&mut self, protocol: Protocol, universe: u16, data: &[u8], t: u64) -> Changes {
+        let mut values = [0; UNIVERSE_SIZE];
+        let len = data.len().min(UNIVERSE_SIZE);
+        values[..len].copy_from_slice(&data[..len]);
+
+        let (changed, first) = match self.received.entry((protocol, universe)) {
+            Entry::Occupied(mut entry) => (entry.get_mut().update(&values, t), false),
+            Entry::Vacant(entry) => {
+                entry.insert(Tracked::new(&values, t));
+                ((0..UNIVERSE_SIZE).collect(), true)
+            }
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        Changes {
+            channels: changed
+                .into_iter()
+                .map(|index| (index as u16 + 1, values[index]))
+                .collect(),
+            first,
+        }
+    }
+
+    /// Drops `universe`, such as when its last source stops sending.
+    pub fn forget(&mut self, protocol: Protocol, universe: u16) {
+        self.received.remove(&(protocol, universe));
+    }
+
+    /// The values last received on `universe`, over sACN or else Art-Net.
+    #[must_use]
+    pub fn received_universe(&self, universe: u16) -> Option<&[u8; UNIVERSE_SIZE]> {
+        [Protocol::Sacn, Protocol::Artnet]
+            .iter()
+            .find_map(|protocol| self.received.get(&(*protocol, universe)))
+            .map(|input| &input.values)
+    }
+
+    /// Each received universe that `output_id` sends, by its index within
+    /// the output. A universe received over the protocol the output sends
+    /// is taken over one received over the other.
+    fn output_inputs(&self, project: &Project, output_id: u64) -> Vec<(usize, &Tracked)> {
+        let Some((first_universe, universe_count)) = dmx_universes(project, output_id) else {
+            return Vec::new();
+        };
+        let protocols = if is_artnet_output(project, output_id) {
+            [Protocol::Artnet, Protocol::Sacn]
+        } else {
+            [Protocol::Sacn, Protocol::Artnet]
+        };
+        (0..universe_count)
+            .filter_map(|index| {
+                let universe = u16::try_from(first_universe + index).ok()?;
+                protocols
+                    .iter()
+                    .find_map(|protocol| self.received.get(&(*protocol, universe)))
+                    .map(|input| (index as usize, input))
+            })
+            .collect()
+    }
+
+    /// Renders the received universes that `output_id` sends. The rest of
+    /// the output stays as it was.
+    pub(crate) fn render_pass_through<T: RenderTarget<T>>(
+        &self,
+        render_target: &mut T,
+        output_id: u64,
+        project: &Project,
+    ) {
+        for (index, input) in self.output_inputs(project, output_id) {
+            render_target.apply_recorded_universe(index, &input.values);
+        }
+    }
+
+    /// Merges the received universes into a frame rendered at `t` in
+    /// `render_mode`. Blackout and the debug modes are left as rendered, as
+    /// is pass-through, which is the input already.
+    pub(crate) fn merge(
+        &mut self,
+        project: &Project,
+        render_mode: &RenderMode,
+        output_id: u64,
+        render_target: &mut DmxRenderTarget,
+        t: u64,
+    ) {
+        let merge = project
+            .dmx_input
+            .as_ref()
+            .map_or(Merge::None, DmxInput::merge);
+        if merge == Merge::None
+            || !matches!(
+                render_mode.mode,
+                Some(
+                    Mode::Scene(_)
+                        | Mode::Autopilot(_)
+                        | Mode::CueList(_)
+                        | Mode::TimecodedShow(_)
+                        | Mode::RecordingPlayback(_)
+                )
+            )
+        {
+            return;
+        }
+
+        let rendered = render_target.get_universes();
+        // Tracked whether or not anything is received, so input arriving
+        // later is later than the render it meets.
+        if merge == Merge::Ltp {
+            for (index, values) in rendered.iter().enumerate() {
+                match self.rendered.entry((output_id, index)) {
+                    Entry::Occupied(mut entry) => {
+                        entry.get_mut().update(values, t);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(Tracked::new(values, t));
+                    }
+                }
+            }
+        }
+
+        for (index, input) in self.output_inputs(project, output_id) {
+            let Some(values) = rendered.get(index) else {
+                continue;
+            };
+            let mut merged = *values;
+            for (channel, value) in merged.iter_mut().enumerate() {
+                let take_input = match merge {
+                    Merge::Htp => input.values[channel] > *value,
+                    Merge::Ltp => self
+                        .rendered
+                        .get(&(output_id, index))
+                        .is_none_or(|render| input.changed_t[channel] > render.changed_t[channel]),
+                    Merge::None => false,
+                };
+                if take_input {
+                    *value = input.values[channel];
+                }
+            }
+            render_target.apply_recorded_universe(index, &merged);
+        }
+    }
+}
+
+fn is_artnet_output(project: &Project, output_id: u64) -> bool {
+    matches!(
+        project
+            .patches
+            .get(&project.active_patch)
+            .and_then(|p| p.outputs.get(&output_id))
+            .and_then(|o| o.output.as_ref()),
+        Some(ProtoOutput::ArtnetDmxOutput(_))
+    )
+}
+
+/// Performs the input bindings of the channels of `universe` that changed, as
+/// returned by [`DmxInputState::receive`]. Channels bound as continuous act as
+/// faders, the way 14-bit MIDI control changes do; the rest as buttons pressed
+/// above half. When a universe is first received only the faders pick up
+/// their position, so buttons resting above half are not pressed.
+pub fn perform_actions(
+    universe: u16,
+    changes: &Changes,
+    t: u64,
+) -> Result<Vec<ActionResult>, String> {
+    let bound = project::with_project(|project| {
+        let Some(binding_id) = project
+            .controller_mapping
+            .as_ref()
+            .and_then(|cm| cm.controller_to_binding.get(DMX_INPUT_CONTROLLER).copied())
+        else {
+            return Ok(Vec::new());
+        };
+        let context = BindingContext::Scene {
+            scene_id: project.active_scene,
+        };
+
+        Ok(changes
+            .channels
+            .iter()
+            .filter_map(|(channel, value)| {
+                let name = channel_name(universe, *channel);
+                let binding = find_binding(project, &context, binding_id, &name)?;
+                let continuous = binding.input_type() == InputType::Continuous;
+                if changes.first && !continuous {
+                    return None;
+                }
+                let cct = continuous.then_some(ControlCommandType::Msb);
+                Some((binding_id, name, f64::from(*value) / 255.0, cct))
+            })
+            .collect::<Vec<_>>())
+    })?;
+
+    bound
+        .iter()
+        .map(|(binding_id, name, value, cct)| perform_action(*binding_id, name, *value, *cct, t))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{
+        ArtnetDmxOutput, ControllerBindingsMap, ControllerMapping, CueGoAction, FixtureDefinitions,
+        InputBinding, Output, Patch,
+        controller_bindings_map::ControllerBindings,
+        cue_list::Cue,
+        input_binding::Action,
+        output::Output as ProtoOutput,
+        render_mode::{CueList, DmxInput as DmxInputMode},
+    };
+    use crate::render::render::render_dmx_in_mode;
+
+    fn project(output_id: u64, universe: u32, merge: Merge) -> Project {
+        let output = Output {
+            output: Some(ProtoOutput::ArtnetDmxOutput(ArtnetDmxOutput {
+                universe,
+                universe_count: 2,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let mut dmx_input = DmxInput::default();
+        dmx_input.set_merge(merge);
+        Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(output_id, output)]),
+                    ..Default::default()
+                },
+            )]),
+            fixture_definitions: Some(FixtureDefinitions::default()),
+            dmx_input: Some(dmx_input),
+            ..Default::default()
+        }
+    }
+
+    fn render(
+        project: &Project,
+        mode: Mode,
+        output_id: u64,
+        t: u64,
+        input: &mut DmxInputState,
+    ) -> Vec<[u8; UNIVERSE_SIZE]> {
+        let render_mode = RenderMode { mode: Some(mode) };
+        render_dmx_in_mode(
+            project,
+            &render_mode,
+            output_id,
+            t,
+            0,
+            &crate::audio::AudioAnalysis::default(),
+            None,
+            Some(input),
+        )
+        .unwrap()
+        .frame
+    }
+
+    fn cue_list() -> Mode {
+        Mode::CueList(CueList { cue_list_id: 1 })
+    }
+
+    #[test]
+    fn reports_the_channels_that_changed() {
+        let mut input = DmxInputState::default();
+        let first = input.receive(Protocol::Artnet, 0x200, &[5, 6], 1);
+        assert!(first.first);
+        assert_eq!(first.channels.len(), UNIVERSE_SIZE);
+        assert!(
+            input
+                .receive(Protocol::Artnet, 0x200, &[5, 6], 2)
+                .channels
+                .is_empty()
+        );
+        assert_eq!(
+            input.receive(Protocol::Artnet, 0x200, &[5, 7, 1], 3),
+            Changes {
+                channels: vec![(2, 7), (3, 1)],
+                first: false,
+            }
+        );
+
+        input.forget(Protocol::Artnet, 0x200);
+        let returned = input.receive(Protocol::Artnet, 0x200, &[5, 7, 1], 4);
+        assert!(returned.first);
+        assert_eq!(returned.channels.len(), UNIVERSE_SIZE);
+    }
+
+    #[test]
+    fn buttons_are_not_pressed_when_a_universe_is_first_received() {
+        let _guard = crate::project::tests::lock_state();
+        let binding = InputBinding {
+            input_type: InputType::Binary.into(),
+            action: Some(Action::CueGo(CueGoAction { cue_list_id: 1 })),
+        };
+        project::load(Project {
+            cue_lists: HashMap::from([(
+                1,
+                crate::proto::CueList {
+                    cues: vec![
+                        Cue {
+                            id: 10,
+                            ..Default::default()
+                        },
+                        Cue {
+                            id: 20,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            )]),
+            controller_mapping: Some(ControllerMapping {
+                controller_to_binding: HashMap::from([(DMX_INPUT_CONTROLLER.to_string(), 7)]),
+                ..Default::default()
+            }),
+            live_page_controller_bindings: Some(ControllerBindingsMap {
+                bindings: HashMap::from([(
+                    7,
+                    ControllerBindings {
+                        bindings: HashMap::from([(channel_name(1, 1), binding)]),
+                    },
+                )]),
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        let active_cue =
+            || project::with_project(|project| Ok(project.cue_lists[&1].active_cue)).unwrap();
+
+        let mut input = DmxInputState::default();
+        let changes = input.receive(Protocol::Sacn, 1, &[255], 1);
+        perform_actions(1, &changes, 1).unwrap();
+        assert_eq!(active_cue(), 0);
+
+        // Once received, pressing the button again does GO.
+        for (value, t) in [(0, 2), (255, 3)] {
+            let changes = input.receive(Protocol::Sacn, 1, &[value], t);
+            perform_actions(1, &changes, t).unwrap();
+        }
+        assert_eq!(active_cue(), 10);
+    }
+
+    #[test]
+    fn passes_through_the_universes_an_output_sends() {
+        let project = project(0x210, 0x210, Merge::None);
+        let mut input = DmxInputState::default();
+        input.receive(Protocol::Artnet, 0x211, &[9, 8], 1);
+
+        let pass_through = Mode::DmxInput(DmxInputMode {});
+        let frame = render(&project, pass_through, 0x210, 2, &mut input);
+        assert_eq!(frame[0][..2], [0, 0]);
+        assert_eq!(frame[1][..2], [9, 8]);
+
+        // Without a merge the show render ignores the input.
+        assert_eq!(
+            render(&project, cue_list(), 0x210, 3, &mut input)[1][..2],
+            [0, 0]
+        );
+    }
+
+    #[test]
+    fn merges_by_highest_or_latest() {
+        let htp = project(0x220, 0x220, Merge::Htp);
+        let mut input = DmxInputState::default();
+        input.receive(Protocol::Artnet, 0x220, &[40], 1);
+        assert_eq!(render(&htp, cue_list(), 0x220, 2, &mut input)[0][0], 40);
+
+        // The render has been dark since before the input arrived, so the
+        // input is latest.
+        let ltp = project(0x230, 0x230, Merge::Ltp);
+        let mut input = DmxInputState::default();
+        render(&ltp, cue_list(), 0x230, 1, &mut input);
+        input.receive(Protocol::Artnet, 0x230, &[60], 2);
+        assert_eq!(render(&ltp, cue_list(), 0x230, 3, &mut input)[0][0], 60);
+
+        // Input that was there before the render started loses to it until
+        // the input changes.
+        let ltp = project(0x240, 0x240, Merge::Ltp);
+        let mut input = DmxInputState::default();
+        input.receive(Protocol::Artnet, 0x240, &[70], 1);
+        assert_eq!(render(&ltp, cue_list(), 0x240, 2, &mut input)[0][0], 0);
+        input.receive(Protocol::Artnet, 0x240, &[71], 3);
+        assert_eq!(render(&ltp, cue_list(), 0x240, 4, &mut input)[0][0], 71);
+    }
+
+    #[test]
+    fn protocols_keep_their_own_universes() {
+        let project = project(0x260, 0x260, Merge::None);
+        let mut input = DmxInputState::default();
+        let pass_through = || Mode::DmxInput(DmxInputMode {});
+
+        input.receive(Protocol::Sacn, 0x260, &[1], 1);
+        assert_eq!(
+            render(&project, pass_through(), 0x260, 2, &mut input)[0][0],
+            1
+        );
+
+        // The Art-Net output takes Art-Net over sACN, which is still kept.
+        input.receive(Protocol::Artnet, 0x260, &[2], 3);
+        assert_eq!(
+            render(&project, pass_through(), 0x260, 4, &mut input)[0][0],
+            2
+        );
+        input.forget(Protocol::Artnet, 0x260);
+        assert_eq!(
+            render(&project, pass_through(), 0x260, 5, &mut input)[0][0],
+            1
+        );
+    }
+
+    #[test]
+    fn renders_without_input_ignore_what_is_received() {
+        let project = project(0x250, 0x250, Merge::Htp);
+        let mut input = DmxInputState::default();
+        input.receive(Protocol::Artnet, 0x250, &[90], 1);
+
+        let render_mode = RenderMode {
+            mode: Some(cue_list()),
+        };
+        let offline = render_dmx_in_mode(
+            &project,
+            &render_mode,
+            0x250,
+            2,
+            0,
+            &crate::audio::AudioAnalysis::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(offline.frame[0][0], 0);
+    }
+}
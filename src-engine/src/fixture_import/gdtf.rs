@@ -0,0 +1,567 @@
+//! GDTF fixture archives, see <https://gdtf.eu/gdtf/file-spec/>.
+
+use std::io::{Cursor, Read};
+
+use roxmltree::{Document, Node};
+
+use super::{
+    ImportedFixtureDefinition, channel_type_from_name, mode_key, warn_unrendered_channels,
+};
+use crate::proto::{
+    Color, DmxFixtureDefinition,
+    dmx_fixture_definition::{
+        Channel, Emitter, Mode,
+        channel::{
+            AmountMapping, AngleMapping, ColorWheelMapping, Mapping,
+            color_wheel_mapping::ColorWheelColor,
+        },
+    },
+};
+
+/// Converts a GDTF archive, which is a zip file holding a `description.xml`.
+pub fn import_gdtf(archive: &[u8]) -> Result<ImportedFixtureDefinition, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive))
+        .map_err(|e| format!("Could not open GDTF archive: {e}"))?;
+    let mut description = String::new();
+    archive
+        .by_name("description.xml")
+        .map_err(|e| format!("Could not find description.xml in GDTF archive: {e}"))?
+        .read_to_string(&mut description)
+        .map_err(|e| format!("Could not read description.xml from GDTF archive: {e}"))?;
+
+    import_description(&description)
+}
+
+fn import_description(description: &str) -> Result<ImportedFixtureDefinition, String> {
+    let document = Document::parse(description)
+        .map_err(|e| format!("Could not parse GDTF description: {e}"))?;
+    let fixture_type = document
+        .descendants()
+        .find(|n| n.has_tag_name("FixtureType"))
+        .ok_or("GDTF description does not have a FixtureType")?;
+
+    let mut definition = DmxFixtureDefinition {
+        global_id: fixture_type
+            .attribute("FixtureTypeID")
+            .unwrap_or_default()
+            .to_string(),
+        name: fixture_type
+            .attribute("LongName")
+            .filter(|n| !n.is_empty())
+            .or(fixture_type.attribute("Name"))
+            .unwrap_or_default()
+            .to_string(),
+        manufacturer: fixture_type
+            .attribute("Manufacturer")
+            .unwrap_or_default()
+            .to_string(),
+        ..Default::default()
+    };
+
+    let mut warnings = Vec::new();
+
+    for mode_node in fixture_type
+        .descendants()
+        .filter(|n| n.has_tag_name("DMXMode"))
+    {
+        let mode_name = mode_node.attribute("Name").unwrap_or_default();
+        let mut mode = Mode {
+            name: mode_name.to_string(),
+            ..Default::default()
+        };
+
+        for channel_node in mode_node
+            .descendants()
+            .filter(|n| n.has_tag_name("DMXChannel"))
+        {
+            add_channel(fixture_type, channel_node, &mut mode, &mut warnings);
+        }
+
+        mode.num_channels = mode.channels.keys().max().copied().unwrap_or_default();
+        warn_unrendered_channels(&mode, &mut warnings);
+        definition.modes.insert(mode_key(mode_name), mode);
+    }
+
+    if definition.modes.is_empty() {
+        return Err(format!(
+            "GDTF fixture \"{}\" does not have any DMX modes",
+            definition.name
+        ));
+    }
+
+    import_emitters(fixture_type, &mut definition);
+    import_rdm(fixture_type, &mut definition);
+
+    Ok(ImportedFixtureDefinition {
+        definition,
+        warnings,
+    })
+}
+
+fn add_channel(
+    fixture_type: Node,
+    channel_node: Node,
+    mode: &mut Mode,
+    warnings: &mut Vec<String>,
+) {
+    // Virtual channels have no offset.
+    let offsets: Vec<u32> = channel_node
+        .attribute("Offset")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|o| o.trim().parse().ok())
+        .collect();
+    let Some(&coarse_offset) = offsets.first() else {
+        return;
+    };
+
+    if let Some(dmx_break) = channel_node.attribute("DMXBreak")
+        && dmx_break != "1"
+        && dmx_break != "Overwrite"
+    {
+        warnings.push(format!(
+            "Mode \"{}\" channel {coarse_offset} is on DMX break {dmx_break} which is not supported",
+            mode.name
+        ));
+        return;
+    }
+
+    let Some(logical_channel) = channel_node
+        .children()
+        .find(|n| n.has_tag_name("LogicalChannel"))
+    else {
+        return;
+    };
+    let functions: Vec<Node> = logical_channel
+        .children()
+        .filter(|n| n.has_tag_name("ChannelFunction"))
+        .collect();
+
+    let attribute = logical_channel.attribute("Attribute").unwrap_or_default();
+    let byte_count = u32::try_from(offsets.len()).unwrap_or(1);
+    let mut channel = channel_from_functions(fixture_type, attribute, &functions);
+
+    // GDTF 1.1 moved the default onto the channel function.
+    let initial_function = initial_function(channel_node, &functions);
+    if let Some(default) = initial_function
+        .and_then(|f| f.attribute("Default"))
+        .or(channel_node.attribute("Default"))
+        .and_then(|d| dmx_value(d, byte_count))
+    {
+        channel.default_value = default;
+    }
+
+    if offsets.len() > 3 {
+        warnings.push(format!(
+            "Mode \"{}\" channel {coarse_offset} has {} bytes, only the first 3 are used",
+            mode.name,
+            offsets.len()
+        ));
+    }
+
+    channel.fine_channel = offsets.get(1).copied();
+    channel.ultra_channel = offsets.get(2).copied();
+    for (suffix, &offset) in ["fine", "ultra"].iter().zip(offsets.iter().skip(1)) {
+        mode.channels.insert(
+            offset,
+            Channel {
+                r#type: format!("{}-{suffix}", channel.r#type),
+                ..Default::default()
+            },
+        );
+    }
+    mode.channels.insert(coarse_offset, channel);
+}
+
+/// Finds the function named by the channel's `InitialFunction`, which looks
+/// like `Geometry_Attribute.Attribute.Function`.
+fn initial_function<'a, 'input>(
+    channel_node: Node,
+    functions: &[Node<'a, 'input>],
+) -> Option<Node<'a, 'input>> {
+    let name = channel_node
+        .attribute("InitialFunction")
+        .and_then(|f| f.rsplit('.').next());
+    functions
+        .iter()
+        .find(|f| f.attribute("Name") == name)
+        .or(functions.first())
+        .copied()
+}
+
+fn channel_from_functions(fixture_type: Node, attribute: &str, functions: &[Node]) -> Channel {
+    let channel_type = match attribute {
+        a if let Some(color) = additive_color_type(a) => color,
+        "Dimmer" => "dimmer",
+        "Pan" => "pan",
+        "Tilt" => "tilt",
+        "ColorSub_C" => "cyan",
+        "ColorSub_M" => "magenta",
+        "ColorSub_Y" => "yellow",
+        "Zoom" => "zoom",
+        "PanTiltSpeed" | "MSpeed" => "speed",
+        a if a.starts_with("Shutter") => "strobe",
+        a if a.starts_with("Color") && functions.iter().any(|f| f.has_attribute("Wheel")) => {
+            "color_wheel"
+        }
+        a => return channel_type_named(a),
+    };
+
+    let amount_mapping = |function: Option<&Node>| {
+        let (min_value, max_value) = function.map_or((0, 255), |f| function_range(f, functions));
+        Some(Mapping::AmountMapping(AmountMapping {
+            min_value,
+            max_value,
+        }))
+    };
+
+    let mapping = match channel_type {
+        "pan" | "tilt" => functions.first().map(|f| {
+            let from = physical(f, "PhysicalFrom");
+            let to = physical(f, "PhysicalTo");
+            #[allow(clippy::cast_possible_truncation)]
+            Mapping::AngleMapping(AngleMapping {
+                min_degrees: from.min(to).round() as i32,
+                max_degrees: from.max(to).round() as i32,
+            })
+        }),
+        "dimmer" | "zoom" => amount_mapping(functions.first()),
+        "speed" => {
+            let mut mapping = amount_mapping(functions.first());
+            // Speed functions in GDTF run from fast to slow when the
+            // physical range is descending.
+            if let (Some(Mapping::AmountMapping(m)), Some(f)) = (&mut mapping, functions.first())
+                && physical(f, "PhysicalFrom") > physical(f, "PhysicalTo")
+            {
+                std::mem::swap(&mut m.min_value, &mut m.max_value);
+            }
+            mapping
+        }
+        "strobe" => amount_mapping(functions.iter().find(|f| {
+            f.attribute("Attribute")
+                .is_some_and(|a| a.contains("Strobe"))
+        })),
+        "color_wheel" => color_wheel_mapping(fixture_type, functions),
+        _ => None,
+    };
+
+    Channel {
+        r#type: channel_type.to_string(),
+        mapping,
+        ..Default::default()
+    }
+}
+
+/// Returns the channel type of an additive color attribute, which drives a
+/// single emitter.
+fn additive_color_type(attribute: &str) -> Option<&'static str> {
+    Some(match attribute {
+        "ColorAdd_R" | "ColorRGB_Red" => "red",
+        "ColorAdd_G" | "ColorRGB_Green" => "green",
+        "ColorAdd_B" | "ColorRGB_Blue" => "blue",
+        "ColorAdd_W" => "white",
+        "ColorAdd_RY" => "amber",
+        "ColorAdd_GY" => "lime",
+        "ColorAdd_WW" => "warm-white",
+        "ColorAdd_CW" => "cold-white",
+        _ => return None,
+    })
+}
+
+/// Copies the color of each emitter that an additive color channel drives
+/// into the definition.
+fn import_emitters(fixture_type: Node, definition: &mut DmxFixtureDefinition) {
+    for logical_channel in fixture_type
+        .descendants()
+        .filter(|n| n.has_tag_name("LogicalChannel"))
+    {
+        let Some(channel_type) = logical_channel
+            .attribute("Attribute")
+            .and_then(additive_color_type)
+        else {
+            continue;
+        };
+        let Some(emitter_name) = logical_channel
+            .children()
+            .filter(|n| n.has_tag_name("ChannelFunction"))
+            .find_map(|f| f.attribute("Emitter"))
+        else {
+            continue;
+        };
+        let Some(emitter) = fixture_type
+            .descendants()
+            .find(|n| n.has_tag_name("Emitter") && n.attribute("Name") == Some(emitter_name))
+        else {
+            continue;
+        };
+
+        // Emitters defined without a color have nothing to solve with.
+        if let Some([x, y, luminance]) = emitter.attribute("Color").and_then(parse_cie_components) {
+            definition
+                .emitters
+                .entry(channel_type.to_string())
+                .or_insert(Emitter { x, y, luminance });
+        }
+    }
+}
+
+fn channel_type_named(attribute: &str) -> Channel {
+    Channel {
+        r#type: channel_type_from_name(attribute),
+        ..Default::default()
+    }
+}
+
+fn color_wheel_mapping(fixture_type: Node, functions: &[Node]) -> Option<Mapping> {
+    let function = functions.iter().find(|f| f.has_attribute("Wheel"))?;
+    let wheel_name = function.attribute("Wheel")?;
+    let slots: Vec<Node> = fixture_type
+        .descendants()
+        .find(|n| n.has_tag_name("Wheel") && n.attribute("Name") == Some(wheel_name))?
+        .children()
+        .filter(|n| n.has_tag_name("Slot"))
+        .collect();
+
+    let colors: Vec<ColorWheelColor> = function
+        .children()
+        .filter(|n| n.has_tag_name("ChannelSet"))
+        .filter_map(|set| {
+            let slot_index: usize = set.attribute("WheelSlotIndex")?.parse().ok()?;
+            let slot = slots.get(slot_index.checked_sub(1)?)?;
+            Some(ColorWheelColor {
+                name: set
+                    .attribute("Name")
+                    .filter(|n| !n.is_empty())
+                    .or(slot.attribute("Name"))
+                    .unwrap_or_default()
+                    .to_string(),
+                value: dmx_value(set.attribute("DMXFrom")?, 1)?,
+                color: slot.attribute("Color").and_then(parse_cie_color),
+                split_value: None,
+            })
+        })
+        .collect();
+
+    if colors.is_empty() {
+        return None;
+    }
+    Some(Mapping::ColorWheelMapping(ColorWheelMapping {
+        colors,
+        ..Default::default()
+    }))
+}
+
+/// Copies the IDs the fixture reports over RDM, and the personality that
+/// selects each mode, into the definition.
+fn import_rdm(fixture_type: Node, definition: &mut DmxFixtureDefinition) {
+    let Some(rdm) = fixture_type.descendants().find(|n| n.has_tag_name("FTRDM")) else {
+        return;
+    };
+    definition.rdm_manufacturer_id = rdm
+        .attribute("ManufacturerID")
+        .and_then(rdm_id)
+        .unwrap_or(0);
+    definition.rdm_model_id = rdm.attribute("DeviceModelID").and_then(rdm_id).unwrap_or(0);
+
+    for personality in rdm
+        .descendants()
+        .filter(|n| n.has_tag_name("DMXPersonality"))
+    {
+        let (Some(mode_name), Some(value)) = (
+            personality.attribute("DMXMode"),
+            personality.attribute("Value").and_then(rdm_id),
+        ) else {
+            continue;
+        };
+        if let Some(mode) = definition.modes.get_mut(&mode_key(mode_name)) {
+            mode.rdm_personality = value;
+        }
+    }
+}
+
+/// Parses an RDM ID, which GDTF writes in hexadecimal such as `0x08BD`.
+fn rdm_id(value: &str) -> Option<u32> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Returns the coarse DMX range a channel function covers, which runs until
+/// the next function starts.
+fn function_range(function: &Node, functions: &[Node]) -> (u32, u32) {
+    let start = |f: &Node| f.attribute("DMXFrom").and_then(|v| dmx_value(v, 1));
+    let min_value = start(function).unwrap_or(0);
+    let max_value = functions
+        .iter()
+        .filter_map(start)
+        .filter(|&s| s > min_value)
+        .min()
+        .map_or(255, |next| next - 1);
+    (min_value, max_value)
+}
+
+/// Parses a GDTF DMX value such as `32768/2`, which is a two byte value, down
+/// to the coarse byte.
+fn dmx_value(value: &str, default_byte_count: u32) -> Option<u32> {
+    let (value, byte_count) = match value.split_once('/') {
+        Some((value, byte_count)) => (value, byte_count.parse().ok()?),
+        None => (value, default_byte_count),
+    };
+    let value: u32 = value.parse().ok()?;
+    Some((value >> (8 * byte_count.saturating_sub(1))).min(255))
+}
+
+fn physical(function: &Node, attribute: &str) -> f64 {
+    function
+        .attribute(attribute)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.0)
+}
+
+/// Parses a GDTF `x,y,Y` color into its components.
+fn parse_cie_components(color: &str) -> Option<[f64; 3]> {
+    let mut components = color.split(',').map(|c| c.trim().parse::<f64>());
+    let x = components.next()?.ok()?;
+    let y = components.next()?.ok()?;
+    let luminance = components.next()?.ok()?;
+    Some([x, y, luminance])
+}
+
+/// Parses a GDTF `x,y,Y` color.
+fn parse_cie_color(color: &str) -> Option<Color> {
+    let [x, y, _] = parse_cie_components(color)?;
+    Some(Color::from_cie_xy(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOT_MOVER: &[u8] = include_bytes!("../../testdata/fixtures/spot-mover.gdtf");
+
+    #[test]
+    fn imports_modes_and_mappings() {
+        let imported = import_gdtf(SPOT_MOVER).unwrap();
+        let definition = &imported.definition;
+
+        assert_eq!(definition.name, "Generic Spot Mover");
+        assert_eq!(definition.manufacturer, "Generic");
+        assert_eq!(definition.global_id, "3F2B1C6E-6D0A-4E61-9D3B-3C1C1E0F5A10");
+
+        let mode = &definition.modes[&mode_key("Standard")];
+        assert_eq!(mode.num_channels, 8);
+
+        let pan = &mode.channels[&1];
+        assert_eq!(pan.r#type, "pan");
+        assert_eq!(pan.default_value, 128);
+        assert_eq!(
+            pan.mapping,
+            Some(Mapping::AngleMapping(AngleMapping {
+                min_degrees: -270,
+                max_degrees: 270,
+            }))
+        );
+        assert_eq!(pan.fine_channel, Some(2));
+        assert_eq!(mode.channels[&2].r#type, "pan-fine");
+
+        let dimmer = &mode.channels[&5];
+        assert_eq!(dimmer.r#type, "dimmer");
+        assert_eq!(
+            dimmer.mapping,
+            Some(Mapping::AmountMapping(AmountMapping {
+                min_value: 0,
+                max_value: 255,
+            }))
+        );
+
+        let strobe = &mode.channels[&6];
+        assert_eq!(strobe.r#type, "strobe");
+        assert_eq!(
+            strobe.mapping,
+            Some(Mapping::AmountMapping(AmountMapping {
+                min_value: 32,
+                max_value: 255,
+            }))
+        );
+    }
+
+    #[test]
+    fn imports_color_wheels() {
+        let imported = import_gdtf(SPOT_MOVER).unwrap();
+        let wheel = &imported.definition.modes[&mode_key("Standard")].channels[&7];
+
+        assert_eq!(wheel.r#type, "color_wheel");
+        let Some(Mapping::ColorWheelMapping(mapping)) = &wheel.mapping else {
+            panic!("Expected a color wheel mapping");
+        };
+        let names: Vec<_> = mapping
+            .colors
+            .iter()
+            .map(|c| (c.name.as_str(), c.value))
+            .collect();
+        assert_eq!(names, [("Open", 0), ("Red", 20), ("Blue", 40)]);
+
+        let red = mapping.colors[1].color.unwrap();
+        assert!(red.red > 0.99 && red.green < 0.05 && red.blue < 0.05);
+        let open = mapping.colors[0].color.unwrap();
+        assert!(open.red > 0.95 && open.green > 0.95 && open.blue > 0.95);
+    }
+
+    #[test]
+    fn imports_emitters() {
+        let imported = import_gdtf(SPOT_MOVER).unwrap();
+        let definition = &imported.definition;
+
+        let mode = &definition.modes[&mode_key("RGBA")];
+        let types: Vec<_> = (1..=4).map(|i| mode.channels[&i].r#type.as_str()).collect();
+        assert_eq!(types, ["red", "green", "blue", "amber"]);
+
+        assert_eq!(definition.emitters.len(), 4);
+        assert_eq!(
+            definition.emitters["amber"],
+            Emitter {
+                x: 0.575,
+                y: 0.424,
+                luminance: 40.0,
+            }
+        );
+    }
+
+    #[test]
+    fn warns_about_unrendered_channels() {
+        let imported = import_gdtf(SPOT_MOVER).unwrap();
+        assert_eq!(imported.warnings.len(), 1);
+        assert!(imported.warnings[0].contains("\"gobo1\""));
+    }
+
+    #[test]
+    fn imports_rdm_ids_and_personalities() {
+        let imported = import_description(
+            r#"<GDTF><FixtureType Name="Par" Manufacturer="Generic">
+                <DMXModes><DMXMode Name="Standard"/><DMXMode Name="Extended"/></DMXModes>
+                <Protocols><FTRDM ManufacturerID="0x08BD" DeviceModelID="0x0102">
+                    <SoftwareVersionID Value="0x01">
+                        <DMXPersonality DMXMode="Extended" Value="0x0002"/>
+                    </SoftwareVersionID>
+                </FTRDM></Protocols>
+            </FixtureType></GDTF>"#,
+        )
+        .unwrap();
+        let definition = &imported.definition;
+
+        assert_eq!(definition.rdm_manufacturer_id, 0x08BD);
+        assert_eq!(definition.rdm_model_id, 0x0102);
+        assert_eq!(definition.modes[&mode_key("Standard")].rdm_personality, 0);
+        assert_eq!(definition.modes[&mode_key("Extended")].rdm_personality, 2);
+    }
+
+    #[test]
+    fn rejects_archives_without_a_description() {
+        assert!(import_gdtf(b"not a zip").is_err());
+        assert!(import_description("<GDTF></GDTF>").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Converts fixture definitions from the Open Fixture Library and GDTF into
+//! `DmxFixtureDefinition`s.
+
+mod gdtf;
+mod ofl;
+
+use std::path::Path;
+
+use crate::proto::{DmxFixtureDefinition, dmx_fixture_definition::Mode};
+
+pub use gdtf::import_gdtf;
+pub use ofl::import_ofl;
+
+/// Channel types that `DmxRenderTarget` knows how to drive. Each may also have
+/// `-fine` and `-ultra` channels holding its lower bytes.
+const RENDERED_CHANNEL_TYPES: [&str; 21] = [
+    "red",
+    "green",
+    "blue",
+    "white",
+    "amber",
+    "lime",
+    "warm-white",
+    "cold-white",
+    "cyan",
+    "magenta",
+    "yellow",
+    "cto",
+    "color_wheel",
+    "pan",
+    "tilt",
+    "dimmer",
+    "strobe",
+    "width",
+    "height",
+    "zoom",
+    "speed",
+];
+
+/// A fixture definition along with anything about the source file that could
+/// not be represented faithfully.
+#[derive(Debug)]
+pub struct ImportedFixtureDefinition {
+    pub definition: DmxFixtureDefinition,
+    pub warnings: Vec<String>,
+}
+
+/// Reads an Open Fixture Library `.json` file or a `.gdtf` archive from disk.
+pub fn import_fixture_definition_file(path: &Path) -> Result<ImportedFixtureDefinition, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Could not read fixture file {}: {e}", path.display()))?;
+
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("gdtf" | "zip") => import_gdtf(&bytes),
+        Some("json") => {
+            let json = String::from_utf8(bytes)
+                .map_err(|e| format!("Fixture file {} is not UTF-8: {e}", path.display()))?;
+            // OFL stores fixtures as `<manufacturer>/<fixture>.json`.
+            let manufacturer = path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            import_ofl(&json, manufacturer)
+        }
+        _ => Err(format!(
+            "Unrecognized fixture file type for {}, expected .json or .gdtf",
+            path.display()
+        )),
+    }
+}
+
+/// Modes are keyed by a UUID derived from their name so that importing the
+/// same fixture twice produces the same keys.
+fn mode_key(name: &str) -> String {
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_DNS, name.as_bytes()).to_string()
+}
+
+fn is_rendered_channel_type(channel_type: &str) -> bool {
+    let coarse_type = channel_type
+        .strip_suffix("-fine")
+        .or(channel_type.strip_suffix("-ultra"))
+        .unwrap_or(channel_type);
+    RENDERED_CHANNEL_TYPES.contains(&coarse_type)
+}
+
+/// Warns about every channel of a mode that the renderer will leave at its
+/// default value.
+fn warn_unrendered_channels(mode: &Mode, warnings: &mut Vec<String>) {
+    let mut indices: Vec<_> = mode.channels.keys().collect();
+    indices.sort();
+    for index in indices {
+        let channel_type = &mode.channels[index].r#type;
+        if !is_rendered_channel_type(channel_type) {
+            warnings.push(format!(
+                "Mode \"{}\" channel {index} has type \"{channel_type}\" which is not rendered and will stay at its default value",
+                mode.name
+            ));
+        }
+    }
+}
+
+/// Turns a free form name such as "Gobo Wheel" into a channel type such as
+/// "gobo-wheel".
+fn channel_type_from_name(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_keys_are_stable_uuids() {
+        assert_eq!(mode_key("9-channel"), mode_key("9-channel"));
+        assert_ne!(mode_key("9-channel"), mode_key("16-channel"));
+        assert_eq!(mode_key("9-channel").len(), 36);
+    }
+
+    #[test]
+    fn reads_both_formats_from_disk() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/fixtures");
+
+        let ofl = import_fixture_definition_file(&dir.join("generic/spot-mover.json")).unwrap();
+        assert_eq!(ofl.definition.manufacturer, "generic");
+
+        let gdtf = import_fixture_definition_file(&dir.join("spot-mover.gdtf")).unwrap();
+        assert_eq!(gdtf.definition.manufacturer, "Generic");
+
+        assert!(import_fixture_definition_file(&dir.join("missing.json")).is_err());
+    }
+}
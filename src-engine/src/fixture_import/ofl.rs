@@ -0,0 +1,554 @@
+//! Open Fixture Library fixture files, see
+//! <https://github.com/OpenLightingProject/open-fixture-library/blob/master/docs/fixture-format.md>.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{
+    ImportedFixtureDefinition, channel_type_from_name, mode_key, warn_unrendered_channels,
+};
+use crate::proto::{
+    Color, DmxFixtureDefinition,
+    dmx_fixture_definition::{
+        Channel, Mode,
+        channel::{
+            AmountMapping, AngleMapping, ColorWheelMapping, Mapping,
+            color_wheel_mapping::ColorWheelColor,
+        },
+    },
+};
+
+/// Converts the contents of an OFL fixture file. `manufacturer` is used when
+/// the file itself does not name one, which is the case for files taken
+/// straight out of the library's `fixtures/<manufacturer>/` directories.
+pub fn import_ofl(json: &str, manufacturer: &str) -> Result<ImportedFixtureDefinition, String> {
+    let fixture: Value =
+        serde_json::from_str(json).map_err(|e| format!("Could not parse OFL fixture: {e}"))?;
+
+    let name = fixture["name"]
+        .as_str()
+        .ok_or("OFL fixture does not have a name")?;
+    let manufacturer = fixture["manufacturerKey"].as_str().unwrap_or(manufacturer);
+    let fixture_key = fixture["fixtureKey"]
+        .as_str()
+        .map_or_else(|| channel_type_from_name(name), str::to_string);
+
+    let mut warnings = Vec::new();
+    let (channels, fine_aliases) = available_channels(&fixture, &mut warnings);
+
+    let mut definition = DmxFixtureDefinition {
+        global_id: format!("ofl:{manufacturer}/{fixture_key}"),
+        name: name.to_string(),
+        manufacturer: manufacturer.to_string(),
+        // Only the model ID is read here; the manufacturer's RDM ID is kept in
+        // the library's manufacturer list rather than in fixture files.
+        rdm_model_id: rdm_id(&fixture["rdm"]["modelId"]),
+        ..Default::default()
+    };
+
+    for mode_value in fixture["modes"].as_array().into_iter().flatten() {
+        let Some(mode_name) = mode_value["name"].as_str() else {
+            warnings.push("Skipping a mode without a name".to_string());
+            continue;
+        };
+
+        let mode_channels = mode_value["channels"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut mode = Mode {
+            name: mode_name.to_string(),
+            num_channels: u32::try_from(mode_channels.len())
+                .map_err(|_| format!("Mode \"{mode_name}\" has too many channels"))?,
+            rdm_personality: rdm_id(&mode_value["rdmPersonalityIndex"]),
+            ..Default::default()
+        };
+
+        for (index, channel_key) in (1u32..).zip(mode_channels) {
+            match channel_key {
+                // Unused channels are listed as null.
+                Value::Null => {}
+                Value::String(key) => match channels.get(key.as_str()) {
+                    Some(channel) => {
+                        mode.channels.insert(index, channel.clone());
+                    }
+                    None => warnings.push(format!(
+                        "Mode \"{mode_name}\" references unknown channel \"{key}\""
+                    )),
+                },
+                _ => warnings.push(format!(
+                    "Mode \"{mode_name}\" channel {index} uses a matrix channel insert which is not supported"
+                )),
+            }
+        }
+
+        // Point each coarse channel at wherever this mode put its lower bytes.
+        for (index, channel_key) in (1u32..).zip(mode_channels) {
+            let Some((coarse_key, byte)) = channel_key.as_str().and_then(|k| fine_aliases.get(k))
+            else {
+                continue;
+            };
+            let Some(coarse) = (1u32..)
+                .zip(mode_channels)
+                .find(|(_, k)| k.as_str() == Some(coarse_key.as_str()))
+                .and_then(|(coarse_index, _)| mode.channels.get_mut(&coarse_index))
+            else {
+                continue;
+            };
+            match byte {
+                FineByte::Fine => coarse.fine_channel = Some(index),
+                FineByte::Ultra => coarse.ultra_channel = Some(index),
+            }
+        }
+
+        warn_unrendered_channels(&mode, &mut warnings);
+        definition.modes.insert(mode_key(mode_name), mode);
+    }
+
+    if definition.modes.is_empty() {
+        return Err(format!("OFL fixture \"{name}\" does not have any modes"));
+    }
+
+    Ok(ImportedFixtureDefinition {
+        definition,
+        warnings,
+    })
+}
+
+/// An RDM ID or index, or 0 if the file does not give one.
+fn rdm_id(value: &Value) -> u32 {
+    value
+        .as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .unwrap_or_default()
+}
+
+/// Which lower byte of a multi-byte channel a fine channel alias holds.
+enum FineByte {
+    Fine,
+    Ultra,
+}
+
+/// Builds every channel the fixture defines, including fine channel aliases,
+/// keyed by the name modes use to reference them. Also returns the coarse
+/// channel and byte of each fine channel alias.
+fn available_channels(
+    fixture: &Value,
+    warnings: &mut Vec<String>,
+) -> (
+    HashMap<String, Channel>,
+    HashMap<String, (String, FineByte)>,
+) {
+    let mut channels = HashMap::new();
+    let mut fine_aliases = HashMap::new();
+
+    if fixture.get("templateChannels").is_some() {
+        warnings.push("Matrix template channels are not supported".to_string());
+    }
+
+    let Some(available) = fixture["availableChannels"].as_object() else {
+        return (channels, fine_aliases);
+    };
+
+    for (key, channel_value) in available {
+        let capabilities: Vec<&Value> = match (
+            channel_value.get("capability"),
+            channel_value["capabilities"].as_array(),
+        ) {
+            (Some(capability), _) => vec![capability],
+            (None, Some(capabilities)) => capabilities.iter().collect(),
+            (None, None) => Vec::new(),
+        };
+
+        // Capability ranges are given in the resolution of the coarse
+        // channel unless the file says otherwise.
+        let range_shift = match channel_value["dmxValueResolution"].as_str() {
+            Some("16bit") => 8,
+            Some("24bit") => 16,
+            _ => 0,
+        };
+
+        let mut channel = channel_from_capabilities(fixture, key, &capabilities, range_shift);
+        channel.default_value =
+            dmx_value(&channel_value["defaultValue"], range_shift).unwrap_or(channel.default_value);
+
+        let aliases: Vec<&str> = channel_value["fineChannelAliases"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        if aliases.len() > 2 {
+            warnings.push(format!(
+                "Channel \"{key}\" has {} fine channels, only the first 2 are used",
+                aliases.len()
+            ));
+        }
+        for ((suffix, byte), alias) in [("fine", FineByte::Fine), ("ultra", FineByte::Ultra)]
+            .into_iter()
+            .zip(aliases)
+        {
+            channels.insert(
+                alias.to_string(),
+                Channel {
+                    r#type: format!("{}-{suffix}", channel.r#type),
+                    ..Default::default()
+                },
+            );
+            fine_aliases.insert(alias.to_string(), (key.clone(), byte));
+        }
+
+        channels.insert(key.clone(), channel);
+    }
+
+    (channels, fine_aliases)
+}
+
+fn channel_from_capabilities(
+    fixture: &Value,
+    key: &str,
+    capabilities: &[&Value],
+    range_shift: u32,
+) -> Channel {
+    let other = || Channel {
+        r#type: channel_type_from_name(key),
+        ..Default::default()
+    };
+
+    let Some(first) = capabilities.first() else {
+        return other();
+    };
+
+    let amount_mapping = |capability: &Value| {
+        let (min_value, max_value) = dmx_range(capability, range_shift).unwrap_or((0, 255));
+        Some(Mapping::AmountMapping(AmountMapping {
+            min_value,
+            max_value,
+        }))
+    };
+
+    match first["type"].as_str().unwrap_or_default() {
+        "Intensity" => Channel {
+            r#type: "dimmer".to_string(),
+            mapping: amount_mapping(first),
+            ..Default::default()
+        },
+        "ColorIntensity" => Channel {
+            r#type: channel_type_from_name(first["color"].as_str().unwrap_or(key)),
+            ..Default::default()
+        },
+        axis @ ("Pan" | "Tilt") => {
+            let max_key = if axis == "Pan" { "panMax" } else { "tiltMax" };
+            let full_range = fixture["physical"]["focus"][max_key].as_f64();
+            let start = angle(&first["angleStart"], full_range).unwrap_or(0.0);
+            let end = angle(&first["angleEnd"], full_range)
+                .or(full_range)
+                .unwrap_or(0.0);
+            #[allow(clippy::cast_possible_truncation)]
+            Channel {
+                r#type: axis.to_ascii_lowercase(),
+                mapping: Some(Mapping::AngleMapping(AngleMapping {
+                    min_degrees: start.min(end).round() as i32,
+                    max_degrees: start.max(end).round() as i32,
+                })),
+                ..Default::default()
+            }
+        }
+        "Zoom" => Channel {
+            r#type: "zoom".to_string(),
+            mapping: amount_mapping(first),
+            ..Default::default()
+        },
+        "PanTiltSpeed" => {
+            let mut mapping = amount_mapping(first);
+            // Speed channels often run from fast to slow.
+            if first["speedStart"].as_str() == Some("fast")
+                && let Some(Mapping::AmountMapping(m)) = &mut mapping
+            {
+                std::mem::swap(&mut m.min_value, &mut m.max_value);
+            }
+            Channel {
+                r#type: "speed".to_string(),
+                mapping,
+                ..Default::default()
+            }
+        }
+        "ShutterStrobe" => strobe_channel(capabilities, range_shift),
+        "WheelSlot" => wheel_channel(fixture, key, capabilities, range_shift).unwrap_or_else(other),
+        _ => other(),
+    }
+}
+
+/// Strobe channels map the strobing range from slow to fast and default to
+/// the open shutter, if there is one.
+fn strobe_channel(capabilities: &[&Value], range_shift: u32) -> Channel {
+    let strobe = capabilities
+        .iter()
+        .find(|c| c["shutterEffect"].as_str() == Some("Strobe"));
+    let open = capabilities
+        .iter()
+        .find(|c| c["shutterEffect"].as_str() == Some("Open"));
+
+    let (min_value, max_value) = strobe
+        .and_then(|c| dmx_range(c, range_shift))
+        .unwrap_or((0, 255));
+
+    Channel {
+        r#type: "strobe".to_string(),
+        default_value: open
+            .and_then(|c| dmx_range(c, range_shift))
+            .map_or(0, |(start, _)| start),
+        mapping: Some(Mapping::AmountMapping(AmountMapping {
+            min_value,
+            max_value,
+        })),
+        ..Default::default()
+    }
+}
+
+/// Returns a color wheel channel if the wheel the capabilities refer to
+/// holds colors.
+fn wheel_channel(
+    fixture: &Value,
+    key: &str,
+    capabilities: &[&Value],
+    range_shift: u32,
+) -> Option<Channel> {
+    let mut colors = Vec::new();
+    let mut slot_numbers = Vec::new();
+    let mut splits = Vec::new();
+
+    for capability in capabilities {
+        if capability["type"].as_str() != Some("WheelSlot") {
+            continue;
+        }
+        let wheel_name = capability["wheel"].as_str().unwrap_or(key);
+        let slots = fixture["wheels"][wheel_name]["slots"].as_array()?;
+
+        // Slots halfway between two whole numbers are split colors.
+        let Some(slot_number) = capability["slotNumber"].as_u64() else {
+            if let Some(slot_number) = capability["slotNumber"].as_f64()
+                && (slot_number.fract() - 0.5).abs() < f64::EPSILON
+            {
+                let (value, _) = dmx_range(capability, range_shift)?;
+                #[allow(clippy::cast_possible_truncation)]
+                #[allow(clippy::cast_sign_loss)]
+                splits.push((slot_number.floor() as u64, value));
+            }
+            continue;
+        };
+        let slot = slots.get(usize::try_from(slot_number).ok()?.checked_sub(1)?)?;
+
+        let color = match slot["type"].as_str() {
+            Some("Open") => Color {
+                red: 1.0,
+                green: 1.0,
+                blue: 1.0,
+                white: None,
+            },
+            Some("Color") => parse_hex_color(slot["colors"][0].as_str()?)?,
+            _ => return None,
+        };
+
+        let (value, _) = dmx_range(capability, range_shift)?;
+        colors.push(ColorWheelColor {
+            name: slot["name"]
+                .as_str()
+                .or(slot["type"].as_str())
+                .unwrap_or_default()
+                .to_string(),
+            value,
+            color: Some(color),
+            split_value: None,
+        });
+        slot_numbers.push(slot_number);
+    }
+
+    if colors.is_empty() {
+        return None;
+    }
+
+    for (slot_number, value) in splits {
+        if let Some(i) = slot_numbers.iter().position(|n| *n == slot_number)
+            && slot_numbers.get(i + 1) == Some(&(slot_number + 1))
+        {
+            colors[i].split_value = Some(value);
+        }
+    }
+
+    Some(Channel {
+        r#type: "color_wheel".to_string(),
+        default_value: colors[0].value,
+        mapping: Some(Mapping::ColorWheelMapping(ColorWheelMapping {
+            colors,
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+fn dmx_range(capability: &Value, range_shift: u32) -> Option<(u32, u32)> {
+    let range = capability["dmxRange"].as_array()?;
+    Some((
+        dmx_value(range.first()?, range_shift)?,
+        dmx_value(range.get(1)?, range_shift)?,
+    ))
+}
+
+fn dmx_value(value: &Value, range_shift: u32) -> Option<u32> {
+    let value = u32::try_from(value.as_u64()?).ok()? >> range_shift;
+    Some(value.min(255))
+}
+
+/// Parses an OFL angle such as "540deg" or "50%" of the full range.
+fn angle(value: &Value, full_range: Option<f64>) -> Option<f64> {
+    let value = value.as_str()?;
+    if let Some(degrees) = value.strip_suffix("deg") {
+        degrees.parse().ok()
+    } else if let Some(percent) = value.strip_suffix('%') {
+        Some(percent.parse::<f64>().ok()? / 100.0 * full_range?)
+    } else {
+        None
+    }
+}
+
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let component = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|v| f64::from(v) / 255.0)
+    };
+    Some(Color {
+        red: component(0)?,
+        green: component(2)?,
+        blue: component(4)?,
+        white: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOT_MOVER: &str = include_str!("../../testdata/fixtures/generic/spot-mover.json");
+
+    fn mode<'a>(definition: &'a DmxFixtureDefinition, name: &str) -> &'a Mode {
+        &definition.modes[&mode_key(name)]
+    }
+
+    #[test]
+    fn imports_modes_and_mappings() {
+        let imported = import_ofl(SPOT_MOVER, "generic").unwrap();
+        let definition = &imported.definition;
+
+        assert_eq!(definition.name, "Spot Mover");
+        assert_eq!(definition.global_id, "ofl:generic/spot-mover");
+        assert_eq!(definition.rdm_model_id, 258);
+        assert_eq!(definition.modes.len(), 2);
+        assert_eq!(mode(definition, "RGB").rdm_personality, 0);
+
+        let mode = mode(definition, "11-channel");
+        assert_eq!(mode.num_channels, 11);
+        assert_eq!(mode.rdm_personality, 2);
+
+        let pan = &mode.channels[&1];
+        assert_eq!(pan.r#type, "pan");
+        assert_eq!(
+            pan.mapping,
+            Some(Mapping::AngleMapping(AngleMapping {
+                min_degrees: 0,
+                max_degrees: 540,
+            }))
+        );
+        assert_eq!(pan.fine_channel, Some(2));
+        assert_eq!(pan.ultra_channel, None);
+        assert_eq!(mode.channels[&2].r#type, "pan-fine");
+        assert_eq!(mode.channels[&3].fine_channel, Some(4));
+        assert_eq!(mode.channels[&4].r#type, "tilt-fine");
+
+        let dimmer = &mode.channels[&5];
+        assert_eq!(dimmer.r#type, "dimmer");
+        assert_eq!(
+            dimmer.mapping,
+            Some(Mapping::AmountMapping(AmountMapping {
+                min_value: 0,
+                max_value: 255,
+            }))
+        );
+
+        let strobe = &mode.channels[&6];
+        assert_eq!(strobe.r#type, "strobe");
+        assert_eq!(strobe.default_value, 0);
+        assert_eq!(
+            strobe.mapping,
+            Some(Mapping::AmountMapping(AmountMapping {
+                min_value: 20,
+                max_value: 255,
+            }))
+        );
+
+        let speed = &mode.channels[&10];
+        assert_eq!(speed.r#type, "speed");
+        assert_eq!(
+            speed.mapping,
+            Some(Mapping::AmountMapping(AmountMapping {
+                min_value: 255,
+                max_value: 0,
+            }))
+        );
+    }
+
+    #[test]
+    fn imports_color_wheels() {
+        let imported = import_ofl(SPOT_MOVER, "generic").unwrap();
+        let wheel = &mode(&imported.definition, "11-channel").channels[&7];
+
+        assert_eq!(wheel.r#type, "color_wheel");
+        let Some(Mapping::ColorWheelMapping(mapping)) = &wheel.mapping else {
+            panic!("Expected a color wheel mapping");
+        };
+        let colors: Vec<_> = mapping
+            .colors
+            .iter()
+            .map(|c| (c.name.as_str(), c.value))
+            .collect();
+        assert_eq!(colors, [("Open", 0), ("Red", 16), ("Blue", 32)]);
+        let splits: Vec<_> = mapping.colors.iter().map(|c| c.split_value).collect();
+        assert_eq!(splits, [Some(8), None, None]);
+        assert_eq!(
+            mapping.colors[1].color,
+            Some(Color {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                white: None,
+            })
+        );
+    }
+
+    #[test]
+    fn warns_about_unrendered_channels() {
+        let imported = import_ofl(SPOT_MOVER, "generic").unwrap();
+
+        let gobo_warnings: Vec<_> = imported
+            .warnings
+            .iter()
+            .filter(|w| w.contains("\"gobo-wheel\""))
+            .collect();
+        // Both modes use the gobo wheel.
+        assert_eq!(gobo_warnings.len(), 2);
+        assert!(!imported.warnings.iter().any(|w| w.contains("\"amber\"")));
+        assert!(!imported.warnings.iter().any(|w| w.contains("\"pan\"")));
+    }
+
+    #[test]
+    fn rejects_files_without_modes() {
+        assert!(import_ofl(r#"{"name": "Empty", "modes": []}"#, "generic").is_err());
+        assert!(import_ofl("not json", "generic").is_err());
+    }
+}
@@ -1,13 +1,22 @@
 pub mod audio;
 pub mod beat;
 pub mod color;
+pub mod cue;
+pub mod dmx_input;
+pub mod fixture_import;
 pub mod hash;
+pub mod masters;
 pub mod midi;
 pub mod palette;
+pub mod park;
+pub mod placement;
+pub mod programmer;
 pub mod project;
 pub mod project_util;
 pub mod proto;
+pub mod recording;
 pub mod render;
 pub mod tile;
+pub mod validate;
 pub mod visualizer;
 pub mod waveform;
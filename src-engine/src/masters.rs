@@ -0,0 +1,74 @@
+use crate::proto::{Masters, Project};
+
+/// Returns the grand master level, where 1.0 is full.
+#[must_use]
+pub fn grand_master(project: &Project) -> f64 {
+    project
+        .masters
+        .as_ref()
+        .and_then(|m| m.grand_master)
+        .unwrap_or(1.0)
+}
+
+pub fn set_grand_master(project: &mut Project, level: f64) {
+    project
+        .masters
+        .get_or_insert_with(Masters::default)
+        .grand_master = Some(level.clamp(0.0, 1.0));
+}
+
+/// Returns the submaster level of a target group, where 1.0 is full.
+#[must_use]
+pub fn submaster(project: &Project, group_id: u64) -> f64 {
+    project
+        .masters
+        .as_ref()
+        .and_then(|m| m.group_submasters.get(&group_id))
+        .copied()
+        .unwrap_or(1.0)
+}
+
+pub fn set_submaster(project: &mut Project, group_id: u64, level: f64) -> Result<(), String> {
+    if !project.groups.contains_key(&group_id) {
+        return Err(format!("Could not find group {group_id}"));
+    }
+
+    project
+        .masters
+        .get_or_insert_with(Masters::default)
+        .group_submasters
+        .insert(group_id, level.clamp(0.0, 1.0));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::TargetGroup;
+
+    #[test]
+    fn unset_levels_are_full() {
+        let project = Project::default();
+        assert!((grand_master(&project) - 1.0).abs() < f64::EPSILON);
+        assert!((submaster(&project, 7) - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn levels_are_clamped() {
+        let mut project = Project::default();
+        project.groups.insert(7, TargetGroup::default());
+
+        set_grand_master(&mut project, 1.5);
+        set_submaster(&mut project, 7, -0.5).unwrap();
+
+        assert!((grand_master(&project) - 1.0).abs() < f64::EPSILON);
+        assert!(submaster(&project, 7).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn submaster_requires_an_existing_group() {
+        let mut project = Project::default();
+        assert!(set_submaster(&mut project, 7, 0.5).is_err());
+        assert!(project.masters.is_none());
+    }
+}
@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 
 use crate::beat::{set_bpm, set_first_beat};
+use crate::cue::{self, back, go, goto};
+use crate::masters::{grand_master, set_grand_master, set_submaster, submaster};
 use crate::project;
 use crate::proto::{self, InputBinding, InputType, TileStrengthAction};
 use crate::tile::{calculate_tile_strength, enable_tile, toggle_tile};
@@ -49,7 +51,7 @@ pub enum ControlCommandType {
 }
 
 /// Looks up a binding in the hierarchy, starting from scene and falling back to global.
-fn find_binding<'a>(
+pub(crate) fn find_binding<'a>(
     project: &'a proto::Project,
     binding_context: &BindingContext,
     binding_id: u64,
@@ -149,6 +151,41 @@ pub fn perform_action(
                     Ok(ActionResult::unchanged())
                 }
             }
+            proto::input_binding::Action::GrandMaster(_) => {
+                set_grand_master(project, value);
+                Ok(ActionResult::with_action(*action, true))
+            }
+            proto::input_binding::Action::Submaster(submaster_action) => {
+                set_submaster(project, submaster_action.group_id, value)
+                    .and(Ok(ActionResult::with_action(*action, true)))
+            }
+            proto::input_binding::Action::CueGo(cue_action) => {
+                // Only trigger on press (value > 0.5)
+                if value > 0.5 {
+                    let modified = go(project, cue_action.cue_list_id, t)?;
+                    Ok(ActionResult::with_action(*action, modified))
+                } else {
+                    Ok(ActionResult::unchanged())
+                }
+            }
+            proto::input_binding::Action::CueBack(cue_action) => {
+                // Only trigger on press (value > 0.5)
+                if value > 0.5 {
+                    let modified = back(project, cue_action.cue_list_id, t)?;
+                    Ok(ActionResult::with_action(*action, modified))
+                } else {
+                    Ok(ActionResult::unchanged())
+                }
+            }
+            proto::input_binding::Action::CueGoto(cue_action) => {
+                // Only trigger on press (value > 0.5)
+                if value > 0.5 {
+                    goto(project, cue_action.cue_list_id, cue_action.cue_id, t)
+                        .and(Ok(ActionResult::with_action(*action, true)))
+                } else {
+                    Ok(ActionResult::unchanged())
+                }
+            }
         }
     })
 }
@@ -268,6 +305,30 @@ pub fn calculate_midi_output(
                     }
                 }
                 Some(proto::input_binding::Action::ColorPalette(_)) => 1.0,
+                Some(proto::input_binding::Action::GrandMaster(_)) => grand_master(project),
+                Some(proto::input_binding::Action::Submaster(submaster_action)) => {
+                    submaster(project, submaster_action.group_id)
+                }
+                Some(proto::input_binding::Action::CueGo(cue_action)) => {
+                    cue_led(project, cue_action.cue_list_id, t, |cue_list, position| {
+                        // Lit while the active cue is fading in.
+                        position.active.is_some_and(|i| {
+                            t < position.go_t + cue::fade_end_ms(&cue_list.cues[i])
+                        })
+                    })
+                }
+                Some(proto::input_binding::Action::CueBack(cue_action)) => {
+                    cue_led(project, cue_action.cue_list_id, t, |_, position| {
+                        position.active.is_some_and(|i| i > 0)
+                    })
+                }
+                Some(proto::input_binding::Action::CueGoto(cue_action)) => {
+                    cue_led(project, cue_action.cue_list_id, t, |cue_list, position| {
+                        position
+                            .active
+                            .is_some_and(|i| cue_list.cues[i].id == cue_action.cue_id)
+                    })
+                }
                 None => 0.0,
             };
 
@@ -279,6 +340,19 @@ pub fn calculate_midi_output(
     })
 }
 
+/// Lights a cue action's LED when `lit` holds for where the cue list stands.
+fn cue_led(
+    project: &proto::Project,
+    cue_list_id: u64,
+    t: u64,
+    lit: impl Fn(&proto::CueList, &cue::CuePosition) -> bool,
+) -> f64 {
+    match project.cue_lists.get(&cue_list_id) {
+        Some(cue_list) if lit(cue_list, &cue::position(cue_list, t)) => 1.0,
+        _ => 0.0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +385,199 @@ mod tests {
         project
     }
 
+    const TEST_CONTROLLER: &str = "TestController";
+
+    /// Loads `project` into the global state with `bindings` bound globally
+    /// to [`TEST_CONTROLLER`] as binding 1.
+    fn load_with_bindings(
+        mut project: proto::Project,
+        bindings: Vec<(&str, proto::input_binding::Action, InputType)>,
+    ) {
+        use std::collections::HashMap;
+
+        project.controller_mapping = Some(proto::ControllerMapping {
+            controller_to_binding: HashMap::from([(TEST_CONTROLLER.to_string(), 1)]),
+            binding_names: HashMap::new(),
+        });
+        let mut controller_bindings = proto::controller_bindings_map::ControllerBindings::default();
+        for (channel, action, input_type) in bindings {
+            controller_bindings.bindings.insert(
+                channel.to_string(),
+                proto::InputBinding {
+                    input_type: input_type.into(),
+                    action: Some(action),
+                },
+            );
+        }
+        project.live_page_controller_bindings = Some(proto::ControllerBindingsMap {
+            bindings: HashMap::from([(1, controller_bindings)]),
+        });
+        project::load(project).unwrap();
+    }
+
+    #[test]
+    fn test_faders_set_the_grand_master_and_submasters() {
+        let _guard = project::tests::lock_state();
+        let mut project = create_test_project_with_tile();
+        project.groups.insert(5, proto::TargetGroup::default());
+        load_with_bindings(
+            project,
+            vec![
+                (
+                    "grand",
+                    proto::input_binding::Action::GrandMaster(proto::GrandMasterAction {}),
+                    InputType::Continuous,
+                ),
+                (
+                    "group",
+                    proto::input_binding::Action::Submaster(proto::SubmasterAction { group_id: 5 }),
+                    InputType::Continuous,
+                ),
+            ],
+        );
+
+        let close = |a: f64, b: f64| (a - b).abs() < 0.001;
+        let output = calculate_midi_output(TEST_CONTROLLER, 0).unwrap();
+        assert!(close(output["grand"], 1.0));
+        assert!(close(output["group"], 1.0));
+
+        let cct = Some(ControlCommandType::Msb);
+        assert!(perform_action(1, "grand", 0.25, cct, 0).unwrap().modified);
+        assert!(perform_action(1, "group", 0.5, cct, 0).unwrap().modified);
+
+        project::with_project(|project| {
+            assert!(close(grand_master(project), 0.25));
+            assert!(close(submaster(project, 5), 0.5));
+            Ok(())
+        })
+        .unwrap();
+        let output = calculate_midi_output(TEST_CONTROLLER, 0).unwrap();
+        assert!(close(output["grand"], 0.25));
+        assert!(close(output["group"], 0.5));
+    }
+
+    #[test]
+    fn test_submaster_of_a_missing_group_fails() {
+        let _guard = project::tests::lock_state();
+        load_with_bindings(
+            create_test_project_with_tile(),
+            vec![(
+                "group",
+                proto::input_binding::Action::Submaster(proto::SubmasterAction { group_id: 5 }),
+                InputType::Continuous,
+            )],
+        );
+
+        assert!(perform_action(1, "group", 0.5, Some(ControlCommandType::Msb), 0).is_err());
+    }
+
+    /// Loads a project with cue list 1 holding cues 10 and 20, each fading
+    /// for a second, and GO, BACK and GOTO 20 buttons bound to it.
+    fn load_cue_bindings() {
+        use std::collections::HashMap;
+
+        let mut project = create_test_project_with_tile();
+        let cue = |id| proto::cue_list::Cue {
+            id,
+            up_ms: 1_000,
+            ..Default::default()
+        };
+        project.cue_lists = HashMap::from([(
+            1,
+            proto::CueList {
+                cues: vec![cue(10), cue(20)],
+                ..Default::default()
+            },
+        )]);
+        load_with_bindings(
+            project,
+            vec![
+                (
+                    "go",
+                    proto::input_binding::Action::CueGo(proto::CueGoAction { cue_list_id: 1 }),
+                    InputType::Binary,
+                ),
+                (
+                    "back",
+                    proto::input_binding::Action::CueBack(proto::CueBackAction { cue_list_id: 1 }),
+                    InputType::Binary,
+                ),
+                (
+                    "goto",
+                    proto::input_binding::Action::CueGoto(proto::CueGotoAction {
+                        cue_list_id: 1,
+                        cue_id: 20,
+                    }),
+                    InputType::Binary,
+                ),
+            ],
+        );
+    }
+
+    fn active_cue() -> u64 {
+        project::with_project(|project| Ok(project.cue_lists[&1].active_cue)).unwrap()
+    }
+
+    #[test]
+    fn test_cue_buttons_trigger_on_press() {
+        let _guard = project::tests::lock_state();
+        load_cue_bindings();
+
+        for channel in ["go", "back", "goto"] {
+            assert!(!perform_action(1, channel, 0.5, None, 0).unwrap().modified);
+        }
+        assert_eq!(active_cue(), 0);
+
+        assert!(perform_action(1, "go", 1.0, None, 0).unwrap().modified);
+        assert_eq!(active_cue(), 10);
+        assert!(perform_action(1, "goto", 1.0, None, 0).unwrap().modified);
+        assert_eq!(active_cue(), 20);
+        assert!(perform_action(1, "back", 1.0, None, 0).unwrap().modified);
+        assert_eq!(active_cue(), 10);
+    }
+
+    #[test]
+    fn test_cue_leds_follow_the_cue_list() {
+        let _guard = project::tests::lock_state();
+        load_cue_bindings();
+
+        let leds = |t| {
+            let output = calculate_midi_output(TEST_CONTROLLER, t).unwrap();
+            let lit = |channel: &str| output[channel] > 0.5;
+            (lit("go"), lit("back"), lit("goto"))
+        };
+        assert_eq!(leds(0), (false, false, false));
+
+        perform_action(1, "go", 1.0, None, 1_000).unwrap();
+        // GO is lit while cue 10 fades in; BACK is unlit on the first cue.
+        assert_eq!(leds(1_500), (true, false, false));
+        assert_eq!(leds(2_000), (false, false, false));
+
+        perform_action(1, "go", 1.0, None, 3_000).unwrap();
+        assert_eq!(leds(4_000), (false, true, true));
+    }
+
+    #[test]
+    fn test_cue_leds_of_empty_and_stale_cue_lists_are_unlit() {
+        let _guard = project::tests::lock_state();
+        load_cue_bindings();
+
+        for cues in [Vec::new(), vec![proto::cue_list::Cue::default()]] {
+            project::with_project_mut(|project| {
+                let cue_list = project.cue_lists.get_mut(&1).unwrap();
+                cue_list.cues.clone_from(&cues);
+                // The active cue was deleted.
+                cue_list.active_cue = 10;
+                cue_list.go_t = 0;
+                Ok(())
+            })
+            .unwrap();
+
+            let output = calculate_midi_output(TEST_CONTROLLER, 0).unwrap();
+            assert!(output.values().all(|value| *value < 0.5), "{output:?}");
+        }
+    }
+
     #[test]
     fn test_tile_strength_invert_normal() {
         let mut project = create_test_project_with_tile();
@@ -503,6 +770,7 @@ mod tests {
         use crate::project;
         use std::collections::HashMap;
 
+        let _guard = project::tests::lock_state();
         let mut project = create_test_project_with_tile();
 
         // Set tile strength to 0.75
@@ -1,6 +1,10 @@
 use std::sync::LazyLock;
 
-use crate::proto::{Color, ColorPalette, Project, color_palette::ColorDescription};
+use crate::proto::{
+    Color, ColorInterpolation, ColorPalette, Project,
+    color_palette::ColorDescription,
+    timecoded_show::{PaletteKeyframe, palette_keyframe::Curve},
+};
 
 /// Default color palette used as a fallback when a palette cannot be found.
 pub static DEFAULT_COLOR_PALETTE: LazyLock<ColorPalette> = LazyLock::new(|| ColorPalette {
@@ -32,9 +36,14 @@ pub static DEFAULT_COLOR_PALETTE: LazyLock<ColorPalette> = LazyLock::new(|| Colo
     }),
 });
 
-/// Interpolate between two color palettes.
+/// Interpolate between two color palettes, blending colors through `space`.
 #[must_use]
-pub fn interpolate_palettes(a: &ColorPalette, b: &ColorPalette, t: f64) -> ColorPalette {
+pub fn interpolate_palettes(
+    a: &ColorPalette,
+    b: &ColorPalette,
+    t: f64,
+    space: ColorInterpolation,
+) -> ColorPalette {
     let interpolate_desc = |a: Option<&ColorDescription>,
                             b: Option<&ColorDescription>,
                             t: f64|
@@ -42,7 +51,7 @@ pub fn interpolate_palettes(a: &ColorPalette, b: &ColorPalette, t: f64) -> Color
         match (a, b) {
             (Some(a_desc), Some(b_desc)) => match (&a_desc.color, &b_desc.color) {
                 (Some(a_color), Some(b_color)) => Some(ColorDescription {
-                    color: Some(a_color.lerp(b_color, t)),
+                    color: Some(a_color.interpolate(b_color, t, space)),
                 }),
                 _ => None,
             },
@@ -98,5 +107,46 @@ pub fn interpolated_scene_palette(project: &Project, scene_id: u64, system_t: u6
         .cloned()
         .unwrap_or_else(|| DEFAULT_COLOR_PALETTE.clone());
 
-    interpolate_palettes(&last_palette, &active_palette, color_palette_t)
+    interpolate_palettes(
+        &last_palette,
+        &active_palette,
+        color_palette_t,
+        scene.color_palette_interpolation(),
+    )
+}
+
+/// Calculate the color palette at `t` ms into a timecoded show, crossfading
+/// from each keyframe into the next with the keyframe's curve.
+#[allow(clippy::cast_precision_loss)]
+pub fn show_palette_at(keyframes: &[PaletteKeyframe], t: u64) -> ColorPalette {
+    let mut keyframes: Vec<(&PaletteKeyframe, &ColorPalette)> = keyframes
+        .iter()
+        .filter_map(|k| Some((k, k.color_palette.as_ref()?)))
+        .collect();
+    keyframes.sort_by_key(|(k, _)| k.t);
+
+    let next_index = keyframes.partition_point(|(k, _)| k.t <= t);
+    let (previous, next) = match (
+        next_index.checked_sub(1).map(|i| keyframes[i]),
+        keyframes.get(next_index).copied(),
+    ) {
+        (Some(previous), Some(next)) => (previous, next),
+        // Before the first or after the last keyframe.
+        (Some((_, palette)), None) | (None, Some((_, palette))) => return palette.clone(),
+        (None, None) => return DEFAULT_COLOR_PALETTE.clone(),
+    };
+
+    let progress = (t - previous.0.t) as f64 / (next.0.t - previous.0.t) as f64;
+    let progress = match previous.0.curve() {
+        Curve::Linear => progress,
+        Curve::Hold => return previous.1.clone(),
+        Curve::Eased => progress * progress * (3.0 - 2.0 * progress),
+    };
+
+    interpolate_palettes(
+        previous.1,
+        next.1,
+        progress,
+        previous.0.color_interpolation(),
+    )
 }
@@ -0,0 +1,356 @@
+use crate::proto::{
+    ColorPalette, FixtureState, ParkedValues, Project, QualifiedFixtureId, output::Output,
+    parked_values,
+};
+use crate::render::dmx_render_target::{DmxRenderTarget, UNIVERSE_SIZE};
+use crate::render::render::RenderError;
+use crate::render::render_target::RenderTarget;
+
+/// Returns the number of universes of a DMX output in the active patch, or
+/// `None` if there is no such output.
+fn dmx_universe_count(project: &Project, output_id: u64) -> Option<u32> {
+    match project
+        .patches
+        .get(&project.active_patch)?
+        .outputs
+        .get(&output_id)?
+        .output
+        .as_ref()?
+    {
+        Output::SerialDmxOutput(_) => Some(1),
+        Output::SacnDmxOutput(sacn) => Some(sacn.universe_count.max(1)),
+        Output::ArtnetDmxOutput(artnet) => Some(artnet.universe_count.max(1)),
+        _ => None,
+    }
+}
+
+/// Holds a raw channel of a DMX output at `value`.
+pub fn park_channel(
+    project: &mut Project,
+    output_id: u64,
+    universe: u32,
+    channel: u32,
+    value: u8,
+) -> Result<(), String> {
+    let Some(universe_count) = dmx_universe_count(project, output_id) else {
+        return Err(format!("Could not find DMX output {output_id}"));
+    };
+    if universe >= universe_count {
+        return Err(format!(
+            "Universe {universe} is outside of output {output_id}'s {universe_count} universes"
+        ));
+    }
+    if channel == 0 || channel as usize > UNIVERSE_SIZE {
+        return Err(format!("Channel {channel} is outside of the universe"));
+    }
+
+    unpark_channel(project, output_id, universe, channel);
+    project
+        .parked
+        .get_or_insert_with(ParkedValues::default)
+        .channels
+        .push(parked_values::Channel {
+            output_id,
+            universe,
+            channel,
+            value: u32::from(value),
+        });
+    Ok(())
+}
+
+/// Releases a parked raw channel. Returns whether the channel was parked.
+pub fn unpark_channel(project: &mut Project, output_id: u64, universe: u32, channel: u32) -> bool {
+    let Some(parked) = project.parked.as_mut() else {
+        return false;
+    };
+
+    let before = parked.channels.len();
+    parked
+        .channels
+        .retain(|c| (c.output_id, c.universe, c.channel) != (output_id, universe, channel));
+    parked.channels.len() != before
+}
+
+/// Holds a fixture at `state`, replacing any state it was already parked at.
+pub fn park_fixture(
+    project: &mut Project,
+    fixture_id: QualifiedFixtureId,
+    state: FixtureState,
+) -> Result<(), String> {
+    let exists = project
+        .patches
+        .get(&fixture_id.patch)
+        .and_then(|p| p.outputs.get(&fixture_id.output))
+        .and_then(|o| o.output.as_ref())
+        .is_some_and(|o| match o {
+            Output::SerialDmxOutput(serial) => serial.fixtures.contains_key(&fixture_id.fixture),
+            Output::SacnDmxOutput(sacn) => sacn.fixtures.contains_key(&fixture_id.fixture),
+            Output::ArtnetDmxOutput(artnet) => artnet.fixtures.contains_key(&fixture_id.fixture),
+            _ => false,
+        });
+    if !exists {
+        return Err(format!(
+            "Could not find DMX fixture {} on output {} of patch {}",
+            fixture_id.fixture, fixture_id.output, fixture_id.patch
+        ));
+    }
+
+    unpark_fixture(project, &fixture_id);
+    project
+        .parked
+        .get_or_insert_with(ParkedValues::default)
+        .fixtures
+        .push(parked_values::Fixture {
+            fixture_id: Some(fixture_id),
+            state: Some(state),
+        });
+    Ok(())
+}
+
+/// Releases a parked fixture. Returns whether the fixture was parked.
+pub fn unpark_fixture(project: &mut Project, fixture_id: &QualifiedFixtureId) -> bool {
+    let Some(parked) = project.parked.as_mut() else {
+        return false;
+    };
+
+    let before = parked.fixtures.len();
+    parked
+        .fixtures
+        .retain(|f| f.fixture_id.as_ref() != Some(fixture_id));
+    parked.fixtures.len() != before
+}
+
+/// Releases every parked channel and fixture.
+pub fn unpark_all(project: &mut Project) {
+    project.parked = None;
+}
+
+/// Applies everything parked on a DMX output over what was rendered for it.
+/// Returns what parked states could not apply.
+pub(crate) fn apply_parked(
+    project: &Project,
+    output_id: u64,
+    render_target: &mut DmxRenderTarget,
+    color_palette: &ColorPalette,
+) -> Vec<RenderError> {
+    let mut errors = Vec::new();
+    let Some(parked) = &project.parked else {
+        return errors;
+    };
+
+    for fixture in &parked.fixtures {
+        if let (Some(fixture_id), Some(state)) = (&fixture.fixture_id, &fixture.state)
+            && fixture_id.patch == project.active_patch
+            && fixture_id.output == output_id
+        {
+            errors.extend(
+                render_target
+                    .apply_state(fixture_id, state, color_palette)
+                    .err(),
+            );
+        }
+    }
+
+    for channel in parked.channels.iter().filter(|c| c.output_id == output_id) {
+        render_target.set_channel(
+            channel.universe as usize,
+            channel.channel as usize,
+            f64::from(channel.value) / 255.0,
+        );
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::proto::{
+        DmxFixtureDefinition, PaletteColor, Patch, PhysicalDmxFixture, SacnDmxOutput,
+        dmx_fixture_definition::{
+            Channel, Mode,
+            channel::{AmountMapping, Mapping},
+        },
+        fixture_state::LightColor,
+    };
+
+    const OUTPUT: u64 = 10;
+
+    fn fixture_id() -> QualifiedFixtureId {
+        QualifiedFixtureId {
+            patch: 1,
+            output: OUTPUT,
+            fixture: 100,
+        }
+    }
+
+    fn project() -> Project {
+        let fixture = PhysicalDmxFixture {
+            fixture_definition_id: 1,
+            fixture_mode: "dimmer".to_string(),
+            channel_offset: 4,
+            ..Default::default()
+        };
+        Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(
+                        OUTPUT,
+                        crate::proto::Output {
+                            output: Some(Output::SacnDmxOutput(SacnDmxOutput {
+                                universe_count: 2,
+                                fixtures: HashMap::from([(100, fixture)]),
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn definitions() -> HashMap<u64, DmxFixtureDefinition> {
+        let mut mode = Mode::default();
+        mode.channels.insert(
+            1,
+            Channel {
+                r#type: "dimmer".to_string(),
+                mapping: Some(Mapping::AmountMapping(AmountMapping {
+                    min_value: 0,
+                    max_value: 255,
+                })),
+                ..Default::default()
+            },
+        );
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("dimmer".to_string(), mode);
+        HashMap::from([(1, definition)])
+    }
+
+    fn fixtures(project: &Project) -> &HashMap<u64, PhysicalDmxFixture> {
+        match project.patches[&1].outputs[&OUTPUT].output.as_ref() {
+            Some(Output::SacnDmxOutput(sacn)) => &sacn.fixtures,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn parked_values_override_the_render() {
+        let mut project = project();
+        park_fixture(
+            &mut project,
+            fixture_id(),
+            FixtureState {
+                dimmer: Some(0.4),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        park_channel(&mut project, OUTPUT, 1, 512, 77).unwrap();
+
+        let definitions = definitions();
+        let mut render_target = DmxRenderTarget::new(fixtures(&project), &definitions, 2);
+        render_target
+            .apply_state(
+                &fixture_id(),
+                &FixtureState {
+                    dimmer: Some(1.0),
+                    ..Default::default()
+                },
+                &ColorPalette::default(),
+            )
+            .unwrap();
+
+        let errors = apply_parked(
+            &project,
+            OUTPUT,
+            &mut render_target,
+            &ColorPalette::default(),
+        );
+        assert!(errors.is_empty());
+
+        let universes = render_target.get_universes();
+        assert_eq!(universes[0][4], 102);
+        assert_eq!(universes[1][511], 77);
+    }
+
+    #[test]
+    fn parked_fixtures_report_missing_palette_colors() {
+        let mut project = project();
+        park_fixture(
+            &mut project,
+            fixture_id(),
+            FixtureState {
+                light_color: Some(LightColor::PaletteColor(
+                    PaletteColor::PalettePrimary.into(),
+                )),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let definitions = definitions();
+        let mut render_target = DmxRenderTarget::new(fixtures(&project), &definitions, 2);
+        let errors = apply_parked(
+            &project,
+            OUTPUT,
+            &mut render_target,
+            &ColorPalette::default(),
+        );
+
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(matches!(
+            errors[0],
+            RenderError::MissingPaletteColor {
+                color: "primary",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parking_again_replaces_the_parked_value() {
+        let mut project = project();
+        park_channel(&mut project, OUTPUT, 0, 1, 10).unwrap();
+        park_channel(&mut project, OUTPUT, 0, 1, 20).unwrap();
+
+        let channels = &project.parked.as_ref().unwrap().channels;
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].value, 20);
+    }
+
+    #[test]
+    fn unparking_releases_the_value() {
+        let mut project = project();
+        park_channel(&mut project, OUTPUT, 0, 1, 10).unwrap();
+        park_fixture(&mut project, fixture_id(), FixtureState::default()).unwrap();
+
+        assert!(unpark_channel(&mut project, OUTPUT, 0, 1));
+        assert!(!unpark_channel(&mut project, OUTPUT, 0, 1));
+        assert!(unpark_fixture(&mut project, &fixture_id()));
+
+        let parked = project.parked.as_ref().unwrap();
+        assert!(parked.channels.is_empty());
+        assert!(parked.fixtures.is_empty());
+    }
+
+    #[test]
+    fn rejects_addresses_outside_the_output() {
+        let mut project = project();
+        assert!(park_channel(&mut project, OUTPUT, 2, 1, 10).is_err());
+        assert!(park_channel(&mut project, OUTPUT, 0, 0, 10).is_err());
+        assert!(park_channel(&mut project, OUTPUT, 0, 513, 10).is_err());
+        assert!(park_channel(&mut project, 99, 0, 1, 10).is_err());
+
+        let mut missing = fixture_id();
+        missing.fixture = 999;
+        assert!(park_fixture(&mut project, missing, FixtureState::default()).is_err());
+        assert!(project.parked.is_none());
+    }
+}
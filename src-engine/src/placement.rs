@@ -0,0 +1,198 @@
+use crate::proto::{
+    FixturePlacement, StagePoint,
+    dmx_fixture_definition::{
+        Mode,
+        channel::{AngleMapping, Mapping},
+    },
+};
+
+type Vector = [f64; 3];
+
+fn rotate_x([x, y, z]: Vector, degrees: f64) -> Vector {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [x, y * cos - z * sin, y * sin + z * cos]
+}
+
+fn rotate_y([x, y, z]: Vector, degrees: f64) -> Vector {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [x * cos + z * sin, y, -x * sin + z * cos]
+}
+
+fn rotate_z([x, y, z]: Vector, degrees: f64) -> Vector {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [x * cos - y * sin, x * sin + y * cos, z]
+}
+
+fn angle_mapping<'a>(mode: &'a Mode, channel_type: &str) -> Option<&'a AngleMapping> {
+    mode.channels
+        .values()
+        .filter(|c| c.r#type == channel_type)
+        .find_map(|c| match &c.mapping {
+            Some(Mapping::AngleMapping(mapping)) => Some(mapping),
+            _ => None,
+        })
+}
+
+fn contains(mapping: AngleMapping, degrees: f64) -> bool {
+    let (min, max) = (
+        f64::from(mapping.min_degrees),
+        f64::from(mapping.max_degrees),
+    );
+    degrees >= min.min(max) - 1e-9 && degrees <= min.max(max) + 1e-9
+}
+
+/// Returns the pan and tilt in degrees that aim a placed fixture at `focus`.
+/// Of the reachable solutions, the one nearest the middle of the pan range is
+/// used.
+pub fn focus_pan_tilt(
+    placement: &FixturePlacement,
+    mode: &Mode,
+    focus: &StagePoint,
+) -> Result<(f64, f64), String> {
+    let (Some(pan), Some(tilt)) = (angle_mapping(mode, "pan"), angle_mapping(mode, "tilt")) else {
+        return Err(format!(
+            "Mode \"{}\" does not have both pan and tilt channels",
+            mode.name
+        ));
+    };
+
+    let position = placement.position.unwrap_or_default();
+    let world = [
+        focus.x - position.x,
+        focus.y - position.y,
+        focus.z - position.z,
+    ];
+    let length = world.iter().map(|c| c * c).sum::<f64>().sqrt();
+    if length < 1e-9 {
+        return Err("Cannot focus a fixture on its own position".to_string());
+    }
+
+    // Undo the fixture's rotation to get the beam direction it has to make.
+    let [x, y, z] = rotate_z(
+        rotate_x(
+            rotate_y(world.map(|c| c / length), -placement.yaw),
+            -placement.pitch,
+        ),
+        -placement.roll,
+    );
+    let base_tilt = y.clamp(-1.0, 1.0).acos().to_degrees();
+    // Any pan works when aiming along the pan axis, so stay at home.
+    let base_pan = if x.hypot(z) < 1e-9 {
+        0.0
+    } else {
+        x.atan2(z).to_degrees()
+    };
+
+    // Panning half a turn and tilting the other way reaches the same point,
+    // as does panning any number of whole turns.
+    let pan_center = f64::midpoint(f64::from(pan.min_degrees), f64::from(pan.max_degrees));
+    [(base_pan, base_tilt), (base_pan + 180.0, -base_tilt)]
+        .into_iter()
+        .flat_map(|(p, t)| (-2..=2).map(move |turns| (p + f64::from(turns) * 360.0, t)))
+        .filter(|&(p, t)| contains(*pan, p) && contains(*tilt, t))
+        .min_by(|a, b| {
+            (a.0 - pan_center)
+                .abs()
+                .total_cmp(&(b.0 - pan_center).abs())
+        })
+        .ok_or_else(|| {
+            format!(
+                "Point ({}, {}, {}) is out of reach of mode \"{}\"",
+                focus.x, focus.y, focus.z, mode.name
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::dmx_fixture_definition::Channel;
+
+    fn mode(pan: (i32, i32), tilt: (i32, i32)) -> Mode {
+        let mut mode = Mode {
+            name: "moving head".to_string(),
+            ..Default::default()
+        };
+        for (index, channel_type, (min_degrees, max_degrees)) in
+            [(1, "pan", pan), (2, "tilt", tilt)]
+        {
+            mode.channels.insert(
+                index,
+                Channel {
+                    r#type: channel_type.to_string(),
+                    mapping: Some(Mapping::AngleMapping(AngleMapping {
+                        min_degrees,
+                        max_degrees,
+                    })),
+                    ..Default::default()
+                },
+            );
+        }
+        mode
+    }
+
+    fn point(x: f64, y: f64, z: f64) -> StagePoint {
+        StagePoint { x, y, z }
+    }
+
+    fn assert_aims_at(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-6 && (actual.1 - expected.1).abs() < 1e-6,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn floor_fixtures_aim_up_and_out() {
+        let placement = FixturePlacement::default();
+        let mode = mode((-270, 270), (-135, 135));
+
+        let up = focus_pan_tilt(&placement, &mode, &point(0.0, 10.0, 0.0)).unwrap();
+        assert_aims_at(up, (0.0, 0.0));
+
+        let audience = focus_pan_tilt(&placement, &mode, &point(0.0, 0.0, 5.0)).unwrap();
+        assert_aims_at(audience, (0.0, 90.0));
+
+        let house_right = focus_pan_tilt(&placement, &mode, &point(5.0, 0.0, 0.0)).unwrap();
+        assert_aims_at(house_right, (90.0, 90.0));
+    }
+
+    #[test]
+    fn hung_fixtures_aim_down() {
+        let placement = FixturePlacement {
+            position: Some(point(2.0, 5.0, 0.0)),
+            roll: 180.0,
+            ..Default::default()
+        };
+        let mode = mode((-270, 270), (-135, 135));
+
+        let below = focus_pan_tilt(&placement, &mode, &point(2.0, 0.0, 0.0)).unwrap();
+        assert_aims_at(below, (0.0, 0.0));
+
+        let downstage = focus_pan_tilt(&placement, &mode, &point(2.0, 0.0, 5.0)).unwrap();
+        assert_aims_at(downstage, (0.0, 45.0));
+    }
+
+    #[test]
+    fn picks_a_solution_within_the_ranges() {
+        let placement = FixturePlacement::default();
+        // Tilt only leans one way, so reaching behind takes half a turn of pan.
+        let mode = mode((0, 540), (0, 270));
+
+        let behind = focus_pan_tilt(&placement, &mode, &point(0.0, 0.0, -5.0)).unwrap();
+        assert_aims_at(behind, (180.0, 90.0));
+
+        let front = focus_pan_tilt(&placement, &mode, &point(0.0, 0.0, 5.0)).unwrap();
+        assert_aims_at(front, (360.0, 90.0));
+    }
+
+    #[test]
+    fn rejects_points_out_of_reach() {
+        let placement = FixturePlacement::default();
+        let mode = mode((-270, 270), (-10, 10));
+
+        assert!(focus_pan_tilt(&placement, &mode, &point(0.0, 0.0, 5.0)).is_err());
+        assert!(focus_pan_tilt(&placement, &mode, &point(0.0, 0.0, 0.0)).is_err());
+        assert!(focus_pan_tilt(&placement, &Mode::default(), &point(0.0, 1.0, 0.0)).is_err());
+    }
+}
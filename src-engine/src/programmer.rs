@@ -0,0 +1,515 @@
+//! The programmer: live values set on a selection of fixtures and groups.
+//!
+//! Values in the programmer track, so setting the dimmer and then the color of
+//! a selection leaves both set. They render on top of the render mode until
+//! the programmer is cleared, and can be recorded into a scene tile or a cue.
+//!
+//! The programmer is not part of the project, so saves, undo and offline
+//! renders never see it. The live one is [`PROGRAMMER_REF`], which the live
+//! renders hand to the renderer.
+
+use std::sync::{LazyLock, Mutex};
+
+use crate::{
+    proto::{
+        ColorPalette, Effect, FixtureState, OutputTarget, Programmer, Project, TargetedEffect,
+        cue_list::cue::TrackedState,
+        effect::{Effect as EffectKind, StaticEffect},
+        programmer::{Entry, Selection},
+    },
+    render::{render::RenderError, render_target::RenderTarget, util::apply_state},
+};
+
+/// The live programmer, rendered over the live render mode.
+pub static PROGRAMMER_REF: LazyLock<Mutex<Programmer>> =
+    LazyLock::new(|| Mutex::new(Programmer::default()));
+
+/// Runs `f` on the live programmer.
+pub fn with_programmer<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut Programmer) -> Result<T, String>,
+{
+    let mut programmer = PROGRAMMER_REF
+        .lock()
+        .map_err(|e| format!("Failed to lock programmer: {e}"))?;
+    f(&mut programmer)
+}
+
+/// Replaces the fixtures and groups that attribute changes apply to.
+pub fn select(programmer: &mut Programmer, targets: Vec<OutputTarget>) {
+    programmer.selection = Some(Selection { targets });
+}
+
+/// Sets the attributes present in `state` on every selected target, keeping
+/// any attributes set earlier that `state` leaves out.
+pub fn set_state(programmer: &mut Programmer, state: &FixtureState) -> Result<(), String> {
+    let targets = programmer
+        .selection
+        .as_ref()
+        .map(|s| s.targets.as_slice())
+        .unwrap_or_default();
+    if targets.is_empty() {
+        return Err("Nothing is selected in the programmer".to_string());
+    }
+
+    for target in targets {
+        // Move the entry to the end so it wins over overlapping targets.
+        let mut merged = match programmer
+            .entries
+            .iter()
+            .position(|e| e.output_target.as_ref() == Some(target))
+        {
+            Some(index) => programmer.entries.remove(index).state.unwrap_or_default(),
+            None => FixtureState::default(),
+        };
+        merge_state(&mut merged, state);
+        programmer.entries.push(Entry {
+            output_target: Some(target.clone()),
+            state: Some(merged),
+        });
+    }
+    Ok(())
+}
+
+/// Clears every value and the selection.
+pub fn clear(programmer: &mut Programmer) {
+    *programmer = Programmer::default();
+}
+
+/// Returns the values set in the programmer, or an error if there are none.
+fn entries(programmer: &Programmer) -> Result<Vec<(OutputTarget, FixtureState)>, String> {
+    let entries: Vec<_> = programmer
+        .entries
+        .iter()
+        .filter_map(|e| Some((e.output_target.clone()?, e.state.clone()?)))
+        .collect();
+    if entries.is_empty() {
+        return Err("The programmer has no values to record".to_string());
+    }
+    Ok(entries)
+}
+
+/// Records the programmer into a scene tile as static effects, merging into
+/// static effects the tile already has on the same targets.
+pub fn record_to_tile(
+    project: &mut Project,
+    programmer: &Programmer,
+    scene_id: u64,
+    tile_id: u64,
+) -> Result<(), String> {
+    let entries = entries(programmer)?;
+    let tile = project
+        .scenes
+        .get_mut(&scene_id)
+        .ok_or_else(|| format!("Could not find scene {scene_id}"))?
+        .tile_map
+        .iter_mut()
+        .find(|t| t.id == tile_id)
+        .and_then(|t| t.tile.as_mut())
+        .ok_or_else(|| format!("Could not find tile {tile_id} in scene {scene_id}"))?;
+
+    for (output_target, state) in entries {
+        let existing = tile.targeted_effects.iter_mut().find_map(|e| match e {
+            TargetedEffect {
+                effect:
+                    Some(Effect {
+                        effect: Some(EffectKind::StaticEffect(StaticEffect { state, .. })),
+                    }),
+                output_target: Some(target),
+            } if *target == output_target => Some(state.get_or_insert_with(FixtureState::default)),
+            _ => None,
+        });
+        match existing {
+            Some(existing) => merge_state(existing, &state),
+            None => tile.targeted_effects.push(TargetedEffect {
+                effect: Some(Effect {
+                    effect: Some(EffectKind::StaticEffect(StaticEffect {
+                        state: Some(state),
+                    })),
+                }),
+                output_target: Some(output_target),
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// Records the programmer into a cue as tracked states, merging into states
+/// the cue already has on the same targets.
+pub fn record_to_cue(
+    project: &mut Project,
+    programmer: &Programmer,
+    cue_list_id: u64,
+    cue_id: u64,
+) -> Result<(), String> {
+    let entries = entries(programmer)?;
+    let cue = project
+        .cue_lists
+        .get_mut(&cue_list_id)
+        .ok_or_else(|| format!("Cue list {cue_list_id} not found"))?
+        .cues
+        .iter_mut()
+        .find(|c| c.id == cue_id)
+        .ok_or_else(|| format!("Cue {cue_id} not found in cue list {cue_list_id}"))?;
+
+    for (output_target, state) in entries {
+        match cue
+            .tracked_states
+            .iter_mut()
+            .find(|s| s.output_target.as_ref() == Some(&output_target))
+        {
+            Some(existing) => {
+                merge_state(
+                    existing.state.get_or_insert_with(FixtureState::default),
+                    &state,
+                );
+            }
+            None => cue.tracked_states.push(TrackedState {
+                output_target: Some(output_target),
+                state: Some(state),
+            }),
+        }
+    }
+    Ok(())
+}
+
+/// Copies the attributes set in `from` over `into`. Every field is named, so
+/// a field added to `FixtureState` has to be merged here before this builds.
+fn merge_state(into: &mut FixtureState, from: &FixtureState) {
+    let FixtureState {
+        channels,
+        light_color,
+        dimmer,
+        pan,
+        tilt,
+        width,
+        height,
+        zoom,
+        speed,
+        strobe,
+        focus,
+        wled_effect,
+        wled_palette,
+        send_palette,
+        visualizer_ids,
+    } = from;
+
+    for channel in channels {
+        into.channels.retain(|c| c.index != channel.index);
+        into.channels.push(*channel);
+    }
+    if light_color.is_some() {
+        into.light_color.clone_from(light_color);
+    }
+    if focus.is_some() {
+        into.focus.clone_from(focus);
+    }
+    if !visualizer_ids.is_empty() {
+        into.visualizer_ids.clone_from(visualizer_ids);
+    }
+    into.dimmer = dimmer.or(into.dimmer);
+    into.pan = pan.or(into.pan);
+    into.tilt = tilt.or(into.tilt);
+    into.width = width.or(into.width);
+    into.height = height.or(into.height);
+    into.zoom = zoom.or(into.zoom);
+    into.speed = speed.or(into.speed);
+    into.strobe = strobe.or(into.strobe);
+    into.wled_effect = wled_effect.or(into.wled_effect);
+    into.wled_palette = wled_palette.or(into.wled_palette);
+    into.send_palette = send_palette.or(into.send_palette);
+}
+
+/// Applies the programmer's values over what was rendered.
+pub(crate) fn apply_programmer<T: RenderTarget<T>>(
+    project: &Project,
+    programmer: &Programmer,
+    render_target: &mut T,
+    color_palette: &ColorPalette,
+) -> Result<(), RenderError> {
+    let mut errors = Vec::new();
+    for entry in &programmer.entries {
+        if let (Some(output_target), Some(state)) = (&entry.output_target, &entry.state)
+            && let Err(error) =
+                apply_state(project, render_target, output_target, state, color_palette)
+        {
+            errors.push(error);
+        }
+    }
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        audio::AudioAnalysis,
+        proto::{
+            Color, CueList, DmxFixtureDefinition, FixtureDefinitions, Patch, PhysicalDmxFixture,
+            QualifiedFixtureId, RenderMode, SacnDmxOutput, Scene, StagePoint,
+            cue_list::Cue,
+            dmx_fixture_definition::{
+                Channel, Mode,
+                channel::{AmountMapping, Mapping},
+            },
+            fixture_state,
+            output::Output,
+            output_target::{self, FixtureMapping},
+            render_mode,
+            scene::{Tile, TileMap},
+        },
+        render::render::render_dmx_in_mode,
+    };
+
+    const OUTPUT: u64 = 10;
+
+    fn fixture() -> OutputTarget {
+        OutputTarget {
+            output: Some(output_target::Output::Fixtures(FixtureMapping {
+                fixture_ids: vec![QualifiedFixtureId {
+                    patch: 1,
+                    output: OUTPUT,
+                    fixture: 100,
+                }],
+            })),
+        }
+    }
+
+    fn everything() -> OutputTarget {
+        OutputTarget {
+            output: Some(output_target::Output::Group(0)),
+        }
+    }
+
+    fn dimmer(level: f64) -> FixtureState {
+        FixtureState {
+            dimmer: Some(level),
+            ..Default::default()
+        }
+    }
+
+    fn project() -> Project {
+        let mut mode = Mode::default();
+        mode.channels.insert(
+            1,
+            Channel {
+                r#type: "dimmer".to_string(),
+                mapping: Some(Mapping::AmountMapping(AmountMapping {
+                    min_value: 0,
+                    max_value: 255,
+                })),
+                ..Default::default()
+            },
+        );
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("dimmer".to_string(), mode);
+
+        let physical_fixture = PhysicalDmxFixture {
+            fixture_definition_id: 1,
+            fixture_mode: "dimmer".to_string(),
+            ..Default::default()
+        };
+        Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(
+                        OUTPUT,
+                        crate::proto::Output {
+                            output: Some(Output::SacnDmxOutput(SacnDmxOutput {
+                                fixtures: HashMap::from([(100, physical_fixture)]),
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            fixture_definitions: Some(FixtureDefinitions {
+                dmx_fixture_definitions: HashMap::from([(1, definition)]),
+            }),
+            scenes: HashMap::from([(
+                1,
+                Scene {
+                    tile_map: vec![TileMap {
+                        id: 5,
+                        tile: Some(Tile {
+                            targeted_effects: vec![TargetedEffect {
+                                effect: Some(Effect {
+                                    effect: Some(EffectKind::StaticEffect(StaticEffect {
+                                        state: Some(FixtureState {
+                                            pan: Some(0.5),
+                                            ..dimmer(0.1)
+                                        }),
+                                    })),
+                                }),
+                                output_target: Some(fixture()),
+                            }],
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )]),
+            active_scene: 1,
+            cue_lists: HashMap::from([(
+                1,
+                CueList {
+                    cues: vec![Cue {
+                        id: 3,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn render_dimmer(project: &Project, programmer: &Programmer, mode: render_mode::Mode) -> u8 {
+        let render_mode = RenderMode { mode: Some(mode) };
+        let rendered = render_dmx_in_mode(
+            project,
+            &render_mode,
+            OUTPUT,
+            0,
+            0,
+            &AudioAnalysis::default(),
+            Some(programmer),
+            None,
+        )
+        .unwrap();
+        assert!(rendered.error.is_none(), "{:?}", rendered.error);
+        rendered.frame[0][0]
+    }
+
+    #[test]
+    fn values_track_and_render_over_the_render_mode() {
+        let project = project();
+        let mut programmer = Programmer::default();
+        let cue_list = render_mode::Mode::CueList(render_mode::CueList { cue_list_id: 1 });
+        assert_eq!(render_dimmer(&project, &programmer, cue_list.clone()), 0);
+
+        select(&mut programmer, vec![fixture()]);
+        set_state(&mut programmer, &dimmer(0.2)).unwrap();
+        select(&mut programmer, vec![everything()]);
+        set_state(&mut programmer, &dimmer(0.6)).unwrap();
+        assert_eq!(render_dimmer(&project, &programmer, cue_list.clone()), 153);
+
+        // Setting the fixture again keeps its dimmer and puts it back on top.
+        select(&mut programmer, vec![fixture()]);
+        set_state(
+            &mut programmer,
+            &FixtureState {
+                tilt: Some(0.3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let entries = &programmer.entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].state,
+            Some(FixtureState {
+                tilt: Some(0.3),
+                ..dimmer(0.2)
+            })
+        );
+        assert_eq!(render_dimmer(&project, &programmer, cue_list.clone()), 51);
+
+        // Blackout stays dark.
+        let blackout = render_mode::Mode::Blackout(render_mode::Blackout {});
+        assert_eq!(render_dimmer(&project, &programmer, blackout), 0);
+
+        clear(&mut programmer);
+        assert_eq!(render_dimmer(&project, &programmer, cue_list), 0);
+    }
+
+    #[test]
+    fn setting_values_requires_a_selection() {
+        let mut project = project();
+        let mut programmer = Programmer::default();
+        assert!(set_state(&mut programmer, &dimmer(1.0)).is_err());
+        select(&mut programmer, Vec::new());
+        assert!(set_state(&mut programmer, &dimmer(1.0)).is_err());
+        assert!(record_to_cue(&mut project, &programmer, 1, 3).is_err());
+    }
+
+    #[test]
+    fn records_into_tiles_and_cues() {
+        let mut project = project();
+        let mut programmer = Programmer::default();
+        select(&mut programmer, vec![fixture()]);
+        set_state(&mut programmer, &dimmer(0.8)).unwrap();
+
+        record_to_tile(&mut project, &programmer, 1, 5).unwrap();
+        let tile = project.scenes[&1].tile_map[0].tile.as_ref().unwrap();
+        assert_eq!(tile.targeted_effects.len(), 1);
+        assert!(matches!(
+            &tile.targeted_effects[0].effect,
+            Some(Effect {
+                effect: Some(EffectKind::StaticEffect(StaticEffect { state: Some(state) })),
+            }) if *state == FixtureState { pan: Some(0.5), ..dimmer(0.8) }
+        ));
+
+        record_to_cue(&mut project, &programmer, 1, 3).unwrap();
+        let cue = &project.cue_lists[&1].cues[0];
+        assert_eq!(
+            cue.tracked_states,
+            vec![TrackedState {
+                output_target: Some(fixture()),
+                state: Some(dimmer(0.8)),
+            }]
+        );
+
+        assert!(record_to_tile(&mut project, &programmer, 1, 6).is_err());
+        assert!(record_to_cue(&mut project, &programmer, 2, 3).is_err());
+    }
+
+    /// Lists every field, so adding one to `FixtureState` breaks this test
+    /// until it is set here and merged by `merge_state`.
+    #[test]
+    fn merging_copies_every_field() {
+        let from = FixtureState {
+            channels: vec![fixture_state::Channel {
+                index: 3,
+                value: 40,
+            }],
+            light_color: Some(fixture_state::LightColor::Color(Color {
+                red: 1.0,
+                green: 0.5,
+                blue: 0.25,
+                white: None,
+            })),
+            dimmer: Some(0.1),
+            pan: Some(0.2),
+            tilt: Some(0.3),
+            width: Some(0.4),
+            height: Some(0.5),
+            zoom: Some(0.6),
+            speed: Some(0.7),
+            strobe: Some(0.8),
+            focus: Some(StagePoint {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            }),
+            wled_effect: Some(4),
+            wled_palette: Some(5),
+            send_palette: Some(true),
+            visualizer_ids: vec![6],
+        };
+        let mut into = FixtureState::default();
+        merge_state(&mut into, &from);
+        assert_eq!(into, from);
+
+        // Nothing set leaves everything as it was.
+        merge_state(&mut into, &FixtureState::default());
+        assert_eq!(into, from);
+    }
+}
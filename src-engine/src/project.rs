@@ -427,14 +427,15 @@ pub fn new_project() -> Result<Vec<u8>, String> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // `PROJECT_STATE` is a process-global singleton, so tests that mutate it must
-    // not run concurrently with each other. Serialize them through this guard.
+    // not run concurrently with each other, in this module or any other.
+    // Serialize them through this guard.
     static STATE_LOCK: Mutex<()> = Mutex::new(());
 
-    fn lock_state() -> std::sync::MutexGuard<'static, ()> {
+    pub(crate) fn lock_state() -> std::sync::MutexGuard<'static, ()> {
         STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
     }
 
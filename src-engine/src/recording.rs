@@ -0,0 +1,708 @@
+//! Offline DMX renders, captures from another console and the frame log they
+//! are stored in.
+//!
+//! A frame log is a little-endian binary file:
+//!
+//! ```text
+//! magic         b"DMXREC"
+//! version       u16 (1)
+//! fps           u32
+//! stream_count  u32
+//! streams       stream_count × { output_id u64, first_universe u32, universe_count u32 }
+//! frames        until EOF, each { t_ms u64, universes × 512 bytes }
+//! ```
+//!
+//! Every frame holds the universes of every stream, in stream order, so frames
+//! have a fixed size and can be seeked to directly.
+//!
+//! A capture has one stream per run of consecutive protocol universes, with
+//! an `output_id` of zero.
+//!
+//! Frame logs are stored in the CAS. Hosts decode the ones the project refers
+//! to with [`load`] so playback can find them by digest.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Write},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use crate::{
+    audio::AudioAnalysis,
+    dmx_input::DmxInputState,
+    proto::{Project, RenderMode, output::Output},
+    render::{
+        dmx_render_target::UNIVERSE_SIZE,
+        render::{RenderError, Rendered, render_dmx_in_mode},
+    },
+};
+
+const MAGIC: &[u8; 6] = b"DMXREC";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 2 + 4 + 4;
+const STREAM_SIZE: usize = 8 + 4 + 4;
+
+/// The universes recorded from one output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stream {
+    /// The output of the project the stream was rendered from, or zero for
+    /// universes captured from another console.
+    pub output_id: u64,
+    /// Protocol universe of the output's first universe. Zero for serial
+    /// outputs.
+    pub first_universe: u32,
+    pub universe_count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Milliseconds since the start of the recording.
+    pub t_ms: u64,
+    /// The universes of every stream, in stream order.
+    pub universes: Vec<[u8; UNIVERSE_SIZE]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    pub fps: u32,
+    pub streams: Vec<Stream>,
+    pub frames: Vec<Frame>,
+}
+
+/// Frame logs decoded by [`load`], keyed by CAS digest.
+static LOADED: LazyLock<Mutex<HashMap<String, Arc<Recording>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Decodes the frame log stored in the CAS under `digest` so it can be played
+/// back.
+pub fn load(digest: &str, bytes: &[u8]) -> Result<(), String> {
+    let recording = Recording::decode(bytes)?;
+    LOADED
+        .lock()
+        .map_err(|e| format!("Failed to lock recordings: {e}"))?
+        .insert(digest.to_string(), Arc::new(recording));
+    Ok(())
+}
+
+/// Returns the frame log loaded under `digest`, if any.
+#[must_use]
+pub fn loaded(digest: &str) -> Option<Arc<Recording>> {
+    LOADED.lock().ok()?.get(digest).cloned()
+}
+
+/// How an offline render samples time.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordingOptions {
+    pub fps: u32,
+    pub duration_ms: u64,
+    /// The `system_t` of the first frame.
+    pub start_t: u64,
+}
+
+/// Renders every DMX output of the active patch in `render_mode` at a fixed
+/// frame rate, through the same path as live output.
+///
+/// Frames are handed to `out` as they are rendered, so long renders are never
+/// held in memory. Audio-reactive effects see silence. Errors in parts of a
+/// frame do not stop the render; each distinct one is returned alongside the
+/// number of frames written.
+pub fn render_recording(
+    project: &Project,
+    render_mode: &RenderMode,
+    options: RecordingOptions,
+    out: &mut impl FrameWriter,
+) -> Result<Rendered<u64>, String> {
+    if options.fps == 0 {
+        return Err("Frame rate must be at least 1 fps".to_string());
+    }
+    let patch = project
+        .patches
+        .get(&project.active_patch)
+        .ok_or_else(|| format!("Active patch {} not found", project.active_patch))?;
+
+    let mut streams: Vec<Stream> = patch
+        .outputs
+        .iter()
+        .filter_map(|(output_id, output)| {
+            let first_universe = match output.output.as_ref()? {
+                Output::SerialDmxOutput(_) => 0,
+                Output::SacnDmxOutput(sacn) => sacn.universe,
+                Output::ArtnetDmxOutput(artnet) => artnet.universe,
+                Output::WledOutput(_) | Output::DdpOutput(_) => return None,
+            };
+            Some(Stream {
+                output_id: *output_id,
+                first_universe,
+                universe_count: 0,
+            })
+        })
+        .collect();
+    streams.sort_unstable_by_key(|s| s.output_id);
+
+    let audio_analysis = AudioAnalysis::default();
+    let frame_count = (options.duration_ms * u64::from(options.fps)).div_ceil(1000);
+    let mut errors: Vec<RenderError> = Vec::new();
+    for index in 0..frame_count {
+        let t_ms = index * 1000 / u64::from(options.fps);
+        let mut universes = Vec::new();
+        for stream in &mut streams {
+            #[allow(clippy::cast_possible_truncation)]
+            let rendered = render_dmx_in_mode(
+                project,
+                render_mode,
+                stream.output_id,
+                options.start_t + t_ms,
+                index as u32,
+                &audio_analysis,
+                None,
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+
+            #[allow(clippy::cast_possible_truncation)]
+            let universe_count = rendered.frame.len() as u32;
+            stream.universe_count = universe_count;
+            universes.extend(rendered.frame);
+
+            if let Some(error) = rendered.error {
+                let message = error.to_string();
+                if !errors.iter().any(|e| e.to_string() == message) {
+                    errors.push(error);
+                }
+            }
+        }
+        // Universe counts are only known once the first frame is rendered.
+        if index == 0 {
+            out.start(options.fps, &streams)
+                .map_err(|e| format!("Failed to write recording: {e}"))?;
+        }
+        out.write_frame(&Frame { t_ms, universes })
+            .map_err(|e| format!("Failed to write recording: {e}"))?;
+    }
+    if frame_count == 0 {
+        out.start(options.fps, &streams)
+            .map_err(|e| format!("Failed to write recording: {e}"))?;
+    }
+    out.finish()
+        .map_err(|e| format!("Failed to write recording: {e}"))?;
+
+    Ok(Rendered {
+        frame: frame_count,
+        error: RenderError::from_errors(errors),
+    })
+}
+
+/// The streams a capture from another console records: the universes the
+/// sACN and Art-Net outputs of the active patch send, which are the ones
+/// playback can send back out.
+pub fn capture_streams(project: &Project) -> Result<Vec<Stream>, String> {
+    let patch = project
+        .patches
+        .get(&project.active_patch)
+        .ok_or_else(|| format!("Active patch {} not found", project.active_patch))?;
+
+    let universes: BTreeSet<u32> = patch
+        .outputs
+        .values()
+        .filter_map(|output| match output.output.as_ref()? {
+            Output::SacnDmxOutput(sacn) => Some((sacn.universe, sacn.universe_count)),
+            Output::ArtnetDmxOutput(artnet) => Some((artnet.universe, artnet.universe_count)),
+            _ => None,
+        })
+        .flat_map(|(first, count)| first..first.saturating_add(count.max(1)))
+        .collect();
+
+    let mut streams: Vec<Stream> = Vec::new();
+    for universe in universes {
+        match streams.last_mut() {
+            Some(stream) if stream.first_universe + stream.universe_count == universe => {
+                stream.universe_count += 1;
+            }
+            _ => streams.push(Stream {
+                output_id: 0,
+                first_universe: universe,
+                universe_count: 1,
+            }),
+        }
+    }
+    Ok(streams)
+}
+
+/// Samples the universes of the captured `streams` from what `dmx_input` has
+/// received. Universes nothing is sending are dark.
+#[must_use]
+pub fn capture_frame(streams: &[Stream], dmx_input: &DmxInputState, t_ms: u64) -> Frame {
+    let universes = streams
+        .iter()
+        .flat_map(|stream| {
+            (0..stream.universe_count).map(move |offset| stream.first_universe + offset)
+        })
+        .map(|universe| {
+            u16::try_from(universe)
+                .ok()
+                .and_then(|universe| dmx_input.received_universe(universe))
+                .copied()
+                .unwrap_or([0; UNIVERSE_SIZE])
+        })
+        .collect();
+    Frame { t_ms, universes }
+}
+
+impl Recording {
+    /// Encodes the recording as a frame log.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = FrameLogWriter::new(Vec::with_capacity(
+            HEADER_SIZE
+                + self.streams.len() * STREAM_SIZE
+                + self.frames.len() * (8 + self.universe_count() * UNIVERSE_SIZE),
+        ));
+        self.write_to(&mut writer)
+            .expect("writing to a Vec cannot fail");
+        writer.into_inner()
+    }
+
+    /// Decodes a frame log written by [`Recording::encode`] or a
+    /// [`FrameLogWriter`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a DMX frame log".to_string());
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != VERSION {
+            return Err(format!("Unsupported frame log version {version}"));
+        }
+        let fps = u32::from_le_bytes(reader.array()?);
+        let stream_count = u32::from_le_bytes(reader.array()?);
+        let streams = (0..stream_count)
+            .map(|_| {
+                Ok(Stream {
+                    output_id: u64::from_le_bytes(reader.array()?),
+                    first_universe: u32::from_le_bytes(reader.array()?),
+                    universe_count: u32::from_le_bytes(reader.array()?),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let recording = Recording {
+            fps,
+            streams,
+            frames: Vec::new(),
+        };
+        let universe_count = recording.universe_count();
+        let mut frames = Vec::new();
+        while !reader.bytes.is_empty() {
+            let t_ms = u64::from_le_bytes(reader.array()?);
+            let universes = (0..universe_count)
+                .map(|_| reader.array())
+                .collect::<Result<Vec<_>, String>>()?;
+            frames.push(Frame { t_ms, universes });
+        }
+
+        Ok(Recording {
+            frames,
+            ..recording
+        })
+    }
+
+    /// Writes the recording as CSV, see [`CsvWriter`].
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        self.write_to(&mut CsvWriter::new(out))
+    }
+
+    /// Hands every frame of the recording to `out`.
+    pub fn write_to(&self, out: &mut impl FrameWriter) -> io::Result<()> {
+        out.start(self.fps, &self.streams)?;
+        for frame in &self.frames {
+            out.write_frame(frame)?;
+        }
+        out.finish()
+    }
+
+    /// Length of the recording, up to the end of its last frame.
+    #[must_use]
+    pub fn duration_ms(&self) -> u64 {
+        self.frames
+            .last()
+            .map_or(0, |f| f.t_ms + 1000 / u64::from(self.fps.max(1)))
+    }
+
+    /// Returns the frame showing at `t_ms`. Before the first frame this is the
+    /// first frame, and after the last it is the last.
+    #[must_use]
+    pub fn frame_at(&self, t_ms: u64) -> Option<&Frame> {
+        let index = self.frames.partition_point(|f| f.t_ms <= t_ms);
+        self.frames.get(index.saturating_sub(1))
+    }
+
+    /// Splits the universes of `frame` by the stream they belong to.
+    pub fn stream_universes<'a>(
+        &'a self,
+        frame: &'a Frame,
+    ) -> impl Iterator<Item = (&'a Stream, &'a [[u8; UNIVERSE_SIZE]])> {
+        let mut rest = frame.universes.as_slice();
+        self.streams.iter().map(move |stream| {
+            let (universes, tail) = rest.split_at((stream.universe_count as usize).min(rest.len()));
+            rest = tail;
+            (stream, universes)
+        })
+    }
+
+    /// Total universes across every stream, i.e. the universes in each frame.
+    fn universe_count(&self) -> usize {
+        self.streams.iter().map(|s| s.universe_count as usize).sum()
+    }
+}
+
+/// Receives a recording one frame at a time as it is rendered or captured.
+pub trait FrameWriter {
+    /// Called once, before the first frame.
+    fn start(&mut self, fps: u32, streams: &[Stream]) -> io::Result<()>;
+
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Called once, after the last frame.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Writes frames to `out` as a frame log.
+pub struct FrameLogWriter<W: Write> {
+    out: W,
+    /// Universes in each frame, known once started.
+    universe_count: Option<usize>,
+}
+
+impl<W: Write> FrameLogWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            universe_count: None,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> FrameWriter for FrameLogWriter<W> {
+    fn start(&mut self, fps: u32, streams: &[Stream]) -> io::Result<()> {
+        let stream_count = u32::try_from(streams.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many streams"))?;
+        self.out.write_all(MAGIC)?;
+        self.out.write_all(&VERSION.to_le_bytes())?;
+        self.out.write_all(&fps.to_le_bytes())?;
+        self.out.write_all(&stream_count.to_le_bytes())?;
+        for stream in streams {
+            self.out.write_all(&stream.output_id.to_le_bytes())?;
+            self.out.write_all(&stream.first_universe.to_le_bytes())?;
+            self.out.write_all(&stream.universe_count.to_le_bytes())?;
+        }
+        self.universe_count = Some(streams.iter().map(|s| s.universe_count as usize).sum());
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        // Frames have a fixed size, so a frame of another size would corrupt
+        // every frame after it.
+        if self.universe_count != Some(frame.universes.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Frame does not match the recording's streams",
+            ));
+        }
+        self.out.write_all(&frame.t_ms.to_le_bytes())?;
+        for universe in &frame.universes {
+            self.out.write_all(universe)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Writes frames to `out` as CSV, one row per frame and universe: the frame
+/// index, its time, the output, the protocol universe and then its 512 channel
+/// values.
+pub struct CsvWriter<W: Write> {
+    out: W,
+    streams: Vec<Stream>,
+    index: u64,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            streams: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl<W: Write> FrameWriter for CsvWriter<W> {
+    fn start(&mut self, _fps: u32, streams: &[Stream]) -> io::Result<()> {
+        self.streams = streams.to_vec();
+        write!(self.out, "frame,t_ms,output_id,universe")?;
+        for channel in 1..=UNIVERSE_SIZE {
+            write!(self.out, ",{channel}")?;
+        }
+        writeln!(self.out)
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut universes = frame.universes.iter();
+        for stream in &self.streams {
+            for offset in 0..stream.universe_count {
+                let Some(universe) = universes.next() else {
+                    break;
+                };
+                write!(
+                    self.out,
+                    "{},{},{},{}",
+                    self.index,
+                    frame.t_ms,
+                    stream.output_id,
+                    stream.first_universe + offset
+                )?;
+                for value in universe {
+                    write!(self.out, ",{value}")?;
+                }
+                writeln!(self.out)?;
+            }
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < len {
+            return Err("Frame log is truncated".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        self.take(N)
+            .map(|b| b.try_into().expect("took exactly N bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::proto::{
+        ArtnetDmxOutput, FixtureDefinitions, Patch, SerialDmxOutput,
+        render_mode::{FixtureDebug, Mode},
+    };
+
+    fn project() -> Project {
+        let output = |output| crate::proto::Output {
+            output: Some(output),
+            ..Default::default()
+        };
+        Project {
+            active_patch: 1,
+            fixture_definitions: Some(FixtureDefinitions::default()),
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    name: "Main".to_string(),
+                    outputs: HashMap::from([
+                        (
+                            20,
+                            output(Output::ArtnetDmxOutput(ArtnetDmxOutput {
+                                universe: 4,
+                                universe_count: 2,
+                                ..Default::default()
+                            })),
+                        ),
+                        (
+                            10,
+                            output(Output::SerialDmxOutput(SerialDmxOutput::default())),
+                        ),
+                    ]),
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn debug_mode() -> RenderMode {
+        RenderMode {
+            mode: Some(Mode::FixtureDebug(FixtureDebug {
+                output_id: 20,
+                universe: 1,
+                channel_offset: 2,
+                channel_values: vec![255, 128],
+            })),
+        }
+    }
+
+    /// Renders through a [`FrameLogWriter`] and decodes what it wrote.
+    fn render(options: RecordingOptions) -> Rendered<Recording> {
+        let mut writer = FrameLogWriter::new(Vec::new());
+        let rendered = render_recording(&project(), &debug_mode(), options, &mut writer).unwrap();
+        let recording = Recording::decode(&writer.into_inner()).unwrap();
+        assert_eq!(rendered.frame, recording.frames.len() as u64);
+        Rendered {
+            frame: recording,
+            error: rendered.error,
+        }
+    }
+
+    #[test]
+    fn renders_every_dmx_output_at_a_fixed_rate() {
+        let options = RecordingOptions {
+            fps: 30,
+            duration_ms: 100,
+            start_t: 5_000,
+        };
+        let rendered = render(options);
+        assert!(rendered.error.is_none());
+
+        let recording = rendered.frame;
+        assert_eq!(
+            recording.streams,
+            [
+                Stream {
+                    output_id: 10,
+                    first_universe: 0,
+                    universe_count: 1,
+                },
+                Stream {
+                    output_id: 20,
+                    first_universe: 4,
+                    universe_count: 2,
+                },
+            ]
+        );
+        let times: Vec<u64> = recording.frames.iter().map(|f| f.t_ms).collect();
+        assert_eq!(times, [0, 33, 66]);
+        for frame in &recording.frames {
+            assert_eq!(frame.universes.len(), 3);
+            assert_eq!(frame.universes[2][2..4], [255, 128]);
+            assert!(frame.universes[..2].iter().flatten().all(|v| *v == 0));
+        }
+    }
+
+    #[test]
+    fn frame_logs_round_trip() {
+        let options = RecordingOptions {
+            fps: 10,
+            duration_ms: 250,
+            start_t: 0,
+        };
+        let recording = render(options).frame;
+        let bytes = recording.encode();
+        assert_eq!(
+            bytes.len(),
+            HEADER_SIZE + 2 * STREAM_SIZE + 3 * (8 + 3 * UNIVERSE_SIZE)
+        );
+        assert_eq!(Recording::decode(&bytes).unwrap(), recording);
+        assert!(Recording::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Recording::decode(b"not a frame log").is_err());
+    }
+
+    #[test]
+    fn frames_are_found_by_time_and_split_by_stream() {
+        let options = RecordingOptions {
+            fps: 4,
+            duration_ms: 1_000,
+            start_t: 0,
+        };
+        let recording = render(options).frame;
+        assert_eq!(recording.duration_ms(), 1_000);
+        assert_eq!(recording.frame_at(0).unwrap().t_ms, 0);
+        assert_eq!(recording.frame_at(499).unwrap().t_ms, 250);
+        assert_eq!(recording.frame_at(500).unwrap().t_ms, 500);
+        assert_eq!(recording.frame_at(5_000).unwrap().t_ms, 750);
+
+        let frame = recording.frame_at(0).unwrap();
+        let split: Vec<(u64, usize)> = recording
+            .stream_universes(frame)
+            .map(|(stream, universes)| (stream.output_id, universes.len()))
+            .collect();
+        assert_eq!(split, [(10, 1), (20, 2)]);
+    }
+
+    #[test]
+    fn captures_the_universes_the_patch_sends() {
+        let mut project = project();
+        let patch = project.patches.get_mut(&1).unwrap();
+        patch.outputs.insert(
+            30,
+            crate::proto::Output {
+                output: Some(Output::SacnDmxOutput(crate::proto::SacnDmxOutput {
+                    universe: 5,
+                    universe_count: 3,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        );
+        patch.outputs.insert(
+            40,
+            crate::proto::Output {
+                output: Some(Output::SacnDmxOutput(crate::proto::SacnDmxOutput {
+                    universe: 1,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        );
+
+        let streams = capture_streams(&project).unwrap();
+        let runs: Vec<(u64, u32, u32)> = streams
+            .iter()
+            .map(|s| (s.output_id, s.first_universe, s.universe_count))
+            .collect();
+        assert_eq!(runs, [(0, 1, 1), (0, 4, 4)]);
+
+        let mut dmx_input = DmxInputState::default();
+        dmx_input.receive(crate::dmx_input::Protocol::Artnet, 4, &[7], 0);
+        dmx_input.receive(crate::dmx_input::Protocol::Sacn, 7, &[9], 0);
+        let frame = capture_frame(&streams, &dmx_input, 25);
+        assert_eq!(frame.t_ms, 25);
+        let firsts: Vec<u8> = frame.universes.iter().map(|u| u[0]).collect();
+        assert_eq!(firsts, [0, 7, 0, 0, 9]);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_frame_and_universe() {
+        let options = RecordingOptions {
+            fps: 1,
+            duration_ms: 1_000,
+            start_t: 0,
+        };
+        let recording = render(options).frame;
+        let mut csv = Vec::new();
+        recording.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].starts_with("frame,t_ms,output_id,universe,1,2,"));
+        assert!(rows[0].ends_with(",512"));
+        assert!(rows[1].starts_with("0,0,10,0,0,0,"));
+        assert!(rows[2].starts_with("0,0,20,4,0,0,"));
+        assert!(rows[3].starts_with("0,0,20,5,0,0,255,128,0,"));
+    }
+}
@@ -1,12 +1,12 @@
 use crate::{
-    beat::effective_beat_metadata,
+    beat::{beat_t, effective_beat_metadata},
     hash::hash64,
     palette::interpolate_palettes,
     proto::{
         ColorPalette, Effect, Pattern, Playlist, Project, TargetedEffect,
         playlist::{self, Hold, Transition},
     },
-    render::{render_target::RenderTarget, util::apply_effect},
+    render::{render::RenderError, render_target::RenderTarget, util::apply_effect},
 };
 
 #[allow(clippy::cast_precision_loss)]
@@ -16,26 +16,35 @@ pub fn render_playlist<T: RenderTarget<T>>(
     system_t: u64,
     frame: u32,
     project: &Project,
-) -> Result<(), String> {
+) -> Result<(), RenderError> {
     let Some(playlist) = project.playlists.get(&playlist_id) else {
-        return Err(format!("Could not find playlist {playlist_id}"));
+        return Err(RenderError::PlaylistError(format!(
+            "Could not find playlist {playlist_id}"
+        )));
     };
 
     let Some(beat_metadata) = effective_beat_metadata(project, system_t) else {
-        return Err("Live beat not set!".to_string());
+        return Err(RenderError::PlaylistError("Live beat not set!".to_string()));
     };
 
-    #[allow(clippy::cast_precision_loss)]
-    let beat_t = (system_t - beat_metadata.offset_ms) as f64 / beat_metadata.length_ms;
+    let beat_t = beat_t(&beat_metadata, system_t).map_err(RenderError::PlaylistError)?;
+
+    if playlist.palettes.is_empty() || playlist.patterns.is_empty() {
+        return Err(RenderError::PlaylistError(format!(
+            "Playlist {playlist_id} needs at least one palette and one pattern"
+        )));
+    }
+    let mut errors = Vec::new();
 
     // Calculate color palette
-    let palette_order = resolve_palette_order(playlist)?;
+    let palette_order = resolve_palette_order(playlist).map_err(RenderError::PlaylistError)?;
     let (palette_running_index, palette_ms) = playlist_index(
         playlist.palette_offset_ms,
         playlist.dwell_ms,
         playlist.transition_ms,
         system_t,
-    )?;
+    )
+    .map_err(RenderError::PlaylistError)?;
     let palette_selection = select(
         &palette_order,
         playlist.palettes.len(),
@@ -50,6 +59,7 @@ pub fn render_playlist<T: RenderTarget<T>>(
             curr_palette,
             &playlist.palettes[palette_selection.next_index],
             amount,
+            playlist.palette_interpolation(),
         ),
         None => curr_palette.clone(),
     };
@@ -68,19 +78,21 @@ pub fn render_playlist<T: RenderTarget<T>>(
         && system_t >= start_ms
         && system_t < (start_ms + duration_ms)
     {
-        let before = interpolate_palettes(palette_a, palette_b, blend);
+        let space = playlist.palette_interpolation();
+        let before = interpolate_palettes(palette_a, palette_b, blend, space);
         let amount = (system_t - start_ms) as f64 / duration_ms as f64;
-        color_palette = interpolate_palettes(&before, &color_palette, amount);
+        color_palette = interpolate_palettes(&before, &color_palette, amount, space);
     }
 
     // Calculate pattern
-    let pattern_order = resolve_pattern_order(playlist)?;
+    let pattern_order = resolve_pattern_order(playlist).map_err(RenderError::PlaylistError)?;
     let (pattern_running_index, pattern_ms) = playlist_index(
         playlist.pattern_offset_ms,
         playlist.dwell_ms,
         playlist.transition_ms,
         system_t,
-    )?;
+    )
+    .map_err(RenderError::PlaylistError)?;
     let pattern_selection = select(
         &pattern_order,
         playlist.patterns.len(),
@@ -95,35 +107,47 @@ pub fn render_playlist<T: RenderTarget<T>>(
     // Render
     if let Some(amount) = pattern_selection.transition {
         let mut curr_target = render_target.clone();
-        render_pattern(
-            curr_pattern,
-            &color_palette,
-            &mut curr_target,
-            system_t,
-            beat_t,
-            frame,
-            project,
+        errors.extend(
+            render_pattern(
+                playlist_id,
+                curr_pattern,
+                &color_palette,
+                &mut curr_target,
+                system_t,
+                beat_t,
+                frame,
+                project,
+            )
+            .err(),
         );
         let mut next_target = render_target.clone();
-        render_pattern(
-            next_pattern,
-            &color_palette,
-            &mut next_target,
-            system_t,
-            beat_t,
-            frame,
-            project,
+        errors.extend(
+            render_pattern(
+                playlist_id,
+                next_pattern,
+                &color_palette,
+                &mut next_target,
+                system_t,
+                beat_t,
+                frame,
+                project,
+            )
+            .err(),
         );
         render_target.interpolate(&curr_target, &next_target, amount);
     } else {
-        render_pattern(
-            curr_pattern,
-            &color_palette,
-            render_target,
-            system_t,
-            beat_t,
-            frame,
-            project,
+        errors.extend(
+            render_pattern(
+                playlist_id,
+                curr_pattern,
+                &color_palette,
+                render_target,
+                system_t,
+                beat_t,
+                frame,
+                project,
+            )
+            .err(),
         );
     }
 
@@ -144,24 +168,32 @@ pub fn render_playlist<T: RenderTarget<T>>(
         let mut before = render_target.clone();
 
         let mut a_target = before.clone();
-        render_pattern(
-            pattern_a,
-            &color_palette,
-            &mut a_target,
-            system_t,
-            beat_t,
-            frame,
-            project,
+        errors.extend(
+            render_pattern(
+                playlist_id,
+                pattern_a,
+                &color_palette,
+                &mut a_target,
+                system_t,
+                beat_t,
+                frame,
+                project,
+            )
+            .err(),
         );
         let mut b_target = before.clone();
-        render_pattern(
-            pattern_b,
-            &color_palette,
-            &mut b_target,
-            system_t,
-            beat_t,
-            frame,
-            project,
+        errors.extend(
+            render_pattern(
+                playlist_id,
+                pattern_b,
+                &color_palette,
+                &mut b_target,
+                system_t,
+                beat_t,
+                frame,
+                project,
+            )
+            .err(),
         );
         before.interpolate(&a_target, &b_target, blend);
 
@@ -169,7 +201,7 @@ pub fn render_playlist<T: RenderTarget<T>>(
         render_target.interpolate(&before, &render_target.clone(), amount);
     }
 
-    Ok(())
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
 }
 
 /// Selected current/next item within a playlist collection, plus the crossfade
@@ -345,7 +377,9 @@ fn wrap_index(value: u64, len: usize) -> usize {
     (value % len as u64) as usize
 }
 
+/// Renders every effect of a pattern, carrying on past effects that fail.
 fn render_pattern<T: RenderTarget<T>>(
+    playlist_id: u64,
     pattern: &Pattern,
     palette: &ColorPalette,
     render_target: &mut T,
@@ -353,7 +387,8 @@ fn render_pattern<T: RenderTarget<T>>(
     beat_t: f64,
     frame: u32,
     project: &Project,
-) {
+) -> Result<(), RenderError> {
+    let mut errors = Vec::new();
     for targeted_effect in &pattern.targeted_effects {
         if let TargetedEffect {
             effect:
@@ -363,8 +398,7 @@ fn render_pattern<T: RenderTarget<T>>(
                 }),
             output_target: Some(output_target),
         } = targeted_effect
-        {
-            apply_effect(
+            && let Err(error) = apply_effect(
                 project,
                 render_target,
                 output_target,
@@ -374,9 +408,17 @@ fn render_pattern<T: RenderTarget<T>>(
                 frame,
                 effect,
                 palette,
-            );
+            )
+        {
+            errors.push(RenderError::InPattern {
+                playlist_id,
+                pattern: pattern.name.clone(),
+                error: Box::new(error),
+            });
         }
     }
+
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
 }
 
 #[cfg(test)]
@@ -0,0 +1,476 @@
+//! Works out how hard to drive each emitter of a fixture so that it matches a
+//! target color, whatever mix of emitters the fixture has.
+
+use std::collections::HashMap;
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::proto::{Color, dmx_fixture_definition::Emitter};
+
+/// Additive channel types the solver drives, in the order they are preferred
+/// when several mixes reproduce a color equally well.
+pub(crate) const EMITTER_TYPES: [&str; 8] = [
+    "red",
+    "green",
+    "blue",
+    "white",
+    "amber",
+    "lime",
+    "warm-white",
+    "cold-white",
+];
+
+/// Subtractive channel types, each of which removes one of red, green or blue
+/// from a white source.
+pub(crate) const SUBTRACTIVE_TYPES: [&str; 3] = ["cyan", "magenta", "yellow"];
+
+const EPSILON: f64 = 1e-9;
+
+type Xyz = [f64; 3];
+
+/// Typical LED emitters, used for channels whose definition does not describe
+/// them. The primaries are the sRGB primaries so that a plain RGB fixture
+/// renders a color exactly as it is specified.
+fn default_emitter(channel_type: &str) -> Option<Emitter> {
+    let (x, y, luminance) = match channel_type {
+        "red" => (0.64, 0.33, 0.212_6),
+        "green" => (0.30, 0.60, 0.715_2),
+        "blue" => (0.15, 0.06, 0.072_2),
+        "white" | "cold-white" => (0.312_7, 0.329_0, 1.0),
+        "amber" => (0.575, 0.424, 0.4),
+        "lime" => (0.41, 0.55, 0.9),
+        "warm-white" => (0.457_8, 0.410_1, 1.0),
+        _ => return None,
+    };
+    Some(Emitter { x, y, luminance })
+}
+
+fn emitter_xyz(emitter: &Emitter) -> Xyz {
+    if emitter.y <= 0.0 {
+        return [0.0; 3];
+    }
+    let scale = emitter.luminance / emitter.y;
+    [
+        emitter.x * scale,
+        emitter.luminance,
+        (1.0 - emitter.x - emitter.y) * scale,
+    ]
+}
+
+/// Converts a color to CIE XYZ, folding its white component into RGB.
+fn color_xyz(color: &Color) -> Xyz {
+    let white = color.white.unwrap_or(0.0);
+    let r = srgb_to_linear(color.red + white);
+    let g = srgb_to_linear(color.green + white);
+    let b = srgb_to_linear(color.blue + white);
+    [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175_0 * b,
+        0.019_333_9 * r + 0.119_192_0 * g + 0.950_304_1 * b,
+    ]
+}
+
+/// Returns whether a mode should be rendered through the solver rather than
+/// sending red, green, blue and white straight to their channels.
+pub(crate) fn uses_solver<'a>(
+    channel_types: impl IntoIterator<Item = &'a str>,
+    emitters: &HashMap<String, Emitter>,
+) -> bool {
+    !emitters.is_empty()
+        || channel_types
+            .into_iter()
+            .any(|t| EMITTER_TYPES[4..].contains(&t))
+}
+
+/// Returns the level of each additive channel type present in
+/// `channel_types` needed to reproduce `color`. Levels are sRGB encoded like
+/// the rest of the color pipeline. Colors outside of what the emitters can
+/// make come out as the closest color they can.
+pub(crate) fn solve<'a>(
+    color: &Color,
+    channel_types: impl IntoIterator<Item = &'a str>,
+    emitters: &HashMap<String, Emitter>,
+) -> HashMap<&'static str, f64> {
+    let present: Vec<&str> = channel_types.into_iter().collect();
+    let (types, emitters): (Vec<&'static str>, Vec<Emitter>) = EMITTER_TYPES
+        .into_iter()
+        .filter(|t| present.contains(t))
+        .filter_map(|t| Some((t, emitters.get(t).copied().or_else(|| default_emitter(t))?)))
+        .unzip();
+
+    // Scale emitters so that full red, green and blue together have a
+    // luminance of 1, matching full sRGB white.
+    let luminance = |filter: fn(&&str) -> bool| -> f64 {
+        types
+            .iter()
+            .zip(&emitters)
+            .filter(|(t, _)| filter(t))
+            .map(|(_, e)| e.luminance)
+            .sum()
+    };
+    let rgb_luminance = luminance(|t| ["red", "green", "blue"].contains(t));
+    let scale = if rgb_luminance > 0.0 {
+        rgb_luminance
+    } else {
+        luminance(|_| true)
+    };
+    if scale <= 0.0 {
+        return types.into_iter().map(|t| (t, 0.0)).collect();
+    }
+    let xyz: Vec<Xyz> = emitters
+        .iter()
+        .map(|e| emitter_xyz(e).map(|c| c / scale))
+        .collect();
+
+    types
+        .into_iter()
+        .zip(solve_levels(&color_xyz(color), &xyz))
+        .map(|(t, level)| (t, linear_to_srgb(level)))
+        .collect()
+}
+
+/// Returns the level of each subtractive channel type.
+pub(crate) fn solve_subtractive(color: &Color) -> HashMap<&'static str, f64> {
+    let white = color.white.unwrap_or(0.0);
+    SUBTRACTIVE_TYPES
+        .into_iter()
+        .zip([color.red, color.green, color.blue])
+        .map(|(t, c)| (t, 1.0 - (c + white).clamp(0.0, 1.0)))
+        .collect()
+}
+
+/// Returns the linear level of each emitter that reproduces `target`.
+fn solve_levels(target: &Xyz, xyz: &[Xyz]) -> Vec<f64> {
+    let closest = closest_mix(xyz, target);
+    let reachable = mix(xyz, &closest);
+    if dot(&reachable, &reachable) < EPSILON {
+        return vec![0.0; xyz.len()];
+    }
+
+    // Of all the mixes that make this color, use the proportions of the
+    // brightest one. It leans on whichever emitters are most efficient for
+    // the color, such as amber for oranges or white for pastels.
+    match brightest_mix(xyz, &reachable) {
+        Some((times, levels)) if times > EPSILON => {
+            levels.into_iter().map(|l| l / times.max(1.0)).collect()
+        }
+        _ => {
+            let max = closest.iter().copied().fold(1.0, f64::max);
+            closest.into_iter().map(|l| l / max).collect()
+        }
+    }
+}
+
+/// Finds the simplest mix of up to three emitters that gets closest to
+/// `target`, ignoring the emitters' maximum levels.
+fn closest_mix(xyz: &[Xyz], target: &Xyz) -> Vec<f64> {
+    let mut best: Option<(f64, Vec<usize>, Vec<f64>)> = None;
+    for size in 1..=3 {
+        for subset in (0u32..1 << xyz.len()).filter(|m| m.count_ones() == size) {
+            let indices: Vec<usize> = (0..xyz.len()).filter(|i| subset & (1 << i) != 0).collect();
+            let basis: Vec<Xyz> = indices.iter().map(|&i| xyz[i]).collect();
+            let Some(levels) = least_squares(&basis, target) else {
+                continue;
+            };
+            if levels.iter().any(|&l| l < -EPSILON) {
+                continue;
+            }
+            let error = distance(&mix(&basis, &levels), target);
+            if best.as_ref().is_none_or(|(e, _, _)| error < e - EPSILON) {
+                best = Some((error, indices, levels));
+            }
+        }
+    }
+
+    let mut levels = vec![0.0; xyz.len()];
+    if let Some((_, indices, base_levels)) = best {
+        for (i, level) in indices.into_iter().zip(base_levels) {
+            levels[i] = level.max(0.0);
+        }
+    }
+    levels
+}
+
+/// Finds the brightest mix with every level in [0, 1] whose color is a
+/// multiple of `direction`. Returns that multiple along with the levels.
+///
+/// Every mix the emitters can make forms a zonotope, and the brightest mix
+/// is where a ray along `direction` leaves it. Each face of the zonotope is
+/// spanned by two emitters with every other emitter either off or at full,
+/// depending on which side of the face it points to.
+fn brightest_mix(xyz: &[Xyz], direction: &Xyz) -> Option<(f64, Vec<f64>)> {
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    for i in 0..xyz.len() {
+        for j in i + 1..xyz.len() {
+            let normal = cross(&xyz[i], &xyz[j]);
+            if dot(&normal, &normal) < EPSILON * EPSILON {
+                continue;
+            }
+            for side in [1.0, -1.0] {
+                let normal = normal.map(|c| c * side);
+                let towards = dot(&normal, direction);
+                if towards <= EPSILON {
+                    continue;
+                }
+
+                let mut levels = vec![0.0; xyz.len()];
+                let mut corner = [0.0; 3];
+                for l in (0..xyz.len()).filter(|&l| l != i && l != j) {
+                    if dot(&normal, &xyz[l]) > 0.0 {
+                        levels[l] = 1.0;
+                        for c in 0..3 {
+                            corner[c] += xyz[l][c];
+                        }
+                    }
+                }
+
+                let times = dot(&normal, &corner) / towards;
+                let offset: Xyz = std::array::from_fn(|c| direction[c] * times - corner[c]);
+                let Some(parts) = least_squares(&[xyz[i], xyz[j]], &offset) else {
+                    continue;
+                };
+                if parts
+                    .iter()
+                    .any(|p| !(-EPSILON..=1.0 + EPSILON).contains(p))
+                {
+                    continue;
+                }
+                levels[i] = parts[0].clamp(0.0, 1.0);
+                levels[j] = parts[1].clamp(0.0, 1.0);
+
+                if best.as_ref().is_none_or(|(t, _)| times > *t) {
+                    best = Some((times, levels));
+                }
+            }
+        }
+    }
+    best
+}
+
+fn cross(a: &Xyz, b: &Xyz) -> Xyz {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn mix(basis: &[Xyz], levels: &[f64]) -> Xyz {
+    let mut result = [0.0; 3];
+    for (v, &l) in basis.iter().zip(levels) {
+        for c in 0..3 {
+            result[c] += v[c] * l;
+        }
+    }
+    result
+}
+
+fn distance(a: &Xyz, b: &Xyz) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn dot(a: &Xyz, b: &Xyz) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Solves for the levels of up to three basis vectors whose mix is closest
+/// to `target`, or `None` if the basis is degenerate.
+fn least_squares(basis: &[Xyz], target: &Xyz) -> Option<Vec<f64>> {
+    // Normal equations: (AᵀA) x = Aᵀt.
+    let n = basis.len();
+    let mut gram = [[0.0; 3]; 3];
+    let mut rhs = [0.0; 3];
+    for i in 0..n {
+        for j in 0..n {
+            gram[i][j] = dot(&basis[i], &basis[j]);
+        }
+        rhs[i] = dot(&basis[i], target);
+    }
+    for (i, row) in gram.iter_mut().enumerate().skip(n) {
+        row[i] = 1.0;
+    }
+
+    let det = determinant(&gram);
+    if det.abs() < EPSILON * EPSILON {
+        return None;
+    }
+    // Cramer's rule.
+    Some(
+        (0..n)
+            .map(|column| {
+                let mut replaced = gram;
+                for (row, value) in replaced.iter_mut().zip(rhs) {
+                    row[column] = value;
+                }
+                determinant(&replaced) / det
+            })
+            .collect(),
+    )
+}
+
+fn determinant(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(red: f64, green: f64, blue: f64) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            white: None,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "Expected {expected}, got {actual}"
+        );
+    }
+
+    /// Mixes the solved levels back together, scaled the way the solver
+    /// scales emitters.
+    fn mixed(levels: &HashMap<&str, f64>, emitters: &HashMap<String, Emitter>) -> Xyz {
+        let emitter = |t: &str| emitters.get(t).copied().or_else(|| default_emitter(t));
+        let rgb_luminance: f64 = ["red", "green", "blue"]
+            .into_iter()
+            .filter(|t| levels.contains_key(t))
+            .filter_map(emitter)
+            .map(|e| e.luminance)
+            .sum();
+        let mut result = [0.0; 3];
+        for (t, level) in levels {
+            let xyz = emitter_xyz(&emitter(t).unwrap());
+            for c in 0..3 {
+                result[c] += xyz[c] * srgb_to_linear(*level) / rgb_luminance;
+            }
+        }
+        result
+    }
+
+    fn assert_reproduces(
+        target: &Color,
+        levels: &HashMap<&str, f64>,
+        emitters: &HashMap<String, Emitter>,
+    ) {
+        let mixed = mixed(levels, emitters);
+        let expected = color_xyz(target);
+        for c in 0..3 {
+            assert_close(mixed[c], expected[c]);
+        }
+    }
+
+    #[test]
+    fn rgb_fixtures_without_emitter_data_render_the_color_as_given() {
+        let levels = solve(
+            &color(0.8, 0.3, 0.1),
+            ["red", "green", "blue"],
+            &HashMap::new(),
+        );
+        assert_close(levels["red"], 0.8);
+        assert_close(levels["green"], 0.3);
+        assert_close(levels["blue"], 0.1);
+    }
+
+    #[test]
+    fn white_is_mixed_in_for_pastels() {
+        let types = ["red", "green", "blue", "white"];
+        for target in [color(1.0, 1.0, 1.0), color(1.0, 0.5, 0.5)] {
+            let levels = solve(&target, types, &HashMap::new());
+            assert!(levels["white"] > 0.0);
+            assert_reproduces(&target, &levels, &HashMap::new());
+        }
+    }
+
+    #[test]
+    fn mixes_match_the_target_with_measured_emitters() {
+        // A fixture with a deep red, a green and an amber just outside of the
+        // red to green edge.
+        let emitters = HashMap::from([
+            (
+                "red".to_string(),
+                Emitter {
+                    x: 0.70,
+                    y: 0.30,
+                    luminance: 0.15,
+                },
+            ),
+            (
+                "green".to_string(),
+                Emitter {
+                    x: 0.17,
+                    y: 0.70,
+                    luminance: 0.70,
+                },
+            ),
+            (
+                "blue".to_string(),
+                Emitter {
+                    x: 0.14,
+                    y: 0.04,
+                    luminance: 0.15,
+                },
+            ),
+            (
+                "amber".to_string(),
+                Emitter {
+                    x: 0.575,
+                    y: 0.424,
+                    luminance: 0.4,
+                },
+            ),
+        ]);
+        let target = color(0.6, 0.4, 0.1);
+        let levels = solve(&target, ["red", "green", "blue", "amber"], &emitters);
+
+        // Amber covers part of an orange.
+        assert!(levels["amber"] > 0.0);
+        assert!(levels.values().all(|l| (0.0..=1.0).contains(l)));
+        assert_reproduces(&target, &levels, &emitters);
+    }
+
+    #[test]
+    fn out_of_gamut_colors_use_the_closest_mix() {
+        // Pure sRGB green is outside of a red, amber and white fixture.
+        let levels = solve(
+            &color(0.0, 1.0, 0.0),
+            ["red", "white", "amber"],
+            &HashMap::new(),
+        );
+        assert!(levels.values().all(|l| (0.0..=1.0).contains(l)));
+        assert!(levels.values().any(|&l| l > 0.0));
+    }
+
+    #[test]
+    fn colors_brighter_than_the_fixture_are_clamped() {
+        let emitters = HashMap::from([(
+            "green".to_string(),
+            Emitter {
+                x: 0.30,
+                y: 0.60,
+                luminance: 0.3,
+            },
+        )]);
+        let levels = solve(&color(0.0, 1.0, 0.0), ["red", "green", "blue"], &emitters);
+        assert_close(levels["green"], 1.0);
+        assert_close(levels["red"], 0.0);
+        assert_close(levels["blue"], 0.0);
+    }
+
+    #[test]
+    fn subtractive_channels_filter_out_the_complement() {
+        let levels = solve_subtractive(&color(1.0, 0.25, 0.0));
+        assert_close(levels["cyan"], 0.0);
+        assert_close(levels["magenta"], 0.75);
+        assert_close(levels["yellow"], 1.0);
+    }
+}
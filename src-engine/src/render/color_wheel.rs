@@ -0,0 +1,189 @@
+//! Picks color wheel positions by comparing colors in Oklab, where distances
+//! follow how different two colors look.
+
+use crate::color::{linear_srgb_to_oklab, srgb_to_linear};
+use crate::proto::Color;
+use crate::proto::dmx_fixture_definition::channel::ColorWheelMapping;
+
+/// Channel types that can mix a color when no wheel color is close enough.
+pub(crate) const MIX_CHANNEL_TYPES: [&str; 4] = ["cyan", "magenta", "yellow", "cto"];
+
+/// Linear transmission of a full color temperature orange filter, which takes
+/// daylight down to roughly 3200K.
+const FULL_CTO: [f64; 3] = [1.0, 0.5, 0.2];
+
+/// Number of steps tried between no and full CTO.
+const CTO_STEPS: u32 = 100;
+
+/// Converts a color to linear light with any white folded into red, green and
+/// blue.
+fn linear(color: &Color) -> [f64; 3] {
+    let white = color.white.unwrap_or(0.0);
+    [color.red, color.green, color.blue].map(|c| srgb_to_linear((c + white).clamp(0.0, 1.0)))
+}
+
+/// Converts linear light to Oklab at full brightness. A wheel only filters the
+/// beam, so how bright the color is is left to the dimmer.
+fn oklab_at_full(rgb: [f64; 3]) -> [f64; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    if max <= 0.0 {
+        return linear_srgb_to_oklab(rgb);
+    }
+    linear_srgb_to_oklab(rgb.map(|c| c / max))
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Every slot of the wheel along with the linear color of the beam through it.
+fn slots(mapping: &ColorWheelMapping) -> impl Iterator<Item = (u32, [f64; 3])> {
+    mapping
+        .colors
+        .iter()
+        .filter_map(|c| Some((c.value, linear(c.color.as_ref()?))))
+}
+
+/// Every split position of the wheel, where the beam shows half of a slot and
+/// half of the slot after it.
+fn splits(mapping: &ColorWheelMapping) -> impl Iterator<Item = (u32, [f64; 3])> {
+    let colors = &mapping.colors;
+    colors.iter().enumerate().filter_map(|(i, c)| {
+        let split_value = c.split_value?;
+        let color = linear(c.color.as_ref()?);
+        let next = linear(colors[(i + 1) % colors.len()].color.as_ref()?);
+        Some((
+            split_value,
+            [0, 1, 2].map(|j| f64::midpoint(color[j], next[j])),
+        ))
+    })
+}
+
+fn closest_of(
+    positions: impl Iterator<Item = (u32, [f64; 3])>,
+    color: &Color,
+) -> Option<(f64, f64)> {
+    let target = oklab_at_full(linear(color));
+    positions
+        .map(|(value, rgb)| {
+            (
+                f64::from(value) / 255.0,
+                distance(target, oklab_at_full(rgb)),
+            )
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Returns the normalized channel value of the wheel position closest to
+/// `color` along with how far from it the position is in Oklab.
+pub(crate) fn closest(mapping: &ColorWheelMapping, color: &Color) -> Option<(f64, f64)> {
+    closest_of(slots(mapping).chain(splits(mapping)), color)
+}
+
+/// Returns the normalized channel value of the slot closest to white.
+pub(crate) fn open(mapping: &ColorWheelMapping) -> Option<f64> {
+    let white = Color {
+        red: 1.0,
+        green: 1.0,
+        blue: 1.0,
+        white: None,
+    };
+    closest_of(slots(mapping), &white).map(|(value, _)| value)
+}
+
+/// Returns how much CTO brings white light closest to `color`.
+pub(crate) fn cto_amount(color: &Color) -> f64 {
+    let target = oklab_at_full(linear(color));
+    let distance_through = |amount: f64| {
+        let filtered = FULL_CTO.map(|c| 1.0 - amount * (1.0 - c));
+        distance(target, oklab_at_full(filtered))
+    };
+    (0..=CTO_STEPS)
+        .map(|step| f64::from(step) / f64::from(CTO_STEPS))
+        .min_by(|a, b| distance_through(*a).total_cmp(&distance_through(*b)))
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::linear_to_srgb;
+    use crate::proto::dmx_fixture_definition::channel::color_wheel_mapping::ColorWheelColor;
+
+    fn rgb(red: f64, green: f64, blue: f64) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            white: None,
+        }
+    }
+
+    fn wheel() -> ColorWheelMapping {
+        ColorWheelMapping {
+            colors: vec![
+                ColorWheelColor {
+                    name: "Open".to_string(),
+                    value: 0,
+                    color: Some(rgb(1.0, 1.0, 1.0)),
+                    split_value: None,
+                },
+                ColorWheelColor {
+                    name: "Red".to_string(),
+                    value: 51,
+                    color: Some(rgb(1.0, 0.0, 0.0)),
+                    split_value: Some(76),
+                },
+                ColorWheelColor {
+                    name: "Green".to_string(),
+                    value: 102,
+                    color: Some(rgb(0.0, 1.0, 0.0)),
+                    split_value: None,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_by_hue_regardless_of_brightness() {
+        // Equally far from open and red by RGB distance.
+        let (value, distance) = closest(&wheel(), &rgb(0.5, 0.5, 0.5)).unwrap();
+        assert!(value.abs() < f64::EPSILON);
+        assert!(distance < 1e-6);
+
+        let (value, _) = closest(&wheel(), &rgb(0.2, 0.0, 0.0)).unwrap();
+        assert!((value - 51.0 / 255.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn split_positions_mix_neighboring_slots() {
+        let (value, distance) = closest(&wheel(), &rgb(1.0, 1.0, 0.0)).unwrap();
+        assert!((value - 76.0 / 255.0).abs() < f64::EPSILON);
+        assert!(distance < 1e-6);
+    }
+
+    #[test]
+    fn open_is_the_whitest_slot() {
+        let mut wheel = wheel();
+        wheel.colors.rotate_left(1);
+        assert_eq!(open(&wheel), Some(0.0));
+    }
+
+    #[test]
+    fn cto_warms_white_light() {
+        assert!(cto_amount(&rgb(1.0, 1.0, 1.0)).abs() < f64::EPSILON);
+
+        let full = FULL_CTO.map(linear_to_srgb);
+        let amount = cto_amount(&rgb(full[0], full[1], full[2]));
+        assert!((amount - 1.0).abs() < f64::EPSILON);
+
+        let half = FULL_CTO.map(|c| linear_to_srgb(f64::midpoint(1.0, c)));
+        let amount = cto_amount(&rgb(half[0], half[1], half[2]));
+        assert!((amount - 0.5).abs() < 0.02, "got {amount}");
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::HashSet;
+
+use crate::{
+    beat::{beat_t, effective_beat_metadata},
+    cue::{CuePosition, fades},
+    palette::interpolated_scene_palette,
+    proto::{
+        ColorPalette, CueList, Effect, Project, TargetedEffect,
+        cue_list::{Cue, cue::TrackedState},
+    },
+    render::{
+        render::RenderError,
+        render_target::RenderTarget,
+        util::{apply_effect, apply_state},
+    },
+};
+
+pub fn render_cue_list<T: RenderTarget<T>>(
+    cue_list_id: u64,
+    render_target: &mut T,
+    system_t: u64,
+    frame: u32,
+    project: &Project,
+) -> Result<(), RenderError> {
+    let cue_list = project
+        .cue_lists
+        .get(&cue_list_id)
+        .ok_or(RenderError::MissingCueList { cue_list_id })?;
+
+    let fades = fades(cue_list, system_t);
+    if fades.last().is_none_or(|fade| fade.active.is_none()) {
+        return Ok(());
+    }
+
+    let color_palette = cue_list
+        .color_palette
+        .clone()
+        .unwrap_or_else(|| interpolated_scene_palette(project, project.active_scene, system_t));
+    let look = CueLook {
+        project,
+        cue_list_id,
+        cue_list,
+        frame,
+        color_palette: &color_palette,
+    };
+    let mut errors = Vec::new();
+    *render_target = look.fade(render_target, &fades, system_t, &mut errors);
+
+    // Cues rendered for more than one fade report their errors once.
+    let mut reported = HashSet::new();
+    errors.retain(|error| reported.insert(error.to_string()));
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
+}
+
+/// How far a fade of `fade_ms` has got after `elapsed_ms`.
+fn fade_amount(elapsed_ms: u64, fade_ms: u32) -> f64 {
+    if fade_ms == 0 {
+        return 1.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    (elapsed_ms as f64 / f64::from(fade_ms)).min(1.0)
+}
+
+/// Renders the looks of a cue list's cues and the fades between them.
+struct CueLook<'a> {
+    project: &'a Project,
+    cue_list_id: u64,
+    cue_list: &'a CueList,
+    frame: u32,
+    color_palette: &'a ColorPalette,
+}
+
+impl CueLook<'_> {
+    /// Renders `fades` over `base` at `t`. The last fade goes from where the
+    /// ones before it had got to when it went, or from the look of its
+    /// previous cue if there are none.
+    fn fade<T: RenderTarget<T>>(
+        &self,
+        base: &T,
+        fades: &[CuePosition],
+        t: u64,
+        errors: &mut Vec<RenderError>,
+    ) -> T {
+        let Some((
+            CuePosition {
+                previous,
+                active: Some(active),
+                go_t,
+            },
+            earlier,
+        )) = fades.split_last()
+        else {
+            return base.clone();
+        };
+
+        let from = if earlier.is_empty() {
+            let mut from = base.clone();
+            if let Some(previous) = previous {
+                self.render(&mut from, *previous, t, 1.0, errors);
+            }
+            from
+        } else {
+            self.fade(base, earlier, *go_t, errors)
+        };
+
+        let cue = &self.cue_list.cues[*active];
+        let elapsed = t
+            .saturating_sub(*go_t)
+            .saturating_sub(u64::from(cue.delay_ms));
+        let mut to = base.clone();
+        let effect_t = fade_amount(elapsed, cue.up_ms.max(cue.down_ms));
+        self.render(&mut to, *active, t, effect_t, errors);
+
+        let mut target = base.clone();
+        target.interpolate_split(
+            &from,
+            &to,
+            fade_amount(elapsed, cue.up_ms),
+            fade_amount(elapsed, cue.down_ms),
+        );
+        target
+    }
+
+    /// Renders what the cue at `index` looks like once its fade has
+    /// completed, with its one-shot effects `effect_t` of the way through.
+    fn render<T: RenderTarget<T>>(
+        &self,
+        render_target: &mut T,
+        index: usize,
+        t: u64,
+        effect_t: f64,
+        errors: &mut Vec<RenderError>,
+    ) {
+        let cues = &self.cue_list.cues[..=index];
+
+        // Tracked values carry forward from the last block cue.
+        let first = cues.iter().rposition(|c| c.block).unwrap_or(0);
+        for cue in &cues[first..] {
+            for TrackedState {
+                output_target,
+                state,
+            } in &cue.tracked_states
+            {
+                if let (Some(output_target), Some(state)) = (output_target, state)
+                    && let Err(error) = apply_state(
+                        self.project,
+                        render_target,
+                        output_target,
+                        state,
+                        self.color_palette,
+                    )
+                {
+                    errors.push(self.in_cue(cue, error));
+                }
+            }
+        }
+
+        let beat_t = effective_beat_metadata(self.project, t)
+            .and_then(|bm| beat_t(&bm, t).ok())
+            .unwrap_or(0.0);
+        let cue = &cues[index];
+        for targeted_effect in &cue.effects {
+            if let TargetedEffect {
+                effect: Some(Effect {
+                    effect: Some(effect),
+                }),
+                output_target: Some(output_target),
+            } = targeted_effect
+                && let Err(error) = apply_effect(
+                    self.project,
+                    render_target,
+                    output_target,
+                    t,
+                    Some(&effect_t),
+                    beat_t,
+                    self.frame,
+                    effect,
+                    self.color_palette,
+                )
+            {
+                errors.push(self.in_cue(cue, error));
+            }
+        }
+    }
+
+    fn in_cue(&self, cue: &Cue, error: RenderError) -> RenderError {
+        RenderError::InCue {
+            cue_list_id: self.cue_list_id,
+            cue: cue.name.clone(),
+            error: Box::new(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        cue::go,
+        proto::{
+            Color, DmxFixtureDefinition, EffectTiming, FixtureState, OutputTarget,
+            PhysicalDmxFixture, QualifiedFixtureId,
+            dmx_fixture_definition::{
+                Channel, Mode,
+                channel::{AmountMapping, Mapping},
+            },
+            effect::{Effect as EffectKind, RampEffect},
+            effect_timing::{OneShot, Timing},
+            fixture_state::LightColor,
+            output_target::{FixtureMapping, Output},
+        },
+        render::dmx_render_target::DmxRenderTarget,
+    };
+
+    fn definitions() -> HashMap<u64, DmxFixtureDefinition> {
+        let mut mode = Mode::default();
+        for (index, channel_type) in [(1, "dimmer"), (2, "red"), (3, "green"), (4, "blue")] {
+            mode.channels.insert(
+                index,
+                Channel {
+                    r#type: channel_type.to_string(),
+                    mapping: (channel_type == "dimmer").then_some(Mapping::AmountMapping(
+                        AmountMapping {
+                            min_value: 0,
+                            max_value: 255,
+                        },
+                    )),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("rgb".to_string(), mode);
+        HashMap::from([(1, definition)])
+    }
+
+    fn fixtures() -> HashMap<u64, PhysicalDmxFixture> {
+        HashMap::from([(
+            1,
+            PhysicalDmxFixture {
+                fixture_definition_id: 1,
+                fixture_mode: "rgb".to_string(),
+                ..Default::default()
+            },
+        )])
+    }
+
+    fn tracked(state: FixtureState) -> TrackedState {
+        TrackedState {
+            output_target: Some(OutputTarget {
+                output: Some(Output::Fixtures(FixtureMapping {
+                    fixture_ids: vec![QualifiedFixtureId {
+                        patch: 1,
+                        output: 1,
+                        fixture: 1,
+                    }],
+                })),
+            }),
+            state: Some(state),
+        }
+    }
+
+    fn color(red: f64, green: f64, blue: f64) -> LightColor {
+        LightColor::Color(Color {
+            red,
+            green,
+            blue,
+            white: None,
+        })
+    }
+
+    fn cue(id: u64, up_ms: u32, down_ms: u32, state: FixtureState) -> Cue {
+        Cue {
+            id,
+            name: format!("Cue {id}"),
+            tracked_states: vec![tracked(state)],
+            up_ms,
+            down_ms,
+            ..Default::default()
+        }
+    }
+
+    fn project() -> Project {
+        let cues = vec![
+            cue(
+                1,
+                1_000,
+                0,
+                FixtureState {
+                    dimmer: Some(1.0),
+                    light_color: Some(color(1.0, 0.0, 0.0)),
+                    ..Default::default()
+                },
+            ),
+            cue(
+                2,
+                0,
+                1_000,
+                FixtureState {
+                    dimmer: Some(0.5),
+                    ..Default::default()
+                },
+            ),
+            cue(
+                3,
+                0,
+                0,
+                FixtureState {
+                    light_color: Some(color(0.0, 0.0, 1.0)),
+                    ..Default::default()
+                },
+            ),
+            Cue {
+                block: true,
+                ..cue(
+                    4,
+                    0,
+                    0,
+                    FixtureState {
+                        light_color: Some(color(0.0, 1.0, 0.0)),
+                        ..Default::default()
+                    },
+                )
+            },
+        ];
+        Project {
+            active_patch: 1,
+            cue_lists: HashMap::from([(
+                1,
+                CueList {
+                    name: "Main".to_string(),
+                    cues,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    /// Renders the cue list at `t`, returning the dimmer, red and blue bytes.
+    fn render(project: &Project, t: u64) -> [u8; 3] {
+        let fixtures = fixtures();
+        let definitions = definitions();
+        let mut target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        render_cue_list(1, &mut target, t, 0, project).unwrap();
+        let universe = target.get_universes()[0];
+        [universe[0], universe[1], universe[3]]
+    }
+
+    #[test]
+    fn fades_rising_and_falling_values_with_their_own_times() {
+        let mut project = project();
+        assert_eq!(render(&project, 0), [0, 0, 0]);
+
+        go(&mut project, 1, 1_000).unwrap();
+        assert_eq!(render(&project, 1_500), [127, 127, 0]);
+        assert_eq!(render(&project, 2_000), [255, 255, 0]);
+
+        // Cue 2 only lowers the dimmer, over its down time.
+        go(&mut project, 1, 3_000).unwrap();
+        assert_eq!(render(&project, 3_500), [191, 255, 0]);
+        assert_eq!(render(&project, 4_000), [127, 255, 0]);
+    }
+
+    #[test]
+    fn values_track_until_a_block_cue() {
+        let mut project = project();
+        for t in 1..=3 {
+            go(&mut project, 1, t).unwrap();
+        }
+        // The dimmer tracks from cue 2 while cue 3 changes the color.
+        assert_eq!(render(&project, 10), [127, 0, 255]);
+
+        go(&mut project, 1, 20).unwrap();
+        assert_eq!(render(&project, 20), [0, 0, 0]);
+    }
+
+    #[test]
+    fn go_during_a_fade_starts_from_where_it_had_got_to() {
+        let mut project = project();
+        go(&mut project, 1, 1_000).unwrap();
+        assert_eq!(render(&project, 1_500), [127, 127, 0]);
+
+        // Cue 2 holds the dimmer where cue 1's fade left it, rather than
+        // jumping to full and fading down.
+        go(&mut project, 1, 1_500).unwrap();
+        assert_eq!(render(&project, 1_500), [127, 255, 0]);
+        assert_eq!(render(&project, 2_500), [127, 255, 0]);
+    }
+
+    #[test]
+    fn down_times_only_apply_to_falling_intensity() {
+        let mut project = project();
+        project.cue_lists.get_mut(&1).unwrap().cues[2].down_ms = 1_000;
+        for t in 1..=3 {
+            go(&mut project, 1, t * 10_000).unwrap();
+        }
+        // Red falls with the color change, not over the down time.
+        assert_eq!(render(&project, 30_500), [127, 0, 255]);
+    }
+
+    #[test]
+    fn one_shot_effects_run_over_the_fade() {
+        let mut project = project();
+        let cue = &mut project.cue_lists.get_mut(&1).unwrap().cues[0];
+        cue.up_ms = 0;
+        cue.down_ms = 1_000;
+        cue.effects = vec![TargetedEffect {
+            effect: Some(Effect {
+                effect: Some(EffectKind::RampEffect(RampEffect {
+                    state_start: Some(FixtureState {
+                        dimmer: Some(0.0),
+                        ..Default::default()
+                    }),
+                    state_end: Some(FixtureState {
+                        dimmer: Some(1.0),
+                        ..Default::default()
+                    }),
+                    timing_mode: Some(EffectTiming {
+                        timing: Some(Timing::OneShot(OneShot {})),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })),
+            }),
+            output_target: tracked(FixtureState::default()).output_target,
+        }];
+
+        go(&mut project, 1, 1_000).unwrap();
+        assert_eq!(render(&project, 1_250)[0], 63);
+        assert_eq!(render(&project, 1_500)[0], 127);
+    }
+}
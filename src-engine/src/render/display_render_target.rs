@@ -1,8 +1,12 @@
 #![allow(clippy::cast_possible_truncation)]
 
+use crate::proto::Color;
+use crate::proto::ColorInterpolation;
 use crate::proto::ColorPalette;
 use crate::proto::DisplayBuffer;
 use crate::proto::DisplayRenderTarget;
+use crate::proto::scene::tile::BlendMode;
+use crate::render::render::RenderError;
 use crate::render::render_target::RenderTarget;
 
 impl DisplayBuffer {
@@ -94,12 +98,12 @@ impl RenderTarget<DisplayRenderTarget> for DisplayRenderTarget {
         qualified_fixture_id: &crate::proto::QualifiedFixtureId,
         state: &crate::proto::FixtureState,
         color_palette: &ColorPalette,
-    ) {
+    ) -> Result<(), RenderError> {
         if qualified_fixture_id.output != self.id {
-            return;
+            return Ok(());
         }
 
-        if let Some(color) = state.get_color(color_palette) {
+        if let Some(color) = state.try_get_color(color_palette)? {
             self.color = Some(color);
         }
 
@@ -113,6 +117,20 @@ impl RenderTarget<DisplayRenderTarget> for DisplayRenderTarget {
             self.visualizer_tree =
                 crate::visualizer::tree::build_effect_visualizer_tree(&state.visualizer_ids);
         }
+        Ok(())
+    }
+
+    fn interpolate_split(
+        &mut self,
+        a: &DisplayRenderTarget,
+        b: &DisplayRenderTarget,
+        up: f64,
+        down: f64,
+    ) {
+        self.interpolate(a, b, up);
+        if b.dimmer < a.dimmer {
+            self.dimmer = ((1.0 - down) * f64::from(a.dimmer) + down * f64::from(b.dimmer)) as f32;
+        }
     }
 
     fn interpolate(&mut self, a: &DisplayRenderTarget, b: &DisplayRenderTarget, t: f64) {
@@ -133,7 +151,86 @@ impl RenderTarget<DisplayRenderTarget> for DisplayRenderTarget {
         );
     }
 
-    fn apply_fixture_debug(&mut self, _fixture_debug: &crate::proto::render_mode::FixtureDebug) {
-        panic!("Cannot perform fixture debug for display render target!");
+    fn interpolate_colors(
+        &mut self,
+        a: &DisplayRenderTarget,
+        b: &DisplayRenderTarget,
+        t: f64,
+        space: ColorInterpolation,
+    ) {
+        if let (Some(a_color), Some(b_color)) = (&a.color, &b.color) {
+            self.color = Some(a_color.interpolate(b_color, t, space));
+        }
+    }
+
+    fn blend_layer(&self, mode: BlendMode) -> DisplayRenderTarget {
+        let mut layer = self.clone();
+        let identity = mode.identity();
+        if mode.blends_intensity() {
+            layer.dimmer = identity as f32;
+        }
+        if mode.blends_color() {
+            layer.color = Some(Color {
+                red: identity,
+                green: identity,
+                blue: identity,
+                white: Some(identity),
+            });
+        }
+        layer
+    }
+
+    fn blend(
+        &mut self,
+        base: &DisplayRenderTarget,
+        layer: &DisplayRenderTarget,
+        t: f64,
+        mode: BlendMode,
+    ) {
+        self.interpolate(base, layer, t);
+
+        if mode.blends_intensity() {
+            self.dimmer =
+                mode.blend_value(f64::from(base.dimmer), f64::from(layer.dimmer), t) as f32;
+        }
+
+        if mode.blends_color() {
+            // A display without a color yet blends as if it were black.
+            let a = base.color.unwrap_or_default();
+            let b = layer.color.unwrap_or_default();
+            self.color = Some(Color {
+                red: mode.blend_value(a.red, b.red, t),
+                green: mode.blend_value(a.green, b.green, t),
+                blue: mode.blend_value(a.blue, b.blue, t),
+                white: Some(mode.blend_value(a.white.unwrap_or(0.0), b.white.unwrap_or(0.0), t)),
+            });
+        }
+    }
+
+    fn scale_intensity(
+        &mut self,
+        qualified_fixture_id: &crate::proto::QualifiedFixtureId,
+        amount: f64,
+    ) {
+        if qualified_fixture_id.output != self.id {
+            return;
+        }
+
+        self.dimmer *= amount as f32;
+    }
+
+    fn apply_fixture_debug(
+        &mut self,
+        _fixture_debug: &crate::proto::render_mode::FixtureDebug,
+    ) -> Result<(), RenderError> {
+        // Fixture debug only addresses DMX channels.
+        Err(RenderError::WrongOutputType)
+    }
+
+    fn apply_recorded_universe(
+        &mut self,
+        _universe: usize,
+        _values: &[u8; crate::render::dmx_render_target::UNIVERSE_SIZE],
+    ) {
     }
 }
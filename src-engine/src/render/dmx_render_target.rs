@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use std::vec;
 
-use crate::proto::dmx_fixture_definition::Mode;
-use crate::proto::{Color, ColorPalette, DmxFixtureDefinition, PhysicalDmxFixture};
+use crate::proto::dmx_fixture_definition::{Emitter, Mode};
+use crate::proto::scene::tile::BlendMode;
+use crate::proto::{
+    Color, ColorInterpolation, ColorPalette, DmxFixtureDefinition, PhysicalDmxFixture,
+};
+use crate::render::render::RenderError;
 use crate::render::render_target::RenderTarget;
+use crate::render::{color_solver, color_wheel};
 
 macro_rules! apply_channel_updates {
     ($updates:expr, $fixture_offset:expr, $mode:expr, $state:expr, [
@@ -23,12 +28,88 @@ macro_rules! apply_channel_updates {
     };
 }
 
+/// Number of channels in a single DMX universe.
+pub const UNIVERSE_SIZE: usize = 512;
+
+const COLOR_CHANNEL_TYPES: [&str; 8] = color_solver::EMITTER_TYPES;
+
+/// What a channel controls, as far as blending tiles is concerned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BlendChannel {
+    Dimmer,
+    Color,
+    /// A color channel on a fixture without a dimmer, which doubles as its
+    /// intensity.
+    DimmedColor,
+}
+
+impl BlendChannel {
+    fn is_intensity(self) -> bool {
+        matches!(self, BlendChannel::Dimmer | BlendChannel::DimmedColor)
+    }
+
+    fn blended_by(self, mode: BlendMode) -> bool {
+        match self {
+            BlendChannel::Dimmer => mode.blends_intensity(),
+            BlendChannel::Color => mode.blends_color(),
+            BlendChannel::DimmedColor => mode.blends_intensity() || mode.blends_color(),
+        }
+    }
+}
+
+/// How far above its zero a dimmer may be and still count as dark, half a DMX
+/// step.
+const DARK_THRESHOLD: f64 = 0.5 / 255.0;
+
+/// The color wheels of a fixture that only turn while it is dark, along with
+/// the index and zero value of each of its dimmers.
+#[derive(Clone, Debug)]
+struct MoveInBlack {
+    wheels: Vec<usize>,
+    dimmers: Vec<(usize, f64)>,
+}
+
+/// The channels of a fixture that mixes its color from red, green and blue,
+/// and optionally white.
+#[derive(Clone, Debug)]
+struct RgbChannels {
+    rgb: [usize; 3],
+    white: Option<usize>,
+}
+
+/// Splits a normalized value into `byte_count` bytes, most significant first.
+fn split_bytes(value: f64, byte_count: usize) -> impl Iterator<Item = u8> {
+    #[allow(clippy::cast_precision_loss)]
+    let max = ((1u64 << (8 * byte_count)) - 1) as f64;
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    let value = (value.clamp(0.0, 1.0) * max).round() as u64;
+    #[allow(clippy::cast_possible_truncation)]
+    (0..byte_count).rev().map(move |i| (value >> (8 * i)) as u8)
+}
+
 #[derive(Clone, Debug)]
 pub struct DmxRenderTarget<'a> {
-    universe: [f64; 512],
+    /// Every universe of the output back to back, `UNIVERSE_SIZE` channels each.
+    universe: Vec<f64>,
     fixtures: &'a HashMap<u64, PhysicalDmxFixture>,
     fixture_definitions: &'a HashMap<u64, DmxFixtureDefinition>,
-    non_interpolated_indices: OnceLock<Vec<u16>>,
+    non_interpolated_indices: OnceLock<Vec<usize>>,
+    blend_channels: OnceLock<Vec<(usize, BlendChannel)>>,
+    /// Indices of the bytes of every 16 and 24 bit channel, most significant
+    /// first. The full precision value is kept at the first index.
+    multi_byte_channels: OnceLock<Vec<Vec<usize>>>,
+    move_in_black: OnceLock<Vec<MoveInBlack>>,
+    rgb_channels: OnceLock<Vec<RgbChannels>>,
+    /// The color each fixture shows, with any virtual dimmer applied, so that
+    /// fades can blend colors before they are solved into channel levels.
+    colors: HashMap<u64, Color>,
+}
+
+/// Returns the zero-based index into the flattened universes of the first
+/// channel of `fixture`. Channel map keys are one-based and are added to this.
+fn fixture_base(fixture: &PhysicalDmxFixture) -> usize {
+    fixture.universe as usize * UNIVERSE_SIZE + fixture.channel_offset as usize
 }
 
 impl<'a> DmxRenderTarget<'a> {
@@ -36,61 +117,149 @@ impl<'a> DmxRenderTarget<'a> {
     pub fn new(
         fixtures: &'a HashMap<u64, PhysicalDmxFixture>,
         fixture_definitions: &'a HashMap<u64, DmxFixtureDefinition>,
+        universe_count: usize,
     ) -> Self {
-        let mut universe = [0.0; 512];
+        let mut target = DmxRenderTarget {
+            universe: vec![0.0; universe_count.max(1) * UNIVERSE_SIZE],
+            fixtures,
+            fixture_definitions,
+            non_interpolated_indices: OnceLock::new(),
+            blend_channels: OnceLock::new(),
+            multi_byte_channels: OnceLock::new(),
+            move_in_black: OnceLock::new(),
+            rgb_channels: OnceLock::new(),
+            colors: HashMap::new(),
+        };
 
+        let mut defaults = Vec::new();
         for fixture in fixtures.values() {
-            let definition = fixture_definitions
+            // Fixtures without a mode stay dark and are reported by
+            // `missing_fixture_modes`.
+            let Some(mode) = fixture_definitions
                 .get(&fixture.fixture_definition_id)
-                .unwrap();
-            let mode = definition.modes.get(fixture.fixture_mode.as_str()).unwrap();
+                .and_then(|d| d.modes.get(&fixture.fixture_mode))
+            else {
+                continue;
+            };
 
             for (channel_index, channel) in &mode.channels {
-                universe[(channel_index + fixture.channel_offset - 1) as usize] =
-                    f64::from(channel.default_value) / 255.0;
+                let index = fixture_base(fixture) + *channel_index as usize - 1;
+                defaults.push((index, f64::from(channel.default_value) / 255.0));
             }
         }
+        // Each byte of a multi-byte channel has its own default.
+        target.write_bytes(defaults);
 
-        DmxRenderTarget {
-            universe,
-            fixtures,
-            fixture_definitions,
-            non_interpolated_indices: OnceLock::new(),
+        target
+    }
+
+    /// Returns an error for every fixture whose definition or mode could not
+    /// be found and so is not rendered.
+    pub fn missing_fixture_modes(&self) -> Vec<RenderError> {
+        let mut ids: Vec<u64> = self
+            .fixtures
+            .keys()
+            .copied()
+            .filter(|id| self.get_fixture_mode(*id).is_none())
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter()
+            .map(|fixture_id| {
+                let fixture = &self.fixtures[&fixture_id];
+                RenderError::MissingFixtureMode {
+                    fixture_id,
+                    definition_id: fixture.fixture_definition_id,
+                    mode: fixture.fixture_mode.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the rendered universes in order, starting with the output's
+    /// first universe.
+    pub fn get_universes(&self) -> Vec<[u8; UNIVERSE_SIZE]> {
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_sign_loss)]
+        let mut universes: Vec<[u8; UNIVERSE_SIZE]> = self
+            .universe
+            .as_chunks::<UNIVERSE_SIZE>()
+            .0
+            .iter()
+            .map(|chunk| chunk.map(|v| (v * 255.0).clamp(0.0, 255.0) as u8))
+            .collect();
+
+        for bytes in self.get_multi_byte_channels() {
+            let Some(&value) = self.universe.get(bytes[0]) else {
+                continue;
+            };
+            for (&index, byte) in bytes.iter().zip(split_bytes(value, bytes.len())) {
+                if let Some(channel) = universes
+                    .get_mut(index / UNIVERSE_SIZE)
+                    .map(|u| &mut u[index % UNIVERSE_SIZE])
+                {
+                    *channel = byte;
+                }
+            }
         }
+
+        universes
     }
 
-    pub fn get_universe(&self) -> [u8; 512] {
-        let mut universe = self.universe;
-        for fixture in self.fixtures.values() {
-            let mode = self
-                .fixture_definitions
-                .get(&fixture.fixture_definition_id)
-                .unwrap()
-                .modes
-                .get(&fixture.fixture_mode)
-                .unwrap();
+    /// Sets a raw channel, given its universe index and one-based channel
+    /// number, to a normalized value.
+    pub fn set_channel(&mut self, universe: usize, channel: usize, value: f64) {
+        if channel == 0 || channel > UNIVERSE_SIZE {
+            return;
+        }
+        self.write_bytes(vec![(universe * UNIVERSE_SIZE + channel - 1, value)]);
+    }
 
-            for (index, channel) in &mode.channels {
-                let t = &channel.r#type;
-                if t.ends_with("-fine") {
-                    let fine_index = (index + fixture.channel_offset - 1) as usize;
-                    let coarse_type = t.strip_suffix("-fine").unwrap();
-                    let coarse_entry = mode.channels.iter().find(|(_, c)| c.r#type == coarse_type);
-                    if coarse_entry.is_none() {
-                        continue;
-                    }
-                    let coarse_index =
-                        (coarse_entry.unwrap().0 + fixture.channel_offset - 1) as usize;
-                    let coarse_value = universe[coarse_index];
-                    let fine_value = (coarse_value * 255.0).fract();
-                    universe[fine_index] = fine_value;
+    /// Writes normalized raw byte values, then rebuilds the full precision
+    /// value of every multi-byte channel they touch. The bytes of those
+    /// channels that are not written keep their part of the current value.
+    fn write_bytes(&mut self, writes: Vec<(usize, f64)>) {
+        let written: HashSet<usize> = writes.iter().map(|(index, _)| *index).collect();
+        let multi_byte_channels: Vec<Vec<usize>> = self
+            .get_multi_byte_channels()
+            .iter()
+            .filter(|bytes| bytes.iter().any(|i| written.contains(i)))
+            .cloned()
+            .collect();
+
+        for bytes in &multi_byte_channels {
+            let Some(&value) = self.universe.get(bytes[0]) else {
+                continue;
+            };
+            for (&index, byte) in bytes.iter().zip(split_bytes(value, bytes.len())) {
+                if let Some(v) = self.universe.get_mut(index) {
+                    *v = f64::from(byte) / 255.0;
                 }
             }
         }
 
-        #[allow(clippy::cast_possible_truncation)]
-        #[allow(clippy::cast_sign_loss)]
-        universe.map(|v| (v * 255.0).clamp(0.0, 255.0) as u8)
+        for (index, value) in writes {
+            if let Some(v) = self.universe.get_mut(index) {
+                *v = value;
+            }
+        }
+
+        for bytes in multi_byte_channels {
+            let Some(joined) = bytes.iter().try_fold(0u64, |joined, &index| {
+                #[allow(clippy::cast_possible_truncation)]
+                #[allow(clippy::cast_sign_loss)]
+                let byte = (self.universe.get(index)? * 255.0)
+                    .round()
+                    .clamp(0.0, 255.0) as u64;
+                Some(joined << 8 | byte)
+            }) else {
+                continue;
+            };
+            #[allow(clippy::cast_precision_loss)]
+            let max = ((1u64 << (8 * bytes.len())) - 1) as f64;
+            #[allow(clippy::cast_precision_loss)]
+            let value = joined as f64 / max;
+            self.universe[bytes[0]] = value;
+        }
     }
 
     fn get_fixture_mode(&self, fixture_id: u64) -> Option<&Mode> {
@@ -101,9 +270,9 @@ impl<'a> DmxRenderTarget<'a> {
         })
     }
 
-    fn get_non_interpolated_indices(&self) -> Vec<u16> {
+    fn get_non_interpolated_indices(&self) -> Vec<usize> {
         self.non_interpolated_indices.get_or_init(|| {
-            let mut indices: Vec<u16> = Vec::new();
+            let mut indices: Vec<usize> = Vec::new();
             for (fixture_id, fixture) in self.fixtures {
                 let Some(mode) = self.get_fixture_mode(*fixture_id) else {
                     continue;
@@ -111,7 +280,7 @@ impl<'a> DmxRenderTarget<'a> {
 
                 for (index, channel) in &mode.channels {
                     if let Some(crate::proto::dmx_fixture_definition::channel::Mapping::ColorWheelMapping(_)) = channel.mapping {
-                        indices.push(u16::try_from(index + fixture.channel_offset - 1).unwrap());
+                        indices.push(fixture_base(fixture) + *index as usize - 1);
                     }
                 }
             }
@@ -119,9 +288,149 @@ impl<'a> DmxRenderTarget<'a> {
         }).clone()
     }
 
+    fn get_move_in_black(&self) -> &[MoveInBlack] {
+        self.move_in_black.get_or_init(|| {
+            let mut fixtures = Vec::new();
+            for (fixture_id, fixture) in self.fixtures {
+                let Some(mode) = self.get_fixture_mode(*fixture_id) else {
+                    continue;
+                };
+
+                let index = |i: u32| Some(fixture_base(fixture) + i as usize - 1)
+                    .filter(|&index| index < self.universe.len());
+                let wheels: Vec<usize> = mode
+                    .channels
+                    .iter()
+                    .filter(|(_, c)| matches!(
+                        &c.mapping,
+                        Some(crate::proto::dmx_fixture_definition::channel::Mapping::ColorWheelMapping(m)) if m.move_in_black
+                    ))
+                    .filter_map(|(i, _)| index(*i))
+                    .collect();
+                let dimmers: Vec<(usize, f64)> = mode
+                    .channels
+                    .iter()
+                    .filter(|(_, c)| c.r#type == "dimmer")
+                    .filter_map(|(i, c)| {
+                        let floor = match &c.mapping {
+                            Some(crate::proto::dmx_fixture_definition::channel::Mapping::AmountMapping(
+                                mapping,
+                            )) => f64::from(mapping.min_value) / 255.0,
+                            _ => 0.0,
+                        };
+                        Some((index(*i)?, floor))
+                    })
+                    .collect();
+
+                // Without a dimmer the wheel turns halfway through fades.
+                if !wheels.is_empty() && !dimmers.is_empty() {
+                    fixtures.push(MoveInBlack { wheels, dimmers });
+                }
+            }
+            fixtures
+        })
+    }
+
+    fn get_rgb_channels(&self) -> &[RgbChannels] {
+        self.rgb_channels.get_or_init(|| {
+            let mut fixtures = Vec::new();
+            for (fixture_id, fixture) in self.fixtures {
+                let Some(mode) = self.get_fixture_mode(*fixture_id) else {
+                    continue;
+                };
+                let emitters = self
+                    .fixture_definitions
+                    .get(&fixture.fixture_definition_id)
+                    .map(|d| &d.emitters);
+                let channel_types = || mode.channels.values().map(|c| c.r#type.as_str());
+                // Fixtures mixing other emitters are only refaded when their
+                // color is known.
+                if emitters.is_some_and(|e| color_solver::uses_solver(channel_types(), e)) {
+                    continue;
+                }
+
+                let index = |channel_type: &str| {
+                    mode.channels
+                        .iter()
+                        .find(|(_, c)| c.r#type == channel_type)
+                        .map(|(i, _)| fixture_base(fixture) + *i as usize - 1)
+                        .filter(|&index| index < self.universe.len())
+                };
+                if let (Some(red), Some(green), Some(blue)) =
+                    (index("red"), index("green"), index("blue"))
+                {
+                    fixtures.push(RgbChannels {
+                        rgb: [red, green, blue],
+                        white: index("white"),
+                    });
+                }
+            }
+            fixtures
+        })
+    }
+
+    fn get_multi_byte_channels(&self) -> &[Vec<usize>] {
+        self.multi_byte_channels.get_or_init(|| {
+            let mut channels = Vec::new();
+            for (fixture_id, fixture) in self.fixtures {
+                let Some(mode) = self.get_fixture_mode(*fixture_id) else {
+                    continue;
+                };
+
+                for (index, channel) in &mode.channels {
+                    let mut bytes = vec![*index];
+                    if let Some(fine_channel) = channel.fine_channel {
+                        bytes.push(fine_channel);
+                        bytes.extend(channel.ultra_channel);
+                    } else if let Some((fine_channel, _)) = mode.channels.iter().find(|(_, c)| {
+                        !channel.r#type.is_empty()
+                            && c.r#type.strip_suffix("-fine") == Some(channel.r#type.as_str())
+                    }) {
+                        // Older definitions pair a "<type>-fine" channel with
+                        // its coarse channel by type alone.
+                        bytes.push(*fine_channel);
+                    } else {
+                        continue;
+                    }
+                    channels.push(
+                        bytes
+                            .into_iter()
+                            .map(|i| fixture_base(fixture) + i as usize - 1)
+                            .collect(),
+                    );
+                }
+            }
+            channels
+        })
+    }
+
+    fn get_blend_channels(&self) -> &[(usize, BlendChannel)] {
+        self.blend_channels.get_or_init(|| {
+            let mut channels = Vec::new();
+            for (fixture_id, fixture) in self.fixtures {
+                let Some(mode) = self.get_fixture_mode(*fixture_id) else {
+                    continue;
+                };
+
+                let has_dimmer = mode.channels.values().any(|c| c.r#type == "dimmer");
+                for (index, channel) in &mode.channels {
+                    let kind = match channel.r#type.as_str() {
+                        "dimmer" => BlendChannel::Dimmer,
+                        t if COLOR_CHANNEL_TYPES.contains(&t) && has_dimmer => BlendChannel::Color,
+                        t if COLOR_CHANNEL_TYPES.contains(&t) => BlendChannel::DimmedColor,
+                        _ => continue,
+                    };
+                    channels.push((fixture_base(fixture) + *index as usize - 1, kind));
+                }
+            }
+            channels
+        })
+    }
+
     fn compute_color_channel_updates(
-        fixture_offset: u32,
+        fixture_base: usize,
         mode: &Mode,
+        emitters: &HashMap<String, Emitter>,
         color: Color,
     ) -> Vec<(usize, f64)> {
         let red: f64;
@@ -142,42 +451,69 @@ impl<'a> DmxRenderTarget<'a> {
             white = 0.0;
         }
 
-        mode.channels
-            .iter()
-            .filter_map(|(index, channel)| {
-                let channel_index = (index + fixture_offset) as usize;
-
-                match channel.r#type.as_str() {
-                    "red" => Some((channel_index, red)),
-                    "green" => Some((channel_index, green)),
-                    "blue" => Some((channel_index, blue)),
-                    "white" => Some((channel_index, white)),
-                    "color_wheel" => {
-                        match &channel.mapping {
-                            Some(crate::proto::dmx_fixture_definition::channel::Mapping::ColorWheelMapping(color_wheel_mapping)) => {
-                                let mut min_dist = f64::MAX;
-                                let mut update = None;
-                                for c in &color_wheel_mapping.colors {
-                                    let color = c.color?;
-                                    let dist = (color.red - red).powf(2.0) + (color.green - green).powf(2.0) + (color.blue - blue).powf(2.0);
-                                    if dist < min_dist {
-                                        min_dist = dist;
-                                        update = Some((channel_index, f64::from(c.value) / 255.0));
-                                    }
-                                }
-                                update
-                            },
-                            _ => None,
-                        }
+        let channel_types = || mode.channels.values().map(|c| c.r#type.as_str());
+        let mut levels = if color_solver::uses_solver(channel_types(), emitters) {
+            color_solver::solve(&color, channel_types(), emitters)
+        } else {
+            HashMap::from([
+                ("red", red),
+                ("green", green),
+                ("blue", blue),
+                ("white", white),
+            ])
+        };
+        levels.extend(color_solver::solve_subtractive(&color));
+
+        let has_channel = |types: &[&str]| {
+            mode.channels
+                .values()
+                .any(|c| types.contains(&c.r#type.as_str()))
+        };
+        let can_mix = has_channel(&color_wheel::MIX_CHANNEL_TYPES);
+        let mut updates = Vec::new();
+        for (index, channel) in &mode.channels {
+            let Some(crate::proto::dmx_fixture_definition::channel::Mapping::ColorWheelMapping(
+                mapping,
+            )) = &channel.mapping
+            else {
+                continue;
+            };
+            let Some((mut value, distance)) = color_wheel::closest(mapping, &color) else {
+                continue;
+            };
+
+            match mapping.mix_fallback_distance {
+                Some(max_distance) if can_mix && distance > max_distance => {
+                    // Nothing on the wheel is close, so mix the color through
+                    // an open slot instead.
+                    value = color_wheel::open(mapping).unwrap_or(value);
+                    let cto = if has_channel(&color_solver::SUBTRACTIVE_TYPES) {
+                        0.0
+                    } else {
+                        color_wheel::cto_amount(&color)
+                    };
+                    levels.insert("cto", cto);
+                }
+                Some(_) => {
+                    // Keep the wheel color clean.
+                    for channel_type in color_wheel::MIX_CHANNEL_TYPES {
+                        levels.insert(channel_type, 0.0);
                     }
-                    _ => None,
                 }
-            })
-            .collect()
+                None => (),
+            }
+            updates.push((fixture_base + *index as usize - 1, value));
+        }
+
+        updates.extend(mode.channels.iter().filter_map(|(index, channel)| {
+            let level = levels.get(channel.r#type.as_str())?;
+            Some((fixture_base + *index as usize - 1, *level))
+        }));
+        updates
     }
 
     fn compute_amount_channel_updates(
-        fixture_offset: u32,
+        fixture_base: usize,
         mode: &Mode,
         channel_type: &str,
         value: f64,
@@ -193,7 +529,7 @@ impl<'a> DmxRenderTarget<'a> {
                     return None;
                 };
 
-                let channel_index = (index + fixture_offset) as usize;
+                let channel_index = fixture_base + *index as usize - 1;
 
                 let min = f64::from(mapping.min_value) / 255.0;
                 let max = f64::from(mapping.max_value) / 255.0;
@@ -205,7 +541,7 @@ impl<'a> DmxRenderTarget<'a> {
     }
 
     fn compute_angle_channel_updates(
-        fixture_offset: u32,
+        fixture_base: usize,
         mode: &Mode,
         channel_type: &str,
         degrees: f64,
@@ -221,7 +557,7 @@ impl<'a> DmxRenderTarget<'a> {
                     return None;
                 };
 
-                let channel_index = (index + fixture_offset) as usize;
+                let channel_index = fixture_base + *index as usize - 1;
 
                 let min = f64::from(mapping.min_degrees);
                 let max = f64::from(mapping.max_degrees);
@@ -235,7 +571,9 @@ impl<'a> DmxRenderTarget<'a> {
 
     fn apply_updates(&mut self, updates: Vec<(usize, f64)>) {
         for (channel_index, val) in updates {
-            self.universe[channel_index - 1] = val;
+            if let Some(value) = self.universe.get_mut(channel_index) {
+                *value = val;
+            }
         }
     }
 }
@@ -246,28 +584,34 @@ impl<'a> RenderTarget<DmxRenderTarget<'a>> for DmxRenderTarget<'a> {
         qualified_fixture_id: &crate::proto::QualifiedFixtureId,
         state: &crate::proto::FixtureState,
         color_palette: &ColorPalette,
-    ) {
+    ) -> Result<(), RenderError> {
         let Some(fixture) = self.fixtures.get(&qualified_fixture_id.fixture) else {
-            return;
+            return Ok(());
         };
 
         let Some(mode) = self.get_fixture_mode(qualified_fixture_id.fixture) else {
-            return;
+            return Ok(());
+        };
+
+        let Some(definition) = self.fixture_definitions.get(&fixture.fixture_definition_id) else {
+            return Ok(());
         };
 
         let mut all_updates = Vec::new();
 
-        if let Some(color) = state.get_color(color_palette) {
+        let color = state.try_get_color(color_palette)?;
+        if let Some(color) = color {
             all_updates.extend(Self::compute_color_channel_updates(
-                fixture.channel_offset,
+                fixture_base(fixture),
                 mode,
+                &definition.emitters,
                 color,
             ));
         }
 
         apply_channel_updates!(
             all_updates,
-            fixture.channel_offset,
+            fixture_base(fixture),
             mode,
             state,
             [
@@ -282,17 +626,44 @@ impl<'a> RenderTarget<DmxRenderTarget<'a>> for DmxRenderTarget<'a> {
             ]
         );
 
-        let channels: Vec<(usize, f64)> = state
+        // Fixtures that cannot reach the focus keep the state's pan and tilt,
+        // and everything else in the state is still applied.
+        let mut focus_error = None;
+        if let (Some(focus), Some(placement)) = (&state.focus, &fixture.placement) {
+            match crate::placement::focus_pan_tilt(placement, mode, focus) {
+                Ok((pan, tilt)) => {
+                    all_updates.extend(Self::compute_angle_channel_updates(
+                        fixture_base(fixture),
+                        mode,
+                        "pan",
+                        pan,
+                    ));
+                    all_updates.extend(Self::compute_angle_channel_updates(
+                        fixture_base(fixture),
+                        mode,
+                        "tilt",
+                        tilt,
+                    ));
+                }
+                Err(reason) => {
+                    focus_error = Some(RenderError::UnreachableFocus {
+                        fixture: fixture.name.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        let raw_channels: Vec<(usize, f64)> = state
             .channels
             .iter()
             .map(|c| {
                 (
-                    (c.index + fixture.channel_offset) as usize,
+                    fixture_base(fixture) + c.index as usize - 1,
                     f64::from(c.value) / 255.0,
                 )
             })
             .collect();
-        all_updates.extend(channels);
 
         // Calculate color channels while holding immutable borrow.
         let color_channels_for_dimmer: Vec<usize> = {
@@ -302,20 +673,59 @@ impl<'a> RenderTarget<DmxRenderTarget<'a>> for DmxRenderTarget<'a> {
             } else {
                 mode.channels
                     .iter()
-                    .filter(|(_, c)| {
-                        matches!(c.r#type.as_str(), "red" | "green" | "blue" | "white")
-                    })
-                    .map(|(idx, _)| (idx + fixture.channel_offset - 1) as usize)
+                    .filter(|(_, c)| COLOR_CHANNEL_TYPES.contains(&c.r#type.as_str()))
+                    .map(|(idx, _)| fixture_base(fixture) + *idx as usize - 1)
                     .collect()
             }
         };
 
+        let fixture_id = qualified_fixture_id.fixture;
+        if let Some(color) = color {
+            self.colors.insert(fixture_id, color);
+        }
+        // Raw channel values may override the color.
+        if !raw_channels.is_empty() {
+            self.colors.remove(&fixture_id);
+        }
+
         self.apply_updates(all_updates);
+        self.write_bytes(raw_channels);
 
         // Apply virtual dimmer,
-        if let Some(dimmer) = state.dimmer {
+        if let Some(dimmer) = state.dimmer
+            && !color_channels_for_dimmer.is_empty()
+        {
             for channel_index in color_channels_for_dimmer {
-                self.universe[channel_index] *= dimmer;
+                if let Some(value) = self.universe.get_mut(channel_index) {
+                    *value *= dimmer;
+                }
+            }
+            if let Some(color) = self.colors.get_mut(&fixture_id) {
+                *color = Color {
+                    red: color.red * dimmer,
+                    green: color.green * dimmer,
+                    blue: color.blue * dimmer,
+                    white: color.white.map(|w| w * dimmer),
+                };
+            }
+        }
+
+        focus_error.map_or(Ok(()), Err)
+    }
+
+    fn interpolate_split(
+        &mut self,
+        a: &DmxRenderTarget<'a>,
+        b: &DmxRenderTarget<'a>,
+        up: f64,
+        down: f64,
+    ) {
+        let mut falling = self.clone();
+        falling.interpolate(a, b, down);
+        self.interpolate(a, b, up);
+        for &(index, kind) in falling.get_blend_channels() {
+            if kind.is_intensity() && b.universe[index] < a.universe[index] {
+                self.universe[index] = falling.universe[index];
             }
         }
     }
@@ -323,27 +733,246 @@ impl<'a> RenderTarget<DmxRenderTarget<'a>> for DmxRenderTarget<'a> {
     fn interpolate(&mut self, a: &DmxRenderTarget<'a>, b: &DmxRenderTarget<'a>, t: f64) {
         let non_interpolated_indices = self.get_non_interpolated_indices();
 
-        for i in 0..512 {
+        self.colors = a
+            .colors
+            .iter()
+            .filter_map(|(id, a_color)| Some((*id, a_color.lerp(b.colors.get(id)?, t))))
+            .collect();
+
+        for i in 0..self.universe.len() {
             if non_interpolated_indices.contains(&i) {
                 if t < 0.5 {
-                    self.universe[i as usize] = a.universe[i as usize];
+                    self.universe[i] = a.universe[i];
                 } else {
-                    self.universe[i as usize] = b.universe[i as usize];
+                    self.universe[i] = b.universe[i];
                 }
             } else {
-                let a_val = a.universe[i as usize];
-                let b_val = b.universe[i as usize];
+                let a_val = a.universe[i];
+                let b_val = b.universe[i];
                 let diff = b_val - a_val;
 
-                self.universe[i as usize] = a_val + t * diff;
+                self.universe[i] = a_val + t * diff;
+            }
+        }
+
+        for fixture in self.get_move_in_black().to_vec() {
+            let turns = fixture
+                .wheels
+                .iter()
+                .any(|&i| (a.universe[i] - b.universe[i]).abs() > f64::EPSILON);
+            if !turns {
+                continue;
+            }
+
+            let is_dark = |target: &DmxRenderTarget| {
+                fixture
+                    .dimmers
+                    .iter()
+                    .all(|&(i, floor)| target.universe[i] <= floor + DARK_THRESHOLD)
+            };
+            let wheel_source = if is_dark(a) {
+                b
+            } else if is_dark(b) {
+                a
+            } else {
+                // Dip through black and turn the wheel at the bottom.
+                for &(i, floor) in &fixture.dimmers {
+                    self.universe[i] = if t < 0.5 {
+                        a.universe[i] + (floor - a.universe[i]) * t * 2.0
+                    } else {
+                        floor + (b.universe[i] - floor) * (t * 2.0 - 1.0)
+                    };
+                }
+                if t < 0.5 { a } else { b }
+            };
+            for &i in &fixture.wheels {
+                self.universe[i] = wheel_source.universe[i];
+            }
+        }
+    }
+
+    fn blend_layer(&self, mode: BlendMode) -> DmxRenderTarget<'a> {
+        let mut layer = self.clone();
+        for &(index, kind) in self.get_blend_channels() {
+            if kind.blended_by(mode)
+                && let Some(value) = layer.universe.get_mut(index)
+            {
+                *value = mode.identity();
+            }
+        }
+        layer
+    }
+
+    fn interpolate_colors(
+        &mut self,
+        a: &DmxRenderTarget<'a>,
+        b: &DmxRenderTarget<'a>,
+        t: f64,
+        space: ColorInterpolation,
+    ) {
+        if space == ColorInterpolation::Rgb {
+            return;
+        }
+
+        for fixture in self.get_rgb_channels().to_vec() {
+            let color = |target: &DmxRenderTarget| {
+                let [red, green, blue] = fixture.rgb.map(|i| target.universe[i]);
+                Color {
+                    red,
+                    green,
+                    blue,
+                    white: fixture.white.map(|i| target.universe[i]),
+                }
+            };
+            let color = color(a).interpolate(&color(b), t, space);
+            for (index, value) in fixture
+                .rgb
+                .into_iter()
+                .zip([color.red, color.green, color.blue])
+            {
+                self.universe[index] = value;
+            }
+            if let (Some(index), Some(white)) = (fixture.white, color.white) {
+                self.universe[index] = white;
+            }
+        }
+
+        // Where both colors are known they are blended before being solved,
+        // so every emitter, not just red, green and blue, follows `space`.
+        let (fixtures, fixture_definitions) = (self.fixtures, self.fixture_definitions);
+        for (fixture_id, a_color) in &a.colors {
+            let Some(b_color) = b.colors.get(fixture_id) else {
+                continue;
+            };
+            let Some(fixture) = fixtures.get(fixture_id) else {
+                continue;
+            };
+            let Some(definition) = fixture_definitions.get(&fixture.fixture_definition_id) else {
+                continue;
+            };
+            let Some(mode) = definition.modes.get(&fixture.fixture_mode) else {
+                continue;
+            };
+            // Wheels snap rather than fade, so their fixtures keep the
+            // channel fade.
+            if mode.channels.values().any(|c| {
+                matches!(
+                    c.mapping,
+                    Some(
+                        crate::proto::dmx_fixture_definition::channel::Mapping::ColorWheelMapping(
+                            _
+                        )
+                    )
+                )
+            }) {
+                continue;
+            }
+
+            let color = a_color.interpolate(b_color, t, space);
+            self.apply_updates(Self::compute_color_channel_updates(
+                fixture_base(fixture),
+                mode,
+                &definition.emitters,
+                color,
+            ));
+            self.colors.insert(*fixture_id, color);
+        }
+    }
+
+    fn blend(
+        &mut self,
+        base: &DmxRenderTarget<'a>,
+        layer: &DmxRenderTarget<'a>,
+        t: f64,
+        mode: BlendMode,
+    ) {
+        self.interpolate(base, layer, t);
+        if mode.blends_color() {
+            self.colors.clear();
+        }
+        for &(index, kind) in base.get_blend_channels() {
+            if !kind.blended_by(mode) || index >= self.universe.len() {
+                continue;
+            }
+            self.universe[index] = mode.blend_value(base.universe[index], layer.universe[index], t);
+        }
+    }
+
+    fn scale_intensity(
+        &mut self,
+        qualified_fixture_id: &crate::proto::QualifiedFixtureId,
+        amount: f64,
+    ) {
+        let Some(fixture) = self.fixtures.get(&qualified_fixture_id.fixture) else {
+            return;
+        };
+
+        let Some(mode) = self.get_fixture_mode(qualified_fixture_id.fixture) else {
+            return;
+        };
+
+        // Fixtures without a dimmer are dimmed through their color channels.
+        let has_dimmer = mode.channels.values().any(|c| c.r#type == "dimmer");
+        let intensity_channels: Vec<(usize, f64)> = mode
+            .channels
+            .iter()
+            .filter(|(_, channel)| {
+                if has_dimmer {
+                    channel.r#type == "dimmer"
+                } else {
+                    COLOR_CHANNEL_TYPES.contains(&channel.r#type.as_str())
+                }
+            })
+            .map(|(index, channel)| {
+                // Scale within the channel's mapped range so that a dimmer
+                // whose zero is not at DMX 0 still bottoms out at its zero.
+                let floor = match &channel.mapping {
+                    Some(
+                        crate::proto::dmx_fixture_definition::channel::Mapping::AmountMapping(
+                            mapping,
+                        ),
+                    ) => f64::from(mapping.min_value) / 255.0,
+                    _ => 0.0,
+                };
+                (fixture_base(fixture) + *index as usize - 1, floor)
+            })
+            .collect();
+
+        for (channel_index, floor) in intensity_channels {
+            if let Some(value) = self.universe.get_mut(channel_index) {
+                *value = floor + (*value - floor) * amount;
             }
         }
     }
 
-    fn apply_fixture_debug(&mut self, fixture_debug: &crate::proto::render_mode::FixtureDebug) {
-        for (index, &value) in fixture_debug.channel_values.iter().enumerate() {
-            self.universe[fixture_debug.channel_offset as usize + index] = f64::from(value) / 255.0;
+    fn apply_fixture_debug(
+        &mut self,
+        fixture_debug: &crate::proto::render_mode::FixtureDebug,
+    ) -> Result<(), RenderError> {
+        let base =
+            fixture_debug.universe as usize * UNIVERSE_SIZE + fixture_debug.channel_offset as usize;
+        self.colors.clear();
+        self.write_bytes(
+            (base..)
+                .zip(&fixture_debug.channel_values)
+                .map(|(index, &value)| (index, f64::from(value) / 255.0))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn apply_recorded_universe(&mut self, universe: usize, values: &[u8; UNIVERSE_SIZE]) {
+        let base = universe * UNIVERSE_SIZE;
+        if base + UNIVERSE_SIZE > self.universe.len() {
+            return;
         }
+        self.colors.clear();
+        self.write_bytes(
+            (base..)
+                .zip(values)
+                .map(|(index, &value)| (index, f64::from(value) / 255.0))
+                .collect(),
+        );
     }
 }
 
@@ -396,7 +1025,7 @@ mod tests {
         output.fixtures.insert(100u64, physical_fixture);
 
         // Create render target
-        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions);
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
 
         // Create a fixture state with dimmer at 50%
         let mut state = FixtureState::default();
@@ -412,10 +1041,12 @@ mod tests {
         let color_palette = ColorPalette::default();
 
         // Apply the state
-        render_target.apply_state(&qualified_id, &state, &color_palette);
+        render_target
+            .apply_state(&qualified_id, &state, &color_palette)
+            .unwrap();
 
         // Get the universe
-        let universe = render_target.get_universe();
+        let universe = render_target.get_universes()[0];
 
         // Channel 0 should be at 50% (127/128)
         assert!(
@@ -481,7 +1112,7 @@ mod tests {
         output.fixtures.insert(100u64, physical_fixture);
 
         // Create render target
-        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions);
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
 
         // Create a fixture state with pan at 270 degrees and tilt at 135 degrees
         let mut state = FixtureState::default();
@@ -498,10 +1129,12 @@ mod tests {
         let color_palette = ColorPalette::default();
 
         // Apply the state
-        render_target.apply_state(&qualified_id, &state, &color_palette);
+        render_target
+            .apply_state(&qualified_id, &state, &color_palette)
+            .unwrap();
 
         // Get the universe
-        let universe = render_target.get_universe();
+        let universe = render_target.get_universes()[0];
 
         // Pan channel (10) should be at 50% (127/128)
         assert!(
@@ -525,4 +1158,695 @@ mod tests {
             assert_eq!(universe[i], 0, "Channel {i} should be 0");
         }
     }
+
+    #[test]
+    fn test_fixtures_render_into_their_universe() {
+        let mut fixture_def = DmxFixtureDefinition::default();
+        let mut mode = Mode::default();
+        mode.num_channels = 1;
+        let mut dimmer_channel = Channel::default();
+        dimmer_channel.r#type = "dimmer".to_string();
+        dimmer_channel.mapping = Some(Mapping::AmountMapping(
+            crate::proto::dmx_fixture_definition::channel::AmountMapping {
+                min_value: 0,
+                max_value: 255,
+            },
+        ));
+        mode.channels.insert(1, dimmer_channel);
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        // Same channel offset, different universes.
+        let mut output = SerialDmxOutput::default();
+        for (fixture_id, universe) in [(100u64, 0), (101u64, 2)] {
+            let mut physical_fixture = PhysicalDmxFixture::default();
+            physical_fixture.fixture_definition_id = 1;
+            physical_fixture.fixture_mode = "test-mode".to_string();
+            physical_fixture.universe = universe;
+            physical_fixture.channel_offset = 4;
+            output.fixtures.insert(fixture_id, physical_fixture);
+        }
+
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 3);
+
+        let mut state = FixtureState::default();
+        state.dimmer = Some(1.0);
+        render_target
+            .apply_state(
+                &QualifiedFixtureId {
+                    patch: 0,
+                    output: 123,
+                    fixture: 101,
+                },
+                &state,
+                &ColorPalette::default(),
+            )
+            .unwrap();
+
+        let universes = render_target.get_universes();
+        assert_eq!(universes.len(), 3);
+        assert!(universes[0].iter().all(|&v| v == 0));
+        assert!(universes[1].iter().all(|&v| v == 0));
+        assert_eq!(universes[2][4], 255);
+        assert_eq!(universes[2].iter().filter(|&&v| v != 0).count(), 1);
+    }
+
+    #[test]
+    fn test_fixtures_outside_the_universe_range_are_ignored() {
+        let mut fixture_def = DmxFixtureDefinition::default();
+        let mut mode = Mode::default();
+        let mut channel = Channel::default();
+        channel.r#type = "dimmer".to_string();
+        channel.default_value = 255;
+        mode.channels.insert(1, channel);
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut physical_fixture = PhysicalDmxFixture::default();
+        physical_fixture.fixture_definition_id = 1;
+        physical_fixture.fixture_mode = "test-mode".to_string();
+        physical_fixture.universe = 1;
+
+        let mut output = SerialDmxOutput::default();
+        output.fixtures.insert(100u64, physical_fixture);
+
+        let render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
+
+        let universes = render_target.get_universes();
+        assert_eq!(universes.len(), 1);
+        assert!(universes[0].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_fixtures_without_a_mode_stay_dark_and_are_reported() {
+        let mut fixture_def = DmxFixtureDefinition::default();
+        let mut mode = Mode::default();
+        let mut channel = Channel::default();
+        channel.r#type = "dimmer".to_string();
+        channel.default_value = 255;
+        mode.channels.insert(1, channel);
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut output = SerialDmxOutput::default();
+        for (id, definition_id, mode, channel_offset) in [
+            (100, 1, "test-mode", 0),
+            (101, 1, "gone", 1),
+            (102, 2, "test-mode", 2),
+        ] {
+            let mut physical_fixture = PhysicalDmxFixture::default();
+            physical_fixture.fixture_definition_id = definition_id;
+            physical_fixture.fixture_mode = mode.to_string();
+            physical_fixture.channel_offset = channel_offset;
+            output.fixtures.insert(id, physical_fixture);
+        }
+
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
+        render_target
+            .apply_state(
+                &QualifiedFixtureId {
+                    patch: 0,
+                    output: 0,
+                    fixture: 101,
+                },
+                &FixtureState {
+                    dimmer: Some(0.5),
+                    ..Default::default()
+                },
+                &crate::proto::ColorPalette::default(),
+            )
+            .unwrap();
+
+        assert_eq!(&render_target.get_universes()[0][..3], [255, 0, 0]);
+        let missing: Vec<(u64, u64)> = render_target
+            .missing_fixture_modes()
+            .into_iter()
+            .map(|e| match e {
+                RenderError::MissingFixtureMode {
+                    fixture_id,
+                    definition_id,
+                    ..
+                } => (fixture_id, definition_id),
+                e => panic!("unexpected error {e}"),
+            })
+            .collect();
+        assert_eq!(missing, [(101, 1), (102, 2)]);
+    }
+
+    #[test]
+    fn test_scale_intensity_uses_the_dimmer_or_the_color_channels() {
+        let amount_mapping = Some(Mapping::AmountMapping(
+            crate::proto::dmx_fixture_definition::channel::AmountMapping {
+                min_value: 0,
+                max_value: 255,
+            },
+        ));
+
+        let mut dimmer_mode = Mode::default();
+        for (index, channel_type) in [(1, "dimmer"), (2, "red")] {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            channel.mapping = amount_mapping.clone();
+            dimmer_mode.channels.insert(index, channel);
+        }
+        let mut rgb_mode = Mode::default();
+        for (index, channel_type) in [(1, "red"), (2, "green")] {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            rgb_mode.channels.insert(index, channel);
+        }
+
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("dimmer".to_string(), dimmer_mode);
+        fixture_def.modes.insert("rgb".to_string(), rgb_mode);
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut output = SerialDmxOutput::default();
+        for (fixture_id, mode, channel_offset) in [(100u64, "dimmer", 0), (101u64, "rgb", 10)] {
+            let mut physical_fixture = PhysicalDmxFixture::default();
+            physical_fixture.fixture_definition_id = 1;
+            physical_fixture.fixture_mode = mode.to_string();
+            physical_fixture.channel_offset = channel_offset;
+            output.fixtures.insert(fixture_id, physical_fixture);
+        }
+
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
+
+        let mut state = FixtureState::default();
+        state.dimmer = Some(1.0);
+        state.light_color = Some(crate::proto::fixture_state::LightColor::Color(Color {
+            red: 1.0,
+            green: 1.0,
+            blue: 0.0,
+            white: None,
+        }));
+        for fixture in [100, 101] {
+            let qualified_id = QualifiedFixtureId {
+                patch: 0,
+                output: 123,
+                fixture,
+            };
+            render_target
+                .apply_state(&qualified_id, &state, &ColorPalette::default())
+                .unwrap();
+            render_target.scale_intensity(&qualified_id, 0.5);
+        }
+
+        let universe = render_target.get_universes()[0];
+        // Dimmer scaled, color left alone.
+        assert_eq!(&universe[0..2], &[127, 255]);
+        // No dimmer, so the color channels are scaled instead.
+        assert_eq!(&universe[10..12], &[127, 127]);
+    }
+
+    #[test]
+    fn test_mixed_emitter_fixtures_use_the_color_solver() {
+        let mut mode = Mode::default();
+        for (index, channel_type) in
+            (1..).zip(["red", "green", "blue", "amber", "cyan", "magenta", "yellow"])
+        {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            mode.channels.insert(index, channel);
+        }
+
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut output = SerialDmxOutput::default();
+        let mut physical_fixture = PhysicalDmxFixture::default();
+        physical_fixture.fixture_definition_id = 1;
+        physical_fixture.fixture_mode = "test-mode".to_string();
+        output.fixtures.insert(100u64, physical_fixture);
+
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
+
+        let mut state = FixtureState::default();
+        state.light_color = Some(crate::proto::fixture_state::LightColor::Color(Color {
+            red: 1.0,
+            green: 0.6,
+            blue: 0.0,
+            white: None,
+        }));
+        render_target
+            .apply_state(
+                &QualifiedFixtureId {
+                    patch: 0,
+                    output: 123,
+                    fixture: 100,
+                },
+                &state,
+                &ColorPalette::default(),
+            )
+            .unwrap();
+
+        let universe = render_target.get_universes()[0];
+        // Orange is mostly amber.
+        assert!(
+            universe[3] > universe[1],
+            "Expected amber, got {universe:?}"
+        );
+        // The subtractive channels filter out blue.
+        assert_eq!(&universe[4..7], &[0, 102, 255]);
+    }
+
+    #[test]
+    fn test_mixed_emitter_fixtures_fade_their_color_before_solving() {
+        let mut mode = Mode::default();
+        for (index, channel_type) in (1..).zip(["red", "green", "blue", "amber", "lime"]) {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            mode.channels.insert(index, channel);
+        }
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+        let fixture_definitions = HashMap::from([(1u64, fixture_def)]);
+        let mut physical_fixture = PhysicalDmxFixture::default();
+        physical_fixture.fixture_definition_id = 1;
+        physical_fixture.fixture_mode = "test-mode".to_string();
+        let fixtures = HashMap::from([(100u64, physical_fixture)]);
+
+        let qualified_id = QualifiedFixtureId {
+            patch: 0,
+            output: 123,
+            fixture: 100,
+        };
+        let render = |color: Color| {
+            let mut target = DmxRenderTarget::new(&fixtures, &fixture_definitions, 1);
+            let mut state = FixtureState::default();
+            state.light_color = Some(crate::proto::fixture_state::LightColor::Color(color));
+            target
+                .apply_state(&qualified_id, &state, &ColorPalette::default())
+                .unwrap();
+            target
+        };
+        let orange = Color {
+            red: 1.0,
+            green: 0.5,
+            blue: 0.0,
+            white: None,
+        };
+        let blue = Color {
+            red: 0.0,
+            green: 0.0,
+            blue: 1.0,
+            white: None,
+        };
+        let (a, b) = (render(orange), render(blue));
+
+        let mut faded = a.clone();
+        faded.interpolate(&a, &b, 0.5);
+        faded.interpolate_colors(&a, &b, 0.5, ColorInterpolation::Oklab);
+
+        // Amber and lime follow the blend rather than fading linearly.
+        let expected = render(orange.interpolate(&blue, 0.5, ColorInterpolation::Oklab));
+        assert_eq!(
+            &faded.get_universes()[0][0..5],
+            &expected.get_universes()[0][0..5]
+        );
+    }
+
+    #[test]
+    fn test_placed_fixtures_aim_at_the_focus() {
+        let mut mode = Mode::default();
+        for (index, channel_type, min_degrees, max_degrees) in
+            [(1, "pan", -270, 270), (2, "tilt", -135, 135)]
+        {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            channel.mapping = Some(Mapping::AngleMapping(
+                crate::proto::dmx_fixture_definition::channel::AngleMapping {
+                    min_degrees,
+                    max_degrees,
+                },
+            ));
+            mode.channels.insert(index, channel);
+        }
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut output = SerialDmxOutput::default();
+        for (fixture_id, x, channel_offset) in
+            [(100u64, -5.0, 0), (101u64, 5.0, 10), (102u64, 0.0, 20)]
+        {
+            let mut physical_fixture = PhysicalDmxFixture::default();
+            physical_fixture.fixture_definition_id = 1;
+            physical_fixture.fixture_mode = "test-mode".to_string();
+            physical_fixture.channel_offset = channel_offset;
+            physical_fixture.placement = Some(crate::proto::FixturePlacement {
+                position: Some(crate::proto::StagePoint { x, y: 0.0, z: 0.0 }),
+                ..Default::default()
+            });
+            output.fixtures.insert(fixture_id, physical_fixture);
+        }
+
+        let mut render_target = DmxRenderTarget::new(&output.fixtures, &fixture_definitions, 1);
+
+        let mut state = FixtureState::default();
+        state.pan = Some(0.0);
+        state.focus = Some(crate::proto::StagePoint::default());
+        for fixture in [100, 101] {
+            let qualified_id = QualifiedFixtureId {
+                patch: 0,
+                output: 123,
+                fixture,
+            };
+            render_target
+                .apply_state(&qualified_id, &state, &ColorPalette::default())
+                .unwrap();
+        }
+
+        // A fixture standing on the focus cannot aim at it, so it keeps the
+        // state's pan and the error is reported.
+        let qualified_id = QualifiedFixtureId {
+            patch: 0,
+            output: 123,
+            fixture: 102,
+        };
+        let error = render_target
+            .apply_state(&qualified_id, &state, &ColorPalette::default())
+            .unwrap_err();
+        assert!(matches!(error, RenderError::UnreachableFocus { .. }));
+
+        let universe = render_target.get_universes()[0];
+        // Both lean over to the middle of the stage from opposite sides.
+        assert_eq!(&universe[0..2], &[170, 212]);
+        assert_eq!(&universe[10..12], &[85, 212]);
+        assert_eq!(universe[20], 127);
+    }
+
+    /// A dimmer, a color wheel that falls back to mixing and moves in black
+    /// and CMY channels.
+    fn wheel_fixture() -> (
+        HashMap<u64, PhysicalDmxFixture>,
+        HashMap<u64, DmxFixtureDefinition>,
+    ) {
+        use crate::proto::dmx_fixture_definition::channel::{
+            AmountMapping, ColorWheelMapping, color_wheel_mapping::ColorWheelColor,
+        };
+
+        let mut mode = Mode::default();
+        let mut dimmer = Channel::default();
+        dimmer.r#type = "dimmer".to_string();
+        dimmer.mapping = Some(Mapping::AmountMapping(AmountMapping {
+            min_value: 0,
+            max_value: 255,
+        }));
+        mode.channels.insert(1, dimmer);
+
+        let wheel_color = |name: &str, value, red, green, blue| ColorWheelColor {
+            name: name.to_string(),
+            value,
+            color: Some(Color {
+                red,
+                green,
+                blue,
+                white: None,
+            }),
+            split_value: None,
+        };
+        let mut wheel = Channel::default();
+        wheel.r#type = "color_wheel".to_string();
+        wheel.mapping = Some(Mapping::ColorWheelMapping(ColorWheelMapping {
+            colors: vec![
+                wheel_color("Open", 0, 1.0, 1.0, 1.0),
+                wheel_color("Red", 51, 1.0, 0.0, 0.0),
+                wheel_color("Green", 102, 0.0, 1.0, 0.0),
+            ],
+            mix_fallback_distance: Some(0.1),
+            move_in_black: true,
+        }));
+        mode.channels.insert(2, wheel);
+
+        for (index, channel_type) in [(3, "cyan"), (4, "magenta"), (5, "yellow")] {
+            let mut channel = Channel::default();
+            channel.r#type = channel_type.to_string();
+            mode.channels.insert(index, channel);
+        }
+
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut physical_fixture = PhysicalDmxFixture::default();
+        physical_fixture.fixture_definition_id = 1;
+        physical_fixture.fixture_mode = "test-mode".to_string();
+        let mut fixtures = HashMap::new();
+        fixtures.insert(WHEEL_FIXTURE_ID.fixture, physical_fixture);
+
+        (fixtures, fixture_definitions)
+    }
+
+    const WHEEL_FIXTURE_ID: QualifiedFixtureId = QualifiedFixtureId {
+        patch: 0,
+        output: 123,
+        fixture: 100,
+    };
+
+    fn wheel_state(dimmer: f64, red: f64, green: f64, blue: f64) -> FixtureState {
+        let mut state = FixtureState::default();
+        state.dimmer = Some(dimmer);
+        state.light_color = Some(crate::proto::fixture_state::LightColor::Color(Color {
+            red,
+            green,
+            blue,
+            white: None,
+        }));
+        state
+    }
+
+    #[test]
+    fn test_colors_off_the_wheel_are_mixed() {
+        let (fixtures, fixture_definitions) = wheel_fixture();
+        let mut render_target = DmxRenderTarget::new(&fixtures, &fixture_definitions, 1);
+
+        render_target
+            .apply_state(
+                &WHEEL_FIXTURE_ID,
+                &wheel_state(1.0, 1.0, 0.0, 0.0),
+                &ColorPalette::default(),
+            )
+            .unwrap();
+        // Red is on the wheel, so the CMY channels stay open.
+        assert_eq!(&render_target.get_universes()[0][1..5], &[51, 0, 0, 0]);
+
+        render_target
+            .apply_state(
+                &WHEEL_FIXTURE_ID,
+                &wheel_state(1.0, 0.0, 0.0, 1.0),
+                &ColorPalette::default(),
+            )
+            .unwrap();
+        // Blue is not, so it is mixed through the open slot.
+        assert_eq!(&render_target.get_universes()[0][1..5], &[0, 255, 255, 0]);
+    }
+
+    #[test]
+    fn test_wheels_move_in_black() {
+        let (fixtures, fixture_definitions) = wheel_fixture();
+        let target = |state: FixtureState| {
+            let mut render_target = DmxRenderTarget::new(&fixtures, &fixture_definitions, 1);
+            render_target
+                .apply_state(&WHEEL_FIXTURE_ID, &state, &ColorPalette::default())
+                .unwrap();
+            render_target
+        };
+        let lit_red = target(wheel_state(1.0, 1.0, 0.0, 0.0));
+        let dark_red = target(wheel_state(0.0, 1.0, 0.0, 0.0));
+        let lit_green = target(wheel_state(1.0, 0.0, 1.0, 0.0));
+
+        let mut render_target = target(FixtureState::default());
+        // Between two lit colors the fade dips through black.
+        render_target.interpolate(&lit_red, &lit_green, 0.25);
+        assert_eq!(&render_target.get_universes()[0][0..2], &[127, 51]);
+        render_target.interpolate(&lit_red, &lit_green, 0.75);
+        assert_eq!(&render_target.get_universes()[0][0..2], &[127, 102]);
+
+        // Fading up from black turns the wheel straight away.
+        render_target.interpolate(&dark_red, &lit_green, 0.25);
+        assert_eq!(&render_target.get_universes()[0][0..2], &[63, 102]);
+    }
+
+    fn multi_byte_target<'a>(
+        fixtures: &'a HashMap<u64, PhysicalDmxFixture>,
+        fixture_definitions: &'a HashMap<u64, DmxFixtureDefinition>,
+    ) -> DmxRenderTarget<'a> {
+        DmxRenderTarget::new(fixtures, fixture_definitions, 1)
+    }
+
+    /// A 16 bit pan on channels 1 and 2, a 24 bit dimmer sent least
+    /// significant byte first on channels 3 to 5 and an older style 16 bit
+    /// tilt paired by its "-fine" type on channels 6 and 7.
+    fn multi_byte_fixture() -> (
+        HashMap<u64, PhysicalDmxFixture>,
+        HashMap<u64, DmxFixtureDefinition>,
+    ) {
+        let mut mode = Mode::default();
+        let mut pan = Channel::default();
+        pan.r#type = "pan".to_string();
+        pan.fine_channel = Some(2);
+        pan.mapping = Some(Mapping::AngleMapping(
+            crate::proto::dmx_fixture_definition::channel::AngleMapping {
+                min_degrees: 0,
+                max_degrees: 540,
+            },
+        ));
+        mode.channels.insert(1, pan);
+
+        let mut dimmer = Channel::default();
+        dimmer.r#type = "dimmer".to_string();
+        dimmer.fine_channel = Some(4);
+        dimmer.ultra_channel = Some(3);
+        dimmer.mapping = Some(Mapping::AmountMapping(
+            crate::proto::dmx_fixture_definition::channel::AmountMapping {
+                min_value: 0,
+                max_value: 255,
+            },
+        ));
+        mode.channels.insert(5, dimmer);
+
+        let mut tilt = Channel::default();
+        tilt.r#type = "tilt".to_string();
+        tilt.mapping = Some(Mapping::AngleMapping(
+            crate::proto::dmx_fixture_definition::channel::AngleMapping {
+                min_degrees: 0,
+                max_degrees: 270,
+            },
+        ));
+        mode.channels.insert(6, tilt);
+        let mut tilt_fine = Channel::default();
+        tilt_fine.r#type = "tilt-fine".to_string();
+        mode.channels.insert(7, tilt_fine);
+
+        let mut fixture_def = DmxFixtureDefinition::default();
+        fixture_def.modes.insert("test-mode".to_string(), mode);
+        let mut fixture_definitions = HashMap::new();
+        fixture_definitions.insert(1u64, fixture_def);
+
+        let mut physical_fixture = PhysicalDmxFixture::default();
+        physical_fixture.fixture_definition_id = 1;
+        physical_fixture.fixture_mode = "test-mode".to_string();
+        let mut fixtures = HashMap::new();
+        fixtures.insert(100u64, physical_fixture);
+
+        (fixtures, fixture_definitions)
+    }
+
+    const MULTI_BYTE_FIXTURE_ID: QualifiedFixtureId = QualifiedFixtureId {
+        patch: 0,
+        output: 123,
+        fixture: 100,
+    };
+
+    #[test]
+    fn test_multi_byte_channels_render_at_full_precision() {
+        let (fixtures, fixture_definitions) = multi_byte_fixture();
+        let mut render_target = multi_byte_target(&fixtures, &fixture_definitions);
+
+        let mut state = FixtureState::default();
+        state.pan = Some(540.0 / 3.0);
+        state.tilt = Some(270.0);
+        state.dimmer = Some(0.25);
+        render_target
+            .apply_state(&MULTI_BYTE_FIXTURE_ID, &state, &ColorPalette::default())
+            .unwrap();
+
+        let universe = render_target.get_universes()[0];
+        // 65535 / 3 = 0x5555
+        assert_eq!(&universe[0..2], &[0x55, 0x55]);
+        // 0xFFFFFF / 4 rounds to 0x400000, written ultra, fine, coarse.
+        assert_eq!(&universe[2..5], &[0x00, 0x00, 0x40]);
+        // Full scale fills the fine byte too.
+        assert_eq!(&universe[5..7], &[0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_interpolation_is_not_quantized_to_the_coarse_byte() {
+        let (fixtures, fixture_definitions) = multi_byte_fixture();
+        let mut a = multi_byte_target(&fixtures, &fixture_definitions);
+        let mut b = multi_byte_target(&fixtures, &fixture_definitions);
+
+        let mut state = FixtureState::default();
+        state.pan = Some(0.0);
+        a.apply_state(&MULTI_BYTE_FIXTURE_ID, &state, &ColorPalette::default())
+            .unwrap();
+        state.pan = Some(540.0 / 255.0);
+        b.apply_state(&MULTI_BYTE_FIXTURE_ID, &state, &ColorPalette::default())
+            .unwrap();
+
+        let mut render_target = multi_byte_target(&fixtures, &fixture_definitions);
+        render_target.interpolate(&a, &b, 0.25);
+
+        let universe = render_target.get_universes()[0];
+        // A quarter of one coarse step is 257 / 4 fine steps.
+        assert_eq!(&universe[0..2], &[0, 64]);
+    }
+
+    #[test]
+    fn test_raw_bytes_of_multi_byte_channels_are_kept() {
+        let (fixtures, fixture_definitions) = multi_byte_fixture();
+        let mut render_target = multi_byte_target(&fixtures, &fixture_definitions);
+
+        render_target
+            .apply_fixture_debug(&crate::proto::render_mode::FixtureDebug {
+                channel_values: vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let universe = render_target.get_universes()[0];
+        assert_eq!(&universe[0..7], &[0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE]);
+    }
+
+    #[test]
+    fn test_multi_byte_defaults_fill_every_byte() {
+        let (fixtures, mut fixture_definitions) = multi_byte_fixture();
+        let mode = fixture_definitions
+            .get_mut(&1)
+            .and_then(|d| d.modes.get_mut("test-mode"))
+            .unwrap();
+        for index in [1, 6] {
+            mode.channels.get_mut(&index).unwrap().default_value = 128;
+        }
+        let render_target = multi_byte_target(&fixtures, &fixture_definitions);
+
+        let universe = render_target.get_universes()[0];
+        assert_eq!(&universe[0..2], &[0x80, 0x00]);
+        assert_eq!(&universe[5..7], &[0x80, 0x00]);
+    }
+
+    #[test]
+    fn test_raw_fine_byte_writes_are_kept() {
+        let (fixtures, fixture_definitions) = multi_byte_fixture();
+        let mut render_target = multi_byte_target(&fixtures, &fixture_definitions);
+
+        let mut state = FixtureState::default();
+        state.pan = Some(540.0 / 3.0);
+        render_target
+            .apply_state(&MULTI_BYTE_FIXTURE_ID, &state, &ColorPalette::default())
+            .unwrap();
+        let mut state = FixtureState::default();
+        state.channels = vec![crate::proto::fixture_state::Channel {
+            index: 2,
+            value: 0x12,
+        }];
+        render_target
+            .apply_state(&MULTI_BYTE_FIXTURE_ID, &state, &ColorPalette::default())
+            .unwrap();
+
+        let universe = render_target.get_universes()[0];
+        assert_eq!(&universe[0..2], &[0x55, 0x12]);
+    }
 }
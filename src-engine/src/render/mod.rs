@@ -1,18 +1,24 @@
 // Declare submodules
 pub mod autopilot;
+mod color_solver;
+mod color_wheel;
+mod cue_list;
 pub mod display_render_target;
 pub mod dmx_render_target;
+pub mod playback;
 mod preset_effect;
 mod project;
 mod ramp_effect;
 mod random_effect;
 #[allow(clippy::module_inception)]
 pub mod render;
-mod render_target;
+pub(crate) mod render_target;
 pub mod scene;
 pub mod segment_mapping;
 mod sequence_effect;
+pub(crate) mod sequence_index;
+mod shape_effect;
 mod strobe_effect;
 mod timecoded_show;
-mod util;
+pub(crate) mod util;
 pub mod wled_render_target;
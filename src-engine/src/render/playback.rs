@@ -0,0 +1,340 @@
+//! Plays a recorded DMX frame log back to the outputs.
+//!
+//! Playback is synchronized to `system_t` the same way timecoded shows are:
+//! while playing, the render mode holds the `system_t` at which the recording
+//! started, and while paused it holds the position. The controls below
+//! re-anchor that state so the position never jumps.
+
+use crate::{
+    proto::{
+        Project,
+        render_mode::{RecordingPlayback, recording_playback::State},
+    },
+    recording,
+    render::{render::RenderError, render_target::RenderTarget, util::dmx_universes},
+};
+
+pub fn render_recording_playback<T: RenderTarget<T>>(
+    playback: &RecordingPlayback,
+    state: &State,
+    render_target: &mut T,
+    output_id: u64,
+    system_t: u64,
+    project: &Project,
+) -> Result<(), RenderError> {
+    let recording_id = playback.recording_id;
+    let digest = &project
+        .recordings
+        .get(&recording_id)
+        .ok_or(RenderError::MissingRecording { recording_id })?
+        .digest;
+    let recording =
+        recording::loaded(digest).ok_or(RenderError::RecordingNotLoaded { recording_id })?;
+
+    let Some((first_universe, universe_count)) = dmx_universes(project, output_id) else {
+        return Ok(());
+    };
+
+    let mut t = position_ms(playback, state, system_t);
+    let duration = recording.duration_ms();
+    if playback.looping && duration > 0 {
+        t %= duration;
+    }
+    let Some(frame) = recording.frame_at(t) else {
+        return Ok(());
+    };
+
+    // A stream rendered from this output plays back as it was. Anything else,
+    // such as a capture from another console, plays back to the output
+    // sending its protocol universes.
+    let streams: Vec<_> = recording.stream_universes(frame).collect();
+    if let Some((_, universes)) = streams.iter().find(|(s, _)| s.output_id == output_id) {
+        for (index, values) in universes.iter().enumerate() {
+            render_target.apply_recorded_universe(index, values);
+        }
+        return Ok(());
+    }
+    for (stream, universes) in streams.iter().filter(|(s, _)| s.output_id == 0) {
+        for (offset, values) in (0u32..).zip(universes.iter()) {
+            let Some(index) = (stream.first_universe + offset).checked_sub(first_universe) else {
+                continue;
+            };
+            if index < universe_count {
+                render_target.apply_recorded_universe(index as usize, values);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Playback rate, treating anything but a positive speed as real time.
+fn speed(playback: &RecordingPlayback) -> f64 {
+    if playback.speed > 0.0 {
+        playback.speed
+    } else {
+        1.0
+    }
+}
+
+/// Position in the recording at `system_t`, before looping.
+#[must_use]
+pub fn position_ms(playback: &RecordingPlayback, state: &State, system_t: u64) -> u64 {
+    match state {
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        State::StartT(t) => (system_t.saturating_sub(*t) as f64 * speed(playback)) as u64,
+        State::PausedMs(t) => u64::from(*t),
+    }
+}
+
+/// Anchors playback so it is at `position_ms` at `system_t`.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn start_t(playback: &RecordingPlayback, position_ms: u64, system_t: u64) -> u64 {
+    system_t.saturating_sub((position_ms as f64 / speed(playback)) as u64)
+}
+
+/// Holds playback at its current position.
+pub fn pause(playback: &mut RecordingPlayback, system_t: u64) {
+    if let Some(state) = &playback.state {
+        let position = position_ms(playback, state, system_t);
+        playback.state = Some(State::PausedMs(u32::try_from(position).unwrap_or(u32::MAX)));
+    }
+}
+
+/// Plays from the paused position, or from the start if playback never began.
+pub fn resume(playback: &mut RecordingPlayback, system_t: u64) {
+    let position = match &playback.state {
+        Some(State::StartT(_)) => return,
+        Some(State::PausedMs(t)) => u64::from(*t),
+        None => 0,
+    };
+    playback.state = Some(State::StartT(start_t(playback, position, system_t)));
+}
+
+/// Jumps to `position_ms`, staying paused if playback is paused.
+pub fn seek(playback: &mut RecordingPlayback, position_ms: u32, system_t: u64) {
+    playback.state = Some(match playback.state {
+        Some(State::PausedMs(_)) => State::PausedMs(position_ms),
+        _ => State::StartT(start_t(playback, u64::from(position_ms), system_t)),
+    });
+}
+
+/// Changes the playback rate without moving the current position.
+pub fn set_speed(playback: &mut RecordingPlayback, speed: f64, system_t: u64) {
+    let position = match &playback.state {
+        Some(state @ State::StartT(_)) => Some(position_ms(playback, state, system_t)),
+        _ => None,
+    };
+    playback.speed = speed;
+    if let Some(position) = position {
+        playback.state = Some(State::StartT(start_t(playback, position, system_t)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        proto::{
+            ArtnetDmxOutput, DmxRecording, FixtureDefinitions, Patch, RenderMode, output::Output,
+            render_mode::Mode,
+        },
+        recording::{Frame, Recording, Stream},
+        render::{dmx_render_target::UNIVERSE_SIZE, render::render_dmx_in_mode},
+    };
+
+    fn artnet(universe: u32, universe_count: u32) -> crate::proto::Output {
+        crate::proto::Output {
+            output: Some(Output::ArtnetDmxOutput(ArtnetDmxOutput {
+                universe,
+                universe_count,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn project(outputs: HashMap<u64, crate::proto::Output>) -> Project {
+        Project {
+            active_patch: 1,
+            fixture_definitions: Some(FixtureDefinitions::default()),
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    name: "Main".to_string(),
+                    outputs,
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    /// Loads a one second, 10 fps recording of `stream` as recording 1, in
+    /// which channel 1 of universe `u` reads `100 * u` plus the frame index.
+    fn record(project: &mut Project, digest: &str, stream: Stream) {
+        let universe_count = u8::try_from(stream.universe_count).unwrap();
+        let frames = (0u8..10)
+            .map(|index| Frame {
+                t_ms: u64::from(index) * 100,
+                universes: (0..universe_count)
+                    .map(|u| {
+                        let mut universe = [0; UNIVERSE_SIZE];
+                        universe[0] = 100 * u + index;
+                        universe
+                    })
+                    .collect(),
+            })
+            .collect();
+        let recording = Recording {
+            fps: 10,
+            streams: vec![stream],
+            frames,
+        };
+        recording::load(digest, &recording.encode()).unwrap();
+        project.recordings.insert(
+            1,
+            DmxRecording {
+                name: "Take 1".to_string(),
+                digest: digest.to_string(),
+            },
+        );
+    }
+
+    fn playback(looping: bool, state: State) -> RenderMode {
+        RenderMode {
+            mode: Some(Mode::RecordingPlayback(RecordingPlayback {
+                recording_id: 1,
+                looping,
+                speed: 2.0,
+                state: Some(state),
+            })),
+        }
+    }
+
+    fn channel_1(project: &Project, render_mode: &RenderMode, output_id: u64, t: u64) -> u8 {
+        let rendered = render_dmx_in_mode(
+            project,
+            render_mode,
+            output_id,
+            t,
+            0,
+            &crate::audio::AudioAnalysis::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(rendered.error.is_none(), "{:?}", rendered.error);
+        rendered.frame[0][0]
+    }
+
+    #[test]
+    fn plays_back_at_speed_and_loops() {
+        let mut project = project(HashMap::from([(5, artnet(0, 1))]));
+        let stream = Stream {
+            output_id: 5,
+            first_universe: 0,
+            universe_count: 1,
+        };
+        record(&mut project, "plays-back-at-speed", stream);
+
+        // At double speed, 200ms after the start is frame 4.
+        let held = playback(false, State::StartT(1_000));
+        assert_eq!(channel_1(&project, &held, 5, 1_000), 0);
+        assert_eq!(channel_1(&project, &held, 5, 1_200), 4);
+        assert_eq!(channel_1(&project, &held, 5, 3_000), 9);
+
+        let looped = playback(true, State::StartT(1_000));
+        assert_eq!(channel_1(&project, &looped, 5, 1_600), 2);
+
+        let paused = playback(false, State::PausedMs(700));
+        assert_eq!(channel_1(&project, &paused, 5, 99_999), 7);
+    }
+
+    #[test]
+    fn captures_play_back_by_protocol_universe() {
+        let mut project = project(HashMap::from([(5, artnet(3, 1)), (6, artnet(9, 1))]));
+        let stream = Stream {
+            output_id: 0,
+            first_universe: 2,
+            universe_count: 2,
+        };
+        record(&mut project, "captured", stream);
+
+        let mode = playback(false, State::PausedMs(300));
+        assert_eq!(channel_1(&project, &mode, 5, 0), 103);
+        assert_eq!(channel_1(&project, &mode, 6, 0), 0);
+    }
+
+    #[test]
+    fn reports_recordings_that_are_missing_or_not_loaded() {
+        let mut project = project(HashMap::from([(5, artnet(0, 1))]));
+        let mode = playback(false, State::PausedMs(0));
+        let render = |project: &Project| {
+            render_dmx_in_mode(
+                project,
+                &mode,
+                5,
+                0,
+                0,
+                &crate::audio::AudioAnalysis::default(),
+                None,
+                None,
+            )
+            .unwrap()
+            .error
+            .map(|e| e.to_string())
+        };
+
+        assert_eq!(
+            render(&project).as_deref(),
+            Some("Could not find DMX recording 1")
+        );
+        project.recordings.insert(
+            1,
+            DmxRecording {
+                name: "Take 1".to_string(),
+                digest: "never-loaded".to_string(),
+            },
+        );
+        assert_eq!(
+            render(&project).as_deref(),
+            Some("The frame log of DMX recording 1 has not been loaded")
+        );
+    }
+
+    #[test]
+    fn controls_keep_the_position() {
+        let mut playback = RecordingPlayback {
+            recording_id: 1,
+            speed: 1.0,
+            ..Default::default()
+        };
+        resume(&mut playback, 10_000);
+        assert_eq!(playback.state, Some(State::StartT(10_000)));
+
+        set_speed(&mut playback, 2.0, 11_000);
+        let state = playback.state.unwrap();
+        assert_eq!(position_ms(&playback, &state, 11_000), 1_000);
+        assert_eq!(position_ms(&playback, &state, 12_000), 3_000);
+
+        pause(&mut playback, 12_000);
+        assert_eq!(playback.state, Some(State::PausedMs(3_000)));
+
+        seek(&mut playback, 500, 20_000);
+        assert_eq!(playback.state, Some(State::PausedMs(500)));
+
+        resume(&mut playback, 20_000);
+        let state = playback.state.unwrap();
+        assert_eq!(position_ms(&playback, &state, 20_000), 500);
+        assert_eq!(position_ms(&playback, &state, 20_500), 1_500);
+    }
+}
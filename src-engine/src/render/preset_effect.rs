@@ -1,13 +1,16 @@
 #![allow(clippy::cast_precision_loss)]
 
 use crate::{
+    color::{linear_to_srgb, oklab_to_linear_srgb},
     proto::{
         ColorPalette, FixtureState, OutputTarget, Project,
         effect::{PresetEffect, preset_effect},
     },
     render::{
+        render::RenderError,
         render_target::RenderTarget,
-        util::{apply_state, calculate_timing, get_fixtures},
+        shape_effect::{apply_dimmer_chase_effect, apply_shape_effect},
+        util::{apply_state, calculate_timing, get_fixtures, required},
     },
 };
 
@@ -20,36 +23,52 @@ pub fn apply_preset_effect<T: RenderTarget<T>>(
     beat_t: f64,
     preset_effect: &PresetEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
     let Some(effect) = &preset_effect.effect else {
-        return;
+        return Ok(());
     };
 
     match effect {
-        preset_effect::Effect::RainbowEffect(rainbow_effect) => {
-            apply_rainbow_effect(
-                project,
-                render_target,
-                output_target,
-                system_t,
-                effect_t,
-                beat_t,
-                rainbow_effect,
-                color_palette,
-            );
-        }
-        preset_effect::Effect::CircleEffect(circle_effect) => {
-            apply_circle_effect(
-                project,
-                render_target,
-                output_target,
-                system_t,
-                effect_t,
-                beat_t,
-                circle_effect,
-                color_palette,
-            );
-        }
+        preset_effect::Effect::RainbowEffect(rainbow_effect) => apply_rainbow_effect(
+            project,
+            render_target,
+            output_target,
+            system_t,
+            effect_t,
+            beat_t,
+            rainbow_effect,
+            color_palette,
+        ),
+        preset_effect::Effect::CircleEffect(circle_effect) => apply_circle_effect(
+            project,
+            render_target,
+            output_target,
+            system_t,
+            effect_t,
+            beat_t,
+            circle_effect,
+            color_palette,
+        ),
+        preset_effect::Effect::ShapeEffect(shape_effect) => apply_shape_effect(
+            project,
+            render_target,
+            output_target,
+            system_t,
+            effect_t,
+            beat_t,
+            shape_effect,
+            color_palette,
+        ),
+        preset_effect::Effect::DimmerChaseEffect(dimmer_chase_effect) => apply_dimmer_chase_effect(
+            project,
+            render_target,
+            output_target,
+            system_t,
+            effect_t,
+            beat_t,
+            dimmer_chase_effect,
+            color_palette,
+        ),
     }
 }
 
@@ -62,18 +81,20 @@ fn apply_rainbow_effect<T: RenderTarget<T>>(
     beat_t: f64,
     rainbow_effect: &preset_effect::RainbowEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
+    let timing_mode = required(rainbow_effect.timing_mode.as_ref(), "rainbow", "timing")?;
     let fixtures = get_fixtures(project, output_target);
 
     for info in fixtures.values() {
         let t = calculate_timing(
-            &rainbow_effect.timing_mode.unwrap(),
+            "rainbow",
+            timing_mode,
             system_t,
             effect_t,
             beat_t,
             info.phase,
             info.index,
-        );
+        )?;
 
         // Convert HSV to RGB for rainbow effect
         // Hue varies from 0.0 to 1.0 (full color wheel)
@@ -90,8 +111,10 @@ fn apply_rainbow_effect<T: RenderTarget<T>>(
             &info.output_target,
             &state,
             color_palette,
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 fn apply_circle_effect<T: RenderTarget<T>>(
@@ -103,18 +126,20 @@ fn apply_circle_effect<T: RenderTarget<T>>(
     beat_t: f64,
     circle_effect: &preset_effect::CircleEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
+    let timing_mode = required(circle_effect.timing_mode.as_ref(), "circle", "timing")?;
     let fixtures = get_fixtures(project, output_target);
 
     for info in fixtures.values() {
         let t = calculate_timing(
-            &circle_effect.timing_mode.unwrap(),
+            "circle",
+            timing_mode,
             system_t,
             effect_t,
             beat_t,
             info.phase,
             info.index,
-        );
+        )?;
 
         let angle = t * 2.0 * std::f64::consts::PI;
         let pan_amount = f64::midpoint(angle.cos(), 1.0);
@@ -138,8 +163,10 @@ fn apply_circle_effect<T: RenderTarget<T>>(
             &info.output_target,
             &state,
             color_palette,
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Convert hue to RGB (with saturation=1.0, value=1.0)
@@ -191,19 +218,7 @@ fn hue_to_rgb_oklch(h: f64) -> crate::proto::Color {
     let b = c * h_rad.sin();
 
     // Convert Oklab to Linear RGB
-    let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
-    let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
-    let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
-
-    // Cube to get LMS
-    let l_lms = l_ * l_ * l_;
-    let m_lms = m_ * m_ * m_;
-    let s_lms = s_ * s_ * s_;
-
-    // Convert LMS to Linear RGB
-    let r_linear = 4.076_741_662_1 * l_lms - 3.307_711_591_3 * m_lms + 0.230_969_929_2 * s_lms;
-    let g_linear = -1.268_438_004_6 * l_lms + 2.609_757_401_1 * m_lms - 0.341_319_396_5 * s_lms;
-    let b_linear = -0.004_196_086_3 * l_lms - 0.703_418_614_7 * m_lms + 1.707_614_701_0 * s_lms;
+    let [r_linear, g_linear, b_linear] = oklab_to_linear_srgb([l, a, b]);
 
     // Apply sRGB gamma correction and clamp to [0, 1]
     let r = linear_to_srgb(r_linear).clamp(0.0, 1.0);
@@ -218,15 +233,6 @@ fn hue_to_rgb_oklch(h: f64) -> crate::proto::Color {
     }
 }
 
-/// Apply sRGB gamma correction to linear RGB value
-fn linear_to_srgb(linear: f64) -> f64 {
-    if linear <= 0.003_130_8 {
-        linear * 12.92
-    } else {
-        1.055 * linear.powf(1.0 / 2.4) - 0.055
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::hue_to_rgb;
@@ -8,13 +8,10 @@ impl Project {
     pub fn get_all_qualified_fixture_ids(&self) -> Vec<QualifiedFixtureId> {
         let patch_id = self.active_patch;
 
-        let mut outputs: Vec<_> = self
-            .patches
-            .get(&patch_id)
-            .unwrap()
-            .outputs
-            .iter()
-            .collect();
+        let Some(patch) = self.patches.get(&patch_id) else {
+            return Vec::new();
+        };
+        let mut outputs: Vec<_> = patch.outputs.iter().collect();
 
         outputs.sort_by_key(|(output_id, _)| *output_id);
 
@@ -28,7 +25,9 @@ impl Project {
                         | Output::SerialDmxOutput(SerialDmxOutput { fixtures, .. })
                         | Output::ArtnetDmxOutput(ArtnetDmxOutput { fixtures, .. }) => {
                             let mut fixtures: Vec<_> = fixtures.iter().collect();
-                            fixtures.sort_by_key(|(_, fixture)| fixture.channel_offset);
+                            fixtures.sort_by_key(|(_, fixture)| {
+                                (fixture.universe, fixture.channel_offset)
+                            });
                             fixtures
                                 .into_iter()
                                 .map(|(fixture_id, _)| QualifiedFixtureId {
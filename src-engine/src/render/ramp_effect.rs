@@ -1,8 +1,9 @@
 use crate::{
     proto::{ColorPalette, OutputTarget, Project, effect::RampEffect},
     render::{
+        render::RenderError,
         render_target::RenderTarget,
-        util::{apply_state, calculate_timing, get_fixtures},
+        util::{apply_state, calculate_timing, get_fixtures, required},
     },
 };
 
@@ -15,18 +16,23 @@ pub fn apply_ramp_effect<T: RenderTarget<T>>(
     beat_t: f64,
     ramp_effect: &RampEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
+    let timing_mode = required(ramp_effect.timing_mode.as_ref(), "ramp", "timing")?;
+    let state_start = required(ramp_effect.state_start.as_ref(), "ramp", "start state")?;
+    let state_end = required(ramp_effect.state_end.as_ref(), "ramp", "end state")?;
+
     let fixtures = get_fixtures(project, output_target);
 
     for info in fixtures.values() {
         let t = calculate_timing(
-            &ramp_effect.timing_mode.unwrap(),
+            "ramp",
+            timing_mode,
             system_t,
             effect_t,
             beat_t,
             info.phase,
             info.index,
-        );
+        )?;
 
         let mut start = render_target.clone();
         let mut end = render_target.clone();
@@ -35,18 +41,21 @@ pub fn apply_ramp_effect<T: RenderTarget<T>>(
             project,
             &mut start,
             &info.output_target,
-            ramp_effect.state_start.as_ref().unwrap(),
+            state_start,
             color_palette,
-        );
+        )?;
 
         apply_state(
             project,
             &mut end,
             &info.output_target,
-            ramp_effect.state_end.as_ref().unwrap(),
+            state_end,
             color_palette,
-        );
+        )?;
 
         render_target.interpolate(&start, &end, t);
+        render_target.interpolate_colors(&start, &end, t, ramp_effect.color_interpolation());
     }
+
+    Ok(())
 }
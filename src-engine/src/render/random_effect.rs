@@ -3,6 +3,7 @@ use std::sync::LazyLock;
 use crate::{
     proto::{ColorPalette, OutputTarget, Project, effect::RandomEffect},
     render::{
+        render::RenderError,
         render_target::RenderTarget,
         util::{apply_effect, get_fixtures},
     },
@@ -17,7 +18,7 @@ pub fn apply_random_effect<T: RenderTarget<T>>(
     beat_t: f64,
     random_effect: &RandomEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
     #[allow(clippy::cast_precision_loss)]
     let random_number_half = (get_random_numbers().len() / 2) as f64;
     let window_size = get_odd_sum() * f64::from(random_effect.effect_a_variation)
@@ -40,8 +41,9 @@ pub fn apply_random_effect<T: RenderTarget<T>>(
                 random_effect,
                 color_palette,
                 window_size,
-            );
+            )?;
         }
+        Ok(())
     } else {
         apply_random_effect_impl(
             project,
@@ -54,7 +56,7 @@ pub fn apply_random_effect<T: RenderTarget<T>>(
             random_effect,
             color_palette,
             window_size,
-        );
+        )
     }
 }
 
@@ -69,16 +71,24 @@ fn apply_random_effect_impl<T: RenderTarget<T>>(
     random_effect: &RandomEffect,
     color_palette: &ColorPalette,
     window_size: f64,
-) {
+) -> Result<(), RenderError> {
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_sign_loss)]
-    let effect_t = system_t.wrapping_add(
-        LARGE_PRIME.wrapping_mul(
-            LARGE_PRIME
-                .wrapping_mul(seed)
-                .wrapping_add(u64::from(random_effect.seed)),
-        ),
-    ) % window_size as u64;
+    let Some(effect_t) = system_t
+        .wrapping_add(
+            LARGE_PRIME.wrapping_mul(
+                LARGE_PRIME
+                    .wrapping_mul(seed)
+                    .wrapping_add(u64::from(random_effect.seed)),
+            ),
+        )
+        .checked_rem(window_size as u64)
+    else {
+        return Err(RenderError::IncompleteEffect {
+            effect: "random",
+            missing: "duration",
+        });
+    };
 
     let mut counter = 0.0;
     for (i, number) in get_random_numbers().iter().enumerate() {
@@ -103,7 +113,12 @@ fn apply_random_effect_impl<T: RenderTarget<T>>(
                 random_effect.effect_b.as_ref()
             };
 
-            apply_effect(
+            // An unset effect plays nothing for its stretch.
+            let Some(sub_effect) = sub_effect.and_then(|e| e.effect.as_ref()) else {
+                return Ok(());
+            };
+
+            return apply_effect(
                 project,
                 render_target,
                 output_target,
@@ -111,12 +126,13 @@ fn apply_random_effect_impl<T: RenderTarget<T>>(
                 Some(&sub_effect_fract),
                 beat_t,
                 frame,
-                sub_effect.unwrap().effect.as_ref().unwrap(),
+                sub_effect,
                 color_palette,
             );
-            break;
         }
     }
+
+    Ok(())
 }
 
 pub const LARGE_PRIME: u64 = 4_294_967_291;
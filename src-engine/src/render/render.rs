@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{LazyLock, Mutex};
 
@@ -5,15 +6,22 @@ use crate::audio::AudioAnalysis;
 use crate::beat::{beat_t, effective_beat_metadata};
 use crate::palette::interpolated_scene_palette;
 use crate::proto::QualifiedFixtureId;
-use crate::proto::render_mode::{Autopilot, FixtureHighlight, TimecodedShow};
+use crate::proto::render_mode::{
+    Autopilot, CueList, FixtureHighlight, RecordingPlayback, TimecodedShow,
+};
 use crate::render::autopilot::render_playlist;
+use crate::render::cue_list::render_cue_list;
+use crate::render::playback::render_recording_playback;
 use crate::render::timecoded_show::render_timecoded_show;
 use crate::visualizer::uniforms::ShaderUniforms;
 use crate::{
+    dmx_input::DmxInputState,
+    masters, park,
+    programmer::{self, PROGRAMMER_REF},
     project,
     proto::{
-        Color, ColorPalette, DisplayRenderTarget, FixtureState, OutputTarget, Project, RenderMode,
-        WledRenderTarget,
+        Color, ColorPalette, DisplayRenderTarget, FixtureState, OutputTarget, Programmer, Project,
+        RenderMode, WledRenderTarget,
         color_palette::ColorDescription,
         fixture_state::LightColor,
         output::Output,
@@ -22,7 +30,9 @@ use crate::{
         wled_render_target::Segment,
     },
     render::{
-        dmx_render_target::DmxRenderTarget, render_target::RenderTarget, scene::render_scene,
+        dmx_render_target::{DmxRenderTarget, UNIVERSE_SIZE},
+        render_target::RenderTarget,
+        scene::render_scene,
         util::get_fixtures,
     },
 };
@@ -45,6 +55,80 @@ pub enum RenderError {
     PlaylistError(String),
     /// Timecoded show rendering error.
     TimecodedShowError(String),
+    /// A sequence effect refers to a sequence that does not exist.
+    MissingSequence { sequence_id: u64 },
+    /// A sequence plays itself through nested sequence effects.
+    SequenceCycle { sequence_id: u64 },
+    /// An effect is missing something it needs to render, such as its timing.
+    IncompleteEffect {
+        effect: &'static str,
+        missing: &'static str,
+    },
+    /// A field holds an enum value this version does not know.
+    UnknownValue { field: &'static str, value: i32 },
+    /// A state uses a palette color that the palette does not define.
+    MissingPaletteColor {
+        palette: String,
+        color: &'static str,
+    },
+    /// A fixture's definition or mode could not be found, so it stays dark.
+    MissingFixtureMode {
+        fixture_id: u64,
+        definition_id: u64,
+        mode: String,
+    },
+    /// A fixture cannot point at the focus of a state, so it keeps the
+    /// state's pan and tilt.
+    UnreachableFocus { fixture: String, reason: String },
+    /// A tile cannot work out how strongly to apply itself, so it is skipped.
+    InvalidTile {
+        scene_id: u64,
+        tile: String,
+        reason: &'static str,
+    },
+    /// An effect of a scene tile failed to render.
+    InTile {
+        scene_id: u64,
+        tile: String,
+        error: Box<RenderError>,
+    },
+    /// An effect of a playlist pattern failed to render.
+    InPattern {
+        playlist_id: u64,
+        pattern: String,
+        error: Box<RenderError>,
+    },
+    /// A cue of a cue list failed to render.
+    InCue {
+        cue_list_id: u64,
+        cue: String,
+        error: Box<RenderError>,
+    },
+    /// An effect of a timecoded show failed to render.
+    InShow {
+        show_id: u64,
+        error: Box<RenderError>,
+    },
+    /// The cue list being run does not exist.
+    MissingCueList { cue_list_id: u64 },
+    /// A recording playback refers to a recording that does not exist.
+    MissingRecording { recording_id: u64 },
+    /// The host has not loaded the frame log of a recording.
+    RecordingNotLoaded { recording_id: u64 },
+    /// Several parts of a frame failed while the rest rendered.
+    Multiple(Vec<RenderError>),
+}
+
+impl RenderError {
+    /// Combines every error of a frame into one, if there were any.
+    #[must_use]
+    pub fn from_errors(mut errors: Vec<RenderError>) -> Option<RenderError> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Self::Multiple(errors)),
+        }
+    }
 }
 
 impl fmt::Display for RenderError {
@@ -62,6 +146,73 @@ impl fmt::Display for RenderError {
             Self::SceneError(msg) => write!(f, "Scene error: {msg}"),
             Self::PlaylistError(msg) => write!(f, "Playlist error: {msg}"),
             Self::TimecodedShowError(msg) => write!(f, "Timecoded show error: {msg}"),
+            Self::MissingSequence { sequence_id } => {
+                write!(f, "Could not find sequence {sequence_id}")
+            }
+            Self::SequenceCycle { sequence_id } => {
+                write!(f, "Sequence {sequence_id} plays itself")
+            }
+            Self::IncompleteEffect { effect, missing } => {
+                write!(f, "The {effect} effect has no {missing}")
+            }
+            Self::UnknownValue { field, value } => write!(f, "Unknown {field} {value}"),
+            Self::MissingPaletteColor { palette, color } => {
+                write!(f, "Palette \"{palette}\" has no {color} color")
+            }
+            Self::MissingFixtureMode {
+                fixture_id,
+                definition_id,
+                mode,
+            } => write!(
+                f,
+                "Fixture {fixture_id} uses mode \"{mode}\" of definition {definition_id} which does not exist"
+            ),
+            Self::UnreachableFocus { fixture, reason } => {
+                write!(f, "Fixture \"{fixture}\" cannot reach its focus: {reason}")
+            }
+            Self::InvalidTile {
+                scene_id,
+                tile,
+                reason,
+            } => write!(f, "Tile \"{tile}\" of scene {scene_id} {reason}"),
+            Self::InTile {
+                scene_id,
+                tile,
+                error,
+            } => write!(f, "Tile \"{tile}\" of scene {scene_id}: {error}"),
+            Self::InPattern {
+                playlist_id,
+                pattern,
+                error,
+            } => write!(
+                f,
+                "Pattern \"{pattern}\" of playlist {playlist_id}: {error}"
+            ),
+            Self::InCue {
+                cue_list_id,
+                cue,
+                error,
+            } => write!(f, "Cue \"{cue}\" of cue list {cue_list_id}: {error}"),
+            Self::InShow { show_id, error } => write!(f, "Timecoded show {show_id}: {error}"),
+            Self::MissingCueList { cue_list_id } => {
+                write!(f, "Could not find cue list {cue_list_id}")
+            }
+            Self::MissingRecording { recording_id } => {
+                write!(f, "Could not find DMX recording {recording_id}")
+            }
+            Self::RecordingNotLoaded { recording_id } => write!(
+                f,
+                "The frame log of DMX recording {recording_id} has not been loaded"
+            ),
+            Self::Multiple(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{error}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -74,69 +225,147 @@ impl From<String> for RenderError {
     }
 }
 
+/// A rendered frame along with whatever went wrong while rendering it. The
+/// parts of the frame that failed are left as they were, everything else
+/// renders as usual.
+#[derive(Debug)]
+pub struct Rendered<T> {
+    pub frame: T,
+    pub error: Option<RenderError>,
+}
+
 /// Global static render mode instance
 /// Can be accessed from both WASM and Tauri contexts
 pub static RENDER_MODE_REF: LazyLock<Mutex<RenderMode>> =
     LazyLock::new(|| Mutex::new(RenderMode::default()));
 
-pub fn render_dmx(output_id: u64, system_t: u64, frame: u32) -> Result<[u8; 512], RenderError> {
+/// Renders every universe of a DMX output, starting with its first universe,
+/// with the live programmer on top. Received DMX is passed through or merged
+/// in only when `dmx_input` is given, which the output loop does and a preview
+/// does not.
+pub fn render_dmx(
+    output_id: u64,
+    system_t: u64,
+    frame: u32,
+    dmx_input: Option<&mut DmxInputState>,
+) -> Result<Rendered<Vec<[u8; UNIVERSE_SIZE]>>, RenderError> {
     let audio_analysis = crate::audio::get_audio_analysis();
 
     // Use nested Result to carry RenderError through the String-based with_project
-    let nested_result: Result<Result<[u8; 512], RenderError>, String> =
+    #[allow(clippy::type_complexity)]
+    let nested_result: Result<Result<Rendered<Vec<[u8; UNIVERSE_SIZE]>>, RenderError>, String> =
         project::with_project(|project| {
-            let fixtures = match project
-                .patches
-                .get(&project.active_patch)
-                .and_then(|p| p.outputs.get(&output_id))
-                .and_then(|o| o.output.as_ref())
-            {
-                Some(Output::SerialDmxOutput(serial)) => &serial.fixtures,
-                Some(Output::SacnDmxOutput(sacn)) => &sacn.fixtures,
-                Some(Output::ArtnetDmxOutput(artnet)) => &artnet.fixtures,
-                Some(_) => return Ok(Err(RenderError::WrongOutputType)),
-                None => {
-                    return Ok(Err(RenderError::OutputNotFound {
-                        output_id,
-                        patch_id: project.active_patch,
-                    }));
-                }
+            let render_mode = match RENDER_MODE_REF.lock() {
+                Ok(render_mode) => render_mode,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
             };
-
-            let Some(fixture_definitions) = project
-                .fixture_definitions
-                .as_ref()
-                .map(|d| &d.dmx_fixture_definitions)
-            else {
-                return Ok(Err(RenderError::MissingFixtureDefinitions));
+            let programmer = match PROGRAMMER_REF.lock() {
+                Ok(programmer) => programmer,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
             };
-
-            let mut render_target = DmxRenderTarget::new(fixtures, fixture_definitions);
-
-            Ok(render(
+            Ok(render_dmx_in_mode(
+                project,
+                &render_mode,
                 output_id,
-                &mut render_target,
                 system_t,
                 frame,
-                project,
                 &audio_analysis,
-            )
-            .map(|()| render_target.get_universe()))
+                Some(&programmer),
+                dmx_input,
+            ))
         });
 
     // Flatten: String error -> RenderError::LockError, then unwrap inner Result
     nested_result.map_err(RenderError::LockError)?
 }
 
+/// Renders a DMX output of `project` in `render_mode` rather than the live
+/// project and render mode. Used by [`render_dmx`] and by offline renders,
+/// which leave out `programmer` and `dmx_input` so they depend on nothing but
+/// the project.
+pub fn render_dmx_in_mode(
+    project: &Project,
+    render_mode: &RenderMode,
+    output_id: u64,
+    system_t: u64,
+    frame: u32,
+    audio_analysis: &AudioAnalysis,
+    programmer: Option<&Programmer>,
+    mut dmx_input: Option<&mut DmxInputState>,
+) -> Result<Rendered<Vec<[u8; UNIVERSE_SIZE]>>, RenderError> {
+    let (fixtures, universe_count) = match project
+        .patches
+        .get(&project.active_patch)
+        .and_then(|p| p.outputs.get(&output_id))
+        .and_then(|o| o.output.as_ref())
+    {
+        Some(Output::SerialDmxOutput(serial)) => (&serial.fixtures, 1),
+        Some(Output::SacnDmxOutput(sacn)) => (&sacn.fixtures, sacn.universe_count),
+        Some(Output::ArtnetDmxOutput(artnet)) => (&artnet.fixtures, artnet.universe_count),
+        Some(_) => return Err(RenderError::WrongOutputType),
+        None => {
+            return Err(RenderError::OutputNotFound {
+                output_id,
+                patch_id: project.active_patch,
+            });
+        }
+    };
+
+    let fixture_definitions = project
+        .fixture_definitions
+        .as_ref()
+        .map(|d| &d.dmx_fixture_definitions)
+        .ok_or(RenderError::MissingFixtureDefinitions)?;
+
+    let mut render_target =
+        DmxRenderTarget::new(fixtures, fixture_definitions, universe_count as usize);
+    let mut errors = render_target.missing_fixture_modes();
+
+    errors.extend(render(
+        output_id,
+        &mut render_target,
+        system_t,
+        frame,
+        project,
+        render_mode,
+        audio_analysis,
+        programmer,
+        dmx_input.as_deref(),
+    ));
+
+    if let Some(dmx_input) = dmx_input.as_mut() {
+        dmx_input.merge(
+            project,
+            render_mode,
+            output_id,
+            &mut render_target,
+            system_t,
+        );
+    }
+
+    let color_palette = interpolated_scene_palette(project, project.active_scene, system_t);
+    errors.extend(park::apply_parked(
+        project,
+        output_id,
+        &mut render_target,
+        &color_palette,
+    ));
+
+    Ok(Rendered {
+        frame: render_target.get_universes(),
+        error: RenderError::from_errors(errors),
+    })
+}
+
 pub fn render_wled(
     output_id: u64,
     system_t: u64,
     frame: u32,
-) -> Result<WledRenderTarget, RenderError> {
+) -> Result<Rendered<WledRenderTarget>, RenderError> {
     let audio_analysis = crate::audio::get_audio_analysis();
 
     // Use nested Result to carry RenderError through the String-based with_project
-    let nested_result: Result<Result<WledRenderTarget, RenderError>, String> =
+    let nested_result: Result<Result<Rendered<WledRenderTarget>, RenderError>, String> =
         project::with_project(|project| {
             let wled_output = match project
                 .patches
@@ -175,15 +404,31 @@ pub fn render_wled(
                     .collect(),
             };
 
-            Ok(render(
+            let render_mode = match RENDER_MODE_REF.lock() {
+                Ok(render_mode) => render_mode,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
+            };
+            let programmer = match PROGRAMMER_REF.lock() {
+                Ok(programmer) => programmer,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
+            };
+
+            let error = render(
                 output_id,
                 &mut render_target,
                 system_t,
                 frame,
                 project,
+                &render_mode,
                 &audio_analysis,
-            )
-            .map(|()| render_target))
+                Some(&programmer),
+                None,
+            );
+
+            Ok(Ok(Rendered {
+                frame: render_target,
+                error,
+            }))
         });
 
     // Flatten: String error -> RenderError::LockError, then unwrap inner Result
@@ -209,10 +454,10 @@ pub fn render_display_target(
     display_id: u64,
     system_t: u64,
     frame: u32,
-) -> Result<DisplayRenderData, RenderError> {
+) -> Result<Rendered<DisplayRenderData>, RenderError> {
     let audio_analysis = crate::audio::get_audio_analysis();
 
-    let nested_result: Result<Result<DisplayRenderData, RenderError>, String> =
+    let nested_result: Result<Result<Rendered<DisplayRenderData>, RenderError>, String> =
         project::with_project(|project| {
             let Some(display) = project.displays.get(&display_id) else {
                 return Ok(Err(RenderError::OutputNotFound {
@@ -232,27 +477,38 @@ pub fn render_display_target(
                 visualizer_tree: None,
             };
 
-            let render_result = render(
+            let render_mode = match RENDER_MODE_REF.lock() {
+                Ok(render_mode) => render_mode,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
+            };
+            let programmer = match PROGRAMMER_REF.lock() {
+                Ok(programmer) => programmer,
+                Err(e) => return Ok(Err(RenderError::LockError(e.to_string()))),
+            };
+
+            let error = render(
                 display_id,
                 &mut uniforms,
                 system_t,
                 frame,
                 project,
+                &render_mode,
                 &audio_analysis,
+                Some(&programmer),
+                None,
             );
 
-            if let Err(e) = render_result {
-                return Ok(Err(e));
-            }
-
             let shader_uniforms =
                 build_shader_uniforms(project, &uniforms, &audio_analysis, width, height, system_t);
 
-            Ok(Ok(DisplayRenderData {
-                width,
-                height,
-                uniforms,
-                shader_uniforms,
+            Ok(Ok(Rendered {
+                frame: DisplayRenderData {
+                    width,
+                    height,
+                    uniforms,
+                    shader_uniforms,
+                },
+                error,
             }))
         });
 
@@ -313,19 +569,20 @@ fn palette_rgb(desc: Option<&ColorDescription>) -> [f32; 3] {
     color_to_rgb(desc.and_then(|d| d.color.as_ref()))
 }
 
+/// Renders `render_mode` into `render_target`. Errors in parts of the frame
+/// are returned while the rest renders.
 fn render<T: RenderTarget<T>>(
     output_id: u64,
     render_target: &mut T,
     system_t: u64,
     frame: u32,
     project: &Project,
+    render_mode: &RenderMode,
     audio_analysis: &AudioAnalysis,
-) -> Result<(), RenderError> {
-    let render_mode = RENDER_MODE_REF
-        .lock()
-        .map_err(|e| RenderError::LockError(e.to_string()))?;
-
-    match &render_mode.mode {
+    programmer: Option<&Programmer>,
+    dmx_input: Option<&DmxInputState>,
+) -> Option<RenderError> {
+    let result = match &render_mode.mode {
         None
         | Some(
             Mode::Blackout(_)
@@ -333,23 +590,21 @@ fn render<T: RenderTarget<T>>(
             | Mode::TimecodedShow(TimecodedShow {
                 show_id: _,
                 state: None,
-            }),
+            })
+            | Mode::RecordingPlayback(RecordingPlayback { state: None, .. }),
         ) => Ok(()),
         Some(Mode::FixtureDebug(fixture_debug)) => {
             if output_id == fixture_debug.output_id {
-                render_target.apply_fixture_debug(fixture_debug);
+                render_target.apply_fixture_debug(fixture_debug)
+            } else {
+                Ok(())
             }
-            Ok(())
         }
         Some(Mode::FixtureHighlight(FixtureHighlight {
             fixture_id: Some(fixture_id),
-        })) => {
-            render_fixture_highlight(render_target, fixture_id);
-            Ok(())
-        }
+        })) => render_fixture_highlight(render_target, fixture_id),
         Some(Mode::GroupDebug(GroupDebug { group_id })) => {
-            render_group_debug(render_target, project, *group_id);
-            Ok(())
+            render_group_debug(render_target, project, *group_id)
         }
         Some(Mode::Scene(Scene { scene_id })) => render_scene(
             *scene_id,
@@ -358,21 +613,99 @@ fn render<T: RenderTarget<T>>(
             frame,
             project,
             audio_analysis,
-        )
-        .map_err(RenderError::SceneError),
+        ),
         Some(Mode::Autopilot(Autopilot { playlist_id })) => {
             render_playlist(*playlist_id, render_target, system_t, frame, project)
-                .map_err(RenderError::PlaylistError)
+        }
+        Some(Mode::CueList(CueList { cue_list_id })) => {
+            render_cue_list(*cue_list_id, render_target, system_t, frame, project)
         }
         Some(Mode::TimecodedShow(TimecodedShow {
             show_id,
             state: Some(s),
-        })) => render_timecoded_show(*show_id, render_target, s, system_t, frame, project)
-            .map_err(RenderError::TimecodedShowError),
+        })) => render_timecoded_show(*show_id, render_target, s, system_t, frame, project),
+        Some(Mode::RecordingPlayback(
+            playback @ RecordingPlayback {
+                state: Some(state), ..
+            },
+        )) => {
+            render_recording_playback(playback, state, render_target, output_id, system_t, project)
+        }
+        Some(Mode::DmxInput(_)) => {
+            if let Some(dmx_input) = dmx_input {
+                dmx_input.render_pass_through(render_target, output_id, project);
+            }
+            Ok(())
+        }
+    };
+
+    // Debug modes exist to find fixtures, so they ignore the programmer and
+    // the faders.
+    let mut errors: Vec<RenderError> = result.err().into_iter().collect();
+    if matches!(
+        render_mode.mode,
+        Some(
+            Mode::Scene(_)
+                | Mode::Autopilot(_)
+                | Mode::CueList(_)
+                | Mode::TimecodedShow(_)
+                | Mode::RecordingPlayback(_)
+                | Mode::DmxInput(_)
+        )
+    ) {
+        if let Some(programmer) = programmer {
+            let color_palette = interpolated_scene_palette(project, project.active_scene, system_t);
+            errors.extend(
+                programmer::apply_programmer(project, programmer, render_target, &color_palette)
+                    .err(),
+            );
+        }
+        apply_masters(render_target, project);
     }
+
+    RenderError::from_errors(errors)
 }
 
-fn render_group_debug<T: RenderTarget<T>>(render_target: &mut T, project: &Project, group_id: u64) {
+/// Scales every fixture's intensity by the grand master and the submasters of
+/// each group it belongs to.
+fn apply_masters<T: RenderTarget<T>>(render_target: &mut T, project: &Project) {
+    let Some(masters) = &project.masters else {
+        return;
+    };
+
+    let mut levels: HashMap<QualifiedFixtureId, f64> = HashMap::new();
+    for (group_id, level) in &masters.group_submasters {
+        if *level >= 1.0 || !project.groups.contains_key(group_id) {
+            continue;
+        }
+        let group_target = OutputTarget {
+            output: Some(output_target::Output::Group(*group_id)),
+        };
+        for fixture_id in get_fixtures(project, &group_target).into_keys() {
+            *levels.entry(fixture_id).or_insert(1.0) *= level;
+        }
+    }
+
+    let grand_master = masters::grand_master(project);
+    if grand_master < 1.0 {
+        let all_target = OutputTarget {
+            output: Some(output_target::Output::Group(0)),
+        };
+        for fixture_id in get_fixtures(project, &all_target).into_keys() {
+            *levels.entry(fixture_id).or_insert(1.0) *= grand_master;
+        }
+    }
+
+    for (fixture_id, level) in &levels {
+        render_target.scale_intensity(fixture_id, *level);
+    }
+}
+
+fn render_group_debug<T: RenderTarget<T>>(
+    render_target: &mut T,
+    project: &Project,
+    group_id: u64,
+) -> Result<(), RenderError> {
     let group_target = OutputTarget {
         output: Some(output_target::Output::Group(group_id)),
     };
@@ -406,14 +739,15 @@ fn render_group_debug<T: RenderTarget<T>>(render_target: &mut T, project: &Proje
             ..Default::default()
         };
 
-        render_target.apply_state(fixture_id, &state, &ColorPalette::default());
+        render_target.apply_state(fixture_id, &state, &ColorPalette::default())?;
     }
+    Ok(())
 }
 
 fn render_fixture_highlight<T: RenderTarget<T>>(
     render_target: &mut T,
     fixture_id: &QualifiedFixtureId,
-) {
+) -> Result<(), RenderError> {
     let state = FixtureState {
         light_color: Some(LightColor::Color(Color {
             red: 0.0,
@@ -424,5 +758,112 @@ fn render_fixture_highlight<T: RenderTarget<T>>(
         ..Default::default()
     };
 
-    render_target.apply_state(fixture_id, &state, &ColorPalette::default());
+    render_target.apply_state(fixture_id, &state, &ColorPalette::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{
+        DmxFixtureDefinition, Masters, Patch, PhysicalDmxFixture, SerialDmxOutput, TargetGroup,
+        dmx_fixture_definition::{
+            Channel, Mode as FixtureMode,
+            channel::{AmountMapping, Mapping},
+        },
+        output_target::FixtureMapping,
+    };
+
+    fn fixture_id(fixture: u64) -> QualifiedFixtureId {
+        QualifiedFixtureId {
+            patch: 1,
+            output: 10,
+            fixture,
+        }
+    }
+
+    #[test]
+    fn masters_scale_the_rig_and_their_groups() {
+        let mut mode = FixtureMode::default();
+        mode.channels.insert(
+            1,
+            Channel {
+                r#type: "dimmer".to_string(),
+                mapping: Some(Mapping::AmountMapping(AmountMapping {
+                    min_value: 0,
+                    max_value: 255,
+                })),
+                ..Default::default()
+            },
+        );
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("dimmer".to_string(), mode);
+        let definitions = HashMap::from([(1, definition)]);
+
+        let fixtures: HashMap<u64, PhysicalDmxFixture> = [(100, 0), (101, 1)]
+            .into_iter()
+            .map(|(id, channel_offset)| {
+                (
+                    id,
+                    PhysicalDmxFixture {
+                        fixture_definition_id: 1,
+                        fixture_mode: "dimmer".to_string(),
+                        channel_offset,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        let project = Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(
+                        10,
+                        crate::proto::Output {
+                            output: Some(Output::SerialDmxOutput(SerialDmxOutput {
+                                fixtures: fixtures.clone(),
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            groups: HashMap::from([(
+                7,
+                TargetGroup {
+                    name: "House".to_string(),
+                    targets: vec![OutputTarget {
+                        output: Some(output_target::Output::Fixtures(FixtureMapping {
+                            fixture_ids: vec![fixture_id(100)],
+                        })),
+                    }],
+                },
+            )]),
+            masters: Some(Masters {
+                grand_master: Some(0.5),
+                group_submasters: HashMap::from([(7, 0.5)]),
+            }),
+            ..Default::default()
+        };
+
+        let mut render_target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        let full = FixtureState {
+            dimmer: Some(1.0),
+            ..Default::default()
+        };
+        for fixture in [100, 101] {
+            render_target
+                .apply_state(&fixture_id(fixture), &full, &ColorPalette::default())
+                .unwrap();
+        }
+
+        apply_masters(&mut render_target, &project);
+
+        let universe = render_target.get_universes()[0];
+        assert_eq!(&universe[0..2], &[63, 127]);
+    }
 }
@@ -1,8 +1,11 @@
 use std::fmt::Debug;
 
-use crate::proto::{
-    Color, ColorPalette, FixtureState, QualifiedFixtureId, fixture_state::LightColor,
-    render_mode::FixtureDebug,
+use crate::{
+    proto::{
+        Color, ColorInterpolation, ColorPalette, FixtureState, QualifiedFixtureId,
+        fixture_state::LightColor, render_mode::FixtureDebug, scene::tile::BlendMode,
+    },
+    render::{dmx_render_target::UNIVERSE_SIZE, render::RenderError},
 };
 
 const BLACK: Color = Color {
@@ -20,33 +23,93 @@ const WHITE: Color = Color {
 };
 
 pub trait RenderTarget<T: RenderTarget<T>>: Clone + Debug {
+    /// Applies as much of `state` as the fixture can show. Parts it cannot,
+    /// such as a focus out of its reach, are left as they were and reported.
     fn apply_state(
         &mut self,
         fixture_id: &QualifiedFixtureId,
         state: &FixtureState,
         color_palette: &ColorPalette,
-    );
+    ) -> Result<(), RenderError>;
     fn interpolate(&mut self, a: &T, b: &T, t: f64);
-    fn apply_fixture_debug(&mut self, fixture_debug: &FixtureDebug);
+    /// Like `interpolate`, but intensity falling from `a` to `b` moves by
+    /// `down` while everything else moves by `up`.
+    fn interpolate_split(&mut self, a: &T, b: &T, up: f64, down: f64);
+    /// Redoes the colors of `interpolate(a, b, t)` through `space`, leaving
+    /// every other attribute as it is.
+    fn interpolate_colors(&mut self, a: &T, b: &T, t: f64, space: ColorInterpolation);
+    /// Returns a copy of this target for a tile using `mode` to render into.
+    /// The values `mode` blends start at its identity so only what the tile
+    /// writes takes part; everything else is kept and crossfades as usual.
+    fn blend_layer(&self, mode: BlendMode) -> T;
+    /// Composites `layer`, rendered from `base.blend_layer(mode)`, over `base`
+    /// at strength `t`.
+    fn blend(&mut self, base: &T, layer: &T, t: f64, mode: BlendMode);
+    /// Scales the intensity of a fixture by `amount`, leaving every other
+    /// attribute alone.
+    fn scale_intensity(&mut self, fixture_id: &QualifiedFixtureId, amount: f64);
+    fn apply_fixture_debug(&mut self, fixture_debug: &FixtureDebug) -> Result<(), RenderError>;
+    /// Overwrites universe `universe` of the output with recorded channel
+    /// values. Targets without DMX universes ignore it.
+    fn apply_recorded_universe(&mut self, universe: usize, values: &[u8; UNIVERSE_SIZE]);
 }
 
-impl FixtureState {
+impl BlendMode {
+    /// Whether this mode blends dimmer and brightness values.
+    #[must_use]
+    pub fn blends_intensity(self) -> bool {
+        matches!(self, BlendMode::Htp | BlendMode::Multiply)
+    }
+
+    /// Whether this mode blends color components.
+    #[must_use]
+    pub fn blends_color(self) -> bool {
+        matches!(self, BlendMode::Add | BlendMode::Multiply)
+    }
+
+    /// The value a blended layer starts from, which leaves the base unchanged.
+    #[must_use]
+    pub fn identity(self) -> f64 {
+        match self {
+            BlendMode::Multiply => 1.0,
+            BlendMode::Ltp | BlendMode::Htp | BlendMode::Add => 0.0,
+        }
+    }
+
+    /// Blends a single normalized `layer` value over `base` at strength `t`.
     #[must_use]
-    pub fn get_color(&self, color_palette: &ColorPalette) -> Option<Color> {
-        match self.light_color {
-            Some(LightColor::Color(c)) => Some(c),
-            Some(LightColor::PaletteColor(0)) => Some(BLACK),
-            Some(LightColor::PaletteColor(1)) => Some(WHITE),
-            Some(LightColor::PaletteColor(2)) => {
-                Some(color_palette.primary.unwrap().color.unwrap())
-            }
-            Some(LightColor::PaletteColor(3)) => {
-                Some(color_palette.secondary.unwrap().color.unwrap())
-            }
-            Some(LightColor::PaletteColor(4)) => {
-                Some(color_palette.tertiary.unwrap().color.unwrap())
-            }
-            _ => None,
+    pub fn blend_value(self, base: f64, layer: f64, t: f64) -> f64 {
+        match self {
+            BlendMode::Ltp => base + (layer - base) * t,
+            BlendMode::Htp => base.max(layer * t),
+            BlendMode::Add => (base + layer * t).min(1.0),
+            BlendMode::Multiply => base * (1.0 - t + layer * t),
         }
     }
 }
+
+impl FixtureState {
+    /// Returns the color this state sets, failing if it uses a palette color
+    /// the palette does not define.
+    pub fn try_get_color(
+        &self,
+        color_palette: &ColorPalette,
+    ) -> Result<Option<Color>, RenderError> {
+        let (palette_color, name) = match self.light_color {
+            Some(LightColor::Color(c)) => return Ok(Some(c)),
+            Some(LightColor::PaletteColor(0)) => return Ok(Some(BLACK)),
+            Some(LightColor::PaletteColor(1)) => return Ok(Some(WHITE)),
+            Some(LightColor::PaletteColor(2)) => (color_palette.primary, "primary"),
+            Some(LightColor::PaletteColor(3)) => (color_palette.secondary, "secondary"),
+            Some(LightColor::PaletteColor(4)) => (color_palette.tertiary, "tertiary"),
+            _ => return Ok(None),
+        };
+        palette_color
+            .and_then(|c| c.color)
+            .map(Some)
+            .ok_or_else(|| RenderError::MissingPaletteColor {
+                palette: color_palette.name.clone(),
+                color: name,
+            })
+    }
+}
@@ -2,16 +2,16 @@ use std::cmp::Ordering;
 
 use crate::{
     audio::AudioAnalysis,
-    beat::effective_beat_metadata,
+    beat::{beat_t, effective_beat_metadata},
     palette::interpolated_scene_palette,
     proto::{
         BeatMetadata, Duration, Effect, Project, TargetedEffect,
         scene::{
-            TileMap,
-            tile::{LoopDetails, OneShotDetails, TimingDetails, Transition},
+            Tile, TileMap,
+            tile::{BlendMode, LoopDetails, OneShotDetails, TimingDetails, Transition},
         },
     },
-    render::{render_target::RenderTarget, util::apply_effect},
+    render::{render::RenderError, render_target::RenderTarget, util::apply_effect},
 };
 
 impl Eq for TileMap {}
@@ -33,16 +33,77 @@ impl Ord for TileMap {
 }
 
 impl Duration {
+    /// Returns the length of this duration, or zero if it has no amount set.
     #[must_use]
     pub fn as_ms(&self, beat_metadata: &BeatMetadata) -> f64 {
+        self.try_as_ms(beat_metadata).unwrap_or(0.0)
+    }
+
+    /// Returns the length of this duration, or `None` if it has no amount set.
+    #[must_use]
+    pub fn try_as_ms(&self, beat_metadata: &BeatMetadata) -> Option<f64> {
         match self.amount {
-            Some(crate::proto::duration::Amount::Ms(ms)) => f64::from(ms),
-            Some(crate::proto::duration::Amount::Beat(b)) => b * beat_metadata.length_ms,
-            None => panic!("Unknown duration type!"),
+            Some(crate::proto::duration::Amount::Ms(ms)) => Some(f64::from(ms)),
+            Some(crate::proto::duration::Amount::Beat(b)) => Some(b * beat_metadata.length_ms),
+            None => None,
         }
     }
 }
 
+/// Returns how strongly a tile applies at `system_t` from its transition.
+fn transition_amount(
+    tile: &Tile,
+    system_t: u64,
+    beat_metadata: &BeatMetadata,
+) -> Result<f64, &'static str> {
+    let ms = |duration: &Duration| {
+        duration
+            .try_as_ms(beat_metadata)
+            .ok_or("has a fade with no duration")
+    };
+
+    Ok(match &tile.transition {
+        Some(Transition::AbsoluteStrength(a)) => f64::from(*a),
+        Some(Transition::StartFadeInMs(fade_in_time)) => match &tile.timing_details {
+            Some(TimingDetails::OneShot(OneShotDetails {
+                duration: Some(duration),
+            })) =>
+            {
+                #[allow(clippy::cast_sign_loss)]
+                #[allow(clippy::cast_possible_truncation)]
+                if system_t.saturating_sub(*fade_in_time) > ms(duration)? as u64 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Some(TimingDetails::Loop(LoopDetails {
+                fade_in: Some(fade_in_duration),
+                fade_out: _,
+            })) =>
+            {
+                #[allow(clippy::cast_precision_loss)]
+                (system_t.saturating_sub(*fade_in_time) as f64 / ms(fade_in_duration)?)
+                    .clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        },
+        Some(Transition::StartFadeOutMs(fade_out_time)) => match &tile.timing_details {
+            Some(TimingDetails::Loop(LoopDetails {
+                fade_in: _,
+                fade_out: Some(fade_out_duration),
+            })) =>
+            {
+                #[allow(clippy::cast_precision_loss)]
+                (1.0 - (system_t.saturating_sub(*fade_out_time) as f64 / ms(fade_out_duration)?))
+                    .clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        },
+        None => return Err("has no transition"),
+    })
+}
+
 pub fn render_scene<T: RenderTarget<T>>(
     scene_id: u64,
     render_target: &mut T,
@@ -50,19 +111,21 @@ pub fn render_scene<T: RenderTarget<T>>(
     frame: u32,
     project: &Project,
     audio_analysis: &AudioAnalysis,
-) -> Result<(), String> {
+) -> Result<(), RenderError> {
     let Some(scene) = project.scenes.get(&scene_id) else {
-        return Err(format!("Could not find scene {scene_id}"));
+        return Err(RenderError::SceneError(format!(
+            "Could not find scene {scene_id}"
+        )));
     };
 
     let Some(beat_metadata) = effective_beat_metadata(project, system_t) else {
-        return Err("Live beat not set!".to_string());
+        return Err(RenderError::SceneError("Live beat not set!".to_string()));
     };
 
-    #[allow(clippy::cast_precision_loss)]
-    let beat_t = (system_t - beat_metadata.offset_ms) as f64 / beat_metadata.length_ms;
+    let beat_t = beat_t(&beat_metadata, system_t).map_err(RenderError::SceneError)?;
 
     let color_palette = interpolated_scene_palette(project, scene_id, system_t);
+    let mut errors = Vec::new();
 
     // Sort tiles by priority, then y (descending), then x (descending)
     let mut tile_map = scene.tile_map.clone();
@@ -75,46 +138,16 @@ pub fn render_scene<T: RenderTarget<T>>(
         };
 
         // Calculate amount (fade in/out)
-        let mut amount: f64 = match &tile.transition {
-            Some(Transition::AbsoluteStrength(a)) => f64::from(*a),
-            Some(Transition::StartFadeInMs(fade_in_time)) => match &tile.timing_details {
-                Some(TimingDetails::OneShot(OneShotDetails {
-                    duration: Some(duration),
-                })) =>
-                {
-                    #[allow(clippy::cast_sign_loss)]
-                    #[allow(clippy::cast_possible_truncation)]
-                    if system_t - fade_in_time > duration.as_ms(&beat_metadata) as u64 {
-                        0.0
-                    } else {
-                        1.0
-                    }
-                }
-                Some(TimingDetails::Loop(LoopDetails {
-                    fade_in: Some(fade_in_duration),
-                    fade_out: _,
-                })) =>
-                {
-                    #[allow(clippy::cast_precision_loss)]
-                    ((system_t - *fade_in_time) as f64 / fade_in_duration.as_ms(&beat_metadata))
-                        .clamp(0.0, 1.0)
-                }
-                _ => 0.0,
-            },
-            Some(Transition::StartFadeOutMs(fade_out_time)) => match &tile.timing_details {
-                Some(TimingDetails::Loop(LoopDetails {
-                    fade_in: _,
-                    fade_out: Some(fade_out_duration),
-                })) =>
-                {
-                    #[allow(clippy::cast_precision_loss)]
-                    (1.0 - ((system_t - *fade_out_time) as f64
-                        / fade_out_duration.as_ms(&beat_metadata)))
-                    .clamp(0.0, 1.0)
-                }
-                _ => 0.0,
-            },
-            _ => panic!("Unknown transition type!"),
+        let mut amount = match transition_amount(tile, system_t, &beat_metadata) {
+            Ok(amount) => amount,
+            Err(reason) => {
+                errors.push(RenderError::InvalidTile {
+                    scene_id,
+                    tile: tile.name.clone(),
+                    reason,
+                });
+                continue;
+            }
         };
 
         amount *= match tile.audio_details {
@@ -152,13 +185,15 @@ pub fn render_scene<T: RenderTarget<T>>(
                 Some(Transition::StartFadeInMs(fade_in_time)),
             ) => Some(
                 #[allow(clippy::cast_precision_loss)]
-                ((system_t - fade_in_time) as f64 / duration.as_ms(&beat_metadata)).clamp(0.0, 1.0),
+                (system_t.saturating_sub(fade_in_time) as f64 / duration.as_ms(&beat_metadata))
+                    .clamp(0.0, 1.0),
             ),
             _ => None,
         };
 
+        let blend_mode = tile.blend_mode();
         let before = render_target.clone();
-        let mut after = render_target.clone();
+        let mut after = render_target.blend_layer(blend_mode);
 
         // Process tile based on description type
         for channel in &tile.targeted_effects {
@@ -170,8 +205,7 @@ pub fn render_scene<T: RenderTarget<T>>(
                     }),
                 output_target: Some(output_target),
             } = channel
-            {
-                apply_effect(
+                && let Err(error) = apply_effect(
                     project,
                     &mut after,
                     output_target,
@@ -181,13 +215,392 @@ pub fn render_scene<T: RenderTarget<T>>(
                     frame,
                     effect,
                     &color_palette,
-                );
+                )
+            {
+                // Keep the rest of the tile; the failed effect leaves its
+                // fixtures as they were.
+                errors.push(RenderError::InTile {
+                    scene_id,
+                    tile: tile.name.clone(),
+                    error: Box::new(error),
+                });
             }
         }
 
-        // Interpolate between before and after based on amount
-        render_target.interpolate(&before, &after, amount);
+        // Blend the tile over the tiles beneath it based on amount
+        render_target.blend(&before, &after, amount, blend_mode);
+        if blend_mode == BlendMode::Ltp {
+            render_target.interpolate_colors(&before, &after, amount, tile.color_interpolation());
+        }
+    }
+
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::proto::{
+        Color, ColorInterpolation, ColorPalette, DisplayRenderTarget, DmxFixtureDefinition,
+        FixtureState, OutputTarget, PaletteColor, PhysicalDmxFixture, QualifiedFixtureId, Scene,
+        WledRenderTarget,
+        dmx_fixture_definition::{
+            Channel, Mode,
+            channel::{AmountMapping, Mapping},
+        },
+        effect::{Effect as EffectKind, RampEffect, StaticEffect},
+        fixture_state::LightColor,
+        output_target::{FixtureMapping, Output},
+        scene::tile::BlendMode,
+        scene::{Tile, tile::Transition},
+        wled_render_target::{Color as WledColor, Segment},
+    };
+    use crate::render::dmx_render_target::DmxRenderTarget;
+
+    const DMX_OUTPUT: u64 = 10;
+    const WLED_OUTPUT: u64 = 20;
+    const DISPLAY: u64 = 30;
+    const FIXTURE: u64 = 100;
+
+    fn dimmer_rgb_definitions() -> HashMap<u64, DmxFixtureDefinition> {
+        let mut mode = Mode::default();
+        for (index, channel_type) in [(1, "dimmer"), (2, "red"), (3, "green"), (4, "blue")] {
+            mode.channels.insert(
+                index,
+                Channel {
+                    r#type: channel_type.to_string(),
+                    mapping: (channel_type == "dimmer").then_some(Mapping::AmountMapping(
+                        AmountMapping {
+                            min_value: 0,
+                            max_value: 255,
+                        },
+                    )),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("rgb".to_string(), mode);
+        HashMap::from([(1, definition)])
+    }
+
+    fn fixtures() -> HashMap<u64, PhysicalDmxFixture> {
+        HashMap::from([(
+            FIXTURE,
+            PhysicalDmxFixture {
+                fixture_definition_id: 1,
+                fixture_mode: "rgb".to_string(),
+                ..Default::default()
+            },
+        )])
+    }
+
+    fn tile(priority: i32, blend_mode: BlendMode, state: &FixtureState) -> TileMap {
+        let targets = [
+            Output::Fixtures(FixtureMapping {
+                fixture_ids: vec![QualifiedFixtureId {
+                    patch: 1,
+                    output: DMX_OUTPUT,
+                    fixture: FIXTURE,
+                }],
+            }),
+            Output::Fixtures(FixtureMapping {
+                fixture_ids: vec![QualifiedFixtureId {
+                    patch: 1,
+                    output: WLED_OUTPUT,
+                    fixture: 0,
+                }],
+            }),
+            Output::Display(DISPLAY),
+        ];
+        let mut tile = Tile {
+            transition: Some(Transition::AbsoluteStrength(1.0)),
+            targeted_effects: targets
+                .into_iter()
+                .map(|output| TargetedEffect {
+                    effect: Some(Effect {
+                        effect: Some(EffectKind::StaticEffect(StaticEffect {
+                            state: Some(state.clone()),
+                        })),
+                    }),
+                    output_target: Some(OutputTarget {
+                        output: Some(output),
+                    }),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        tile.set_blend_mode(blend_mode);
+        TileMap {
+            tile: Some(tile),
+            priority,
+            ..Default::default()
+        }
+    }
+
+    /// A dim red base tile underneath an accent tile.
+    fn project(accent: TileMap) -> Project {
+        let base = FixtureState {
+            light_color: Some(LightColor::Color(Color {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                white: None,
+            })),
+            dimmer: Some(0.4),
+            ..Default::default()
+        };
+        Project {
+            active_patch: 1,
+            live_beat: Some(BeatMetadata {
+                offset_ms: 0,
+                length_ms: 500.0,
+            }),
+            scenes: HashMap::from([(
+                1,
+                Scene {
+                    tile_map: vec![accent, tile(0, BlendMode::Ltp, &base)],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn accent(blend_mode: BlendMode, dimmer: Option<f64>, color: Option<Color>) -> TileMap {
+        tile(
+            1,
+            blend_mode,
+            &FixtureState {
+                light_color: color.map(LightColor::Color),
+                dimmer,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Renders the scene and returns the fixture's dimmer, red, green and blue.
+    fn render_dmx(project: &Project) -> [u8; 4] {
+        let fixtures = fixtures();
+        let definitions = dimmer_rgb_definitions();
+        let mut target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        render_scene(1, &mut target, 1000, 0, project, &AudioAnalysis::default()).unwrap();
+        let universe = target.get_universes()[0];
+        [universe[0], universe[1], universe[2], universe[3]]
+    }
+
+    fn render_wled(project: &Project) -> Segment {
+        let mut target = WledRenderTarget {
+            id: WLED_OUTPUT,
+            color_palette: None,
+            segments: vec![Segment {
+                primary_color: Some(WledColor {
+                    red: 0.0,
+                    green: 0.0,
+                    blue: 0.0,
+                }),
+                brightness: 1.0,
+                speed: 1.0,
+                ..Default::default()
+            }],
+        };
+        render_scene(1, &mut target, 1000, 0, project, &AudioAnalysis::default()).unwrap();
+        target.segments[0]
+    }
+
+    fn render_display(project: &Project) -> DisplayRenderTarget {
+        let mut target = DisplayRenderTarget {
+            id: DISPLAY,
+            color: None,
+            dimmer: 1.0,
+            visualizer_tree: None,
+        };
+        render_scene(1, &mut target, 1000, 0, project, &AudioAnalysis::default()).unwrap();
+        target
+    }
+
+    const BLUE: Color = Color {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.5,
+        white: None,
+    };
+
+    #[test]
+    fn ltp_tile_replaces_the_dimmer_beneath_it() {
+        let project = project(accent(BlendMode::Ltp, Some(0.2), None));
+
+        assert_eq!(render_dmx(&project), [51, 255, 0, 0]);
+        assert!((render_wled(&project).brightness - 0.2).abs() < 1e-6);
+        assert!((render_display(&project).dimmer - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn htp_tile_keeps_the_brighter_dimmer_and_the_color_beneath_it() {
+        let dimmer = project(accent(BlendMode::Htp, Some(0.2), None));
+        assert_eq!(render_dmx(&dimmer), [102, 255, 0, 0]);
+        assert!((render_wled(&dimmer).brightness - 0.4).abs() < 1e-6);
+        assert!((render_display(&dimmer).dimmer - 0.4).abs() < 1e-6);
+
+        let flash = project(accent(BlendMode::Htp, Some(1.0), None));
+        assert_eq!(render_dmx(&flash), [255, 255, 0, 0]);
+        assert!((render_wled(&flash).brightness - 1.0).abs() < 1e-6);
+        assert!((render_display(&flash).dimmer - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ltp_tile_crossfades_color_through_its_interpolation() {
+        let cyan = Color {
+            red: 0.0,
+            green: 1.0,
+            blue: 1.0,
+            white: None,
+        };
+        let halfway = |space: ColorInterpolation| {
+            let mut accent = accent(BlendMode::Ltp, None, Some(cyan));
+            let tile = accent.tile.as_mut().unwrap();
+            tile.transition = Some(Transition::AbsoluteStrength(0.5));
+            tile.set_color_interpolation(space);
+            render_dmx(&project(accent))
+        };
+
+        assert_eq!(halfway(ColorInterpolation::Rgb), [102, 127, 127, 127]);
+        let [_, red, green, blue] = halfway(ColorInterpolation::OklchShorterHue);
+        assert!(green > blue && red > blue, "{red} {green} {blue}");
+    }
+
+    #[test]
+    fn htp_tile_color_still_crossfades() {
+        let project = project(accent(BlendMode::Htp, None, Some(BLUE)));
+
+        assert_eq!(render_dmx(&project), [102, 0, 0, 127]);
+    }
+
+    #[test]
+    fn add_tile_adds_its_color_to_the_color_beneath_it() {
+        let project = project(accent(BlendMode::Add, None, Some(BLUE)));
+
+        assert_eq!(render_dmx(&project), [102, 255, 0, 127]);
+
+        let wled_color = render_wled(&project).primary_color.unwrap();
+        assert_eq!((wled_color.red, wled_color.blue), (1.0, 0.5));
+
+        let display_color = render_display(&project).color.unwrap();
+        assert_eq!((display_color.red, display_color.blue), (1.0, 0.5));
+    }
+
+    #[test]
+    fn multiply_tile_masks_the_intensity_beneath_it() {
+        let project = project(accent(BlendMode::Multiply, Some(0.5), None));
+
+        // Only the dimmer is masked; the color the tile left alone is kept.
+        assert_eq!(render_dmx(&project), [51, 255, 0, 0]);
+        assert!((render_wled(&project).brightness - 0.2).abs() < 1e-6);
+        assert_eq!(render_wled(&project).primary_color.unwrap().red, 1.0);
+        assert!((render_display(&project).dimmer - 0.2).abs() < 1e-6);
     }
 
-    Ok(())
+    #[test]
+    fn broken_tiles_are_reported_while_the_rest_of_the_scene_renders() {
+        let mut broken = accent(BlendMode::Ltp, Some(0.2), None);
+        let tile = broken.tile.as_mut().unwrap();
+        tile.name = "Broken".to_string();
+        tile.targeted_effects[0].effect = Some(Effect {
+            effect: Some(EffectKind::RampEffect(RampEffect::default())),
+        });
+        tile.targeted_effects[1].effect = Some(Effect {
+            effect: Some(EffectKind::StaticEffect(StaticEffect {
+                state: Some(FixtureState {
+                    light_color: Some(LightColor::PaletteColor(
+                        PaletteColor::PalettePrimary.into(),
+                    )),
+                    ..Default::default()
+                }),
+            })),
+        });
+        let mut project = project(broken);
+        let scene = project.scenes.get_mut(&1).unwrap();
+        scene.tile_map[1].tile.as_mut().unwrap().transition = None;
+        scene.color_palettes = vec![ColorPalette {
+            id: 1,
+            name: "Empty".to_string(),
+            ..Default::default()
+        }];
+        scene.active_color_palette = 1;
+        scene.last_active_color_palette = 1;
+
+        let fixtures = fixtures();
+        let definitions = dimmer_rgb_definitions();
+        let mut target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        let Err(RenderError::Multiple(errors)) =
+            render_scene(1, &mut target, 1000, 0, &project, &AudioAnalysis::default())
+        else {
+            panic!("expected errors from both tiles");
+        };
+
+        // The base tile renders first, beneath the broken one.
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            RenderError::InvalidTile {
+                reason: "has no transition",
+                ..
+            }
+        ));
+        assert!(matches!(
+            &errors[1],
+            RenderError::InTile { error, .. }
+                if matches!(**error, RenderError::IncompleteEffect { effect: "ramp", .. })
+        ));
+        assert_eq!(
+            errors[1].to_string(),
+            "Tile \"Broken\" of scene 1: The ramp effect has no timing"
+        );
+        // The palette color is missing for the WLED output it targets.
+        let mut wled = WledRenderTarget {
+            id: WLED_OUTPUT,
+            color_palette: None,
+            segments: vec![Segment::default()],
+        };
+        let Err(RenderError::Multiple(errors)) =
+            render_scene(1, &mut wled, 1000, 0, &project, &AudioAnalysis::default())
+        else {
+            panic!("expected errors from both tiles");
+        };
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(matches!(
+            &errors[2],
+            RenderError::InTile { error, .. }
+                if matches!(**error, RenderError::MissingPaletteColor { color: "primary", .. })
+        ));
+        // The display effect of the broken tile still renders.
+        let mut display = DisplayRenderTarget {
+            id: DISPLAY,
+            color: None,
+            dimmer: 1.0,
+            visualizer_tree: None,
+        };
+        let _ = render_scene(
+            1,
+            &mut display,
+            1000,
+            0,
+            &project,
+            &AudioAnalysis::default(),
+        );
+        assert!((display.dimmer - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn blended_tiles_respect_their_strength() {
+        let mut accent = accent(BlendMode::Htp, Some(1.0), None);
+        accent.tile.as_mut().unwrap().transition = Some(Transition::AbsoluteStrength(0.6));
+        let project = project(accent);
+
+        // max(0.4, 1.0 * 0.6)
+        assert_eq!(render_dmx(&project), [153, 255, 0, 0]);
+    }
 }
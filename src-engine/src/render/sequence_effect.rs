@@ -3,8 +3,10 @@
 use crate::{
     proto::{ColorPalette, OutputTarget, Project, effect::SequenceEffect},
     render::{
+        render::RenderError,
         render_target::RenderTarget,
-        util::{apply_effect, calculate_timing, get_fixtures},
+        sequence_index::sequence_index,
+        util::{apply_effect, calculate_timing, get_fixtures, required},
     },
 };
 
@@ -20,40 +22,39 @@ pub fn apply_sequence_effect<T: RenderTarget<T>>(
     frame: u32,
     sequence_effect: &SequenceEffect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
     if sequence_effect.sequence_id == 0 {
-        return;
+        return Ok(());
     }
 
-    let sequence = project
-        .sequences
-        .get(&sequence_effect.sequence_id)
-        .expect("Could not find sequence!");
+    let index = sequence_index(project, sequence_effect.sequence_id)?;
+    let sequence = &project.sequences[&sequence_effect.sequence_id];
+    let timing_mode = required(sequence_effect.timing_mode.as_ref(), "sequence", "timing")?;
 
     let fixtures = get_fixtures(project, output_target);
 
     for info in fixtures.values() {
         let t = calculate_timing(
-            &sequence_effect.timing_mode.unwrap(),
+            "sequence",
+            timing_mode,
             system_t,
             effect_t,
             beat_t / f64::from(sequence.native_beats),
             info.phase,
             info.index,
-        );
+        )?;
 
         #[allow(clippy::cast_lossless)]
         #[allow(clippy::cast_possible_truncation)]
         #[allow(clippy::cast_sign_loss)]
-        let sequence_t = (t * SEQUENCE_BEAT_RESOLUTION * f64::from(sequence.native_beats)) as u64;
+        let sequence_t = (t * SEQUENCE_BEAT_RESOLUTION * f64::from(sequence.native_beats)) as u32;
 
-        for layer in &sequence.layers {
-            // This is super expensive to do per fixture per layer. Consider optimizing by creating a BST for each layer per sequence apply.
-            let effect_option = layer.effects.iter().find(|e| {
-                (e.start_ms <= u32::try_from(sequence_t).unwrap())
-                    && (e.end_ms > u32::try_from(sequence_t).unwrap())
-            });
-            let Some(effect) = effect_option else {
+        for (layer, playing) in sequence.layers.iter().zip(index.effects_at(sequence_t)) {
+            let Some(effect) = playing.map(|i| &layer.effects[i]) else {
+                continue;
+            };
+            // A layer entry without an effect plays nothing.
+            let Some(kind) = effect.effect.as_ref().and_then(|e| e.effect.as_ref()) else {
                 continue;
             };
 
@@ -61,16 +62,18 @@ pub fn apply_sequence_effect<T: RenderTarget<T>>(
                 project,
                 render_target,
                 &info.output_target,
-                sequence_t,
+                u64::from(sequence_t),
                 Some(
-                    &(f64::from(u32::try_from(sequence_t).unwrap() - effect.start_ms)
+                    &(f64::from(sequence_t - effect.start_ms)
                         / f64::from(effect.end_ms - effect.start_ms)),
                 ),
                 beat_t,
                 frame,
-                effect.effect.as_ref().unwrap().effect.as_ref().unwrap(),
+                kind,
                 color_palette,
-            );
+            )?;
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,292 @@
+//! Looks up which effect of each sequence layer is playing without scanning
+//! the layer every frame. Indices are kept until the sequences they were built
+//! from change.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::{
+    proto::{
+        Effect, Layer, Project, Sequence,
+        effect::{Effect as EffectKind, SequenceEffect},
+    },
+    render::render::RenderError,
+};
+
+/// The effect playing between each pair of neighbouring start and end times
+/// of a layer.
+#[derive(Debug)]
+struct LayerIndex {
+    boundaries: Vec<u32>,
+    /// Index into the layer's effects of the effect playing from each
+    /// boundary up to the next.
+    playing: Vec<Option<usize>>,
+}
+
+impl LayerIndex {
+    fn new(layer: &Layer) -> Self {
+        let mut boundaries: Vec<u32> = layer
+            .effects
+            .iter()
+            .flat_map(|e| [e.start_ms, e.end_ms])
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        // Where effects overlap the first one listed plays.
+        let playing = boundaries
+            .iter()
+            .map(|&t| {
+                layer
+                    .effects
+                    .iter()
+                    .position(|e| e.start_ms <= t && e.end_ms > t)
+            })
+            .collect();
+
+        LayerIndex {
+            boundaries,
+            playing,
+        }
+    }
+
+    fn effect_at(&self, t: u32) -> Option<usize> {
+        let next = self.boundaries.partition_point(|&b| b <= t);
+        self.playing[next.checked_sub(1)?]
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SequenceIndex {
+    layers: Vec<LayerIndex>,
+}
+
+impl SequenceIndex {
+    fn new(sequence: &Sequence) -> Self {
+        SequenceIndex {
+            layers: sequence.layers.iter().map(LayerIndex::new).collect(),
+        }
+    }
+
+    /// Returns the index of the effect playing at `t` in each layer, if any.
+    pub(crate) fn effects_at(&self, t: u32) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.layers.iter().map(move |layer| layer.effect_at(t))
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    /// The sequences the cached indices were built from.
+    sequences: HashMap<u64, Sequence>,
+    cyclic: Option<Arc<HashSet<u64>>>,
+    indices: HashMap<u64, Arc<SequenceIndex>>,
+}
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| Mutex::new(Cache::default()));
+
+/// Calls `f` with every sequence an effect plays directly.
+fn nested_sequences(effect: &Effect, f: &mut impl FnMut(u64)) {
+    match &effect.effect {
+        Some(EffectKind::SequenceEffect(SequenceEffect { sequence_id, .. })) => f(*sequence_id),
+        Some(EffectKind::RandomEffect(random_effect)) => {
+            for effect in [&random_effect.effect_a, &random_effect.effect_b]
+                .into_iter()
+                .flatten()
+            {
+                nested_sequences(effect, f);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Returns every sequence that plays itself, directly or through other
+/// sequences.
+pub(crate) fn cyclic_sequences(project: &Project) -> HashSet<u64> {
+    let children: HashMap<u64, Vec<u64>> = project
+        .sequences
+        .iter()
+        .map(|(id, sequence)| {
+            let mut children = Vec::new();
+            for effect in sequence.layers.iter().flat_map(|l| &l.effects) {
+                if let Some(effect) = &effect.effect {
+                    nested_sequences(effect, &mut |child| children.push(child));
+                }
+            }
+            (*id, children)
+        })
+        .collect();
+
+    children
+        .keys()
+        .copied()
+        .filter(|&start| {
+            let mut visited = HashSet::new();
+            let mut stack = children[&start].clone();
+            while let Some(id) = stack.pop() {
+                if id == start {
+                    return true;
+                }
+                if visited.insert(id) {
+                    stack.extend(children.get(&id).into_iter().flatten());
+                }
+            }
+            false
+        })
+        .collect()
+}
+
+/// Returns the index of a sequence, failing if the sequence is missing or
+/// plays itself.
+pub(crate) fn sequence_index(
+    project: &Project,
+    sequence_id: u64,
+) -> Result<Arc<SequenceIndex>, RenderError> {
+    let Some(sequence) = project.sequences.get(&sequence_id) else {
+        return Err(RenderError::MissingSequence { sequence_id });
+    };
+
+    let mut cache = CACHE
+        .lock()
+        .map_err(|e| RenderError::LockError(e.to_string()))?;
+    if cache.sequences != project.sequences {
+        *cache = Cache {
+            sequences: project.sequences.clone(),
+            ..Default::default()
+        };
+    }
+
+    let cyclic = cache
+        .cyclic
+        .get_or_insert_with(|| Arc::new(cyclic_sequences(project)));
+    if cyclic.contains(&sequence_id) {
+        return Err(RenderError::SequenceCycle { sequence_id });
+    }
+
+    Ok(cache
+        .indices
+        .entry(sequence_id)
+        .or_insert_with(|| Arc::new(SequenceIndex::new(sequence)))
+        .clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{TimecodedEffect, effect::StaticEffect};
+
+    fn timecoded(start_ms: u32, end_ms: u32, effect: EffectKind) -> TimecodedEffect {
+        TimecodedEffect {
+            effect: Some(Effect {
+                effect: Some(effect),
+            }),
+            start_ms,
+            end_ms,
+        }
+    }
+
+    fn static_effect(start_ms: u32, end_ms: u32) -> TimecodedEffect {
+        timecoded(
+            start_ms,
+            end_ms,
+            EffectKind::StaticEffect(StaticEffect::default()),
+        )
+    }
+
+    fn plays(sequence_id: u64) -> TimecodedEffect {
+        timecoded(
+            0,
+            100,
+            EffectKind::SequenceEffect(SequenceEffect {
+                sequence_id,
+                ..Default::default()
+            }),
+        )
+    }
+
+    fn sequence(effects: Vec<TimecodedEffect>) -> Sequence {
+        Sequence {
+            layers: vec![Layer { effects }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_the_effect_playing_at_a_time() {
+        let layer = Layer {
+            effects: vec![
+                static_effect(100, 200),
+                static_effect(0, 50),
+                static_effect(150, 300),
+            ],
+        };
+        let index = LayerIndex::new(&layer);
+
+        let at = |t| index.effect_at(t);
+        assert_eq!(at(0), Some(1));
+        assert_eq!(at(49), Some(1));
+        assert_eq!(at(50), None);
+        // Overlapping effects play the first listed, matching a linear scan.
+        assert_eq!(at(175), Some(0));
+        assert_eq!(at(200), Some(2));
+        assert_eq!(at(300), None);
+
+        for t in 0..400 {
+            let scanned = layer
+                .effects
+                .iter()
+                .position(|e| e.start_ms <= t && e.end_ms > t);
+            assert_eq!(at(t), scanned, "at {t}");
+        }
+    }
+
+    #[test]
+    fn reports_missing_and_cyclic_sequences() {
+        let project = Project {
+            sequences: HashMap::from([
+                (1, sequence(vec![plays(2)])),
+                (2, sequence(vec![plays(3)])),
+                (3, sequence(vec![plays(1)])),
+                (4, sequence(vec![plays(1)])),
+                (5, sequence(vec![static_effect(0, 100)])),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            sequence_index(&project, 2),
+            Err(RenderError::SequenceCycle { sequence_id: 2 })
+        ));
+        // Playing a cycle is only caught once the cycle itself is reached.
+        assert!(sequence_index(&project, 4).is_ok());
+        assert!(sequence_index(&project, 5).is_ok());
+        assert!(matches!(
+            sequence_index(&project, 6),
+            Err(RenderError::MissingSequence { sequence_id: 6 })
+        ));
+    }
+
+    #[test]
+    fn indices_follow_changes_to_their_sequence() {
+        let mut project = Project {
+            sequences: HashMap::from([(1, sequence(vec![static_effect(0, 100)]))]),
+            ..Default::default()
+        };
+        let playing = |project: &Project| -> Vec<Option<usize>> {
+            sequence_index(project, 1).unwrap().effects_at(50).collect()
+        };
+        assert_eq!(playing(&project), [Some(0)]);
+
+        project
+            .sequences
+            .insert(1, sequence(vec![static_effect(200, 300)]));
+        assert_eq!(playing(&project), [None]);
+
+        // A sequence that starts playing itself is caught straight away.
+        project.sequences.insert(1, sequence(vec![plays(1)]));
+        assert!(matches!(
+            sequence_index(&project, 1),
+            Err(RenderError::SequenceCycle { sequence_id: 1 })
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+#![allow(clippy::cast_precision_loss)]
+
+use std::f64::consts::PI;
+
+use crate::{
+    hash::hash64,
+    proto::{
+        ColorPalette, EffectTiming, FixtureState, OutputTarget, Project,
+        effect::preset_effect::{
+            DimmerChaseEffect, ShapeEffect, dimmer_chase_effect::Waveform, shape_effect::Shape,
+        },
+        effect_timing::{Absolute, Beat, Timing},
+    },
+    render::{
+        render::RenderError,
+        render_target::RenderTarget,
+        util::{apply_state, calculate_timing, get_fixtures, required},
+    },
+};
+
+/// Number of points a random walk wanders through each cycle.
+const RANDOM_WALK_POINTS: u64 = 8;
+
+const DEFAULT_PULSE_WIDTH: f64 = 0.25;
+
+/// Returns a copy of `timing` that cycles `speed` times as fast. One-shot
+/// timing is driven externally and is left alone.
+fn at_speed(timing: &EffectTiming, speed: Option<f64>) -> EffectTiming {
+    let speed = speed.filter(|s| *s > 0.0).unwrap_or(1.0);
+    let mut timing = *timing;
+    match &mut timing.timing {
+        Some(Timing::Absolute(Absolute { duration_ms })) => {
+            #[allow(clippy::cast_possible_truncation)]
+            #[allow(clippy::cast_sign_loss)]
+            let scaled = (f64::from(*duration_ms) / speed).round().max(1.0) as u32;
+            *duration_ms = scaled;
+        }
+        Some(Timing::Beat(Beat { multiplier })) => {
+            #[allow(clippy::cast_possible_truncation)]
+            let scaled = (f64::from(*multiplier) / speed) as f32;
+            *multiplier = scaled;
+        }
+        _ => (),
+    }
+    timing
+}
+
+/// Moves along straight lines between `corners` and back to the first one.
+fn polygon(corners: &[(f64, f64)], t: f64) -> (f64, f64) {
+    let position = t.rem_euclid(1.0) * corners.len() as f64;
+    let edge = position.floor();
+    let along = position - edge;
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    let edge = edge as usize % corners.len();
+    let (from, to) = (corners[edge], corners[(edge + 1) % corners.len()]);
+    (
+        from.0 + (to.0 - from.0) * along,
+        from.1 + (to.1 - from.1) * along,
+    )
+}
+
+/// Returns a random value in [-1, 1] for a point of a fixture's random walk.
+fn random_coordinate(seed: u32, fixture_index: usize, point: u64, axis: u64) -> f64 {
+    let hash =
+        hash64((u64::from(seed) << 32) ^ ((fixture_index as u64) << 16) ^ (point << 1) ^ axis);
+    hash as f64 / u64::MAX as f64 * 2.0 - 1.0
+}
+
+fn random_walk(seed: u32, fixture_index: usize, t: f64) -> (f64, f64) {
+    let position = t.rem_euclid(1.0) * RANDOM_WALK_POINTS as f64;
+    let along = position.fract();
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    let from = position as u64 % RANDOM_WALK_POINTS;
+    let to = (from + 1) % RANDOM_WALK_POINTS;
+
+    // Ease in and out of every point so the walk never jerks.
+    let eased = along * along * (3.0 - 2.0 * along);
+    let coordinate = |axis| {
+        let a = random_coordinate(seed, fixture_index, from, axis);
+        let b = random_coordinate(seed, fixture_index, to, axis);
+        a + (b - a) * eased
+    };
+    (coordinate(0), coordinate(1))
+}
+
+/// Returns the pan and tilt offsets, each in [-1, 1], of a shape at `t`.
+fn shape_point(shape: Shape, seed: u32, fixture_index: usize, t: f64) -> (f64, f64) {
+    let angle = t * 2.0 * PI;
+    match shape {
+        Shape::FigureEight => (angle.sin(), (2.0 * angle).sin()),
+        Shape::Square => polygon(&[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)], t),
+        Shape::Triangle => polygon(&[(0.0, 1.0), (1.0, -1.0), (-1.0, -1.0)], t),
+        Shape::TiltSaw => (0.0, 2.0 * t.rem_euclid(1.0) - 1.0),
+        Shape::PanSine => (angle.sin(), 0.0),
+        // A wide pan sweep with tilt dipping at the middle of each pass.
+        Shape::Ballyhoo => (angle.sin(), (2.0 * angle).cos()),
+        Shape::RandomWalk => random_walk(seed, fixture_index, t),
+    }
+}
+
+/// Returns the value, in [-1, 1], of a waveform at `t`.
+fn wave(waveform: Waveform, pulse_width: f64, t: f64) -> f64 {
+    let t = t.rem_euclid(1.0);
+    match waveform {
+        Waveform::Sine => (t * 2.0 * PI).sin(),
+        Waveform::Saw => 2.0 * t - 1.0,
+        Waveform::Square => {
+            if t < 0.5 {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        Waveform::Pulse => {
+            if t < pulse_width {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+    }
+}
+
+pub fn apply_shape_effect<T: RenderTarget<T>>(
+    project: &Project,
+    render_target: &mut T,
+    output_target: &OutputTarget,
+    system_t: u64,
+    effect_t: Option<&f64>,
+    beat_t: f64,
+    shape_effect: &ShapeEffect,
+    color_palette: &ColorPalette,
+) -> Result<(), RenderError> {
+    let timing_mode = required(shape_effect.timing_mode.as_ref(), "shape", "timing")?;
+    let timing_mode = at_speed(timing_mode, shape_effect.speed);
+    let shape = shape_effect.shape();
+
+    for info in get_fixtures(project, output_target).values() {
+        let t = calculate_timing(
+            "shape",
+            &timing_mode,
+            system_t,
+            effect_t,
+            beat_t,
+            info.phase,
+            info.index,
+        )?;
+
+        let (pan, tilt) = shape_point(shape, shape_effect.seed, info.index, t);
+        let state = FixtureState {
+            pan: Some(shape_effect.center_pan + pan * shape_effect.pan_size),
+            tilt: Some(shape_effect.center_tilt + tilt * shape_effect.tilt_size),
+            ..Default::default()
+        };
+
+        apply_state(
+            project,
+            render_target,
+            &info.output_target,
+            &state,
+            color_palette,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn apply_dimmer_chase_effect<T: RenderTarget<T>>(
+    project: &Project,
+    render_target: &mut T,
+    output_target: &OutputTarget,
+    system_t: u64,
+    effect_t: Option<&f64>,
+    beat_t: f64,
+    dimmer_chase_effect: &DimmerChaseEffect,
+    color_palette: &ColorPalette,
+) -> Result<(), RenderError> {
+    let timing_mode = required(
+        dimmer_chase_effect.timing_mode.as_ref(),
+        "dimmer chase",
+        "timing",
+    )?;
+    let timing_mode = at_speed(timing_mode, dimmer_chase_effect.speed);
+    let waveform = dimmer_chase_effect.waveform();
+    let pulse_width = dimmer_chase_effect
+        .pulse_width
+        .unwrap_or(DEFAULT_PULSE_WIDTH);
+
+    for info in get_fixtures(project, output_target).values() {
+        let t = calculate_timing(
+            "dimmer chase",
+            &timing_mode,
+            system_t,
+            effect_t,
+            beat_t,
+            info.phase,
+            info.index,
+        )?;
+
+        let level =
+            dimmer_chase_effect.center + wave(waveform, pulse_width, t) * dimmer_chase_effect.size;
+        let state = FixtureState {
+            dimmer: Some(level.clamp(0.0, 1.0)),
+            ..Default::default()
+        };
+
+        apply_state(
+            project,
+            render_target,
+            &info.output_target,
+            &state,
+            color_palette,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::render::dmx_render_target::DmxRenderTarget;
+
+    fn assert_point(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn shapes_pass_through_their_corners() {
+        assert_point(shape_point(Shape::Square, 0, 0, 0.0), (-1.0, -1.0));
+        assert_point(shape_point(Shape::Square, 0, 0, 0.375), (1.0, 0.0));
+        assert_point(shape_point(Shape::Triangle, 0, 0, 1.0 / 3.0), (1.0, -1.0));
+        assert_point(shape_point(Shape::TiltSaw, 0, 0, 0.75), (0.0, 0.5));
+        assert_point(shape_point(Shape::PanSine, 0, 0, 0.25), (1.0, 0.0));
+        // The figure eight crosses itself at the center half way through.
+        assert_point(shape_point(Shape::FigureEight, 0, 0, 0.5), (0.0, 0.0));
+        assert_point(shape_point(Shape::Ballyhoo, 0, 0, 0.25), (1.0, -1.0));
+    }
+
+    #[test]
+    fn random_walks_loop_and_differ_between_fixtures() {
+        let start = shape_point(Shape::RandomWalk, 7, 0, 0.0);
+        assert_point(shape_point(Shape::RandomWalk, 7, 0, 1.0), start);
+        assert_ne!(shape_point(Shape::RandomWalk, 7, 1, 0.0), start);
+        assert_ne!(shape_point(Shape::RandomWalk, 8, 0, 0.0), start);
+
+        for step in 0..100 {
+            let (pan, tilt) = shape_point(Shape::RandomWalk, 7, 0, f64::from(step) / 100.0);
+            assert!(pan.abs() <= 1.0 && tilt.abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn waves_swing_between_the_extremes() {
+        assert!((wave(Waveform::Sine, 0.25, 0.25) - 1.0).abs() < 1e-9);
+        assert!((wave(Waveform::Saw, 0.25, 0.0) + 1.0).abs() < 1e-9);
+        assert!((wave(Waveform::Square, 0.25, 0.4) - 1.0).abs() < 1e-9);
+        assert!((wave(Waveform::Square, 0.25, 0.6) + 1.0).abs() < 1e-9);
+        assert!((wave(Waveform::Pulse, 0.25, 0.2) - 1.0).abs() < 1e-9);
+        assert!((wave(Waveform::Pulse, 0.25, 0.3) + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn speed_shortens_the_cycle() {
+        let timing = EffectTiming {
+            timing: Some(Timing::Absolute(Absolute { duration_ms: 1000 })),
+            ..Default::default()
+        };
+        assert_eq!(
+            at_speed(&timing, Some(4.0)).timing,
+            Some(Timing::Absolute(Absolute { duration_ms: 250 }))
+        );
+        assert_eq!(at_speed(&timing, None), timing);
+
+        let timing = EffectTiming {
+            timing: Some(Timing::Beat(Beat { multiplier: 2.0 })),
+            ..Default::default()
+        };
+        assert_eq!(
+            at_speed(&timing, Some(2.0)).timing,
+            Some(Timing::Beat(Beat { multiplier: 1.0 }))
+        );
+    }
+
+    #[test]
+    fn effects_without_timing_are_incomplete() {
+        let (fixtures, definitions) = (HashMap::new(), HashMap::new());
+        let mut target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        let (project, output_target) = (Project::default(), OutputTarget::default());
+        let palette = ColorPalette::default();
+
+        let shape = apply_shape_effect(
+            &project,
+            &mut target,
+            &output_target,
+            0,
+            None,
+            0.0,
+            &ShapeEffect::default(),
+            &palette,
+        );
+        assert!(matches!(
+            shape,
+            Err(RenderError::IncompleteEffect {
+                effect: "shape",
+                missing: "timing"
+            })
+        ));
+
+        let chase = apply_dimmer_chase_effect(
+            &project,
+            &mut target,
+            &output_target,
+            0,
+            None,
+            0.0,
+            &DimmerChaseEffect::default(),
+            &palette,
+        );
+        assert!(matches!(
+            chase,
+            Err(RenderError::IncompleteEffect {
+                effect: "dimmer chase",
+                missing: "timing"
+            })
+        ));
+    }
+}
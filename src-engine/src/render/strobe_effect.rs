@@ -1,33 +1,147 @@
 use crate::{
-    proto::{ColorPalette, OutputTarget, Project, effect::StrobeEffect},
-    render::{render_target::RenderTarget, util::apply_state},
+    proto::{
+        ColorPalette, OutputTarget, Project,
+        effect::{
+            StrobeEffect,
+            strobe_effect::{BeatDivision, Timing},
+        },
+    },
+    render::{
+        render::RenderError,
+        render_target::RenderTarget,
+        util::{apply_state, required},
+    },
 };
 
+const DEFAULT_DUTY_CYCLE: f64 = 0.5;
+
+/// Returns how many flashes fit in a beat.
+fn flashes_per_beat(division: BeatDivision) -> f64 {
+    match division {
+        BeatDivision::Quarter => 1.0,
+        BeatDivision::Eighth => 2.0,
+        BeatDivision::Sixteenth => 4.0,
+        BeatDivision::QuarterTriplet => 1.5,
+        BeatDivision::EighthTriplet => 3.0,
+        BeatDivision::SixteenthTriplet => 6.0,
+    }
+}
+
+/// Whether the strobe shows state A rather than state B.
+fn in_state_a(
+    strobe_effect: &StrobeEffect,
+    system_t: u64,
+    beat_t: f64,
+    frame: u32,
+) -> Result<bool, RenderError> {
+    #[allow(clippy::cast_precision_loss)]
+    let flashes = match strobe_effect.timing {
+        Some(Timing::RateHz(hz)) => system_t as f64 / 1000.0 * hz,
+        Some(Timing::BeatDivision(division)) => {
+            let division =
+                BeatDivision::try_from(division).map_err(|_| RenderError::UnknownValue {
+                    field: "beat division",
+                    value: division,
+                })?;
+            beat_t * flashes_per_beat(division)
+        }
+        None => {
+            return Ok(frame
+                .checked_rem(
+                    strobe_effect
+                        .state_a_fames
+                        .saturating_add(strobe_effect.state_b_fames),
+                )
+                .is_none_or(|f| f < strobe_effect.state_a_fames));
+        }
+    };
+    let duty_cycle = strobe_effect.duty_cycle.unwrap_or(DEFAULT_DUTY_CYCLE);
+    Ok(flashes.rem_euclid(1.0) < duty_cycle)
+}
+
 pub fn apply_strobe_effect<T: RenderTarget<T>>(
     project: &Project,
     render_target: &mut T,
     output_target: &OutputTarget,
+    system_t: u64,
+    beat_t: f64,
     frame: u32,
     strobe_effect: &StrobeEffect,
     color_palette: &ColorPalette,
-) {
-    if frame % (strobe_effect.state_a_fames + strobe_effect.state_b_fames)
-        < strobe_effect.state_a_fames
-    {
-        apply_state(
-            project,
-            render_target,
-            output_target,
-            strobe_effect.state_a.as_ref().unwrap(),
-            color_palette,
-        );
+) -> Result<(), RenderError> {
+    let state = if in_state_a(strobe_effect, system_t, beat_t, frame)? {
+        required(strobe_effect.state_a.as_ref(), "strobe", "state A")?
     } else {
-        apply_state(
-            project,
-            render_target,
-            output_target,
-            strobe_effect.state_b.as_ref().unwrap(),
-            color_palette,
-        );
+        required(strobe_effect.state_b.as_ref(), "strobe", "state B")?
+    };
+    apply_state(project, render_target, output_target, state, color_palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_strobes_count_frames() {
+        let strobe = StrobeEffect {
+            state_a_fames: 1,
+            state_b_fames: 2,
+            ..Default::default()
+        };
+        let states: Vec<bool> = (0..6)
+            .map(|f| in_state_a(&strobe, 0, 0.0, f).unwrap())
+            .collect();
+        assert_eq!(states, [true, false, false, true, false, false]);
+
+        // Counts too large to add up still strobe.
+        let strobe = StrobeEffect {
+            state_a_fames: u32::MAX,
+            state_b_fames: 1,
+            ..Default::default()
+        };
+        assert!(in_state_a(&strobe, 0, 0.0, 5).unwrap());
+    }
+
+    #[test]
+    fn rate_strobes_follow_the_clock() {
+        let strobe = StrobeEffect {
+            timing: Some(Timing::RateHz(10.0)),
+            duty_cycle: Some(0.2),
+            ..Default::default()
+        };
+        // 100ms a flash, 20ms of which is state A, whatever the frame.
+        assert!(in_state_a(&strobe, 1010, 0.0, 7).unwrap());
+        assert!(!in_state_a(&strobe, 1030, 0.0, 7).unwrap());
+        assert!(in_state_a(&strobe, 1100, 0.0, 3).unwrap());
+    }
+
+    #[test]
+    fn beat_strobes_follow_the_beat() {
+        let mut strobe = StrobeEffect {
+            timing: Some(Timing::BeatDivision(BeatDivision::Sixteenth.into())),
+            ..Default::default()
+        };
+        assert!(in_state_a(&strobe, 0, 2.0, 0).unwrap());
+        assert!(!in_state_a(&strobe, 0, 2.2, 0).unwrap());
+        assert!(in_state_a(&strobe, 0, 2.25, 0).unwrap());
+
+        strobe.timing = Some(Timing::BeatDivision(BeatDivision::EighthTriplet.into()));
+        assert!(!in_state_a(&strobe, 0, 2.2, 0).unwrap());
+        assert!(in_state_a(&strobe, 0, 2.0 + 1.0 / 3.0, 0).unwrap());
+    }
+
+    #[test]
+    fn unknown_beat_divisions_are_reported() {
+        let strobe = StrobeEffect {
+            timing: Some(Timing::BeatDivision(99)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            in_state_a(&strobe, 0, 0.0, 0),
+            Err(RenderError::UnknownValue {
+                field: "beat division",
+                value: 99
+            })
+        ));
     }
 }
@@ -1,8 +1,8 @@
 use crate::{
     beat::track_beat_at_time,
-    palette::DEFAULT_COLOR_PALETTE,
+    palette::show_palette_at,
     proto::{Effect, Project, render_mode::timecoded_show::State, timecoded_show::AudioTrack},
-    render::{render_target::RenderTarget, util::apply_effect},
+    render::{render::RenderError, render_target::RenderTarget, util::apply_effect},
 };
 
 pub fn render_timecoded_show<T: RenderTarget<T>>(
@@ -12,44 +12,47 @@ pub fn render_timecoded_show<T: RenderTarget<T>>(
     system_t: u64,
     frame: u32,
     project: &Project,
-) -> Result<(), String> {
+) -> Result<(), RenderError> {
+    let error = |message: &str| RenderError::TimecodedShowError(message.to_string());
+
     let show = project
         .shows
         .get(&show_id)
-        .ok_or("Cannot find timecoded show to render!")?;
+        .ok_or_else(|| error("Cannot find timecoded show to render!"))?;
 
     let Some(AudioTrack { track_id }) = show.audio_track else {
         return Ok(());
     };
 
     let Some(track) = project.tracks.get(&track_id) else {
-        return Err("Cannot find track!".to_string());
+        return Err(error("Cannot find track!"));
     };
 
     let t = match state {
-        State::StartT(t) => u32::try_from(system_t - t).map_err(|e| e.to_string())?,
+        State::StartT(t) => {
+            u32::try_from(system_t.saturating_sub(*t)).map_err(|e| error(&e.to_string()))?
+        }
         State::PausedMs(t) => *t,
     };
 
     let beat_t = track_beat_at_time(track, f64::from(t)).unwrap_or(0.0);
 
-    let color_palette = show
-        .palettes
-        .first()
-        .and_then(|keyframe| keyframe.color_palette.clone())
-        .unwrap_or_else(|| DEFAULT_COLOR_PALETTE.clone());
+    let color_palette = show_palette_at(&show.palettes, u64::from(t));
+    let mut errors = Vec::new();
 
     for output in show.outputs.iter().rev() {
         let Some(output_target) = &output.output_target else {
             continue;
         };
-        let layer = output.layer.as_ref().ok_or("Output without layer!")?;
+        let Some(layer) = &output.layer else {
+            errors.push(error("Output without layer!"));
+            continue;
+        };
         for effect in &layer.effects {
             if effect.start_ms <= t
                 && effect.end_ms > t
                 && let Some(Effect { effect: Some(e) }) = &effect.effect
-            {
-                apply_effect(
+                && let Err(error) = apply_effect(
                     project,
                     render_target,
                     output_target,
@@ -63,10 +66,188 @@ pub fn render_timecoded_show<T: RenderTarget<T>>(
                     frame,
                     e,
                     &color_palette,
-                );
+                )
+            {
+                errors.push(error);
             }
         }
     }
 
-    Ok(())
+    match RenderError::from_errors(errors) {
+        Some(error) => Err(RenderError::InShow {
+            show_id,
+            error: Box::new(error),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::proto::{
+        Color, ColorInterpolation, ColorPalette, DmxFixtureDefinition, Layer, OutputTarget,
+        PaletteColor, PhysicalDmxFixture, QualifiedFixtureId, TimecodedEffect, TimecodedShow,
+        Track,
+        color_palette::ColorDescription,
+        dmx_fixture_definition::{Channel, Mode},
+        effect::{Effect as EffectKind, StaticEffect},
+        fixture_state::LightColor,
+        output_target::{FixtureMapping, Output},
+        timecoded_show::{Output as ShowOutput, PaletteKeyframe, palette_keyframe::Curve},
+    };
+    use crate::render::dmx_render_target::DmxRenderTarget;
+
+    fn keyframe(t: u64, red: f64, green: f64, blue: f64, curve: Curve) -> PaletteKeyframe {
+        let mut keyframe = PaletteKeyframe {
+            color_palette: Some(ColorPalette {
+                primary: Some(ColorDescription {
+                    color: Some(Color {
+                        red,
+                        green,
+                        blue,
+                        white: None,
+                    }),
+                }),
+                ..Default::default()
+            }),
+            t,
+            ..Default::default()
+        };
+        keyframe.set_curve(curve);
+        keyframe
+    }
+
+    fn project() -> Project {
+        let output_target = OutputTarget {
+            output: Some(Output::Fixtures(FixtureMapping {
+                fixture_ids: vec![QualifiedFixtureId {
+                    patch: 1,
+                    output: 10,
+                    fixture: 100,
+                }],
+            })),
+        };
+        let effect = TimecodedEffect {
+            effect: Some(Effect {
+                effect: Some(EffectKind::StaticEffect(StaticEffect {
+                    state: Some(crate::proto::FixtureState {
+                        light_color: Some(LightColor::PaletteColor(
+                            PaletteColor::PalettePrimary.into(),
+                        )),
+                        ..Default::default()
+                    }),
+                })),
+            }),
+            start_ms: 0,
+            end_ms: 10_000,
+        };
+        let show = TimecodedShow {
+            // Keyframes are ordered by time, not by where they are stored.
+            palettes: vec![
+                keyframe(2000, 0.0, 1.0, 0.0, Curve::Eased),
+                keyframe(0, 1.0, 0.0, 0.0, Curve::Linear),
+                keyframe(3000, 1.0, 0.0, 0.0, Curve::Linear),
+                keyframe(1000, 0.0, 0.0, 1.0, Curve::Hold),
+            ],
+            audio_track: Some(AudioTrack { track_id: 1 }),
+            outputs: vec![ShowOutput {
+                output_target: Some(output_target),
+                layer: Some(Layer {
+                    effects: vec![effect],
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        Project {
+            active_patch: 1,
+            shows: HashMap::from([(1, show)]),
+            tracks: HashMap::from([(1, Track::default())]),
+            ..Default::default()
+        }
+    }
+
+    /// Renders the show `t` ms in and returns the fixture's red, green and
+    /// blue.
+    fn render_rgb(project: &Project, t: u32) -> [u8; 3] {
+        let mut mode = Mode::default();
+        for (index, channel_type) in [(1, "red"), (2, "green"), (3, "blue")] {
+            mode.channels.insert(
+                index,
+                Channel {
+                    r#type: channel_type.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut definition = DmxFixtureDefinition::default();
+        definition.modes.insert("rgb".to_string(), mode);
+        let definitions = HashMap::from([(1, definition)]);
+        let fixtures = HashMap::from([(
+            100,
+            PhysicalDmxFixture {
+                fixture_definition_id: 1,
+                fixture_mode: "rgb".to_string(),
+                ..Default::default()
+            },
+        )]);
+
+        let mut target = DmxRenderTarget::new(&fixtures, &definitions, 1);
+        render_timecoded_show(1, &mut target, &State::PausedMs(t), 0, 0, project).unwrap();
+        let universe = target.get_universes()[0];
+        [universe[0], universe[1], universe[2]]
+    }
+
+    #[test]
+    fn palettes_crossfade_between_keyframes() {
+        let project = project();
+
+        assert_eq!(render_rgb(&project, 0), [255, 0, 0]);
+        assert_eq!(render_rgb(&project, 500), [127, 0, 127]);
+        // Held until the next keyframe.
+        assert_eq!(render_rgb(&project, 1500), [0, 0, 255]);
+        assert_eq!(render_rgb(&project, 1999), [0, 0, 255]);
+        assert_eq!(render_rgb(&project, 2000), [0, 255, 0]);
+        // Eased a quarter of the way in is only 15.6% of the way there.
+        assert_eq!(render_rgb(&project, 2250), [39, 215, 0]);
+        assert_eq!(render_rgb(&project, 2500), [127, 127, 0]);
+        // The last keyframe holds until the end.
+        assert_eq!(render_rgb(&project, 5000), [255, 0, 0]);
+    }
+
+    #[test]
+    fn keyframes_blend_through_their_color_space() {
+        let mut project = project();
+        let show = project.shows.get_mut(&1).unwrap();
+        let first = show.palettes.iter_mut().find(|k| k.t == 0).unwrap();
+        first.set_color_interpolation(ColorInterpolation::Oklab);
+
+        let red = Color {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+            white: None,
+        };
+        let blue = Color {
+            blue: 1.0,
+            red: 0.0,
+            ..red
+        };
+        let mid = red.interpolate(&blue, 0.5, ColorInterpolation::Oklab);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let expected = [mid.red, mid.green, mid.blue].map(|c| (c * 255.0) as u8);
+        assert_eq!(render_rgb(&project, 500), expected);
+        assert_ne!(expected, [127, 0, 127]);
+    }
+
+    #[test]
+    fn shows_without_keyframes_use_the_default_palette() {
+        let mut project = project();
+        project.shows.get_mut(&1).unwrap().palettes.clear();
+
+        assert_eq!(render_rgb(&project, 500), [255, 0, 255]);
+    }
 }
@@ -5,11 +5,12 @@ use crate::{
         ColorPalette, EffectTiming, FixtureState, OutputTarget, Project, QualifiedFixtureId,
         effect::Effect,
         effect_timing::{Absolute, Beat, EasingFunction, PhaseType, Timing},
+        output::Output as ProtoOutput,
         output_target::{FixtureMapping, Output},
     },
     render::{
         preset_effect::apply_preset_effect, ramp_effect::apply_ramp_effect,
-        random_effect::apply_random_effect, render_target::RenderTarget,
+        random_effect::apply_random_effect, render::RenderError, render_target::RenderTarget,
         sequence_effect::apply_sequence_effect, strobe_effect::apply_strobe_effect,
     },
 };
@@ -37,7 +38,7 @@ pub fn apply_effect<T: RenderTarget<T>>(
     frame: u32,
     effect: &Effect,
     color_palette: &ColorPalette,
-) {
+) -> Result<(), RenderError> {
     match effect {
         Effect::RampEffect(ramp_effect) => apply_ramp_effect(
             project,
@@ -63,13 +64,15 @@ pub fn apply_effect<T: RenderTarget<T>>(
             project,
             render_target,
             output_target,
-            static_effect.state.as_ref().unwrap(),
+            required(static_effect.state.as_ref(), "static", "state")?,
             color_palette,
         ),
         Effect::StrobeEffect(strobe_effect) => apply_strobe_effect(
             project,
             render_target,
             output_target,
+            system_t,
+            beat_t,
             frame,
             strobe_effect,
             color_palette,
@@ -98,12 +101,61 @@ pub fn apply_effect<T: RenderTarget<T>>(
     }
 }
 
+/// The first protocol universe a DMX output sends and how many it sends. A
+/// serial output is universe 0 alone. `None` if the output does not exist or
+/// does not send DMX.
+pub(crate) fn dmx_universes(project: &Project, output_id: u64) -> Option<(u32, u32)> {
+    match project
+        .patches
+        .get(&project.active_patch)
+        .and_then(|p| p.outputs.get(&output_id))
+        .and_then(|o| o.output.as_ref())?
+    {
+        ProtoOutput::SerialDmxOutput(_) => Some((0, 1)),
+        ProtoOutput::SacnDmxOutput(sacn) => Some((sacn.universe, sacn.universe_count.max(1))),
+        ProtoOutput::ArtnetDmxOutput(artnet) => {
+            Some((artnet.universe, artnet.universe_count.max(1)))
+        }
+        _ => None,
+    }
+}
+
+/// Returns a part of an effect it cannot render without, failing with the
+/// name of the effect and the missing part.
+pub fn required<T>(
+    value: Option<T>,
+    effect: &'static str,
+    missing: &'static str,
+) -> Result<T, RenderError> {
+    value.ok_or(RenderError::IncompleteEffect { effect, missing })
+}
+
 pub fn apply_state<T: RenderTarget<T>>(
     project: &Project,
     render_target: &mut T,
     output_target: &OutputTarget,
     state: &FixtureState,
     color_palette: &ColorPalette,
+) -> Result<(), RenderError> {
+    let mut errors = Vec::new();
+    apply_valid_state(
+        project,
+        render_target,
+        output_target,
+        state,
+        color_palette,
+        &mut errors,
+    );
+    RenderError::from_errors(errors).map_or(Ok(()), Err)
+}
+
+fn apply_valid_state<T: RenderTarget<T>>(
+    project: &Project,
+    render_target: &mut T,
+    output_target: &OutputTarget,
+    state: &FixtureState,
+    color_palette: &ColorPalette,
+    errors: &mut Vec<RenderError>,
 ) {
     let Some(output) = &output_target.output else {
         return;
@@ -116,7 +168,7 @@ pub fn apply_state<T: RenderTarget<T>>(
                 .iter()
                 .find(|id| id.patch == project.active_patch)
             {
-                render_target.apply_state(qid, state, color_palette);
+                errors.extend(render_target.apply_state(qid, state, color_palette).err());
             }
         }
         Output::Display(d) => {
@@ -125,12 +177,12 @@ pub fn apply_state<T: RenderTarget<T>>(
                 output: *d,
                 fixture: 0,
             };
-            render_target.apply_state(&qid, state, color_palette);
+            errors.extend(render_target.apply_state(&qid, state, color_palette).err());
         }
         Output::Group(0) => {
             // Apply to all fixtures
             for fixture_id in project.get_all_qualified_fixture_ids() {
-                apply_state(
+                apply_valid_state(
                     project,
                     render_target,
                     &OutputTarget {
@@ -140,11 +192,12 @@ pub fn apply_state<T: RenderTarget<T>>(
                     },
                     state,
                     color_palette,
+                    errors,
                 );
             }
             // Also apply to all virtual displays
             for display_id in project.displays.keys() {
-                apply_state(
+                apply_valid_state(
                     project,
                     render_target,
                     &OutputTarget {
@@ -152,13 +205,14 @@ pub fn apply_state<T: RenderTarget<T>>(
                     },
                     state,
                     color_palette,
+                    errors,
                 );
             }
         }
         Output::Group(id) => {
             if let Some(g) = project.groups.get(id) {
                 for target in &g.targets {
-                    apply_state(project, render_target, target, state, color_palette);
+                    apply_valid_state(project, render_target, target, state, color_palette, errors);
                 }
             }
         }
@@ -363,14 +417,16 @@ pub fn get_fixtures(
     }
 }
 
+/// Returns how far through its cycle `effect` is, from 0 to 1.
 pub fn calculate_timing(
+    effect: &'static str,
     effect_timing: &EffectTiming,
     system_t: u64,
     effect_t: Option<&f64>,
     beat_t: f64,
     group_phase: f64,
     fixture_index: usize,
-) -> f64 {
+) -> Result<f64, RenderError> {
     // Calculate based on timing mode.
     let mut t = match effect_timing.timing {
         Some(Timing::Absolute(Absolute { duration_ms })) => {
@@ -378,7 +434,12 @@ pub fn calculate_timing(
         }
         Some(Timing::Beat(Beat { multiplier })) => beat_t / f64::from(multiplier),
         Some(Timing::OneShot(_)) => *effect_t.unwrap_or(&beat_t),
-        None => panic!("Timing type not specified when trying to calculate timing!"),
+        None => {
+            return Err(RenderError::IncompleteEffect {
+                effect,
+                missing: "timing",
+            });
+        }
     };
 
     // Modify with phase offset based on phase_type.
@@ -398,14 +459,19 @@ pub fn calculate_timing(
     }
 
     // Ease.
-    match EasingFunction::try_from(effect_timing.easing) {
+    Ok(match EasingFunction::try_from(effect_timing.easing) {
         Ok(EasingFunction::Linear) => t,
         Ok(EasingFunction::EaseIn) => t * t * t,
         Ok(EasingFunction::EaseOut) => 1.0 - (1.0 - t).powf(3.0),
         Ok(EasingFunction::EaseInOut) => t * t * (3.0 - 2.0 * t),
         Ok(EasingFunction::Sine) => f64::midpoint(-(PI * t).cos(), 1.0),
-        _ => panic!("Unknown easing type!"),
-    }
+        Err(_) => {
+            return Err(RenderError::UnknownValue {
+                field: "easing",
+                value: effect_timing.easing,
+            });
+        }
+    })
 }
 
 #[cfg(test)]
@@ -1,9 +1,11 @@
 #![allow(clippy::cast_possible_truncation)]
 
 use crate::palette::interpolate_palettes;
-use crate::proto::ColorPalette;
 use crate::proto::WledRenderTarget;
+use crate::proto::scene::tile::BlendMode;
 use crate::proto::wled_render_target::Color;
+use crate::proto::{ColorInterpolation, ColorPalette};
+use crate::render::render::RenderError;
 use crate::render::render_target::RenderTarget;
 
 macro_rules! lerp {
@@ -18,11 +20,13 @@ impl RenderTarget<WledRenderTarget> for WledRenderTarget {
         qualified_fixture_id: &crate::proto::QualifiedFixtureId,
         state: &crate::proto::FixtureState,
         color_palette: &ColorPalette,
-    ) {
+    ) -> Result<(), RenderError> {
         if qualified_fixture_id.output != self.id {
-            return;
+            return Ok(());
         }
 
+        let color = state.try_get_color(color_palette)?;
+
         if self.color_palette.is_none() {
             self.color_palette = Some(color_palette.clone());
         }
@@ -41,7 +45,7 @@ impl RenderTarget<WledRenderTarget> for WledRenderTarget {
             segment.palette = palette;
         }
 
-        if let Some(color) = state.get_color(color_palette) {
+        if let Some(color) = color {
             if let Some(white) = color.white {
                 segment.primary_color = Some(Color {
                     red: (color.red + white) as f32,
@@ -66,12 +70,33 @@ impl RenderTarget<WledRenderTarget> for WledRenderTarget {
         }
 
         self.segments[usize::from(qualified_fixture_id.fixture as u16)] = segment;
+        Ok(())
+    }
+
+    fn interpolate_split(
+        &mut self,
+        a: &WledRenderTarget,
+        b: &WledRenderTarget,
+        up: f64,
+        down: f64,
+    ) {
+        let mut falling = self.clone();
+        falling.interpolate(a, b, down);
+        self.interpolate(a, b, up);
+        for (index, segment) in self.segments.iter_mut().enumerate() {
+            if b.segments[index].brightness < a.segments[index].brightness {
+                segment.brightness = falling.segments[index].brightness;
+            }
+        }
     }
 
     fn interpolate(&mut self, a: &WledRenderTarget, b: &WledRenderTarget, t: f64) {
         let interpolate_color = |a: &Option<Color>, b: &Option<Color>, t: f32| -> Option<Color> {
-            let a_color = a.unwrap();
-            let b_color = b.unwrap();
+            let (a_color, b_color) = match (a, b) {
+                (Some(a_color), Some(b_color)) => (a_color, b_color),
+                (Some(color), None) | (None, Some(color)) => return Some(*color),
+                (None, None) => return None,
+            };
 
             Some(Color {
                 red: (1.0 - t) * a_color.red + t * b_color.red,
@@ -81,9 +106,12 @@ impl RenderTarget<WledRenderTarget> for WledRenderTarget {
         };
 
         self.color_palette = match (&a.color_palette, &b.color_palette) {
-            (Some(a_palette), Some(b_palette)) => {
-                Some(interpolate_palettes(a_palette, b_palette, t))
-            }
+            (Some(a_palette), Some(b_palette)) => Some(interpolate_palettes(
+                a_palette,
+                b_palette,
+                t,
+                ColorInterpolation::Rgb,
+            )),
             (Some(palette), None) | (None, Some(palette)) => Some(palette.clone()),
             (None, None) => None,
         };
@@ -113,7 +141,114 @@ impl RenderTarget<WledRenderTarget> for WledRenderTarget {
         }
     }
 
-    fn apply_fixture_debug(&mut self, _fixture_debug: &crate::proto::render_mode::FixtureDebug) {
-        panic!("Cannot perform fixture debug for WLED render target!");
+    fn interpolate_colors(
+        &mut self,
+        a: &WledRenderTarget,
+        b: &WledRenderTarget,
+        t: f64,
+        space: ColorInterpolation,
+    ) {
+        let to_color = |c: Color| crate::proto::Color {
+            red: f64::from(c.red),
+            green: f64::from(c.green),
+            blue: f64::from(c.blue),
+            white: None,
+        };
+
+        if let (Some(a_palette), Some(b_palette)) = (&a.color_palette, &b.color_palette) {
+            self.color_palette = Some(interpolate_palettes(a_palette, b_palette, t, space));
+        }
+
+        for index in 0..self.segments.len() {
+            if let (Some(a_color), Some(b_color)) = (
+                a.segments[index].primary_color,
+                b.segments[index].primary_color,
+            ) {
+                let color = to_color(a_color).interpolate(&to_color(b_color), t, space);
+                self.segments[index].primary_color = Some(Color {
+                    red: color.red as f32,
+                    green: color.green as f32,
+                    blue: color.blue as f32,
+                });
+            }
+        }
+    }
+
+    fn blend_layer(&self, mode: BlendMode) -> WledRenderTarget {
+        let mut layer = self.clone();
+        let identity = mode.identity() as f32;
+        for segment in &mut layer.segments {
+            if mode.blends_intensity() {
+                segment.brightness = identity;
+            }
+            if mode.blends_color() {
+                segment.primary_color = Some(Color {
+                    red: identity,
+                    green: identity,
+                    blue: identity,
+                });
+            }
+        }
+        layer
+    }
+
+    fn blend(
+        &mut self,
+        base: &WledRenderTarget,
+        layer: &WledRenderTarget,
+        t: f64,
+        mode: BlendMode,
+    ) {
+        self.interpolate(base, layer, t);
+
+        let blend = |a: f32, b: f32| mode.blend_value(f64::from(a), f64::from(b), t) as f32;
+        for (index, segment) in self.segments.iter_mut().enumerate() {
+            let base_segment = base.segments[index];
+            let layer_segment = layer.segments[index];
+
+            if mode.blends_intensity() {
+                segment.brightness = blend(base_segment.brightness, layer_segment.brightness);
+            }
+
+            if mode.blends_color()
+                && let (Some(a), Some(b)) =
+                    (base_segment.primary_color, layer_segment.primary_color)
+            {
+                segment.primary_color = Some(Color {
+                    red: blend(a.red, b.red),
+                    green: blend(a.green, b.green),
+                    blue: blend(a.blue, b.blue),
+                });
+            }
+        }
+    }
+
+    fn scale_intensity(
+        &mut self,
+        qualified_fixture_id: &crate::proto::QualifiedFixtureId,
+        amount: f64,
+    ) {
+        if qualified_fixture_id.output != self.id {
+            return;
+        }
+
+        if let Some(segment) = self.segments.get_mut(qualified_fixture_id.fixture as usize) {
+            segment.brightness *= amount as f32;
+        }
+    }
+
+    fn apply_fixture_debug(
+        &mut self,
+        _fixture_debug: &crate::proto::render_mode::FixtureDebug,
+    ) -> Result<(), RenderError> {
+        // Fixture debug only addresses DMX channels.
+        Err(RenderError::WrongOutputType)
+    }
+
+    fn apply_recorded_universe(
+        &mut self,
+        _universe: usize,
+        _values: &[u8; crate::render::dmx_render_target::UNIVERSE_SIZE],
+    ) {
     }
 }
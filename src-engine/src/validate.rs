@@ -0,0 +1,853 @@
+//! Walks a project looking for structural problems before they reach the
+//! renderer.
+//!
+//! Findings name the offending object by a path of proto field names, map
+//! keys and list indices, e.g. `scenes/3/tile_map/0/tile/targeted_effects/1`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    proto::{
+        ColorPalette, Effect, EffectTiming, OutputTarget, Project, QualifiedFixtureId,
+        TargetedEffect,
+        effect::{Effect as EffectKind, preset_effect::Effect as PresetKind},
+        effect_timing::Timing,
+        output::Output,
+        output_target::Output as TargetOutput,
+        track::beat_keyframe::Info,
+    },
+    render::{dmx_render_target::UNIVERSE_SIZE, sequence_index::cyclic_sequences},
+};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Renders, but probably not as intended.
+    Warning,
+    /// Fails to render or is skipped by the renderer.
+    Error,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+/// Returns every problem found in `project`, in a stable order.
+#[must_use]
+pub fn validate(project: &Project) -> Vec<Finding> {
+    let mut validator = Validator {
+        project,
+        findings: Vec::new(),
+    };
+    validator.check_patches();
+    validator.check_groups();
+    validator.check_scenes();
+    validator.check_playlists();
+    validator.check_shows();
+    validator.check_sequences();
+    validator.check_cue_lists();
+    validator.findings
+}
+
+/// Returns the entries of a map ordered by key.
+fn sorted<K: Ord + Copy, V>(map: &HashMap<K, V>) -> Vec<(K, &V)> {
+    let mut entries: Vec<(K, &V)> = map.iter().map(|(k, v)| (*k, v)).collect();
+    entries.sort_unstable_by_key(|(k, _)| *k);
+    entries
+}
+
+/// Whether `effect` runs once over a time it is given rather than cycling by
+/// itself.
+fn is_one_shot(effect: &Effect) -> bool {
+    let timing = match &effect.effect {
+        Some(EffectKind::RampEffect(ramp)) => ramp.timing_mode.as_ref(),
+        Some(EffectKind::SequenceEffect(sequence)) => sequence.timing_mode.as_ref(),
+        Some(EffectKind::PresetEffect(preset)) => match &preset.effect {
+            Some(PresetKind::RainbowEffect(rainbow)) => rainbow.timing_mode.as_ref(),
+            Some(PresetKind::CircleEffect(circle)) => circle.timing_mode.as_ref(),
+            Some(PresetKind::ShapeEffect(shape)) => shape.timing_mode.as_ref(),
+            Some(PresetKind::DimmerChaseEffect(chase)) => chase.timing_mode.as_ref(),
+            None => None,
+        },
+        _ => None,
+    };
+    matches!(
+        timing.and_then(|t| t.timing.as_ref()),
+        Some(Timing::OneShot(_))
+    )
+}
+
+struct Validator<'a> {
+    project: &'a Project,
+    findings: Vec<Finding>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, severity: Severity, path: &str, message: String) {
+        self.findings.push(Finding {
+            severity,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, path: &str, message: String) {
+        self.report(Severity::Error, path, message);
+    }
+
+    fn warning(&mut self, path: &str, message: String) {
+        self.report(Severity::Warning, path, message);
+    }
+
+    fn check_patches(&mut self) {
+        let definitions = self
+            .project
+            .fixture_definitions
+            .as_ref()
+            .map(|d| &d.dmx_fixture_definitions);
+
+        for (patch_id, patch) in sorted(&self.project.patches) {
+            for (output_id, output) in sorted(&patch.outputs) {
+                let (fixtures, universe_count) = match &output.output {
+                    Some(Output::SerialDmxOutput(serial)) => (&serial.fixtures, 1),
+                    Some(Output::SacnDmxOutput(sacn)) => (&sacn.fixtures, sacn.universe_count),
+                    Some(Output::ArtnetDmxOutput(artnet)) => {
+                        (&artnet.fixtures, artnet.universe_count)
+                    }
+                    _ => continue,
+                };
+                let channel_count = universe_count.max(1) as usize * UNIVERSE_SIZE;
+
+                if let Some(Output::SacnDmxOutput(sacn)) = &output.output {
+                    let universes = sacn.universe..sacn.universe + universe_count.max(1);
+                    if sacn.sync_universe != 0 && universes.contains(&sacn.sync_universe) {
+                        self.error(
+                            &format!("patches/{patch_id}/outputs/{output_id}"),
+                            format!(
+                                "Output \"{}\" synchronizes on universe {}, which it sends data on",
+                                output.name, sacn.sync_universe
+                            ),
+                        );
+                    }
+                }
+
+                // The channels each fixture takes, as a zero-based range.
+                let mut ranges = Vec::new();
+                for (fixture_id, fixture) in sorted(fixtures) {
+                    let path =
+                        format!("patches/{patch_id}/outputs/{output_id}/fixtures/{fixture_id}");
+                    let Some(definition) =
+                        definitions.and_then(|d| d.get(&fixture.fixture_definition_id))
+                    else {
+                        self.error(
+                            &path,
+                            format!(
+                                "Fixture \"{}\" uses definition {} which does not exist",
+                                fixture.name, fixture.fixture_definition_id
+                            ),
+                        );
+                        continue;
+                    };
+                    let Some(mode) = definition.modes.get(&fixture.fixture_mode) else {
+                        self.error(
+                            &path,
+                            format!(
+                                "Fixture \"{}\" uses mode \"{}\" which definition \"{}\" does not have",
+                                fixture.name, fixture.fixture_mode, definition.name
+                            ),
+                        );
+                        continue;
+                    };
+
+                    let width = mode
+                        .channels
+                        .keys()
+                        .copied()
+                        .max()
+                        .unwrap_or(0)
+                        .max(mode.num_channels) as usize;
+                    let start =
+                        fixture.universe as usize * UNIVERSE_SIZE + fixture.channel_offset as usize;
+                    if start + width > channel_count {
+                        self.warning(
+                            &path,
+                            format!(
+                                "Fixture \"{}\" reaches past the last universe of its output",
+                                fixture.name
+                            ),
+                        );
+                    }
+                    if width > 0 {
+                        ranges.push((start..start + width, fixture_id, &fixture.name));
+                    }
+                }
+
+                ranges.sort_by_key(|(range, id, _)| (range.start, *id));
+                for (i, (range, fixture_id, name)) in ranges.iter().enumerate() {
+                    for (other, other_id, other_name) in &ranges[..i] {
+                        if other.end > range.start {
+                            self.error(
+                                &format!(
+                                    "patches/{patch_id}/outputs/{output_id}/fixtures/{fixture_id}"
+                                ),
+                                format!(
+                                    "Fixture \"{name}\" overlaps the channels of fixture \"{other_name}\" ({other_id})"
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_groups(&mut self) {
+        for (group_id, group) in sorted(&self.project.groups) {
+            for (i, target) in group.targets.iter().enumerate() {
+                self.check_target(&format!("groups/{group_id}/targets/{i}"), target);
+            }
+        }
+    }
+
+    fn check_scenes(&mut self) {
+        for (scene_id, scene) in sorted(&self.project.scenes) {
+            for (i, palette) in scene.color_palettes.iter().enumerate() {
+                self.check_palette(&format!("scenes/{scene_id}/color_palettes/{i}"), palette);
+            }
+            for (i, tile_map) in scene.tile_map.iter().enumerate() {
+                let path = format!("scenes/{scene_id}/tile_map/{i}/tile");
+                let Some(tile) = &tile_map.tile else {
+                    continue;
+                };
+                if tile.transition.is_none() {
+                    self.error(&path, format!("Tile \"{}\" has no transition", tile.name));
+                }
+                if tile.timing_details.is_none() {
+                    self.warning(
+                        &path,
+                        format!("Tile \"{}\" has no timing details", tile.name),
+                    );
+                }
+                for (j, targeted_effect) in tile.targeted_effects.iter().enumerate() {
+                    self.check_targeted_effect(
+                        &format!("{path}/targeted_effects/{j}"),
+                        targeted_effect,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_playlists(&mut self) {
+        for (playlist_id, playlist) in sorted(&self.project.playlists) {
+            for (i, palette) in playlist.palettes.iter().enumerate() {
+                self.check_palette(&format!("playlists/{playlist_id}/palettes/{i}"), palette);
+            }
+            for (i, pattern) in playlist.patterns.iter().enumerate() {
+                for (j, targeted_effect) in pattern.targeted_effects.iter().enumerate() {
+                    self.check_targeted_effect(
+                        &format!("playlists/{playlist_id}/patterns/{i}/targeted_effects/{j}"),
+                        targeted_effect,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_shows(&mut self) {
+        for (show_id, show) in sorted(&self.project.shows) {
+            let path = format!("shows/{show_id}");
+            if let Some(audio_track) = show.audio_track {
+                match self.project.tracks.get(&audio_track.track_id) {
+                    None => self.error(
+                        &format!("{path}/audio_track"),
+                        format!(
+                            "Show \"{}\" plays track {} which does not exist",
+                            show.name, audio_track.track_id
+                        ),
+                    ),
+                    Some(track)
+                        if !track
+                            .beat_keyframes
+                            .iter()
+                            .any(|k| matches!(k.info, Some(Info::Bpm(_)))) =>
+                    {
+                        self.warning(
+                            &format!("tracks/{}", audio_track.track_id),
+                            format!(
+                                "Track \"{}\" of show \"{}\" has no BPM keyframe",
+                                track.name, show.name
+                            ),
+                        );
+                    }
+                    Some(_) => (),
+                }
+            }
+
+            for (i, keyframe) in show.palettes.iter().enumerate() {
+                if let Some(palette) = &keyframe.color_palette {
+                    self.check_palette(&format!("{path}/palettes/{i}/color_palette"), palette);
+                }
+            }
+
+            for (i, output) in show.outputs.iter().enumerate() {
+                let output_path = format!("{path}/outputs/{i}");
+                if let Some(target) = &output.output_target {
+                    self.check_target(&format!("{output_path}/output_target"), target);
+                }
+                let Some(layer) = &output.layer else {
+                    self.error(
+                        &output_path,
+                        format!("Show output \"{}\" has no layer", output.name),
+                    );
+                    continue;
+                };
+                for (j, effect) in layer.effects.iter().enumerate() {
+                    if let Some(effect) = &effect.effect {
+                        self.check_effect(
+                            &format!("{output_path}/layer/effects/{j}/effect"),
+                            effect,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_sequences(&mut self) {
+        let cyclic = cyclic_sequences(self.project);
+        for (sequence_id, sequence) in sorted(&self.project.sequences) {
+            let path = format!("sequences/{sequence_id}");
+            if cyclic.contains(&sequence_id) {
+                self.error(
+                    &path,
+                    format!("Sequence \"{}\" plays itself", sequence.name),
+                );
+            }
+            for (i, layer) in sequence.layers.iter().enumerate() {
+                for (j, effect) in layer.effects.iter().enumerate() {
+                    if let Some(effect) = &effect.effect {
+                        self.check_effect(&format!("{path}/layers/{i}/effects/{j}/effect"), effect);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_cue_lists(&mut self) {
+        for (cue_list_id, cue_list) in sorted(&self.project.cue_lists) {
+            let path = format!("cue_lists/{cue_list_id}");
+            match &cue_list.color_palette {
+                Some(palette) => self.check_palette(&format!("{path}/color_palette"), palette),
+                None if !self.project.scenes.contains_key(&self.project.active_scene) => {
+                    self.warning(
+                        &path,
+                        format!(
+                            "Cue list \"{}\" has no palette and falls back to scene {}, which does not exist",
+                            cue_list.name, self.project.active_scene
+                        ),
+                    );
+                }
+                None => (),
+            }
+
+            for (field, cue_id) in [
+                ("active_cue", cue_list.active_cue),
+                ("previous_cue", cue_list.previous_cue),
+            ] {
+                if cue_id != 0 && !cue_list.cues.iter().any(|c| c.id == cue_id) {
+                    self.error(
+                        &format!("{path}/{field}"),
+                        format!(
+                            "Cue list \"{}\" stands at cue {cue_id}, which does not exist",
+                            cue_list.name
+                        ),
+                    );
+                }
+            }
+
+            for (i, cue) in cue_list.cues.iter().enumerate() {
+                let cue_path = format!("{path}/cues/{i}");
+                if cue.id == 0 {
+                    self.error(&cue_path, format!("Cue \"{}\" has no id", cue.name));
+                } else if let Some(other) = cue_list.cues[..i].iter().find(|c| c.id == cue.id) {
+                    self.error(
+                        &cue_path,
+                        format!(
+                            "Cue \"{}\" has the same id as cue \"{}\"",
+                            cue.name, other.name
+                        ),
+                    );
+                }
+                if cue.follow_ms.is_some() && i + 1 == cue_list.cues.len() {
+                    self.warning(
+                        &cue_path,
+                        format!("Cue \"{}\" follows, but is the last cue", cue.name),
+                    );
+                }
+
+                for (j, tracked) in cue.tracked_states.iter().enumerate() {
+                    let tracked_path = format!("{cue_path}/tracked_states/{j}");
+                    match (&tracked.output_target, &tracked.state) {
+                        (Some(target), Some(_)) => {
+                            self.check_target(&format!("{tracked_path}/output_target"), target);
+                        }
+                        _ => self.error(
+                            &tracked_path,
+                            format!(
+                                "A tracked state of cue \"{}\" has no target or no state",
+                                cue.name
+                            ),
+                        ),
+                    }
+                }
+                for (j, targeted_effect) in cue.effects.iter().enumerate() {
+                    let effect_path = format!("{cue_path}/effects/{j}");
+                    self.check_targeted_effect(&effect_path, targeted_effect);
+                    if cue.up_ms.max(cue.down_ms) == 0
+                        && targeted_effect.effect.as_ref().is_some_and(is_one_shot)
+                    {
+                        self.warning(
+                            &effect_path,
+                            format!(
+                                "A one-shot effect of cue \"{}\" has no fade to run over",
+                                cue.name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_targeted_effect(&mut self, path: &str, targeted_effect: &TargetedEffect) {
+        if let Some(effect) = &targeted_effect.effect {
+            self.check_effect(&format!("{path}/effect"), effect);
+        }
+        if let Some(target) = &targeted_effect.output_target {
+            self.check_target(&format!("{path}/output_target"), target);
+        }
+    }
+
+    fn check_target(&mut self, path: &str, target: &OutputTarget) {
+        match &target.output {
+            Some(TargetOutput::Fixtures(mapping)) => {
+                for fixture_id in &mapping.fixture_ids {
+                    if let Some(problem) = self.missing_fixture(fixture_id) {
+                        self.error(path, problem);
+                    }
+                }
+            }
+            Some(TargetOutput::Group(0)) | None => (),
+            Some(TargetOutput::Group(group_id)) => {
+                if !self.project.groups.contains_key(group_id) {
+                    self.error(
+                        path,
+                        format!("Targets group {group_id} which does not exist"),
+                    );
+                }
+            }
+            Some(TargetOutput::Display(display_id)) => {
+                if !self.project.displays.contains_key(display_id) {
+                    self.error(
+                        path,
+                        format!("Targets display {display_id} which does not exist"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Describes why a targeted fixture cannot be found, if it cannot.
+    fn missing_fixture(&self, id: &QualifiedFixtureId) -> Option<String> {
+        let Some(patch) = self.project.patches.get(&id.patch) else {
+            return Some(format!("Targets patch {} which does not exist", id.patch));
+        };
+        let Some(output) = patch.outputs.get(&id.output) else {
+            return Some(format!(
+                "Targets output {} of patch \"{}\" which does not exist",
+                id.output, patch.name
+            ));
+        };
+        let exists = match &output.output {
+            Some(Output::SerialDmxOutput(serial)) => serial.fixtures.contains_key(&id.fixture),
+            Some(Output::SacnDmxOutput(sacn)) => sacn.fixtures.contains_key(&id.fixture),
+            Some(Output::ArtnetDmxOutput(artnet)) => artnet.fixtures.contains_key(&id.fixture),
+            Some(Output::WledOutput(wled)) => {
+                u32::try_from(id.fixture).is_ok_and(|segment| wled.segments.contains_key(&segment))
+            }
+            Some(Output::DdpOutput(_)) | None => false,
+        };
+        (!exists).then(|| {
+            format!(
+                "Targets fixture {} of output \"{}\" which does not exist",
+                id.fixture, output.name
+            )
+        })
+    }
+
+    fn check_effect(&mut self, path: &str, effect: &Effect) {
+        let Some(effect) = &effect.effect else {
+            return;
+        };
+        match effect {
+            EffectKind::StaticEffect(static_effect) => {
+                self.require(path, "static", "state", static_effect.state.is_some());
+            }
+            EffectKind::RampEffect(ramp_effect) => {
+                self.check_timing(path, "ramp", ramp_effect.timing_mode.as_ref());
+                self.require(
+                    path,
+                    "ramp",
+                    "start state",
+                    ramp_effect.state_start.is_some(),
+                );
+                self.require(path, "ramp", "end state", ramp_effect.state_end.is_some());
+            }
+            EffectKind::StrobeEffect(strobe_effect) => {
+                self.require(path, "strobe", "state A", strobe_effect.state_a.is_some());
+                self.require(path, "strobe", "state B", strobe_effect.state_b.is_some());
+            }
+            EffectKind::RandomEffect(random_effect) => {
+                for (name, effect) in [
+                    ("effect_a", &random_effect.effect_a),
+                    ("effect_b", &random_effect.effect_b),
+                ] {
+                    if let Some(effect) = effect {
+                        self.check_effect(&format!("{path}/random_effect/{name}"), effect);
+                    }
+                }
+            }
+            EffectKind::SequenceEffect(sequence_effect) => {
+                if sequence_effect.sequence_id != 0 {
+                    self.check_timing(path, "sequence", sequence_effect.timing_mode.as_ref());
+                    if !self
+                        .project
+                        .sequences
+                        .contains_key(&sequence_effect.sequence_id)
+                    {
+                        self.error(
+                            path,
+                            format!(
+                                "Plays sequence {} which does not exist",
+                                sequence_effect.sequence_id
+                            ),
+                        );
+                    }
+                }
+            }
+            EffectKind::PresetEffect(preset_effect) => match &preset_effect.effect {
+                Some(PresetKind::RainbowEffect(rainbow)) => {
+                    self.check_timing(path, "rainbow", rainbow.timing_mode.as_ref());
+                }
+                Some(PresetKind::CircleEffect(circle)) => {
+                    self.check_timing(path, "circle", circle.timing_mode.as_ref());
+                }
+                Some(PresetKind::ShapeEffect(shape)) => {
+                    self.check_timing(path, "shape", shape.timing_mode.as_ref());
+                }
+                Some(PresetKind::DimmerChaseEffect(chase)) => {
+                    self.check_timing(path, "dimmer chase", chase.timing_mode.as_ref());
+                }
+                None => (),
+            },
+        }
+    }
+
+    fn require(&mut self, path: &str, effect: &str, part: &str, present: bool) {
+        if !present {
+            self.error(path, format!("The {effect} effect has no {part}"));
+        }
+    }
+
+    fn check_timing(&mut self, path: &str, effect: &str, timing: Option<&EffectTiming>) {
+        let present = timing.is_some_and(|t| t.timing.is_some());
+        self.require(path, effect, "timing", present);
+    }
+
+    fn check_palette(&mut self, path: &str, palette: &ColorPalette) {
+        for (name, color) in [
+            ("primary", &palette.primary),
+            ("secondary", &palette.secondary),
+            ("tertiary", &palette.tertiary),
+        ] {
+            if color.and_then(|c| c.color).is_none() {
+                self.warning(
+                    path,
+                    format!("Palette \"{}\" has no {name} color", palette.name),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{
+        ArtnetDmxOutput, CueList, DmxFixtureDefinition, FixtureDefinitions, FixtureState, Patch,
+        PhysicalDmxFixture, SacnDmxOutput, Scene, TargetGroup,
+        cue_list::{Cue, cue::TrackedState},
+        dmx_fixture_definition::Mode,
+        effect::RampEffect,
+        effect_timing::OneShot,
+        output_target::FixtureMapping,
+        scene::{Tile, TileMap, tile::Transition},
+    };
+
+    fn dmx_fixture(mode: &str, channel_offset: u32) -> PhysicalDmxFixture {
+        PhysicalDmxFixture {
+            name: format!("Fixture at {channel_offset}"),
+            fixture_definition_id: 1,
+            fixture_mode: mode.to_string(),
+            channel_offset,
+            ..Default::default()
+        }
+    }
+
+    fn project() -> Project {
+        let mut definition = DmxFixtureDefinition {
+            name: "Par".to_string(),
+            ..Default::default()
+        };
+        definition.modes.insert(
+            "4ch".to_string(),
+            Mode {
+                num_channels: 4,
+                ..Default::default()
+            },
+        );
+        Project {
+            fixture_definitions: Some(FixtureDefinitions {
+                dmx_fixture_definitions: HashMap::from([(1, definition)]),
+            }),
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    name: "Main".to_string(),
+                    outputs: HashMap::from([(
+                        10,
+                        crate::proto::Output {
+                            name: "Art-Net".to_string(),
+                            output: Some(Output::ArtnetDmxOutput(ArtnetDmxOutput {
+                                universe_count: 1,
+                                fixtures: HashMap::from([
+                                    (100, dmx_fixture("4ch", 0)),
+                                    (101, dmx_fixture("4ch", 4)),
+                                ]),
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        },
+                    )]),
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    fn fixture_target(fixture: u64) -> OutputTarget {
+        OutputTarget {
+            output: Some(TargetOutput::Fixtures(FixtureMapping {
+                fixture_ids: vec![QualifiedFixtureId {
+                    patch: 1,
+                    output: 10,
+                    fixture,
+                }],
+            })),
+        }
+    }
+
+    fn artnet_fixtures(project: &mut Project) -> &mut HashMap<u64, PhysicalDmxFixture> {
+        let output = project
+            .patches
+            .get_mut(&1)
+            .and_then(|p| p.outputs.get_mut(&10))
+            .and_then(|o| o.output.as_mut());
+        match output {
+            Some(Output::ArtnetDmxOutput(artnet)) => &mut artnet.fixtures,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn a_well_formed_project_has_no_findings() {
+        assert_eq!(validate(&project()), []);
+    }
+
+    #[test]
+    fn finds_overlapping_and_unknown_fixtures() {
+        let mut project = project();
+        let fixtures = artnet_fixtures(&mut project);
+        fixtures.insert(102, dmx_fixture("4ch", 6));
+        fixtures.insert(103, dmx_fixture("16ch", 20));
+        fixtures.insert(104, dmx_fixture("4ch", 510));
+
+        let findings = validate(&project);
+        let paths: Vec<(&str, Severity)> = findings
+            .iter()
+            .map(|f| (f.path.as_str(), f.severity))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("patches/1/outputs/10/fixtures/103", Severity::Error),
+                ("patches/1/outputs/10/fixtures/104", Severity::Warning),
+                ("patches/1/outputs/10/fixtures/102", Severity::Error),
+            ],
+            "{findings:?}"
+        );
+        assert!(findings[2].message.contains("(101)"), "{findings:?}");
+    }
+
+    #[test]
+    fn finds_sacn_synchronizing_on_a_data_universe() {
+        let with_sync_universe = |sync_universe| {
+            let mut project = project();
+            let output = project.patches.get_mut(&1).unwrap().outputs.get_mut(&10);
+            output.unwrap().output = Some(Output::SacnDmxOutput(SacnDmxOutput {
+                universe: 1,
+                universe_count: 2,
+                sync_universe,
+                ..Default::default()
+            }));
+            validate(&project)
+        };
+
+        let findings = with_sync_universe(2);
+        assert_eq!(findings.len(), 1, "{findings:?}");
+        assert_eq!(findings[0].path, "patches/1/outputs/10");
+        assert_eq!(with_sync_universe(3), []);
+        assert_eq!(with_sync_universe(0), []);
+    }
+
+    #[test]
+    fn finds_dangling_targets_and_incomplete_effects() {
+        let mut project = project();
+        project.groups.insert(
+            5,
+            TargetGroup {
+                name: "Stage".to_string(),
+                targets: vec![
+                    fixture_target(100),
+                    fixture_target(999),
+                    OutputTarget {
+                        output: Some(TargetOutput::Group(6)),
+                    },
+                ],
+            },
+        );
+        project.scenes.insert(
+            3,
+            Scene {
+                tile_map: vec![TileMap {
+                    tile: Some(Tile {
+                        name: "Wash".to_string(),
+                        transition: Some(Transition::AbsoluteStrength(1.0)),
+                        targeted_effects: vec![TargetedEffect {
+                            effect: Some(Effect {
+                                effect: Some(EffectKind::RampEffect(RampEffect::default())),
+                            }),
+                            output_target: Some(OutputTarget {
+                                output: Some(TargetOutput::Display(7)),
+                            }),
+                        }],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        );
+
+        let findings: Vec<(String, Severity)> = validate(&project)
+            .into_iter()
+            .map(|f| (f.path, f.severity))
+            .collect();
+        let effect = "scenes/3/tile_map/0/tile/targeted_effects/0";
+        assert_eq!(
+            findings,
+            [
+                ("groups/5/targets/1".to_string(), Severity::Error),
+                ("groups/5/targets/2".to_string(), Severity::Error),
+                ("scenes/3/tile_map/0/tile".to_string(), Severity::Warning),
+                (format!("{effect}/effect"), Severity::Error),
+                (format!("{effect}/effect"), Severity::Error),
+                (format!("{effect}/effect"), Severity::Error),
+                (format!("{effect}/output_target"), Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_broken_cue_lists() {
+        let mut project = project();
+        let cue = |id, follow_ms| Cue {
+            id,
+            name: format!("Cue {id}"),
+            follow_ms,
+            ..Default::default()
+        };
+        project.cue_lists.insert(
+            2,
+            CueList {
+                name: "Main".to_string(),
+                cues: vec![
+                    Cue {
+                        tracked_states: vec![TrackedState {
+                            output_target: Some(fixture_target(999)),
+                            state: Some(FixtureState::default()),
+                        }],
+                        effects: vec![TargetedEffect {
+                            effect: Some(Effect {
+                                effect: Some(EffectKind::RampEffect(RampEffect {
+                                    state_start: Some(FixtureState::default()),
+                                    state_end: Some(FixtureState::default()),
+                                    timing_mode: Some(EffectTiming {
+                                        timing: Some(Timing::OneShot(OneShot {})),
+                                        ..Default::default()
+                                    }),
+                                    ..Default::default()
+                                })),
+                            }),
+                            output_target: Some(fixture_target(100)),
+                        }],
+                        ..cue(1, None)
+                    },
+                    cue(1, None),
+                    cue(0, Some(500)),
+                ],
+                active_cue: 4,
+                ..Default::default()
+            },
+        );
+
+        let findings: Vec<(String, Severity)> = validate(&project)
+            .into_iter()
+            .map(|f| (f.path, f.severity))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                ("cue_lists/2".to_string(), Severity::Warning),
+                ("cue_lists/2/active_cue".to_string(), Severity::Error),
+                (
+                    "cue_lists/2/cues/0/tracked_states/0/output_target".to_string(),
+                    Severity::Error
+                ),
+                (
+                    "cue_lists/2/cues/0/effects/0".to_string(),
+                    Severity::Warning
+                ),
+                ("cue_lists/2/cues/1".to_string(), Severity::Error),
+                ("cue_lists/2/cues/2".to_string(), Severity::Error),
+                ("cue_lists/2/cues/2".to_string(), Severity::Warning),
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use dmx_runtime::artnet_discovery::ArtnetNode;
 use dmx_runtime::events::EventSink;
 use std::collections::HashMap;
 use std::sync::{Mutex, MutexGuard};
@@ -21,7 +22,10 @@ impl LogEventSink {
 impl EventSink for LogEventSink {
     fn render_error(&self, output_id: u64, message: &str) {
         let mut output_errors = self.lock_output_errors();
-        if output_errors.get(&output_id).is_some_and(|last| last == message) {
+        if output_errors
+            .get(&output_id)
+            .is_some_and(|last| last == message)
+        {
             return;
         }
 
@@ -46,4 +50,20 @@ impl EventSink for LogEventSink {
     fn audio_devices_changed(&self, device_names: &[String]) {
         log::info!("Audio inputs: {}", device_names.join(", "));
     }
+
+    fn artnet_nodes(&self, nodes: &[ArtnetNode]) {
+        let nodes: Vec<String> = nodes
+            .iter()
+            .map(|n| format!("{} ({})", n.short_name, n.ip_address))
+            .collect();
+        log::info!("Art-Net nodes: {}", nodes.join(", "));
+    }
+
+    fn artnet_output_status(&self, output_id: u64, alive: bool) {
+        if alive {
+            log::info!("Art-Net output {output_id} node is answering");
+        } else {
+            log::warn!("Art-Net output {output_id} node is not answering");
+        }
+    }
 }
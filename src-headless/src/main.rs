@@ -1,19 +1,31 @@
 mod events;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use dmx_engine::beat::BeatSampler;
 use dmx_engine::project;
 use dmx_engine::proto::playlist::{Hold, PaletteOrder, PatternOrder, Sequential, Shuffle};
-use dmx_engine::proto::render_mode::{Autopilot, Blackout, Mode};
-use dmx_engine::proto::{self, FatProject, Playlist, Project};
+use dmx_engine::proto::render_mode::{
+    Autopilot, Blackout, DmxInput, Mode, RecordingPlayback, Scene, TimecodedShow,
+    timecoded_show::State,
+};
+use dmx_engine::proto::{self, DmxRecording, FatProject, Playlist, Project};
+use dmx_engine::recording::{self, CsvWriter, FrameLogWriter, RecordingOptions, render_recording};
+use dmx_engine::render::playback;
 use dmx_engine::render::render::RENDER_MODE_REF;
+use dmx_engine::validate::{Severity, validate};
+use dmx_runtime::dmx_input::{DmxInputReceiver, ReceiverConfig};
 use dmx_runtime::runtime::{Runtime, RuntimeConfig};
+use dmx_runtime::util::now_ms;
 use log::LevelFilter;
 use prost::Message;
 use std::env;
 use std::format;
+use std::fs::File;
+use std::io::BufWriter;
+use std::net::Ipv4Addr;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use crate::events::LogEventSink;
@@ -25,6 +37,7 @@ const BLACKOUT_DRAIN: Duration = Duration::from_millis(250);
 /// Renders a DMX Controller App project without a display, for unattended installs.
 #[derive(Parser)]
 #[command(name = "dmx-controller-app-headless", version = env!("DMX_CONTROLLER_APP_VERSION"))]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     /// Path to a .dmxapp project exported from the desktop app.
     #[arg(long, value_name = "PATH")]
@@ -35,7 +48,7 @@ struct Args {
     patch: Option<String>,
 
     #[command(subcommand)]
-    mode: RenderModeArgs,
+    command: Command,
 
     /// Minimum level to log. The RUST_LOG env filter refines it per module.
     #[arg(long, default_value = "info", value_name = "LEVEL")]
@@ -53,6 +66,43 @@ struct Args {
     /// Skip MIDI, disabling controller input.
     #[arg(long)]
     no_midi: bool,
+
+    /// Skip Art-Net discovery, which polls for nodes and listens on UDP port
+    /// 6454.
+    #[arg(long)]
+    no_artnet_discovery: bool,
+
+    /// Skip DMX input, which listens for sACN on UDP port 5568 and Art-Net on
+    /// 6454.
+    #[arg(long)]
+    no_dmx_input: bool,
+
+    /// The directed broadcast address of the Art-Net network, such as
+    /// 2.255.255.255, for polls and `ArtSync`. Defaults to 255.255.255.255.
+    #[arg(long, value_name = "IP")]
+    artnet_broadcast: Option<Ipv4Addr>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Mode(RenderModeArgs),
+    /// Play back a DMX recording, either one stored in the project or a frame
+    /// log on disk.
+    Playback(PlaybackArgs),
+    /// Pass the DMX received over sACN and Art-Net straight through to the
+    /// outputs sending the same universes.
+    PassThrough,
+    /// Check the project for problems and exit without rendering. Exits with
+    /// a failure if any errors are found.
+    Validate(ValidateArgs),
+    /// Render a show, scene or playlist offline into a DMX recording and
+    /// exit, without touching any outputs.
+    Render(RenderArgs),
+    /// Record the DMX another console sends over sACN and Art-Net, on the
+    /// universes the active patch sends, into a frame log and exit, without
+    /// touching any outputs.
+    Capture(CaptureArgs),
 }
 
 #[derive(Subcommand)]
@@ -61,6 +111,98 @@ enum RenderModeArgs {
     Autopilot(AutopilotArgs),
 }
 
+#[derive(Parser, Default)]
+struct ValidateArgs {
+    /// Print the findings as a JSON array instead of one per line.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Parser)]
+#[command(group(ArgGroup::new("source").required(true)))]
+struct PlaybackArgs {
+    /// Play a recording stored in the project.
+    #[arg(long, value_name = "RECORDING_ID", group = "source")]
+    recording: Option<u64>,
+
+    /// Play a frame log written by `render` or captured from another console.
+    #[arg(long, value_name = "PATH", group = "source")]
+    file: Option<PathBuf>,
+
+    /// Start over at the end of the recording instead of holding the last
+    /// frame.
+    #[arg(long = "loop")]
+    looping: bool,
+
+    /// Playback rate, where 1 is real time.
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// Position in the recording to start from, in milliseconds.
+    #[arg(long, default_value_t = 0, value_name = "MS")]
+    from_ms: u32,
+}
+
+#[derive(Parser)]
+#[command(group(ArgGroup::new("source").required(true)))]
+struct RenderArgs {
+    /// Render a timecoded show from its start.
+    #[arg(long, value_name = "SHOW_ID", group = "source")]
+    show: Option<u64>,
+
+    /// Render a scene.
+    #[arg(long, value_name = "SCENE_ID", group = "source")]
+    scene: Option<u64>,
+
+    /// Render a playlist in Autopilot mode.
+    #[arg(long, value_name = "PLAYLIST_ID", group = "source")]
+    playlist: Option<u64>,
+
+    /// How long to render, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    duration_ms: u64,
+
+    /// Frames rendered per second.
+    #[arg(long, default_value_t = 40)]
+    fps: u32,
+
+    /// The synthetic wall-clock time of the first frame, in Unix milliseconds.
+    /// Beat-synced effects are phased relative to the project's beat.
+    #[arg(long, default_value_t = 0, value_name = "MS")]
+    start_t: u64,
+
+    /// File to write the recording to.
+    #[arg(long, value_name = "PATH")]
+    output: PathBuf,
+
+    /// Format of the recording.
+    #[arg(long, value_enum, default_value_t = RecordingFormat::Binary)]
+    format: RecordingFormat,
+}
+
+#[derive(Parser)]
+struct CaptureArgs {
+    /// How long to record, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    duration_ms: u64,
+
+    /// Frames recorded per second.
+    #[arg(long, default_value_t = 40)]
+    fps: u32,
+
+    /// File to write the frame log to.
+    #[arg(long, value_name = "PATH")]
+    output: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum RecordingFormat {
+    /// Binary frame log with a fixed size per frame.
+    Binary,
+    /// One row per frame and universe.
+    Csv,
+}
+
 #[derive(Parser, Default)]
 #[allow(clippy::struct_excessive_bools)]
 struct AutopilotArgs {
@@ -120,7 +262,25 @@ async fn run(args: Args) -> Result<(), String> {
         set_patch(&mut project, &patch_name)?;
     }
 
-    let render_mode = resolve_render_mode(&args.mode, &mut project)?;
+    let render_mode = match &args.command {
+        Command::Validate(validate_args) => return print_validation(&project, validate_args),
+        Command::Render(render_args) => return render_to_file(&mut project, render_args),
+        Command::Capture(capture_args) => {
+            if args.no_dmx_input {
+                return Err("Capturing needs DMX input".to_string());
+            }
+            return capture_to_file(project, capture_args).await;
+        }
+        Command::PassThrough => {
+            if args.no_dmx_input {
+                return Err("Pass-through needs DMX input".to_string());
+            }
+            log::info!("Passing DMX input through");
+            Mode::DmxInput(DmxInput {})
+        }
+        Command::Playback(playback_args) => playback_mode(playback_args, &mut project)?,
+        Command::Mode(mode) => resolve_render_mode(mode, &mut project)?,
+    };
 
     project::load(project)?;
     set_render_mode(render_mode)?;
@@ -136,6 +296,9 @@ async fn run(args: Args) -> Result<(), String> {
         enable_visualizer: !args.no_visualizer,
         enable_audio: !args.no_audio,
         enable_midi: !args.no_midi,
+        enable_artnet_discovery: !args.no_artnet_discovery,
+        enable_dmx_input: !args.no_dmx_input,
+        artnet_broadcast: args.artnet_broadcast,
     })
     .await?;
 
@@ -163,11 +326,191 @@ fn read_project(path: &Path) -> Result<Project, String> {
         .project
         .ok_or_else(|| format!("{} contains no project", path.display()))?;
 
+    for dmx_recording in project.recordings.values() {
+        let Some(bytes) = fat_project.cas.get(&dmx_recording.digest) else {
+            log::warn!(
+                "Recording \"{}\" is missing from {}",
+                dmx_recording.name,
+                path.display()
+            );
+            continue;
+        };
+        recording::load(&dmx_recording.digest, bytes)
+            .map_err(|e| format!("Failed to load recording \"{}\": {e}", dmx_recording.name))?;
+    }
+
     log::info!("Loaded project \"{}\"", project.name);
 
     Ok(project)
 }
 
+/// Prints every finding to stdout, failing if any of them is an error.
+fn print_validation(project: &Project, args: &ValidateArgs) -> Result<(), String> {
+    let findings = validate(project);
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&findings)
+            .map_err(|e| format!("Failed to encode findings: {e}"))?;
+        println!("{json}");
+    } else {
+        for finding in &findings {
+            let severity = match finding.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            println!("{severity}: {}: {}", finding.path, finding.message);
+        }
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    match errors {
+        0 => {
+            log::info!("Validation passed with {} warnings", findings.len());
+            Ok(())
+        }
+        _ => Err(format!(
+            "Validation failed: {errors} of {} findings are errors",
+            findings.len()
+        )),
+    }
+}
+
+/// Renders the requested source offline, writing each frame to disk as it is
+/// rendered.
+fn render_to_file(project: &mut Project, args: &RenderArgs) -> Result<(), String> {
+    let mode = offline_render_mode(project, args)?;
+    let render_mode = proto::RenderMode { mode: Some(mode) };
+    let options = RecordingOptions {
+        fps: args.fps,
+        duration_ms: args.duration_ms,
+        start_t: args.start_t,
+    };
+
+    let output_path = expand_home(&args.output)?;
+    let file = create_file(&output_path)?;
+    let rendered = match args.format {
+        RecordingFormat::Binary => render_recording(
+            project,
+            &render_mode,
+            options,
+            &mut FrameLogWriter::new(file),
+        )?,
+        RecordingFormat::Csv => {
+            render_recording(project, &render_mode, options, &mut CsvWriter::new(file))?
+        }
+    };
+    if let Some(error) = &rendered.error {
+        log::warn!("Rendered with errors: {error}");
+    }
+
+    log::info!(
+        "Wrote {} frames to {}",
+        rendered.frame,
+        output_path.display()
+    );
+    Ok(())
+}
+
+/// Creates `path` for a recording to be streamed into.
+fn create_file(path: &Path) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| format!("Failed to create {}: {e}", path.display()))
+}
+
+/// Listens for DMX input, without starting any outputs, and writes what
+/// arrives to disk.
+async fn capture_to_file(project: Project, args: &CaptureArgs) -> Result<(), String> {
+    let streams = recording::capture_streams(&project)?;
+    // Loaded so received universes can drive the project's input bindings.
+    project::load(project)?;
+
+    let receiver = Arc::new(DmxInputReceiver::new(
+        Arc::new(LogEventSink::default()),
+        Arc::new(Mutex::new(BeatSampler::default())),
+        ReceiverConfig::standard(true),
+    ));
+    receiver.start_receiver();
+    log::info!(
+        "Capturing {} universes for {}ms",
+        streams.iter().map(|s| s.universe_count).sum::<u32>(),
+        args.duration_ms
+    );
+    let output_path = expand_home(&args.output)?;
+    let mut writer = FrameLogWriter::new(create_file(&output_path)?);
+    let captured = receiver
+        .capture(streams, args.fps, args.duration_ms, &mut writer)
+        .await;
+    receiver.stop_receiver();
+    let frame_count = captured?;
+
+    log::info!(
+        "Wrote {frame_count} captured frames to {}",
+        output_path.display()
+    );
+    Ok(())
+}
+
+fn offline_render_mode(project: &mut Project, args: &RenderArgs) -> Result<Mode, String> {
+    if let Some(show_id) = args.show {
+        let show = project
+            .shows
+            .get(&show_id)
+            .ok_or_else(|| format!("Show {show_id} is not in the project"))?;
+        log::info!("Rendering show \"{}\"", show.name);
+        return Ok(Mode::TimecodedShow(TimecodedShow {
+            show_id,
+            state: Some(State::StartT(args.start_t)),
+        }));
+    }
+
+    if let Some(scene_id) = args.scene {
+        let scene = project
+            .scenes
+            .get(&scene_id)
+            .ok_or_else(|| format!("Scene {scene_id} is not in the project"))?;
+        log::info!("Rendering scene \"{}\"", scene.name);
+        // Parked channels take their palette from the active scene.
+        project.active_scene = scene_id;
+        return Ok(Mode::Scene(Scene { scene_id }));
+    }
+
+    let playlist_id = args.playlist.unwrap_or_default();
+    let playlist = project
+        .playlists
+        .get(&playlist_id)
+        .ok_or_else(|| format!("Playlist {playlist_id} is not in the project"))?;
+    log::info!("Rendering playlist \"{}\"", playlist.name);
+    Ok(Mode::Autopilot(Autopilot { playlist_id }))
+}
+
+/// Resolves which recording to play back, adding a frame log from disk to the
+/// project if needed.
+fn playback_mode(args: &PlaybackArgs, project: &mut Project) -> Result<Mode, String> {
+    let recording_id = match (&args.file, args.recording) {
+        (Some(path), _) => add_recording_file(project, &expand_home(path)?)?,
+        (None, Some(recording_id)) => recording_id,
+        (None, None) => return Err("No recording to play back".to_string()),
+    };
+    let dmx_recording = project
+        .recordings
+        .get(&recording_id)
+        .ok_or_else(|| format!("Recording {recording_id} is not in the project"))?;
+    log::info!("Playing back recording \"{}\"", dmx_recording.name);
+
+    let mut recording_playback = RecordingPlayback {
+        recording_id,
+        looping: args.looping,
+        speed: args.speed,
+        state: None,
+    };
+    playback::seek(&mut recording_playback, args.from_ms, now_ms());
+    Ok(Mode::RecordingPlayback(recording_playback))
+}
+
 fn resolve_render_mode(mode: &RenderModeArgs, project: &mut Project) -> Result<Mode, String> {
     match mode {
         RenderModeArgs::Autopilot(autopilot_args) => {
@@ -198,6 +541,27 @@ fn resolve_render_mode(mode: &RenderModeArgs, project: &mut Project) -> Result<M
     }
 }
 
+/// Loads the frame log at `path` and adds it to the project as a new
+/// recording, returning its id.
+fn add_recording_file(project: &mut Project, path: &Path) -> Result<u64, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    // Files never reach the CAS, so their path stands in for a digest.
+    let digest = format!("file:{}", path.display());
+    recording::load(&digest, &bytes)
+        .map_err(|e| format!("Failed to load {}: {e}", path.display()))?;
+
+    let recording_id = project.recordings.keys().max().map_or(1, |id| id + 1);
+    project.recordings.insert(
+        recording_id,
+        DmxRecording {
+            name: path.display().to_string(),
+            digest,
+        },
+    );
+    Ok(recording_id)
+}
+
 fn pattern_order_override(
     args: &AutopilotArgs,
     playlist: &Playlist,
@@ -449,6 +813,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validation_fails_on_errors_but_not_warnings() {
+        let mut project = project_with_playlist(7);
+        let args = ValidateArgs::default();
+        assert_eq!(print_validation(&project, &args), Ok(()));
+
+        project.groups.insert(
+            1,
+            dmx_engine::proto::TargetGroup {
+                name: "Stage".to_string(),
+                targets: vec![dmx_engine::proto::OutputTarget {
+                    output: Some(dmx_engine::proto::output_target::Output::Group(2)),
+                }],
+            },
+        );
+        assert_eq!(
+            print_validation(&project, &args),
+            Err("Validation failed: 1 of 1 findings are errors".to_string())
+        );
+    }
+
+    fn render_args(output: &Path) -> RenderArgs {
+        RenderArgs {
+            show: None,
+            scene: None,
+            playlist: None,
+            duration_ms: 500,
+            fps: 10,
+            start_t: 0,
+            output: output.to_path_buf(),
+            format: RecordingFormat::Binary,
+        }
+    }
+
+    #[test]
+    fn render_writes_a_recording_of_every_dmx_output() {
+        let mut project = project_with_playlist(7);
+        project.active_patch = 1;
+        project.fixture_definitions = Some(dmx_engine::proto::FixtureDefinitions::default());
+        project.patches.insert(
+            1,
+            dmx_engine::proto::Patch {
+                name: "Main".to_string(),
+                outputs: HashMap::from([(
+                    3,
+                    dmx_engine::proto::Output {
+                        output: Some(dmx_engine::proto::output::Output::SerialDmxOutput(
+                            dmx_engine::proto::SerialDmxOutput::default(),
+                        )),
+                        ..Default::default()
+                    },
+                )]),
+            },
+        );
+        let fixture = Fixture::new("render", &[]);
+        let mut args = render_args(&fixture.0);
+        args.playlist = Some(7);
+
+        render_to_file(&mut project, &args).unwrap();
+
+        let bytes = std::fs::read(&fixture.0).unwrap();
+        let recording = dmx_engine::recording::Recording::decode(&bytes).unwrap();
+        assert_eq!(recording.fps, 10);
+        assert_eq!(recording.streams.len(), 1);
+        assert_eq!(recording.frames.len(), 5);
+    }
+
+    #[test]
+    fn playback_adds_a_frame_log_from_disk_to_the_project() {
+        let frame_log = dmx_engine::recording::Recording {
+            fps: 40,
+            streams: Vec::new(),
+            frames: Vec::new(),
+        };
+        let fixture = Fixture::new("playback", &frame_log.encode());
+        let mut project = project_with_playlist(7);
+        project.recordings.insert(
+            4,
+            DmxRecording {
+                name: "Stored".to_string(),
+                digest: "stored".to_string(),
+            },
+        );
+        let args = PlaybackArgs {
+            recording: None,
+            file: Some(fixture.0.clone()),
+            looping: true,
+            speed: 1.0,
+            from_ms: 0,
+        };
+
+        let Ok(Mode::RecordingPlayback(recording_playback)) = playback_mode(&args, &mut project)
+        else {
+            panic!("expected a recording playback");
+        };
+        assert_eq!(recording_playback.recording_id, 5);
+        assert!(recording_playback.looping);
+        assert_eq!(
+            project.recordings[&5].digest,
+            format!("file:{}", fixture.0.display())
+        );
+        assert!(recording::loaded(&project.recordings[&5].digest).is_some());
+    }
+
+    #[test]
+    fn render_reports_a_missing_source() {
+        let mut project = project_with_playlist(7);
+        let mut args = render_args(Path::new("/nonexistent/render.bin"));
+        args.scene = Some(4);
+
+        assert_eq!(
+            render_to_file(&mut project, &args),
+            Err("Scene 4 is not in the project".to_string())
+        );
+    }
+
     #[test]
     fn autopilot_selects_the_active_playlist() {
         let mut project = project_with_playlist(7);
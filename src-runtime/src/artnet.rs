@@ -1,56 +1,242 @@
-use artnet_protocol::{ArtCommand, Output, PortAddress};
-use std::net::{IpAddr, SocketAddr, UdpSocket};
+use artnet_protocol::{ARTNET_HEADER, ARTNET_PROTOCOL_VERSION, ArtCommand, Output, PortAddress};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::sync::Mutex;
 
-const ARTNET_PORT: u16 = 6454;
+use crate::util::lock_or_recover;
+
+pub(crate) const ARTNET_PORT: u16 = 6454;
+
+/// `OpSync`, which `artnet_protocol` leaves unimplemented.
+const OP_SYNC: u16 = 0x5200;
+
+/// Whether `ip_address` reaches every node rather than one: the limited
+/// broadcast address, or `directed_broadcast`, the configured broadcast
+/// address of the Art-Net network.
+pub(crate) fn is_broadcast(ip_address: Ipv4Addr, directed_broadcast: Ipv4Addr) -> bool {
+    ip_address == Ipv4Addr::BROADCAST || ip_address == directed_broadcast
+}
+
+/// An `ArtSync` packet: the header, the op code, the protocol version and two
+/// zeroed aux bytes.
+fn sync_packet() -> [u8; 14] {
+    let mut packet = [0; 14];
+    packet[..8].copy_from_slice(ARTNET_HEADER);
+    packet[8..10].copy_from_slice(&OP_SYNC.to_le_bytes());
+    packet[10..12].copy_from_slice(&ARTNET_PROTOCOL_VERSION);
+    packet
+}
+
+fn dmx_packet(universe: u16, sequence: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+    let port_address = PortAddress::try_from(universe)
+        .map_err(|e| format!("Invalid Art-Net universe {universe}: {e}"))?;
+
+    ArtCommand::Output(Output {
+        sequence,
+        port_address,
+        data: data.to_vec().into(),
+        ..Output::default()
+    })
+    .write_to_buffer()
+    .map_err(|e| format!("Failed to encode Art-Net packet: {e}"))
+}
+
+pub(crate) fn destination(ip_address: &str) -> Result<SocketAddr, String> {
+    let ip_addr: IpAddr = ip_address
+        .parse()
+        .map_err(|e| format!("Invalid IP address '{ip_address}': {e}"))?;
+    Ok(SocketAddr::new(ip_addr, ARTNET_PORT))
+}
+
+/// The Art-Net outputs that are running, and those that have sent their
+/// universes since the last `ArtSync`.
+#[derive(Default)]
+struct SyncFrame {
+    outputs: BTreeSet<u64>,
+    sent: BTreeSet<u64>,
+}
+
+impl SyncFrame {
+    /// Whether every output has sent, which starts the next frame.
+    fn complete(&mut self) -> bool {
+        if self.sent.is_empty() || !self.outputs.is_subset(&self.sent) {
+            return false;
+        }
+        self.sent.clear();
+        true
+    }
+
+    fn output_sent(&mut self, output_id: u64) -> bool {
+        self.sent.insert(output_id);
+        self.complete()
+    }
+
+    fn remove_output(&mut self, output_id: u64) -> bool {
+        self.outputs.remove(&output_id);
+        self.sent.remove(&output_id);
+        self.complete()
+    }
+}
 
 pub struct ArtnetState {
     socket: Mutex<UdpSocket>,
+    /// Where `ArtSync` goes, so it reaches every node.
+    broadcast: Ipv4Addr,
+    /// Last sequence number sent to each destination and universe.
+    sequences: Mutex<HashMap<(SocketAddr, u16), u8>>,
+    sync_frame: Mutex<SyncFrame>,
 }
 
 impl ArtnetState {
-    pub fn new() -> Result<Self, String> {
+    /// `broadcast` is the directed broadcast address of the Art-Net network,
+    /// or the limited broadcast address.
+    pub fn new(broadcast: Ipv4Addr) -> Result<Self, String> {
         let socket = UdpSocket::bind("0.0.0.0:0")
             .map_err(|e| format!("Failed to create Art-Net socket: {e}"))?;
+        // Outputs may address a whole subnet.
+        socket
+            .set_broadcast(true)
+            .map_err(|e| format!("Failed to enable Art-Net broadcast: {e}"))?;
 
         Ok(ArtnetState {
             socket: Mutex::new(socket),
+            broadcast,
+            sequences: Mutex::new(HashMap::new()),
+            sync_frame: Mutex::new(SyncFrame::default()),
         })
     }
 
+    pub(crate) fn broadcast(&self) -> Ipv4Addr {
+        self.broadcast
+    }
+
+    /// The port every packet is sent from, so the receiver can ignore them.
+    pub(crate) fn local_port(&self) -> Option<u16> {
+        let socket = self.socket.lock().ok()?;
+        socket.local_addr().ok().map(|addr| addr.port())
+    }
+
+    /// Numbers the packets of each universe from 1 to 255 and round again, so
+    /// nodes can drop packets that arrive out of order. Zero would turn
+    /// resequencing off.
+    fn next_sequence(&self, destination: SocketAddr, universe: u16) -> u8 {
+        let mut sequences = lock_or_recover(&self.sequences, "Art-Net sequences");
+        let sequence = sequences.entry((destination, universe)).or_insert(0);
+        *sequence = sequence.wrapping_add(1).max(1);
+        *sequence
+    }
+
+    fn send(&self, bytes: &[u8], destination: SocketAddr) -> Result<(), String> {
+        let socket = self
+            .socket
+            .lock()
+            .map_err(|e| format!("Failed to lock Art-Net socket: {e}"))?;
+
+        socket
+            .send_to(bytes, destination)
+            .map_err(|e| format!("Failed to send Art-Net packet: {e}"))?;
+
+        Ok(())
+    }
+
     pub(crate) fn output_artnet(
         &self,
         universe: u16,
         ip_address: &str,
         data: &[u8],
     ) -> Result<(), String> {
-        let ip_addr: IpAddr = ip_address
-            .parse()
-            .map_err(|e| format!("Invalid IP address '{ip_address}': {e}"))?;
-        let socket_addr = SocketAddr::new(ip_addr, ARTNET_PORT);
+        let destination = destination(ip_address)?;
+        let sequence = self.next_sequence(destination, universe);
+        self.send(&dmx_packet(universe, sequence, data)?, destination)
+    }
 
-        let port_address = PortAddress::try_from(universe)
-            .map_err(|e| format!("Invalid Art-Net universe {universe}: {e}"))?;
+    /// Counts `output_id` in each frame until [`Self::remove_output`].
+    pub(crate) fn add_output(&self, output_id: u64) {
+        lock_or_recover(&self.sync_frame, "Art-Net sync")
+            .outputs
+            .insert(output_id);
+    }
 
-        let command = ArtCommand::Output(Output {
-            port_address,
-            data: data.to_vec().into(),
-            ..Output::default()
-        });
+    /// Stops counting `output_id`, which may complete the frame.
+    pub(crate) fn remove_output(&self, output_id: u64) -> Result<(), String> {
+        let complete = lock_or_recover(&self.sync_frame, "Art-Net sync").remove_output(output_id);
+        if complete { self.sync() } else { Ok(()) }
+    }
 
-        let bytes = command
-            .write_to_buffer()
-            .map_err(|e| format!("Failed to encode Art-Net packet: {e}"))?;
+    /// Marks the universes of `output_id` as sent for this frame. Once every
+    /// output has sent, one `ArtSync` is broadcast so the nodes output all of
+    /// them at once. A faster output's extra frames are overwritten until the
+    /// slowest catches up. Nodes that have never seen an `ArtSync` output each
+    /// universe as it arrives.
+    pub(crate) fn frame_sent(&self, output_id: u64) -> Result<(), String> {
+        let complete = lock_or_recover(&self.sync_frame, "Art-Net sync").output_sent(output_id);
+        if complete { self.sync() } else { Ok(()) }
+    }
 
-        let socket = self
-            .socket
-            .lock()
-            .map_err(|e| format!("Failed to lock Art-Net socket: {e}"))?;
+    fn sync(&self) -> Result<(), String> {
+        self.send(
+            &sync_packet(),
+            SocketAddr::new(self.broadcast.into(), ARTNET_PORT),
+        )
+    }
+}
 
-        socket
-            .send_to(&bytes, socket_addr)
-            .map_err(|e| format!("Failed to send Art-Net DMX data: {e}"))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(())
+    #[test]
+    fn sequences_count_per_universe_and_skip_zero() {
+        let state = ArtnetState::new(Ipv4Addr::BROADCAST).unwrap();
+        let node = destination("10.0.0.1").unwrap();
+        let other_node = destination("10.0.0.2").unwrap();
+
+        assert_eq!(state.next_sequence(node, 0), 1);
+        assert_eq!(state.next_sequence(node, 0), 2);
+        assert_eq!(state.next_sequence(node, 1), 1);
+        assert_eq!(state.next_sequence(other_node, 0), 1);
+
+        for _ in 2..255 {
+            state.next_sequence(node, 0);
+        }
+        assert_eq!(state.next_sequence(node, 0), 1);
+    }
+
+    #[test]
+    fn packets_carry_the_sequence_and_sync_is_well_formed() {
+        let packet = dmx_packet(0x123, 42, &[7; 512]).unwrap();
+        let Ok(ArtCommand::Output(output)) = ArtCommand::from_buffer(&packet) else {
+            panic!("not an ArtDmx packet");
+        };
+        assert_eq!(output.sequence, 42);
+        assert_eq!(output.port_address, PortAddress::try_from(0x123).unwrap());
+
+        let sync = sync_packet();
+        assert_eq!(&sync[..8], b"Art-Net\0");
+        assert_eq!(sync[8..], [0x00, 0x52, 0, 14, 0, 0]);
+    }
+
+    #[test]
+    fn broadcast_is_limited_or_the_configured_directed_address() {
+        let directed = Ipv4Addr::new(2, 255, 255, 255);
+        assert!(is_broadcast(Ipv4Addr::BROADCAST, directed));
+        assert!(is_broadcast(directed, directed));
+        assert!(!is_broadcast(Ipv4Addr::new(10, 0, 0, 255), directed));
+        assert!(!is_broadcast(Ipv4Addr::new(2, 0, 0, 10), directed));
+    }
+
+    #[test]
+    fn frames_complete_once_every_output_has_sent() {
+        let mut frame = SyncFrame::default();
+        frame.outputs.extend([1, 2]);
+
+        assert!(!frame.output_sent(1));
+        assert!(!frame.output_sent(1));
+        assert!(frame.output_sent(2));
+
+        // An output that stops no longer holds the frame back.
+        assert!(!frame.output_sent(2));
+        assert!(frame.remove_output(1));
+        assert!(frame.output_sent(2));
     }
 }
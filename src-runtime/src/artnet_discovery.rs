@@ -0,0 +1,544 @@
+//! Art-Net node discovery.
+//!
+//! An `ArtPoll` goes out every [`POLL_INTERVAL`] and the nodes that answer with
+//! an `ArtPollReply` are kept in a list until they have been silent for
+//! [`NODE_TIMEOUT`]. An Art-Net output is live while a node at its IP address
+//! is in the list.
+
+use artnet_protocol::{ArtCommand, Poll, PollReply};
+use dmx_engine::project;
+use dmx_engine::project_util::rand_id;
+use dmx_engine::proto::output::Output as ProtoOutput;
+use dmx_engine::proto::{ArtnetDmxOutput, Output, Project};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+use crate::artnet::{ARTNET_PORT, is_broadcast};
+use crate::artnet_rdm::RdmReply;
+use crate::dmx_input::DmxInputReceiver;
+use crate::events::EventSink;
+use crate::util::lock_or_recover;
+
+/// Art-Net asks controllers to poll every 2.5 to 3 seconds.
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Long enough to ride out a couple of lost replies.
+const NODE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A node that answered `ArtPoll`. Nodes with more than four ports answer once
+/// per group of four, told apart by `bind_index`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtnetNode {
+    pub ip_address: String,
+    pub bind_index: u8,
+    pub short_name: String,
+    pub long_name: String,
+    /// The universe (15 bit Port-Address) of each port that outputs DMX.
+    pub output_universes: Vec<u16>,
+}
+
+impl ArtnetNode {
+    fn from_reply(reply: &PollReply, from: IpAddr) -> Self {
+        let ip_address = if reply.address.is_unspecified() {
+            from
+        } else {
+            IpAddr::V4(reply.address)
+        };
+
+        // The Port-Address is split over the Net, Sub-Net and port switches.
+        let net = u16::from(reply.port_address[0] & 0x7f) << 8;
+        let sub_net = u16::from(reply.port_address[1] & 0x0f) << 4;
+        let port_count = usize::from(reply.num_ports[1]).min(4);
+        let output_universes = (0..port_count)
+            .filter(|&port| reply.port_types[port] & 0x80 != 0)
+            .map(|port| net | sub_net | u16::from(reply.swout[port] & 0x0f))
+            .collect();
+
+        ArtnetNode {
+            ip_address: ip_address.to_string(),
+            bind_index: reply.bind_index,
+            short_name: null_terminated(&reply.short_name),
+            long_name: null_terminated(&reply.long_name),
+            output_universes,
+        }
+    }
+}
+
+fn null_terminated(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+struct SeenNode {
+    node: ArtnetNode,
+    last_seen: Instant,
+}
+
+pub struct ArtnetDiscovery {
+    events: Arc<dyn EventSink>,
+    poll_address: SocketAddr,
+    /// The directed broadcast address of the Art-Net network.
+    broadcast: Ipv4Addr,
+    nodes: Mutex<BTreeMap<(String, u8), SeenNode>>,
+    /// Last liveness reported for each Art-Net output of the active patch.
+    output_status: Mutex<HashMap<u64, bool>>,
+    dmx_input: Mutex<Option<Arc<DmxInputReceiver>>>,
+    rdm_replies: Mutex<Option<mpsc::Sender<RdmReply>>>,
+    watcher_cancel_tx: Mutex<Option<tokio::sync::watch::Sender<bool>>>,
+}
+
+impl ArtnetDiscovery {
+    /// Broadcasts polls to every node on the network, through `broadcast`,
+    /// its directed broadcast address or the limited broadcast address.
+    pub fn new(events: Arc<dyn EventSink>, broadcast: Ipv4Addr) -> Self {
+        ArtnetDiscovery {
+            broadcast,
+            ..Self::with_poll_address(events, SocketAddr::new(broadcast.into(), ARTNET_PORT))
+        }
+    }
+
+    /// Sends polls to `poll_address` alone, such as a single node.
+    pub fn with_poll_address(events: Arc<dyn EventSink>, poll_address: SocketAddr) -> Self {
+        ArtnetDiscovery {
+            events,
+            poll_address,
+            broadcast: Ipv4Addr::BROADCAST,
+            nodes: Mutex::new(BTreeMap::new()),
+            output_status: Mutex::new(HashMap::new()),
+            dmx_input: Mutex::new(None),
+            rdm_replies: Mutex::new(None),
+            watcher_cancel_tx: Mutex::new(None),
+        }
+    }
+
+    /// The nodes currently answering, ordered by address.
+    pub fn nodes(&self) -> Vec<ArtnetNode> {
+        lock_or_recover(&self.nodes, "Art-Net nodes")
+            .values()
+            .map(|seen| seen.node.clone())
+            .collect()
+    }
+
+    /// Whether a node is answering at the address of each Art-Net output, as
+    /// of the last poll.
+    pub fn output_status(&self) -> HashMap<u64, bool> {
+        lock_or_recover(&self.output_status, "Art-Net output status").clone()
+    }
+
+    /// Start polling for nodes.
+    pub fn start_discovery(self: &Arc<Self>) {
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+        {
+            let mut watcher = lock_or_recover(&self.watcher_cancel_tx, "Watcher cancel");
+            *watcher = Some(cancel_tx);
+        }
+
+        let state = Arc::clone(self);
+        tokio::spawn(async move {
+            Self::discovery_loop(state, cancel_rx).await;
+        });
+
+        log::info!("Art-Net discovery started");
+    }
+
+    /// Signals the loop started by [`Self::start_discovery`] to exit.
+    pub fn stop_discovery(&self) {
+        if let Some(cancel_tx) = lock_or_recover(&self.watcher_cancel_tx, "Watcher cancel").take() {
+            let _ = cancel_tx.send(true);
+        }
+    }
+
+    async fn discovery_loop(
+        state: Arc<ArtnetDiscovery>,
+        mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+    ) {
+        let socket = match bind_socket().await {
+            Ok(socket) => socket,
+            Err(e) => {
+                log::error!("Failed to create Art-Net discovery socket: {e}");
+                return;
+            }
+        };
+        let poll = match ArtCommand::Poll(Poll::default()).write_to_buffer() {
+            Ok(poll) => poll,
+            Err(e) => {
+                log::error!("Failed to encode ArtPoll: {e}");
+                return;
+            }
+        };
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        let mut buffer = [0u8; 1024];
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = socket.send_to(&poll, state.poll_address).await {
+                        log::warn!("Failed to send ArtPoll: {e}");
+                    }
+                    state.expire(Instant::now());
+                }
+                received = socket.recv_from(&mut buffer) => {
+                    if let Ok((len, from)) = received {
+                        state.receive(&buffer[..len], from, Instant::now());
+                    }
+                }
+                _ = cancel_rx.changed() => {
+                    if *cancel_rx.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        log::info!("Art-Net discovery loop exited");
+    }
+
+    /// Hands any DMX received on the Art-Net port to the receiver given to
+    /// [`Self::forward_dmx_to`], since discovery holds that port.
+    pub fn forward_dmx_to(&self, receiver: Arc<DmxInputReceiver>) {
+        *lock_or_recover(&self.dmx_input, "Art-Net DMX input") = Some(receiver);
+    }
+
+    /// Hands the `ArtTodData` and `ArtRdm` packets nodes send back to the
+    /// returned receiver, in place of any earlier one.
+    pub(crate) fn forward_rdm(&self) -> mpsc::Receiver<RdmReply> {
+        let (replies_tx, replies) = mpsc::channel();
+        *lock_or_recover(&self.rdm_replies, "Art-Net RDM replies") = Some(replies_tx);
+        replies
+    }
+
+    /// Records the node behind an `ArtPollReply` and forwards `ArtDmx` and
+    /// RDM replies. Anything else, including the poll itself coming back from
+    /// the broadcast, is ignored.
+    fn receive(&self, packet: &[u8], from: SocketAddr, now: Instant) {
+        let reply = match ArtCommand::from_buffer(packet) {
+            Ok(ArtCommand::PollReply(reply)) => reply,
+            Ok(ArtCommand::Output(_)) => {
+                let receiver = lock_or_recover(&self.dmx_input, "Art-Net DMX input").clone();
+                if let Some(receiver) = receiver {
+                    receiver.receive_artnet(packet, from, now);
+                }
+                return;
+            }
+            Ok(ArtCommand::TodData | ArtCommand::Rdm) => {
+                let mut replies = lock_or_recover(&self.rdm_replies, "Art-Net RDM replies");
+                if let Some(replies_tx) = replies.as_ref()
+                    && replies_tx.send((from.ip(), packet.to_vec())).is_err()
+                {
+                    *replies = None;
+                }
+                return;
+            }
+            _ => return,
+        };
+        let node = ArtnetNode::from_reply(&reply, from.ip());
+
+        let changed = {
+            let mut nodes = lock_or_recover(&self.nodes, "Art-Net nodes");
+            let key = (node.ip_address.clone(), node.bind_index);
+            let changed = nodes.get(&key).is_none_or(|seen| seen.node != node);
+            nodes.insert(
+                key,
+                SeenNode {
+                    node,
+                    last_seen: now,
+                },
+            );
+            changed
+        };
+
+        if changed {
+            self.events.artnet_nodes(&self.nodes());
+            self.update_output_status();
+        }
+    }
+
+    /// Drops the nodes that have stopped answering.
+    fn expire(&self, now: Instant) {
+        let changed = {
+            let mut nodes = lock_or_recover(&self.nodes, "Art-Net nodes");
+            let before = nodes.len();
+            nodes.retain(|_, seen| now.duration_since(seen.last_seen) < NODE_TIMEOUT);
+            nodes.len() != before
+        };
+
+        if changed {
+            self.events.artnet_nodes(&self.nodes());
+        }
+        // Outputs may have been added or readdressed since the last poll.
+        self.update_output_status();
+    }
+
+    fn update_output_status(&self) {
+        let outputs = match project::with_project(|project| Ok(artnet_outputs(project))) {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                log::error!("Failed to read Art-Net outputs: {e}");
+                return;
+            }
+        };
+        let nodes = self.nodes();
+
+        let mut changes = Vec::new();
+        {
+            let mut status = lock_or_recover(&self.output_status, "Art-Net output status");
+            status.retain(|output_id, _| outputs.contains_key(output_id));
+            for (output_id, output) in outputs {
+                let alive = is_alive(&output, &nodes, self.broadcast);
+                if status.insert(output_id, alive) != Some(alive) {
+                    changes.push((output_id, alive));
+                }
+            }
+        }
+
+        for (output_id, alive) in changes {
+            self.events.artnet_output_status(output_id, alive);
+        }
+    }
+}
+
+/// An output is alive while a node answers at its address or, for a broadcast
+/// address, while any node outputs one of its universes.
+fn is_alive(output: &ArtnetDmxOutput, nodes: &[ArtnetNode], broadcast: Ipv4Addr) -> bool {
+    if nodes.iter().any(|n| n.ip_address == output.ip_address) {
+        return true;
+    }
+    if !output
+        .ip_address
+        .parse()
+        .is_ok_and(|ip| is_broadcast(ip, broadcast))
+    {
+        return false;
+    }
+    let universes = output.universe..output.universe + output.universe_count.max(1);
+    nodes.iter().any(|n| {
+        n.output_universes
+            .iter()
+            .any(|u| universes.contains(&u32::from(*u)))
+    })
+}
+
+/// Nodes reply to the Art-Net port whichever port the poll came from, so
+/// discovery listens there. Another Art-Net application may already have it,
+/// in which case only nodes that reply to the sending port are found.
+async fn bind_socket() -> std::io::Result<UdpSocket> {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, ARTNET_PORT)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::warn!("Art-Net port {ARTNET_PORT} is in use, some nodes may not be found: {e}");
+            UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?
+        }
+    };
+    socket.set_broadcast(true)?;
+    Ok(socket)
+}
+
+/// Map from the ID of each Art-Net output in the active patch to its IP address.
+fn artnet_outputs(project: &Project) -> HashMap<u64, ArtnetDmxOutput> {
+    project
+        .patches
+        .get(&project.active_patch)
+        .into_iter()
+        .flat_map(|patch| &patch.outputs)
+        .filter_map(|(output_id, output)| match &output.output {
+            Some(ProtoOutput::ArtnetDmxOutput(artnet)) => Some((*output_id, artnet.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Adds an Art-Net output to the active patch for each output port of `node`
+/// that no output sends to yet. Returns the IDs of the new outputs.
+pub fn add_node_outputs(project: &mut Project, node: &ArtnetNode) -> Result<Vec<u64>, String> {
+    let patch_id = project.active_patch;
+    let patch = project
+        .patches
+        .get_mut(&patch_id)
+        .ok_or_else(|| format!("Active patch {patch_id} not found"))?;
+
+    let name = if node.short_name.is_empty() {
+        &node.ip_address
+    } else {
+        &node.short_name
+    };
+
+    let mut output_ids = Vec::new();
+    for universe in &node.output_universes {
+        let universe = u32::from(*universe);
+        let covered = patch.outputs.values().any(|output| match &output.output {
+            Some(ProtoOutput::ArtnetDmxOutput(artnet)) => {
+                artnet.ip_address == node.ip_address
+                    && (artnet.universe..artnet.universe + artnet.universe_count.max(1))
+                        .contains(&universe)
+            }
+            _ => false,
+        });
+        if covered {
+            continue;
+        }
+
+        let output_id = rand_id();
+        patch.outputs.insert(
+            output_id,
+            Output {
+                name: format!("{name} universe {universe}"),
+                enabled: true,
+                output: Some(ProtoOutput::ArtnetDmxOutput(ArtnetDmxOutput {
+                    universe,
+                    universe_count: 1,
+                    ip_address: node.ip_address.clone(),
+                    fixtures: HashMap::new(),
+                })),
+                ..Default::default()
+            },
+        );
+        output_ids.push(output_id);
+    }
+    Ok(output_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dmx_engine::proto::Patch;
+
+    #[derive(Default)]
+    struct NodeEvents {
+        nodes: Mutex<Vec<Vec<ArtnetNode>>>,
+    }
+
+    impl EventSink for NodeEvents {
+        fn artnet_nodes(&self, nodes: &[ArtnetNode]) {
+            self.nodes.lock().unwrap().push(nodes.to_vec());
+        }
+    }
+
+    fn reply() -> PollReply {
+        let mut reply = PollReply {
+            address: Ipv4Addr::LOCALHOST,
+            port_address: [0x01, 0x02],
+            num_ports: [0, 3],
+            port_types: [0x80, 0x40, 0x80, 0],
+            swout: [0x3, 0x4, 0x5, 0],
+            bind_index: 1,
+            ..PollReply::default()
+        };
+        reply.short_name[..8].copy_from_slice(b"Stand-in");
+        reply
+    }
+
+    #[test]
+    fn replies_list_output_universes() {
+        let node = ArtnetNode::from_reply(&reply(), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(
+            node,
+            ArtnetNode {
+                ip_address: "127.0.0.1".to_string(),
+                bind_index: 1,
+                short_name: "Stand-in".to_string(),
+                long_name: String::new(),
+                output_universes: vec![0x123, 0x125],
+            }
+        );
+    }
+
+    #[test]
+    fn outputs_are_added_for_uncovered_ports() {
+        let node = ArtnetNode::from_reply(&reply(), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let mut project = Project {
+            active_patch: 1,
+            patches: HashMap::from([(1, Patch::default())]),
+            ..Default::default()
+        };
+
+        let output_ids = add_node_outputs(&mut project, &node).unwrap();
+        assert_eq!(output_ids.len(), 2);
+        let outputs = artnet_outputs(&project);
+        assert!(
+            output_ids
+                .iter()
+                .all(|id| outputs[id].ip_address == "127.0.0.1")
+        );
+
+        // Every port is covered now, so nothing more is added.
+        assert!(add_node_outputs(&mut project, &node).unwrap().is_empty());
+    }
+
+    #[test]
+    fn broadcast_outputs_are_alive_while_a_node_outputs_their_universes() {
+        let nodes = [ArtnetNode::from_reply(
+            &reply(),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        )];
+        let output = |ip_address: &str, universe| ArtnetDmxOutput {
+            universe,
+            universe_count: 2,
+            ip_address: ip_address.to_string(),
+            ..Default::default()
+        };
+
+        let broadcast = Ipv4Addr::new(2, 255, 255, 255);
+        assert!(is_alive(&output("127.0.0.1", 0), &nodes, broadcast));
+        assert!(is_alive(&output("2.255.255.255", 0x122), &nodes, broadcast));
+        assert!(is_alive(
+            &output("255.255.255.255", 0x122),
+            &nodes,
+            broadcast
+        ));
+        assert!(!is_alive(
+            &output("2.255.255.255", 0x200),
+            &nodes,
+            broadcast
+        ));
+        assert!(!is_alive(&output("2.0.0.1", 0x122), &nodes, broadcast));
+    }
+
+    #[tokio::test]
+    async fn discovers_nodes_that_answer_polls() {
+        let stand_in = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let stand_in_address = stand_in.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 1024];
+            let (len, from) = stand_in.recv_from(&mut buffer).await.unwrap();
+            assert!(matches!(
+                ArtCommand::from_buffer(&buffer[..len]),
+                Ok(ArtCommand::Poll(_))
+            ));
+            let reply = ArtCommand::PollReply(Box::new(reply()))
+                .write_to_buffer()
+                .unwrap();
+            stand_in.send_to(&reply, from).await.unwrap();
+        });
+
+        let events = Arc::new(NodeEvents::default());
+        let discovery = Arc::new(ArtnetDiscovery::with_poll_address(
+            events.clone(),
+            stand_in_address,
+        ));
+        discovery.start_discovery();
+
+        let nodes = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let nodes = discovery.nodes();
+                if !nodes.is_empty() {
+                    return nodes;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        discovery.stop_discovery();
+
+        assert_eq!(nodes[0].short_name, "Stand-in");
+        assert_eq!(*events.nodes.lock().unwrap(), vec![nodes]);
+
+        // Silent nodes are dropped.
+        discovery.expire(Instant::now() + NODE_TIMEOUT);
+        assert!(discovery.nodes().is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! RDM through an Art-Net node, which `artnet_protocol` leaves unimplemented.
+//!
+//! The node runs discovery on its own ports and hands over its table of
+//! devices for an `ArtTodRequest`. Other requests travel inside `ArtRdm`
+//! packets. Nodes reply to the Art-Net port, which discovery holds, so the
+//! replies arrive through [`crate::artnet_discovery::ArtnetDiscovery::forward_rdm`].
+
+use artnet_protocol::{ARTNET_HEADER, ARTNET_PROTOCOL_VERSION};
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use crate::artnet::is_broadcast;
+use crate::rdm::{RdmPort, START_CODE, Uid};
+
+const OP_TOD_REQUEST: u16 = 0x8000;
+const OP_TOD_DATA: u16 = 0x8100;
+const OP_RDM: u16 = 0x8300;
+/// Bytes before the addressed universe's data in every RDM packet.
+const HEADER_LEN: usize = 24;
+const RDM_VERSION: u8 = 0x01;
+/// `ArtTodData` command response for a complete table.
+const TOD_FULL: u8 = 0x00;
+
+/// The node may have to wait out a slow fixture.
+const RDM_TIMEOUT: Duration = Duration::from_secs(1);
+const TOD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The header shared by the RDM packets: the Art-Net ID, op code and protocol
+/// version, then the universe's Net and, after a command byte, its low byte.
+fn header(op_code: u16, universe: u16, command: u8) -> Vec<u8> {
+    let mut packet = vec![0; HEADER_LEN];
+    packet[..8].copy_from_slice(ARTNET_HEADER);
+    packet[8..10].copy_from_slice(&op_code.to_le_bytes());
+    packet[10..12].copy_from_slice(&ARTNET_PROTOCOL_VERSION);
+    let [net, address] = universe.to_be_bytes();
+    packet[21] = net & 0x7F;
+    packet[22] = command;
+    packet[23] = address;
+    packet
+}
+
+/// The universe a packet from a node is about.
+fn universe(packet: &[u8]) -> u16 {
+    u16::from_be_bytes([packet[21] & 0x7F, packet[23]])
+}
+
+fn op_code(packet: &[u8]) -> Option<u16> {
+    if packet.len() < HEADER_LEN || !packet.starts_with(ARTNET_HEADER) {
+        return None;
+    }
+    Some(u16::from_le_bytes([packet[8], packet[9]]))
+}
+
+/// Asks for the full table of devices on `universe`. The address list holds
+/// that one universe.
+fn tod_request_packet(universe: u16) -> Vec<u8> {
+    let mut packet = header(OP_TOD_REQUEST, universe, TOD_FULL);
+    let [_, address] = universe.to_be_bytes();
+    packet[23] = 1;
+    packet.push(address);
+    packet.resize(HEADER_LEN + 32, 0);
+    packet
+}
+
+/// One block of a node's table of devices.
+struct TodData {
+    universe: u16,
+    uid_total: u16,
+    uids: Vec<Uid>,
+}
+
+fn parse_tod_data(packet: &[u8]) -> Option<TodData> {
+    if op_code(packet)? != OP_TOD_DATA || packet.len() < 28 || packet[22] != TOD_FULL {
+        return None;
+    }
+    let uid_count = usize::from(packet[27]);
+    let uids = packet.get(28..28 + 6 * uid_count)?;
+    Some(TodData {
+        universe: universe(packet),
+        uid_total: u16::from_be_bytes([packet[24], packet[25]]),
+        uids: uids
+            .chunks(6)
+            .map(|uid| {
+                let mut bytes = [0; 6];
+                bytes.copy_from_slice(uid);
+                Uid::from_bytes(bytes)
+            })
+            .collect(),
+    })
+}
+
+/// Carries `message`, an RDM message without its start code, to `universe`.
+fn rdm_packet(universe: u16, message: &[u8]) -> Vec<u8> {
+    let mut packet = header(OP_RDM, universe, 0);
+    packet[12] = RDM_VERSION;
+    packet.extend_from_slice(message);
+    packet
+}
+
+/// The universe and RDM message, without its start code, of an `ArtRdm`.
+fn parse_rdm(packet: &[u8]) -> Option<(u16, &[u8])> {
+    (op_code(packet)? == OP_RDM).then(|| (universe(packet), &packet[HEADER_LEN..]))
+}
+
+/// A packet a node sent back, and the address it came from.
+pub(crate) type RdmReply = (IpAddr, Vec<u8>);
+
+/// One universe of an Art-Net node.
+pub(crate) struct ArtnetRdmPort {
+    socket: UdpSocket,
+    node: SocketAddr,
+    /// Whether `node` is a broadcast address, which any node may answer.
+    any_node: bool,
+    universe: u16,
+    replies: Receiver<RdmReply>,
+}
+
+impl ArtnetRdmPort {
+    /// `broadcast` is the directed broadcast address of the Art-Net network.
+    pub(crate) fn new(
+        node: SocketAddr,
+        broadcast: Ipv4Addr,
+        universe: u16,
+        replies: Receiver<RdmReply>,
+    ) -> Result<Self, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| format!("Failed to create Art-Net RDM socket: {e}"))?;
+        socket
+            .set_broadcast(true)
+            .map_err(|e| format!("Failed to enable Art-Net broadcast: {e}"))?;
+        let any_node = match node.ip() {
+            IpAddr::V4(ip) => is_broadcast(ip, broadcast),
+            IpAddr::V6(_) => false,
+        };
+        Ok(ArtnetRdmPort {
+            socket,
+            node,
+            any_node,
+            universe,
+            replies,
+        })
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<(), String> {
+        self.socket
+            .send_to(packet, self.node)
+            .map_err(|e| format!("Failed to send Art-Net RDM packet: {e}"))?;
+        Ok(())
+    }
+
+    /// The next packet from the node, or from any node when it is addressed
+    /// by broadcast, until `deadline`.
+    fn next_reply(&self, deadline: Instant) -> Option<Vec<u8>> {
+        loop {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            let (from, packet) = self.replies.recv_timeout(remaining).ok()?;
+            if self.any_node || from == self.node.ip() {
+                return Some(packet);
+            }
+        }
+    }
+}
+
+impl RdmPort for ArtnetRdmPort {
+    fn transact(&mut self, request: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let message = request
+            .strip_prefix(&[START_CODE])
+            .filter(|message| message.len() > 14)
+            .ok_or("Not an RDM message")?;
+        self.send(&rdm_packet(self.universe, message))?;
+
+        // Broadcasts are never answered.
+        if message[2..8] == Uid::BROADCAST.to_bytes() {
+            return Ok(None);
+        }
+        let transaction = message[14];
+        let deadline = Instant::now() + RDM_TIMEOUT;
+        while let Some(reply) = self.next_reply(deadline) {
+            if let Some((universe, response)) = parse_rdm(&reply)
+                && universe == self.universe
+                && response.get(14) == Some(&transaction)
+            {
+                let mut response = response.to_vec();
+                response.insert(0, START_CODE);
+                return Ok(Some(response));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Asks the node for the devices it has found, which may come in several
+    /// blocks.
+    fn discover(&mut self) -> Result<Vec<Uid>, String> {
+        self.send(&tod_request_packet(self.universe))?;
+
+        let deadline = Instant::now() + TOD_TIMEOUT;
+        let mut uids = BTreeSet::new();
+        while let Some(reply) = self.next_reply(deadline) {
+            let Some(tod) = parse_tod_data(&reply).filter(|tod| tod.universe == self.universe)
+            else {
+                continue;
+            };
+            uids.extend(tod.uids);
+            if uids.len() >= usize::from(tod.uid_total) {
+                return Ok(uids.into_iter().collect());
+            }
+        }
+
+        if uids.is_empty() {
+            return Err(format!(
+                "The Art-Net node at {} did not send its devices on universe {}",
+                self.node.ip(),
+                self.universe
+            ));
+        }
+        Ok(uids.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdm::simulated::{Line, Responder};
+    use crate::rdm::{discover_devices, set_start_address};
+    use std::sync::mpsc;
+
+    fn tod_data_packet(universe: u16, uids: &[Uid]) -> Vec<u8> {
+        let mut packet = header(OP_TOD_DATA, universe, TOD_FULL);
+        packet[12] = RDM_VERSION;
+        #[allow(clippy::cast_possible_truncation)]
+        let count = uids.len() as u16;
+        packet.extend_from_slice(&count.to_be_bytes());
+        packet.extend_from_slice(&[0, count.to_be_bytes()[1]]);
+        for uid in uids {
+            packet.extend_from_slice(&uid.to_bytes());
+        }
+        packet
+    }
+
+    #[test]
+    fn packets_address_the_universe() {
+        let request = tod_request_packet(0x0123);
+        assert_eq!(request.len(), 56);
+        assert_eq!((request[21], request[23], request[24]), (0x01, 1, 0x23));
+
+        let uid = Uid {
+            manufacturer_id: 1,
+            device_id: 2,
+        };
+        let tod = parse_tod_data(&tod_data_packet(0x0123, &[uid])).unwrap();
+        assert_eq!(
+            (tod.universe, tod.uid_total, tod.uids),
+            (0x0123, 1, vec![uid])
+        );
+
+        let packet = rdm_packet(0x0123, &[1, 2, 3]);
+        assert_eq!(parse_rdm(&packet), Some((0x0123, &[1, 2, 3][..])));
+    }
+
+    #[test]
+    fn talks_to_devices_behind_a_node() {
+        let node = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let node_address = node.local_addr().unwrap();
+        let (replies_tx, replies) = mpsc::channel();
+
+        let uid = Uid {
+            manufacturer_id: 0x08BD,
+            device_id: 1,
+        };
+        std::thread::spawn(move || {
+            let mut line = Line {
+                responders: vec![Responder::new(uid)],
+            };
+            let mut buffer = [0u8; 1024];
+            while let Ok(len) = node.recv(&mut buffer) {
+                let packet = &buffer[..len];
+                let reply = match op_code(packet) {
+                    Some(OP_TOD_REQUEST) => tod_data_packet(7, &[uid]),
+                    Some(OP_RDM) => {
+                        let (universe, message) = parse_rdm(packet).unwrap();
+                        let mut request = vec![START_CODE];
+                        request.extend_from_slice(message);
+                        match line.respond(&request) {
+                            Some(response) => rdm_packet(universe, &response[1..]),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                let from = IpAddr::V4(Ipv4Addr::LOCALHOST);
+                if replies_tx.send((from, reply)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut port = ArtnetRdmPort::new(node_address, Ipv4Addr::BROADCAST, 7, replies).unwrap();
+        let devices = discover_devices(&mut port).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].uid, uid);
+        assert_eq!(devices[0].model, "Spot Mover");
+
+        set_start_address(&mut port, uid, 33).unwrap();
+        assert_eq!(crate::rdm::start_address(&mut port, uid).unwrap(), Some(33));
+    }
+}
@@ -252,7 +252,13 @@ impl DisplayLoopManager {
             let mut buffers: HashMap<u64, DisplayBuffer> = HashMap::new();
             for display_id in &config.display_ids {
                 let data = match render_display_target(*display_id, system_t, frame) {
-                    Ok(data) => data,
+                    Ok(rendered) => {
+                        match &rendered.error {
+                            Some(e) => events.render_error(*display_id, &e.to_string()),
+                            None => events.render_error_clear(*display_id),
+                        }
+                        rendered.frame
+                    }
                     Err(RenderError::OutputNotFound { .. }) => continue, // deleted
                     Err(e) => {
                         log::error!("Failed to render display {display_id}: {e}");
@@ -0,0 +1,726 @@
+use artnet_protocol::ArtCommand;
+use dmx_engine::dmx_input::{self, DmxInputState, Protocol};
+use dmx_engine::midi::ActionResult;
+use dmx_engine::project;
+use dmx_engine::proto::input_binding::Action::BeatMatch;
+use dmx_engine::recording::{self, FrameWriter, Stream};
+use sacn::packet::{ACN_SDT_MULTICAST_PORT, AcnRootLayerProtocol, E131RootLayerData};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::MissedTickBehavior;
+
+use crate::artnet::ARTNET_PORT;
+use crate::beat::SharedBeatSampler;
+use crate::events::EventSink;
+use crate::util::{lock_or_recover, now_ms};
+
+/// How long a source is held after it goes quiet, from E1.31's network data
+/// loss timeout.
+const SOURCE_TIMEOUT: Duration = Duration::from_millis(2500);
+/// How often quiet universes are dropped and the sACN multicast groups are
+/// brought in line with the project.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the receiver listens, and what it must not hear back.
+pub struct ReceiverConfig {
+    pub sacn_port: u16,
+    /// `None` when Art-Net comes through [`DmxInputReceiver::receive_artnet`]
+    /// instead, because discovery already listens on the port.
+    pub artnet_port: Option<u16>,
+    /// The CIDs this app's own sACN is sent under.
+    pub own_sacn_cids: Arc<Mutex<HashSet<[u8; 16]>>>,
+    /// The local port this app's own Art-Net is sent from.
+    pub own_artnet_port: Option<u16>,
+}
+
+impl ReceiverConfig {
+    /// The standard ports, with no output of our own to ignore.
+    #[must_use]
+    pub fn standard(listen_artnet: bool) -> Self {
+        ReceiverConfig {
+            sacn_port: ACN_SDT_MULTICAST_PORT,
+            artnet_port: listen_artnet.then_some(ARTNET_PORT),
+            own_sacn_cids: Arc::default(),
+            own_artnet_port: None,
+        }
+    }
+}
+
+/// A sender of a universe: an sACN source by its CID, or an Art-Net node or
+/// console by its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Source {
+    Sacn([u8; 16]),
+    Artnet(IpAddr),
+}
+
+struct Heard {
+    priority: u8,
+    last_seen: Instant,
+}
+
+/// The sources sending one universe, and the one it is taken from.
+#[derive(Default)]
+struct UniverseSources {
+    sources: HashMap<Source, Heard>,
+    taken: Option<Source>,
+}
+
+impl UniverseSources {
+    /// Whether the universe is taken from `source`: no live source outranks
+    /// it, and among equals the source already taken keeps the universe.
+    fn takes(&self, source: Source, now: Instant) -> bool {
+        let Some(priority) = self.sources.get(&source).map(|heard| heard.priority) else {
+            return false;
+        };
+        self.sources
+            .iter()
+            .filter(|(other, heard)| {
+                **other != source && now.duration_since(heard.last_seen) < SOURCE_TIMEOUT
+            })
+            .all(|(other, heard)| {
+                heard.priority < priority
+                    || (heard.priority == priority && self.taken != Some(*other))
+            })
+    }
+
+    /// Drops `source`. Returns whether any source is left.
+    fn remove(&mut self, source: Source) -> bool {
+        self.sources.remove(&source);
+        if self.taken == Some(source) {
+            self.taken = None;
+        }
+        !self.sources.is_empty()
+    }
+}
+
+pub struct DmxInputReceiver {
+    events: Arc<dyn EventSink>,
+    beat_sampler: SharedBeatSampler,
+    config: ReceiverConfig,
+    /// The sources of each universe, with their priorities and when they
+    /// were last heard.
+    universes: Mutex<HashMap<(Protocol, u16), UniverseSources>>,
+    /// What the output loops pass through or merge.
+    state: Mutex<DmxInputState>,
+    watcher_cancel_tx: Mutex<Option<tokio::sync::watch::Sender<bool>>>,
+}
+
+impl DmxInputReceiver {
+    pub fn new(
+        events: Arc<dyn EventSink>,
+        beat_sampler: SharedBeatSampler,
+        config: ReceiverConfig,
+    ) -> Self {
+        DmxInputReceiver {
+            events,
+            beat_sampler,
+            config,
+            universes: Mutex::new(HashMap::new()),
+            state: Mutex::new(DmxInputState::default()),
+            watcher_cancel_tx: Mutex::new(None),
+        }
+    }
+
+    /// Start listening for sACN and Art-Net.
+    pub fn start_receiver(self: &Arc<Self>) {
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+        {
+            let mut watcher = lock_or_recover(&self.watcher_cancel_tx, "Watcher cancel");
+            *watcher = Some(cancel_tx);
+        }
+
+        let state = Arc::clone(self);
+        tokio::spawn(async move {
+            Self::receive_loop(state, cancel_rx).await;
+        });
+
+        log::info!("DMX input started");
+    }
+
+    /// The received DMX, for an output loop to render with. Held only for
+    /// the length of a render, and never while the project is locked.
+    pub(crate) fn state(&self) -> MutexGuard<'_, DmxInputState> {
+        lock_or_recover(&self.state, "DMX input state")
+    }
+
+    /// Signals the loop started by [`Self::start_receiver`] to exit.
+    pub fn stop_receiver(&self) {
+        if let Some(cancel_tx) = lock_or_recover(&self.watcher_cancel_tx, "Watcher cancel").take() {
+            let _ = cancel_tx.send(true);
+        }
+    }
+
+    /// Records the universes of `streams` as they are received, `fps` times a
+    /// second for `duration_ms`, handing each frame to `out` as it is taken.
+    /// Returns the number of frames captured.
+    pub async fn capture(
+        &self,
+        streams: Vec<Stream>,
+        fps: u32,
+        duration_ms: u64,
+        out: &mut impl FrameWriter,
+    ) -> Result<u64, String> {
+        if fps == 0 {
+            return Err("Frame rate must be at least 1 fps".to_string());
+        }
+        let mut interval =
+            tokio::time::interval(Duration::from_nanos(1_000_000_000 / u64::from(fps)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        out.start(fps, &streams)
+            .map_err(|e| format!("Failed to write capture: {e}"))?;
+        let start = Instant::now();
+        let mut frame_count = 0;
+        loop {
+            interval.tick().await;
+            let t_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+            if t_ms >= duration_ms {
+                break;
+            }
+            let frame = recording::capture_frame(&streams, &self.state(), t_ms);
+            out.write_frame(&frame)
+                .map_err(|e| format!("Failed to write capture: {e}"))?;
+            frame_count += 1;
+        }
+        out.finish()
+            .map_err(|e| format!("Failed to write capture: {e}"))?;
+
+        Ok(frame_count)
+    }
+
+    async fn receive_loop(
+        state: Arc<DmxInputReceiver>,
+        mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+    ) {
+        let sacn_socket = bind_socket("sACN", state.config.sacn_port).await;
+        let artnet_socket = match state.config.artnet_port {
+            Some(port) => bind_socket("Art-Net", port).await,
+            None => None,
+        };
+
+        let mut joined = BTreeSet::new();
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        let mut sacn_buffer = [0u8; 1024];
+        let mut artnet_buffer = [0u8; 1024];
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Some(socket) = &sacn_socket {
+                        join_sacn_universes(socket, &mut joined);
+                    }
+                    state.expire(Instant::now());
+                }
+                received = recv_from(sacn_socket.as_ref(), &mut sacn_buffer) => {
+                    if let Ok((len, _)) = received {
+                        state.receive_sacn(&sacn_buffer[..len], Instant::now());
+                    }
+                }
+                received = recv_from(artnet_socket.as_ref(), &mut artnet_buffer) => {
+                    if let Ok((len, from)) = received {
+                        state.receive_artnet(&artnet_buffer[..len], from, Instant::now());
+                    }
+                }
+                _ = cancel_rx.changed() => {
+                    if *cancel_rx.borrow() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        lock_or_recover(&state.universes, "DMX input").clear();
+        *state.state() = DmxInputState::default();
+        log::info!("DMX input loop exited");
+    }
+
+    /// Takes the universe of an `ArtDmx` packet. Anything else is ignored.
+    pub(crate) fn receive_artnet(&self, packet: &[u8], from: SocketAddr, now: Instant) {
+        if self.config.own_artnet_port == Some(from.port()) {
+            return;
+        }
+        let Ok(ArtCommand::Output(output)) = ArtCommand::from_buffer(packet) else {
+            return;
+        };
+        // Art-Net has no priorities, so its sources all rank the same.
+        self.receive_universe(
+            Protocol::Artnet,
+            u16::from(output.port_address),
+            Source::Artnet(from.ip()),
+            0,
+            output.data.as_ref(),
+            now,
+        );
+    }
+
+    /// Takes the universe of an sACN data packet carrying dimmer data.
+    /// Preview data, other start codes and sync packets are ignored; data is
+    /// taken as it arrives rather than held for a sync.
+    fn receive_sacn(&self, packet: &[u8], now: Instant) {
+        let Ok(packet) = AcnRootLayerProtocol::parse(packet) else {
+            return;
+        };
+        let cid = *packet.pdu.cid.as_bytes();
+        if lock_or_recover(&self.config.own_sacn_cids, "own sACN CIDs").contains(&cid) {
+            return;
+        }
+        let E131RootLayerData::DataPacket(data) = packet.pdu.data else {
+            return;
+        };
+        let source = Source::Sacn(cid);
+        if data.stream_terminated {
+            self.remove_source(Protocol::Sacn, data.universe, source);
+            return;
+        }
+        let Some((0, values)) = data.data.property_values.split_first() else {
+            return;
+        };
+        if data.preview_data {
+            return;
+        }
+        self.receive_universe(
+            Protocol::Sacn,
+            data.universe,
+            source,
+            data.priority,
+            values,
+            now,
+        );
+    }
+
+    /// Takes `values` for `universe` from `source` unless another source
+    /// outranks it, then performs the bindings of the channels that changed.
+    fn receive_universe(
+        &self,
+        protocol: Protocol,
+        universe: u16,
+        source: Source,
+        priority: u8,
+        values: &[u8],
+        now: Instant,
+    ) {
+        {
+            let mut universes = lock_or_recover(&self.universes, "DMX input");
+            let sources = universes.entry((protocol, universe)).or_default();
+            sources.sources.insert(
+                source,
+                Heard {
+                    priority,
+                    last_seen: now,
+                },
+            );
+            if !sources.takes(source, now) {
+                return;
+            }
+            sources.taken = Some(source);
+        }
+
+        let t = now_ms();
+        let changes = self.state().receive(protocol, universe, values, t);
+        if changes.channels.is_empty() {
+            return;
+        }
+        match dmx_input::perform_actions(universe, &changes, t) {
+            Ok(results) => self.handle_action_results(&results, t),
+            Err(e) => log::error!("Failed to perform DMX input action: {e}"),
+        }
+    }
+
+    fn handle_action_results(&self, results: &[ActionResult], t: u64) {
+        let mut modified = false;
+        for result in results {
+            if let Some(BeatMatch(_)) = result.action {
+                let mut sampler = lock_or_recover(&self.beat_sampler, "Beat sampler");
+                crate::beat::add_sample(&mut sampler, self.events.as_ref(), t);
+            }
+            modified |= result.modified;
+        }
+        if modified {
+            self.events.project_updated();
+        }
+    }
+
+    /// Drops `source` from `universe`, and the universe once no source is
+    /// left. Another source takes over with its next packet.
+    fn remove_source(&self, protocol: Protocol, universe: u16, source: Source) {
+        let mut universes = lock_or_recover(&self.universes, "DMX input");
+        if let Some(sources) = universes.get_mut(&(protocol, universe))
+            && !sources.remove(source)
+        {
+            universes.remove(&(protocol, universe));
+            self.state().forget(protocol, universe);
+        }
+    }
+
+    /// Drops the sources that have gone quiet, and the universes they were
+    /// the last source of.
+    fn expire(&self, now: Instant) {
+        let mut universes = lock_or_recover(&self.universes, "DMX input");
+        universes.retain(|(protocol, universe), sources| {
+            let quiet: Vec<Source> = sources
+                .sources
+                .iter()
+                .filter(|(_, heard)| now.duration_since(heard.last_seen) >= SOURCE_TIMEOUT)
+                .map(|(source, _)| *source)
+                .collect();
+            for source in quiet {
+                sources.remove(source);
+            }
+            let alive = !sources.sources.is_empty();
+            if !alive {
+                self.state().forget(*protocol, *universe);
+            }
+            alive
+        });
+    }
+}
+
+/// `None` if the port is taken, such as by another receiver, in which case
+/// that protocol is not received.
+async fn bind_socket(protocol: &str, port: u16) -> Option<UdpSocket> {
+    match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).await {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            log::warn!("{protocol} port {port} is in use, {protocol} will not be received: {e}");
+            None
+        }
+    }
+}
+
+/// Waits forever without a socket, so `select!` skips that protocol.
+async fn recv_from(
+    socket: Option<&UdpSocket>,
+    buffer: &mut [u8],
+) -> std::io::Result<(usize, SocketAddr)> {
+    match socket {
+        Some(socket) => socket.recv_from(buffer).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Joins the multicast groups of the project's sACN input universes and
+/// leaves those no longer listed.
+fn join_sacn_universes(socket: &UdpSocket, joined: &mut BTreeSet<u16>) {
+    let Ok(wanted) = project::with_project(|project| {
+        Ok(project
+            .dmx_input
+            .as_ref()
+            .map(|input| {
+                input
+                    .sacn_universes
+                    .iter()
+                    .filter_map(|u| u16::try_from(*u).ok())
+                    .collect::<BTreeSet<_>>()
+            })
+            .unwrap_or_default())
+    }) else {
+        return;
+    };
+
+    for universe in joined.difference(&wanted).copied().collect::<Vec<_>>() {
+        if let Err(e) = socket.leave_multicast_v4(multicast_group(universe), Ipv4Addr::UNSPECIFIED)
+        {
+            log::warn!("Failed to leave sACN universe {universe}: {e}");
+        }
+        joined.remove(&universe);
+    }
+    for universe in wanted.difference(joined).copied().collect::<Vec<_>>() {
+        match socket.join_multicast_v4(multicast_group(universe), Ipv4Addr::UNSPECIFIED) {
+            Ok(()) => {
+                joined.insert(universe);
+            }
+            Err(e) => log::warn!("Failed to join sACN universe {universe}: {e}"),
+        }
+    }
+}
+
+/// E1.31's multicast address for `universe`.
+fn multicast_group(universe: u16) -> Ipv4Addr {
+    let [high, low] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, high, low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::NullEventSink;
+    use artnet_protocol::{Output, PortAddress};
+    use dmx_engine::audio::AudioAnalysis;
+    use dmx_engine::beat::BeatSampler;
+    use dmx_engine::proto::{
+        Project, RenderMode,
+        render_mode::{DmxInput, Mode},
+    };
+    use dmx_engine::recording::{FrameLogWriter, Recording};
+    use dmx_engine::render::render::render_dmx_in_mode;
+    use sacn::packet::E131_DEFAULT_PRIORITY;
+    use sacn::source::SacnSource;
+
+    fn free_port() -> u16 {
+        std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    fn receiver(config: ReceiverConfig) -> Arc<DmxInputReceiver> {
+        Arc::new(DmxInputReceiver::new(
+            Arc::new(NullEventSink),
+            Arc::new(Mutex::new(BeatSampler::default())),
+            config,
+        ))
+    }
+
+    /// The first universe of `output_id` of `project` in pass-through mode.
+    fn pass_through(
+        receiver: &DmxInputReceiver,
+        project: &Project,
+        output_id: u64,
+        t: u64,
+    ) -> Vec<u8> {
+        let render_mode = RenderMode {
+            mode: Some(Mode::DmxInput(DmxInput::default())),
+        };
+        render_dmx_in_mode(
+            project,
+            &render_mode,
+            output_id,
+            t,
+            0,
+            &AudioAnalysis::default(),
+            None,
+            Some(&mut receiver.state()),
+        )
+        .unwrap()
+        .frame[0]
+            .to_vec()
+    }
+
+    /// Renders `output_id` of `project` until `check` accepts the first
+    /// universe or a second has passed.
+    async fn wait_for(
+        receiver: &DmxInputReceiver,
+        project: &Project,
+        output_id: u64,
+        mut check: impl FnMut(&[u8]) -> bool,
+    ) -> bool {
+        for _ in 0..100 {
+            if check(&pass_through(receiver, project, output_id, now_ms())) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    fn artnet_project(output_id: u64, universe: u32) -> Project {
+        use dmx_engine::proto::{
+            ArtnetDmxOutput, FixtureDefinitions, Output as ProtoOutput, Patch, output,
+        };
+        Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(
+                        output_id,
+                        ProtoOutput {
+                            output: Some(output::Output::ArtnetDmxOutput(ArtnetDmxOutput {
+                                universe,
+                                ..Default::default()
+                            })),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            fixture_definitions: Some(FixtureDefinitions::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn multicast_groups_follow_the_universe() {
+        assert_eq!(multicast_group(1), Ipv4Addr::new(239, 255, 0, 1));
+        assert_eq!(multicast_group(0x1234), Ipv4Addr::new(239, 255, 0x12, 0x34));
+    }
+
+    #[tokio::test]
+    async fn receives_artnet_and_sacn_from_loopback_senders() {
+        let sacn_port = free_port();
+        let artnet_port = free_port();
+        let receiver = receiver(ReceiverConfig {
+            sacn_port,
+            artnet_port: Some(artnet_port),
+            own_sacn_cids: Arc::default(),
+            own_artnet_port: None,
+        });
+        receiver.start_receiver();
+
+        let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let artdmx = ArtCommand::Output(Output {
+            port_address: PortAddress::try_from(0x501).unwrap(),
+            data: vec![11, 12].into(),
+            ..Output::default()
+        })
+        .write_to_buffer()
+        .unwrap();
+        let artnet = artnet_project(0x501, 0x501);
+        let artnet_received = wait_for(&receiver, &artnet, 0x501, |u| {
+            let _ = sender.try_send_to(&artdmx, (Ipv4Addr::LOCALHOST, artnet_port).into());
+            u[..2] == [11, 12]
+        })
+        .await;
+        assert!(artnet_received, "Art-Net was not received");
+
+        let mut source = SacnSource::with_ip(
+            "Loopback",
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), free_port()),
+        )
+        .unwrap();
+        source.register_universe(0x502).unwrap();
+        let sacn = artnet_project(0x502, 0x502);
+        let sacn_received = wait_for(&receiver, &sacn, 0x502, |u| {
+            let _ = source.send(
+                &[0x502],
+                &[0, 21, 22],
+                None,
+                Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), sacn_port)),
+                None,
+            );
+            u[..2] == [21, 22]
+        })
+        .await;
+        assert!(sacn_received, "sACN was not received");
+
+        // Terminating the stream drops the universe.
+        sender
+            .send_to(
+                &terminated_packet(&source, 0x502),
+                (Ipv4Addr::LOCALHOST, sacn_port),
+            )
+            .await
+            .unwrap();
+        assert!(wait_for(&receiver, &sacn, 0x502, |u| u[..2] == [0, 0]).await);
+
+        receiver.stop_receiver();
+    }
+
+    fn terminated_packet(source: &SacnSource, universe: u16) -> Vec<u8> {
+        use sacn::packet::{DataPacketDmpLayer, DataPacketFramingLayer, E131RootLayer};
+        AcnRootLayerProtocol {
+            pdu: E131RootLayer {
+                cid: source.cid().unwrap(),
+                data: E131RootLayerData::DataPacket(DataPacketFramingLayer {
+                    source_name: "Loopback".into(),
+                    priority: E131_DEFAULT_PRIORITY,
+                    synchronization_address: 0,
+                    sequence_number: 0,
+                    preview_data: false,
+                    stream_terminated: true,
+                    force_synchronization: false,
+                    universe,
+                    data: DataPacketDmpLayer {
+                        property_values: vec![0].into(),
+                    },
+                }),
+            },
+        }
+        .pack_alloc()
+        .unwrap()
+    }
+
+    #[test]
+    fn lower_priorities_and_own_output_are_ignored() {
+        let receiver = receiver(ReceiverConfig {
+            own_artnet_port: Some(7000),
+            ..ReceiverConfig::standard(false)
+        });
+        let now = Instant::now();
+        let project = artnet_project(0x510, 0x510);
+        let first = |project: &Project| pass_through(&receiver, project, 0x510, 0)[0];
+
+        let (high, low) = (Source::Sacn([1; 16]), Source::Sacn([2; 16]));
+        receiver.receive_universe(Protocol::Sacn, 0x510, high, 150, &[1], now);
+        receiver.receive_universe(Protocol::Sacn, 0x510, low, 100, &[2], now);
+        assert_eq!(first(&project), 1);
+        receiver.receive_universe(Protocol::Sacn, 0x510, low, 100, &[3], now + SOURCE_TIMEOUT);
+        assert_eq!(first(&project), 3);
+
+        let artdmx = ArtCommand::Output(Output {
+            port_address: PortAddress::try_from(0x510).unwrap(),
+            data: vec![4, 4].into(),
+            ..Output::default()
+        })
+        .write_to_buffer()
+        .unwrap();
+        receiver.receive_artnet(&artdmx, SocketAddr::from(([10, 0, 0, 1], 7000)), now);
+        assert_eq!(first(&project), 3);
+    }
+
+    #[test]
+    fn universes_are_held_until_their_last_source_leaves() {
+        let receiver = receiver(ReceiverConfig::standard(false));
+        let now = Instant::now();
+        let project = artnet_project(0x520, 0x520);
+        let first = |project: &Project| pass_through(&receiver, project, 0x520, 0)[0];
+
+        let (taken, other) = (Source::Sacn([1; 16]), Source::Sacn([2; 16]));
+        receiver.receive_universe(Protocol::Sacn, 0x520, taken, 100, &[1], now);
+        receiver.receive_universe(Protocol::Sacn, 0x520, other, 100, &[2], now);
+        assert_eq!(first(&project), 1);
+
+        // The other source takes over once the first terminates.
+        receiver.remove_source(Protocol::Sacn, 0x520, taken);
+        assert_eq!(first(&project), 1);
+        receiver.receive_universe(Protocol::Sacn, 0x520, other, 100, &[2], now);
+        assert_eq!(first(&project), 2);
+
+        // Art-Net senders of the same universe number are tracked apart.
+        let node = Source::Artnet(Ipv4Addr::new(10, 0, 0, 2).into());
+        receiver.receive_universe(Protocol::Artnet, 0x520, node, 0, &[3], now);
+        receiver.remove_source(Protocol::Sacn, 0x520, other);
+        assert_eq!(first(&project), 3);
+        receiver.expire(now + SOURCE_TIMEOUT);
+        assert_eq!(first(&project), 0);
+    }
+
+    #[tokio::test]
+    async fn captures_what_is_received() {
+        let receiver = receiver(ReceiverConfig::standard(false));
+        let node = Source::Artnet(Ipv4Addr::new(10, 0, 0, 3).into());
+        receiver.receive_universe(Protocol::Artnet, 3, node, 0, &[5, 6], Instant::now());
+        let streams = vec![Stream {
+            output_id: 0,
+            first_universe: 2,
+            universe_count: 2,
+        }];
+
+        let mut writer = FrameLogWriter::new(Vec::new());
+        let frame_count = receiver
+            .capture(streams, 50, 100, &mut writer)
+            .await
+            .unwrap();
+        let recording = Recording::decode(&writer.into_inner()).unwrap();
+        assert!(frame_count > 0);
+        assert_eq!(recording.frames.len() as u64, frame_count);
+        for frame in &recording.frames {
+            assert!(frame.t_ms < 100);
+            assert_eq!(frame.universes[0][..2], [0, 0]);
+            assert_eq!(frame.universes[1][..2], [5, 6]);
+        }
+        let mut writer = FrameLogWriter::new(Vec::new());
+        assert!(
+            receiver
+                .capture(Vec::new(), 0, 100, &mut writer)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use dmx_engine::audio::AudioAnalysis;
 use dmx_engine::proto::{DisplayBuffer, WledRenderTarget};
 
+use crate::artnet_discovery::ArtnetNode;
+
 pub trait EventSink: Send + Sync + 'static {
+    /// `data` holds every universe of the output back to back, 512 bytes each.
     fn dmx_render(&self, _output_id: u64, _data: &[u8]) {}
     fn wled_render(&self, _output_id: u64, _target: &WledRenderTarget) {}
     fn display_render(&self, _display_id: u64, _buffer: &DisplayBuffer) {}
@@ -18,6 +21,9 @@ pub trait EventSink: Send + Sync + 'static {
     fn audio_devices_changed(&self, _device_names: &[String]) {}
     fn audio_beat_active(&self, _active: bool) {}
     fn audio_analysis(&self, _analysis: &AudioAnalysis) {}
+
+    fn artnet_nodes(&self, _nodes: &[ArtnetNode]) {}
+    fn artnet_output_status(&self, _output_id: u64, _alive: bool) {}
 }
 
 pub struct NullEventSink;
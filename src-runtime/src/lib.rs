@@ -1,4 +1,6 @@
 pub mod artnet;
+pub mod artnet_discovery;
+pub mod artnet_rdm;
 #[cfg(feature = "audio")]
 pub mod audio_analysis;
 #[cfg(feature = "audio")]
@@ -8,11 +10,13 @@ pub mod beat;
 pub mod ddp;
 #[cfg(feature = "visualizer")]
 pub mod display_loop;
+pub mod dmx_input;
 pub mod events;
 #[cfg(feature = "midi")]
 pub mod midi;
 pub mod output_loop;
 pub mod project_store;
+pub mod rdm;
 pub mod runtime;
 pub mod sacn;
 #[cfg_attr(not(feature = "serial"), path = "serial_stub.rs")]
@@ -1,6 +1,8 @@
 use dmx_engine::project;
 use dmx_engine::proto::output::Output as ProtoOutput;
-use dmx_engine::render::render::{RenderError, render_dmx, render_wled};
+use dmx_engine::render::dmx_render_target::UNIVERSE_SIZE;
+use dmx_engine::render::render::{RenderError, Rendered, render_dmx, render_wled};
+use sacn::packet::E131_DEFAULT_PRIORITY;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -8,10 +10,11 @@ use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 
 use crate::artnet::ArtnetState;
+use crate::dmx_input::DmxInputReceiver;
 use crate::events::EventSink;
-use crate::util::now_ms;
-use crate::sacn::SacnState;
+use crate::sacn::{DEFAULT_SOURCE_NAME, SacnOptions, SacnState};
 use crate::serial::SerialState;
+use crate::util::now_ms;
 use crate::wled::WledState;
 
 // Default FPS for each output type when not specified
@@ -46,11 +49,16 @@ pub enum OutputType {
     },
     Sacn {
         universe: u16,
+        universe_count: u16,
         ip_address: String,
+        priority: u8,
+        source_name: String,
+        sync_universe: Option<u16>,
         fps: u32,
     },
     Artnet {
         universe: u16,
+        universe_count: u16,
         ip_address: String,
         fps: u32,
     },
@@ -69,13 +77,16 @@ struct OutputLoopHandle {
 pub struct OutputLoopManager {
     loops: Mutex<HashMap<u64, OutputLoopHandle>>,
     events: Arc<dyn EventSink>,
+    /// Passed through or merged into every DMX output.
+    dmx_input: Option<Arc<DmxInputReceiver>>,
 }
 
 impl OutputLoopManager {
-    pub fn new(events: Arc<dyn EventSink>) -> Self {
+    pub fn new(events: Arc<dyn EventSink>, dmx_input: Option<Arc<DmxInputReceiver>>) -> Self {
         OutputLoopManager {
             loops: Mutex::new(HashMap::new()),
             events,
+            dmx_input,
         }
     }
 
@@ -114,6 +125,7 @@ impl OutputLoopManager {
         let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
         let output_type_clone = output_type.clone();
         let events = Arc::clone(&self.events);
+        let dmx_input = self.dmx_input.clone();
 
         // The task does NOT remove itself from the map on exit.
         // stop_loop removes the handle before signalling cancel, so the task
@@ -129,6 +141,7 @@ impl OutputLoopManager {
                 artnet_state,
                 wled_state,
                 events,
+                dmx_input,
                 cancel_rx,
             )
             .await
@@ -148,12 +161,16 @@ impl OutputLoopManager {
         Ok(())
     }
 
-    pub async fn stop_all(&self) {
+    pub async fn stop_all(&self, sacn_state: &SacnState) {
         let mut loops = self.loops.lock().await;
 
         let output_ids: Vec<u64> = loops.keys().copied().collect();
         for output_id in output_ids {
+            let output_type = loops.get(&output_id).map(|h| h.output_type.clone());
             stop_loop(output_id, &mut loops).await;
+            if let Some(output_type) = output_type {
+                terminate_sacn_streams(sacn_state, &output_type, None);
+            }
         }
     }
 
@@ -185,11 +202,43 @@ impl OutputLoopManager {
                     },
                     Some(ProtoOutput::SacnDmxOutput(sacn)) => OutputType::Sacn {
                         universe: sacn.universe as u16,
+                        universe_count: u16::try_from(sacn.universe_count.max(1)).map_err(
+                            |_| {
+                                format!(
+                                    "Output {output_id} sends too many universes: {}",
+                                    sacn.universe_count
+                                )
+                            },
+                        )?,
                         ip_address: sacn.ip_address.clone(),
+                        priority: if sacn.priority == 0 {
+                            E131_DEFAULT_PRIORITY
+                        } else {
+                            u8::try_from(sacn.priority).unwrap_or(u8::MAX)
+                        },
+                        source_name: if sacn.source_name.is_empty() {
+                            DEFAULT_SOURCE_NAME.to_string()
+                        } else {
+                            sacn.source_name.clone()
+                        },
+                        sync_universe: match sacn.sync_universe {
+                            0 => None,
+                            u => Some(u16::try_from(u).map_err(|_| {
+                                format!("Output {output_id} syncs on invalid universe {u}")
+                            })?),
+                        },
                         fps: resolve_fps(output.fps, DEFAULT_SACN_FPS),
                     },
                     Some(ProtoOutput::ArtnetDmxOutput(artnet)) => OutputType::Artnet {
                         universe: artnet.universe as u16,
+                        universe_count: u16::try_from(artnet.universe_count.max(1)).map_err(
+                            |_| {
+                                format!(
+                                    "Output {output_id} sends too many universes: {}",
+                                    artnet.universe_count
+                                )
+                            },
+                        )?,
                         ip_address: artnet.ip_address.clone(),
                         fps: resolve_fps(output.fps, DEFAULT_ARTNET_FPS),
                     },
@@ -253,8 +302,15 @@ impl OutputLoopManager {
                 current_loops.get(&output_id),
                 Some(OutputType::Serial { .. })
             );
-            let will_restart = to_start.iter().any(|(id, _)| *id == output_id);
+            let restart = to_start
+                .iter()
+                .find(|(id, _)| *id == output_id)
+                .map(|(_, output_type)| output_type);
+            let will_restart = restart.is_some();
             stop_loop(output_id, &mut loops).await;
+            if let Some(current_type) = current_loops.get(&output_id) {
+                terminate_sacn_streams(&sacn_state, current_type, restart);
+            }
             // Close the serial port when the output is disabled or deleted. Skip
             // this when the loop is being immediately restarted (e.g. FPS change)
             // so we don't briefly drop and reopen the same port.
@@ -285,13 +341,15 @@ impl OutputLoopManager {
         system_t: u64,
         frame: u32,
         events: &dyn EventSink,
-    ) -> Result<Vec<u8>, RenderError> {
-        let dmx_data = render_dmx(output_id, system_t, frame)?;
-        let dmx_vec = dmx_data.to_vec();
+        dmx_input: Option<&DmxInputReceiver>,
+    ) -> Result<Rendered<Vec<[u8; UNIVERSE_SIZE]>>, RenderError> {
+        let mut dmx_input = dmx_input.map(DmxInputReceiver::state);
+        let rendered = render_dmx(output_id, system_t, frame, dmx_input.as_deref_mut())?;
+        drop(dmx_input);
 
-        events.dmx_render(output_id, &dmx_vec);
+        events.dmx_render(output_id, rendered.frame.as_flattened());
 
-        Ok(dmx_vec)
+        Ok(rendered)
     }
 
     async fn run_output_loop(
@@ -302,6 +360,7 @@ impl OutputLoopManager {
         artnet_state: Arc<ArtnetState>,
         wled_state: Arc<WledState>,
         events: Arc<dyn EventSink>,
+        dmx_input: Option<Arc<DmxInputReceiver>>,
         cancel_rx: tokio::sync::watch::Receiver<bool>,
     ) -> Result<(), String> {
         let target_fps = match &output_type {
@@ -316,6 +375,12 @@ impl OutputLoopManager {
 
         log::info!("Starting output loop {output_id} ({output_type:?}) at {target_fps} FPS");
 
+        // Every running Art-Net output sends its frame before the shared sync.
+        let is_artnet = matches!(output_type, OutputType::Artnet { .. });
+        if is_artnet {
+            artnet_state.add_output(output_id);
+        }
+
         loop {
             // Check for cancellation
             if *cancel_rx.borrow() {
@@ -327,12 +392,22 @@ impl OutputLoopManager {
 
             // Render the frame
             let system_t = now_ms();
+            // Errors in parts of the frame, reported once the rest is sent.
+            let mut render_error = None;
 
             let result = match &output_type {
                 OutputType::Serial { .. } => {
-                    match Self::render_and_emit_dmx(output_id, system_t, frame, events.as_ref()) {
-                        Ok(dmx_vec) => {
-                            serial_state.output_dmx(&output_id.to_string(), &dmx_vec)
+                    match Self::render_and_emit_dmx(
+                        output_id,
+                        system_t,
+                        frame,
+                        events.as_ref(),
+                        dmx_input.as_deref(),
+                    ) {
+                        // Serial interfaces only carry a single universe.
+                        Ok(rendered) => {
+                            render_error = rendered.error;
+                            serial_state.output_dmx(&output_id.to_string(), &rendered.frame[0])
                         }
                         Err(RenderError::OutputNotFound { .. }) => {
                             // Output was deleted - exit loop gracefully
@@ -347,11 +422,36 @@ impl OutputLoopManager {
                 OutputType::Sacn {
                     universe,
                     ip_address,
+                    priority,
+                    source_name,
+                    sync_universe,
                     ..
                 } => {
-                    match Self::render_and_emit_dmx(output_id, system_t, frame, events.as_ref()) {
-                        Ok(dmx_vec) => {
-                            sacn_state.output_sacn(*universe, ip_address, &dmx_vec)
+                    match Self::render_and_emit_dmx(
+                        output_id,
+                        system_t,
+                        frame,
+                        events.as_ref(),
+                        dmx_input.as_deref(),
+                    ) {
+                        Ok(rendered) => {
+                            render_error = rendered.error;
+                            let options = SacnOptions {
+                                ip_address,
+                                priority: *priority,
+                                source_name,
+                                sync_universe: *sync_universe,
+                            };
+                            let sent = send_universes(*universe, &rendered.frame, |u, data| {
+                                sacn_state.output_sacn(u, &options, data)
+                            });
+                            if let Some(sync_universe) = sync_universe {
+                                let synced =
+                                    sacn_state.sync_sacn(*sync_universe, ip_address, source_name);
+                                sent.and(synced)
+                            } else {
+                                sent
+                            }
                         }
                         Err(RenderError::OutputNotFound { .. }) => {
                             // Output was deleted - exit loop gracefully
@@ -368,9 +468,18 @@ impl OutputLoopManager {
                     ip_address,
                     ..
                 } => {
-                    match Self::render_and_emit_dmx(output_id, system_t, frame, events.as_ref()) {
-                        Ok(dmx_vec) => {
-                            artnet_state.output_artnet(*universe, ip_address, &dmx_vec)
+                    match Self::render_and_emit_dmx(
+                        output_id,
+                        system_t,
+                        frame,
+                        events.as_ref(),
+                        dmx_input.as_deref(),
+                    ) {
+                        Ok(rendered) => {
+                            render_error = rendered.error;
+                            send_universes(*universe, &rendered.frame, |u, data| {
+                                artnet_state.output_artnet(u, ip_address, data)
+                            })
                         }
                         Err(RenderError::OutputNotFound { .. }) => {
                             // Output was deleted - exit loop gracefully
@@ -385,10 +494,11 @@ impl OutputLoopManager {
                 OutputType::Wled { ip_address, .. } => {
                     // Render WLED
                     match render_wled(output_id, system_t, frame) {
-                        Ok(wled_data) => {
-                            events.wled_render(output_id, &wled_data);
+                        Ok(rendered) => {
+                            render_error = rendered.error;
+                            events.wled_render(output_id, &rendered.frame);
 
-                            wled_state.output_wled(ip_address, &wled_data).await
+                            wled_state.output_wled(ip_address, &rendered.frame).await
                         }
                         Err(RenderError::OutputNotFound { .. }) => {
                             // Output was deleted - exit loop gracefully
@@ -402,6 +512,18 @@ impl OutputLoopManager {
                 }
             };
 
+            // Counted even when the frame failed, so the other Art-Net
+            // outputs are still synced.
+            let result = if is_artnet {
+                let synced = artnet_state.frame_sent(output_id);
+                result.and(synced)
+            } else {
+                result
+            };
+            let result = result.and_then(|()| match render_error {
+                Some(e) => Err(e.to_string()),
+                None => Ok(()),
+            });
             match result {
                 Ok(()) => events.render_error_clear(output_id),
                 Err(e) => events.render_error(output_id, &e),
@@ -426,10 +548,97 @@ impl OutputLoopManager {
             }
         }
 
+        if is_artnet {
+            artnet_state.remove_output(output_id)?;
+        }
+
         Ok(())
     }
 }
 
+/// Sends each rendered universe of a frame, numbering them up from
+/// `first_universe`. Every universe is attempted even if an earlier one fails so
+/// the receivers stay in lock-step; the first error is reported.
+fn send_universes(
+    first_universe: u16,
+    universes: &[[u8; UNIVERSE_SIZE]],
+    mut send: impl FnMut(u16, &[u8]) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut result = Ok(());
+    for (offset, data) in universes.iter().enumerate() {
+        let universe_result = u16::try_from(offset)
+            .ok()
+            .and_then(|offset| first_universe.checked_add(offset))
+            .ok_or_else(|| format!("Universe {first_universe} + {offset} is out of range"))
+            .and_then(|universe| send(universe, data));
+        if result.is_ok() {
+            result = universe_result;
+        }
+    }
+    result
+}
+
+/// How an sACN output sends, or `None` for other outputs.
+fn sacn_options(output_type: &OutputType) -> Option<SacnOptions<'_>> {
+    match output_type {
+        OutputType::Sacn {
+            ip_address,
+            priority,
+            source_name,
+            sync_universe,
+            ..
+        } => Some(SacnOptions {
+            ip_address,
+            priority: *priority,
+            source_name,
+            sync_universe: *sync_universe,
+        }),
+        _ => None,
+    }
+}
+
+/// Each universe an sACN output sends.
+fn sacn_universes(output_type: &OutputType) -> Vec<u16> {
+    match output_type {
+        OutputType::Sacn {
+            universe,
+            universe_count,
+            ..
+        } => (*universe..universe.saturating_add(*universe_count)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Terminates the sACN streams of a stopped output, except those its
+/// replacement goes on sending from the same source to the same address, so
+/// receivers don't drop the source for a moment when only the frame rate or
+/// priority changed.
+fn terminate_sacn_streams(
+    sacn_state: &SacnState,
+    stopped: &OutputType,
+    replacement: Option<&OutputType>,
+) {
+    let Some(options) = sacn_options(stopped) else {
+        return;
+    };
+    let continued = replacement
+        .filter(|r| {
+            sacn_options(r).is_some_and(|r| {
+                r.ip_address == options.ip_address && r.source_name == options.source_name
+            })
+        })
+        .map(sacn_universes)
+        .unwrap_or_default();
+    for universe in sacn_universes(stopped) {
+        if continued.contains(&universe) {
+            continue;
+        }
+        if let Err(e) = sacn_state.terminate_sacn(universe, &options) {
+            log::warn!("{e}");
+        }
+    }
+}
+
 async fn stop_loop(output_id: u64, loops: &mut HashMap<u64, OutputLoopHandle>) {
     let Some(handle) = loops.remove(&output_id) else {
         return;
@@ -466,6 +675,50 @@ mod tests {
         assert_eq!(resolve_fps(0, DEFAULT_WLED_FPS), DEFAULT_WLED_FPS);
     }
 
+    #[test]
+    fn sends_every_universe_in_order() {
+        let universes = [
+            [1u8; UNIVERSE_SIZE],
+            [2u8; UNIVERSE_SIZE],
+            [3u8; UNIVERSE_SIZE],
+        ];
+        let mut sent = Vec::new();
+        send_universes(7, &universes, |universe, data| {
+            sent.push((universe, data[0]));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sent, vec![(7, 1), (8, 2), (9, 3)]);
+    }
+
+    #[test]
+    fn keeps_sending_after_a_universe_fails() {
+        let universes = [[0u8; UNIVERSE_SIZE]; 3];
+        let mut sent = Vec::new();
+        let result = send_universes(1, &universes, |universe, _| {
+            sent.push(universe);
+            if universe == 2 {
+                Err("unreachable".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("unreachable".to_string()));
+        assert_eq!(sent, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_universes_past_the_end_of_the_range() {
+        let universes = [[0u8; UNIVERSE_SIZE]; 2];
+        let mut sent = Vec::new();
+        let result = send_universes(u16::MAX, &universes, |universe, _| {
+            sent.push(universe);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(sent, vec![u16::MAX]);
+    }
+
     #[test]
     fn caps_the_rate_so_the_frame_duration_stays_positive() {
         assert_eq!(resolve_fps(u32::MAX, DEFAULT_SERIAL_FPS), MAX_FPS);
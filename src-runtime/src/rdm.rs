@@ -0,0 +1,1111 @@
+//! Remote Device Management (ANSI E1.20).
+//!
+//! Everything here talks through an [`RdmPort`], which the serial and Art-Net
+//! transports implement. Responders are found with a binary search over the
+//! UID space, muting each as it is found, or taken from an Art-Net node's
+//! table of devices. They are then read and configured with GET and SET
+//! requests, and matched against the project's fixture definitions so a patch
+//! can be built from what is plugged in.
+
+use dmx_engine::project_util::rand_id;
+use dmx_engine::proto::output::Output as ProtoOutput;
+use dmx_engine::proto::{DmxFixtureDefinition, PhysicalDmxFixture, Project};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub(crate) const START_CODE: u8 = 0xCC;
+const SUB_START_CODE: u8 = 0x01;
+/// Bytes from the start code up to the parameter data.
+const HEADER_LEN: usize = 24;
+
+const DISCOVERY_COMMAND: u8 = 0x10;
+const GET_COMMAND: u8 = 0x20;
+const SET_COMMAND: u8 = 0x30;
+
+const RESPONSE_TYPE_ACK: u8 = 0x00;
+const RESPONSE_TYPE_ACK_TIMER: u8 = 0x01;
+const RESPONSE_TYPE_NACK_REASON: u8 = 0x02;
+
+const DISC_UNIQUE_BRANCH: u16 = 0x0001;
+const DISC_MUTE: u16 = 0x0002;
+const DISC_UN_MUTE: u16 = 0x0003;
+const DEVICE_INFO: u16 = 0x0060;
+const DEVICE_MODEL_DESCRIPTION: u16 = 0x0080;
+const MANUFACTURER_LABEL: u16 = 0x0081;
+const DMX_PERSONALITY: u16 = 0x00E0;
+const DMX_PERSONALITY_DESCRIPTION: u16 = 0x00E1;
+const DMX_START_ADDRESS: u16 = 0x00F0;
+const IDENTIFY_DEVICE: u16 = 0x1000;
+
+/// Length of the `DEVICE_INFO` parameter data.
+const DEVICE_INFO_LEN: usize = 19;
+/// The start address of a device with no DMX footprint.
+const NO_START_ADDRESS: u16 = 0xFFFF;
+/// Bytes of a discovery response after its preamble and separator.
+const DISCOVERY_RESPONSE_LEN: usize = 16;
+const DISCOVERY_SEPARATOR: u8 = 0xAA;
+
+/// The UID requests are sent from, in the manufacturer range E1.20 sets aside
+/// for prototypes.
+pub(crate) const CONTROLLER_UID: Uid = Uid {
+    manufacturer_id: 0x7FF0,
+    device_id: 0x444D_5843,
+};
+
+static TRANSACTION: AtomicU8 = AtomicU8::new(0);
+
+/// A responder's unique ID, written `MMMM:DDDDDDDD` in hexadecimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uid {
+    pub manufacturer_id: u16,
+    pub device_id: u32,
+}
+
+impl Uid {
+    /// Addresses every responder.
+    pub const BROADCAST: Uid = Uid {
+        manufacturer_id: 0xFFFF,
+        device_id: 0xFFFF_FFFF,
+    };
+
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; 6] {
+        let mut bytes = [0; 6];
+        bytes[..2].copy_from_slice(&self.manufacturer_id.to_be_bytes());
+        bytes[2..].copy_from_slice(&self.device_id.to_be_bytes());
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 6]) -> Self {
+        Uid {
+            manufacturer_id: u16::from_be_bytes([bytes[0], bytes[1]]),
+            device_id: u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+        }
+    }
+
+    fn to_u64(self) -> u64 {
+        (u64::from(self.manufacturer_id) << 32) | u64::from(self.device_id)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_u64(uid: u64) -> Self {
+        Uid {
+            manufacturer_id: (uid >> 32) as u16,
+            device_id: uid as u32,
+        }
+    }
+}
+
+impl fmt::Display for Uid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}:{:08X}", self.manufacturer_id, self.device_id)
+    }
+}
+
+impl FromStr for Uid {
+    type Err = String;
+
+    fn from_str(uid: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid RDM UID '{uid}'");
+        let (manufacturer_id, device_id) = uid.split_once(':').ok_or_else(invalid)?;
+        Ok(Uid {
+            manufacturer_id: u16::from_str_radix(manufacturer_id, 16).map_err(|_| invalid())?,
+            device_id: u32::from_str_radix(device_id, 16).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Serialize for Uid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A DMX line that RDM requests can be sent down.
+pub trait RdmPort {
+    /// Sends `request`, a whole RDM message from its start code, and returns
+    /// whatever came back, or `None` if nothing did before the response
+    /// timeout.
+    fn transact(&mut self, request: &[u8]) -> Result<Option<Vec<u8>>, String>;
+
+    /// The UIDs of the responders on the line.
+    fn discover(&mut self) -> Result<Vec<Uid>, String> {
+        discover_by_branch(self)
+    }
+}
+
+/// An RDM request or response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Message {
+    pub destination: Uid,
+    pub source: Uid,
+    pub transaction: u8,
+    /// The port a request goes out of, or the type of a response.
+    pub port_or_response_type: u8,
+    pub command_class: u8,
+    pub pid: u16,
+    pub data: Vec<u8>,
+}
+
+impl Message {
+    fn request(destination: Uid, command_class: u8, pid: u16, data: &[u8]) -> Self {
+        Message {
+            destination,
+            source: CONTROLLER_UID,
+            transaction: TRANSACTION.fetch_add(1, Ordering::Relaxed),
+            port_or_response_type: 1,
+            command_class,
+            pid,
+            data: data.to_vec(),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let data = &self.data[..self.data.len().min(usize::from(u8::MAX) - HEADER_LEN)];
+
+        let mut packet = Vec::with_capacity(HEADER_LEN + data.len() + 2);
+        packet.extend_from_slice(&[START_CODE, SUB_START_CODE, (HEADER_LEN + data.len()) as u8]);
+        packet.extend_from_slice(&self.destination.to_bytes());
+        packet.extend_from_slice(&self.source.to_bytes());
+        packet.extend_from_slice(&[self.transaction, self.port_or_response_type, 0]);
+        // Sub-device 0 is the root device.
+        packet.extend_from_slice(&[0, 0, self.command_class]);
+        packet.extend_from_slice(&self.pid.to_be_bytes());
+        packet.push(data.len() as u8);
+        packet.extend_from_slice(data);
+        packet.extend_from_slice(&checksum(&packet).to_be_bytes());
+        packet
+    }
+
+    /// Parses a message starting at its start code.
+    pub(crate) fn parse(packet: &[u8]) -> Result<Self, String> {
+        if packet.len() < HEADER_LEN + 2 || packet[..2] != [START_CODE, SUB_START_CODE] {
+            return Err("Not an RDM message".to_string());
+        }
+        let len = usize::from(packet[2]);
+        let data_len = usize::from(packet[23]);
+        if len != HEADER_LEN + data_len || packet.len() < len + 2 {
+            return Err("Truncated RDM message".to_string());
+        }
+        if checksum(&packet[..len]).to_be_bytes() != packet[len..len + 2] {
+            return Err("RDM message failed its checksum".to_string());
+        }
+
+        let uid = |at: usize| {
+            let mut bytes = [0; 6];
+            bytes.copy_from_slice(&packet[at..at + 6]);
+            Uid::from_bytes(bytes)
+        };
+        Ok(Message {
+            destination: uid(3),
+            source: uid(9),
+            transaction: packet[15],
+            port_or_response_type: packet[16],
+            command_class: packet[20],
+            pid: u16::from_be_bytes([packet[21], packet[22]]),
+            data: packet[HEADER_LEN..len].to_vec(),
+        })
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u16 {
+    bytes
+        .iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(u16::from(*byte)))
+}
+
+/// Finds the response addressed to this controller in what came back, which
+/// on a serial line may begin with the echo of the request.
+fn find_response(reply: &[u8]) -> Result<Message, String> {
+    (0..reply.len())
+        .filter(|&at| reply[at] == START_CODE)
+        .filter_map(|at| Message::parse(&reply[at..]).ok())
+        .find(|message| message.destination == CONTROLLER_UID)
+        .ok_or_else(|| "Garbled RDM response".to_string())
+}
+
+/// The UID in a discovery response, or `None` if responders collided.
+fn decode_discovery_response(reply: &[u8]) -> Option<Uid> {
+    (0..reply.len())
+        .filter(|&at| reply[at] == DISCOVERY_SEPARATOR)
+        .find_map(|at| {
+            let encoded = reply.get(at + 1..at + 1 + DISCOVERY_RESPONSE_LEN)?;
+            let decoded: Vec<u8> = encoded.chunks(2).map(|pair| pair[0] & pair[1]).collect();
+            let sum = u16::from_be_bytes([decoded[6], decoded[7]]);
+            if sum != checksum(&encoded[..12]) {
+                return None;
+            }
+            let mut bytes = [0; 6];
+            bytes.copy_from_slice(&decoded[..6]);
+            Some(Uid::from_bytes(bytes))
+        })
+}
+
+fn pid_name(pid: u16) -> String {
+    match pid {
+        DEVICE_INFO => "DEVICE_INFO".to_string(),
+        DEVICE_MODEL_DESCRIPTION => "DEVICE_MODEL_DESCRIPTION".to_string(),
+        MANUFACTURER_LABEL => "MANUFACTURER_LABEL".to_string(),
+        DMX_PERSONALITY => "DMX_PERSONALITY".to_string(),
+        DMX_PERSONALITY_DESCRIPTION => "DMX_PERSONALITY_DESCRIPTION".to_string(),
+        DMX_START_ADDRESS => "DMX_START_ADDRESS".to_string(),
+        IDENTIFY_DEVICE => "IDENTIFY_DEVICE".to_string(),
+        _ => format!("parameter {pid:#06X}"),
+    }
+}
+
+fn nack_reason(data: &[u8]) -> String {
+    let reason = match data {
+        [high, low, ..] => u16::from_be_bytes([*high, *low]),
+        _ => return "no reason given".to_string(),
+    };
+    match reason {
+        0x0000 => "unknown parameter".to_string(),
+        0x0001 => "format error".to_string(),
+        0x0002 => "hardware fault".to_string(),
+        0x0003 => "proxy rejected".to_string(),
+        0x0004 => "write protected".to_string(),
+        0x0005 => "unsupported command class".to_string(),
+        0x0006 => "data out of range".to_string(),
+        0x0007 => "buffer full".to_string(),
+        0x0008 => "packet size unsupported".to_string(),
+        0x0009 => "sub-device out of range".to_string(),
+        0x000A => "proxy buffer full".to_string(),
+        _ => format!("reason {reason:#06X}"),
+    }
+}
+
+/// Sends a request to `uid` and returns the parameter data it acknowledged
+/// with.
+fn request<P: RdmPort + ?Sized>(
+    port: &mut P,
+    uid: Uid,
+    command_class: u8,
+    pid: u16,
+    data: &[u8],
+) -> Result<Vec<u8>, String> {
+    let request = Message::request(uid, command_class, pid, data);
+    let reply = port
+        .transact(&request.encode())?
+        .ok_or_else(|| format!("{uid} did not answer {}", pid_name(pid)))?;
+    let response = find_response(&reply)?;
+    if response.source != uid
+        || response.transaction != request.transaction
+        || response.command_class != command_class + 1
+        || response.pid != pid
+    {
+        return Err(format!("{uid} answered {} out of turn", pid_name(pid)));
+    }
+
+    match response.port_or_response_type {
+        RESPONSE_TYPE_ACK => Ok(response.data),
+        RESPONSE_TYPE_NACK_REASON => Err(format!(
+            "{uid} refused {}: {}",
+            pid_name(pid),
+            nack_reason(&response.data)
+        )),
+        RESPONSE_TYPE_ACK_TIMER => Err(format!("{uid} is busy, try again")),
+        other => Err(format!(
+            "{uid} answered {} with unsupported response type {other}",
+            pid_name(pid)
+        )),
+    }
+}
+
+/// Whether `uid` acknowledged being muted, which proves it is really there.
+fn mute<P: RdmPort + ?Sized>(port: &mut P, uid: Uid) -> Result<bool, String> {
+    let request = Message::request(uid, DISCOVERY_COMMAND, DISC_MUTE, &[]);
+    let Some(reply) = port.transact(&request.encode())? else {
+        return Ok(false);
+    };
+    Ok(find_response(&reply).is_ok_and(|response| {
+        response.source == uid && response.port_or_response_type == RESPONSE_TYPE_ACK
+    }))
+}
+
+/// Finds every responder on the line. Each branch of the UID space is asked
+/// who is in it: silence means nobody, a clean answer is a responder to mute
+/// before asking again, and a collision splits the branch in two.
+pub fn discover_by_branch<P: RdmPort + ?Sized>(port: &mut P) -> Result<Vec<Uid>, String> {
+    // Broadcasts are never answered.
+    let unmute = Message::request(Uid::BROADCAST, DISCOVERY_COMMAND, DISC_UN_MUTE, &[]);
+    port.transact(&unmute.encode())?;
+
+    let mut found = Vec::new();
+    let mut branches = vec![(0, Uid::BROADCAST.to_u64())];
+    while let Some((lower, upper)) = branches.pop() {
+        let mut bounds = Uid::from_u64(lower).to_bytes().to_vec();
+        bounds.extend_from_slice(&Uid::from_u64(upper).to_bytes());
+        let branch = Message::request(
+            Uid::BROADCAST,
+            DISCOVERY_COMMAND,
+            DISC_UNIQUE_BRANCH,
+            &bounds,
+        );
+        let Some(reply) = port.transact(&branch.encode())? else {
+            continue;
+        };
+
+        if let Some(uid) = decode_discovery_response(&reply)
+            && !found.contains(&uid)
+            && mute(port, uid)?
+        {
+            found.push(uid);
+            branches.push((lower, upper));
+            continue;
+        }
+        // A UID that keeps colliding with itself is left out.
+        if lower < upper {
+            let middle = lower + (upper - lower) / 2;
+            branches.push((middle + 1, upper));
+            branches.push((lower, middle));
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// One of the modes a device can be switched into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RdmPersonality {
+    /// Starting at 1.
+    pub index: u8,
+    pub footprint: u16,
+    pub description: String,
+}
+
+/// What a responder reports about itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RdmDevice {
+    pub uid: Uid,
+    pub model_id: u16,
+    pub manufacturer: String,
+    pub model: String,
+    /// How many DMX channels the current personality takes.
+    pub footprint: u16,
+    /// `None` for a device without any DMX channels.
+    pub start_address: Option<u16>,
+    pub personality: u8,
+    pub personalities: Vec<RdmPersonality>,
+}
+
+/// A text parameter, which may be padded with nulls.
+fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// Reads what `uid` reports about itself. Labels and personality descriptions
+/// are optional in E1.20, so any the device refuses are left empty.
+pub fn read_device<P: RdmPort + ?Sized>(port: &mut P, uid: Uid) -> Result<RdmDevice, String> {
+    let info = request(port, uid, GET_COMMAND, DEVICE_INFO, &[])?;
+    if info.len() < DEVICE_INFO_LEN {
+        return Err(format!("{uid} sent a short DEVICE_INFO"));
+    }
+    let start_address = u16::from_be_bytes([info[14], info[15]]);
+
+    let mut optional_text = |pid| {
+        request(port, uid, GET_COMMAND, pid, &[])
+            .map(|data| text(&data))
+            .unwrap_or_default()
+    };
+    let manufacturer = optional_text(MANUFACTURER_LABEL);
+    let model = optional_text(DEVICE_MODEL_DESCRIPTION);
+
+    let personalities = (1..=info[13])
+        .filter_map(|index| {
+            let data = request(
+                port,
+                uid,
+                GET_COMMAND,
+                DMX_PERSONALITY_DESCRIPTION,
+                &[index],
+            )
+            .ok()?;
+            (data.len() >= 3).then(|| RdmPersonality {
+                index,
+                footprint: u16::from_be_bytes([data[1], data[2]]),
+                description: text(&data[3..]),
+            })
+        })
+        .collect();
+
+    Ok(RdmDevice {
+        uid,
+        model_id: u16::from_be_bytes([info[2], info[3]]),
+        manufacturer,
+        model,
+        footprint: u16::from_be_bytes([info[10], info[11]]),
+        start_address: (start_address != NO_START_ADDRESS).then_some(start_address),
+        personality: info[12],
+        personalities,
+    })
+}
+
+/// Finds and reads every responder on the line. Devices that stop answering
+/// partway through are left out.
+pub fn discover_devices<P: RdmPort + ?Sized>(port: &mut P) -> Result<Vec<RdmDevice>, String> {
+    let uids = port.discover()?;
+    Ok(uids
+        .into_iter()
+        .filter_map(|uid| match read_device(port, uid) {
+            Ok(device) => Some(device),
+            Err(e) => {
+                log::warn!("Failed to read RDM device {uid}: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+/// The DMX start address of `uid`, `None` if it has no DMX channels.
+pub fn start_address<P: RdmPort + ?Sized>(port: &mut P, uid: Uid) -> Result<Option<u16>, String> {
+    let data = request(port, uid, GET_COMMAND, DMX_START_ADDRESS, &[])?;
+    match data[..] {
+        [high, low, ..] => {
+            let address = u16::from_be_bytes([high, low]);
+            Ok((address != NO_START_ADDRESS).then_some(address))
+        }
+        _ => Err(format!("{uid} sent a short DMX_START_ADDRESS")),
+    }
+}
+
+pub fn set_start_address<P: RdmPort + ?Sized>(
+    port: &mut P,
+    uid: Uid,
+    start_address: u16,
+) -> Result<(), String> {
+    if !(1..=512).contains(&start_address) {
+        return Err(format!(
+            "DMX start address {start_address} is not between 1 and 512"
+        ));
+    }
+    request(
+        port,
+        uid,
+        SET_COMMAND,
+        DMX_START_ADDRESS,
+        &start_address.to_be_bytes(),
+    )?;
+    Ok(())
+}
+
+/// The current personality of `uid` and how many it has.
+pub fn personality<P: RdmPort + ?Sized>(port: &mut P, uid: Uid) -> Result<(u8, u8), String> {
+    let data = request(port, uid, GET_COMMAND, DMX_PERSONALITY, &[])?;
+    match data[..] {
+        [current, count, ..] => Ok((current, count)),
+        _ => Err(format!("{uid} sent a short DMX_PERSONALITY")),
+    }
+}
+
+pub fn set_personality<P: RdmPort + ?Sized>(
+    port: &mut P,
+    uid: Uid,
+    personality: u8,
+) -> Result<(), String> {
+    request(port, uid, SET_COMMAND, DMX_PERSONALITY, &[personality])?;
+    Ok(())
+}
+
+/// Starts or stops `uid` flashing or otherwise making itself known.
+pub fn identify<P: RdmPort + ?Sized>(port: &mut P, uid: Uid, identify: bool) -> Result<(), String> {
+    request(
+        port,
+        uid,
+        SET_COMMAND,
+        IDENTIFY_DEVICE,
+        &[u8::from(identify)],
+    )?;
+    Ok(())
+}
+
+/// Lowercase letters and digits alone, so "Chauvet DJ" matches the OFL
+/// manufacturer key `chauvet-dj`.
+fn normalized(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// An RDM ID decides a match when the definition has one; otherwise the names
+/// must agree.
+fn id_or_name_matches(id: u32, device_id: u16, name: &str, device_name: &str) -> bool {
+    if id != 0 {
+        return id == u32::from(device_id);
+    }
+    !name.is_empty() && normalized(name) == normalized(device_name)
+}
+
+/// The mode of `definition` the device is in: the one its personality selects,
+/// else one named after its personality, else the one with its footprint.
+fn matching_mode(definition: &DmxFixtureDefinition, device: &RdmDevice) -> Option<String> {
+    let mut modes: Vec<_> = definition.modes.iter().collect();
+    modes.sort_by_key(|(key, _)| *key);
+    let footprint = u32::from(device.footprint);
+    let description = device
+        .personalities
+        .iter()
+        .find(|p| p.index == device.personality)
+        .map(|p| normalized(&p.description));
+
+    modes
+        .iter()
+        .find(|(_, mode)| {
+            mode.rdm_personality != 0 && mode.rdm_personality == u32::from(device.personality)
+        })
+        .or_else(|| {
+            modes.iter().find(|(_, mode)| {
+                mode.num_channels == footprint
+                    && description.as_ref() == Some(&normalized(&mode.name))
+            })
+        })
+        .or_else(|| {
+            modes
+                .iter()
+                .find(|(_, mode)| mode.num_channels == footprint)
+        })
+        .map(|(key, _)| (*key).clone())
+}
+
+/// The ID of the fixture definition describing `device` and the key of the
+/// mode it is in. Definitions that carry RDM IDs are preferred.
+#[must_use]
+pub fn match_definition<S: BuildHasher>(
+    definitions: &HashMap<u64, DmxFixtureDefinition, S>,
+    device: &RdmDevice,
+) -> Option<(u64, String)> {
+    let mut candidates: Vec<_> = definitions
+        .iter()
+        .filter(|(_, definition)| {
+            id_or_name_matches(
+                definition.rdm_manufacturer_id,
+                device.uid.manufacturer_id,
+                &definition.manufacturer,
+                &device.manufacturer,
+            ) && id_or_name_matches(
+                definition.rdm_model_id,
+                device.model_id,
+                &definition.name,
+                &device.model,
+            )
+        })
+        .collect();
+    candidates.sort_by_key(|(id, definition)| {
+        let ids =
+            u8::from(definition.rdm_manufacturer_id != 0) + u8::from(definition.rdm_model_id != 0);
+        (std::cmp::Reverse(ids), **id)
+    });
+
+    candidates
+        .into_iter()
+        .find_map(|(id, definition)| Some((*id, matching_mode(definition, device)?)))
+}
+
+/// Adds a fixture to `universe`, an index within output `output_id` of the
+/// active patch, for each device that matches a fixture definition and is not
+/// patched at its address yet. Returns the IDs of the new fixtures.
+pub fn add_device_fixtures(
+    project: &mut Project,
+    output_id: u64,
+    universe: u32,
+    devices: &[RdmDevice],
+) -> Result<Vec<u64>, String> {
+    let definitions = project
+        .fixture_definitions
+        .as_ref()
+        .map(|d| d.dmx_fixture_definitions.clone())
+        .unwrap_or_default();
+
+    let patch_id = project.active_patch;
+    let output = project
+        .patches
+        .get_mut(&patch_id)
+        .ok_or_else(|| format!("Active patch {patch_id} not found"))?
+        .outputs
+        .get_mut(&output_id)
+        .ok_or_else(|| format!("Output {output_id} is not in the active patch"))?;
+    let fixtures = match &mut output.output {
+        Some(ProtoOutput::SerialDmxOutput(serial)) => &mut serial.fixtures,
+        Some(ProtoOutput::SacnDmxOutput(sacn)) => &mut sacn.fixtures,
+        Some(ProtoOutput::ArtnetDmxOutput(artnet)) => &mut artnet.fixtures,
+        _ => return Err(format!("Output {output_id} does not send DMX")),
+    };
+
+    let mut fixture_ids = Vec::new();
+    for device in devices {
+        let Some(start_address) = device.start_address else {
+            continue;
+        };
+        let Some((fixture_definition_id, fixture_mode)) = match_definition(&definitions, device)
+        else {
+            log::info!(
+                "No fixture definition matches RDM device {} ({} {})",
+                device.uid,
+                device.manufacturer,
+                device.model
+            );
+            continue;
+        };
+
+        // Devices can report an address their footprint does not fit at,
+        // such as zero when they have not been addressed yet.
+        let last_address = 513 - device.footprint.clamp(1, 512);
+        if !(1..=last_address).contains(&start_address) {
+            log::warn!(
+                "RDM device {} reports start address {start_address}, which its {}-channel \
+                 footprint does not fit at",
+                device.uid,
+                device.footprint
+            );
+            continue;
+        }
+        let channel_offset = u32::from(start_address) - 1;
+        let patched = fixtures.values().any(|fixture| {
+            fixture.universe == universe
+                && fixture.channel_offset == channel_offset
+                && fixture.fixture_definition_id == fixture_definition_id
+        });
+        if patched {
+            continue;
+        }
+
+        let name = if device.model.is_empty() {
+            definitions[&fixture_definition_id].name.clone()
+        } else {
+            device.model.clone()
+        };
+        let fixture_id = rand_id();
+        fixtures.insert(
+            fixture_id,
+            PhysicalDmxFixture {
+                name: format!("{name} {start_address}"),
+                fixture_definition_id,
+                fixture_mode,
+                universe,
+                channel_offset,
+                ..Default::default()
+            },
+        );
+        fixture_ids.push(fixture_id);
+    }
+    Ok(fixture_ids)
+}
+
+/// A line of simulated responders for tests.
+#[cfg(test)]
+pub(crate) mod simulated {
+    use super::*;
+
+    pub(crate) struct Responder {
+        pub uid: Uid,
+        pub model_id: u16,
+        pub manufacturer: &'static str,
+        pub model: &'static str,
+        /// The footprint and description of each personality.
+        pub personalities: Vec<(u16, &'static str)>,
+        pub personality: u8,
+        pub start_address: u16,
+        pub identifying: bool,
+        pub muted: bool,
+    }
+
+    impl Responder {
+        pub(crate) fn new(uid: Uid) -> Self {
+            Responder {
+                uid,
+                model_id: 0x0102,
+                manufacturer: "Generic",
+                model: "Spot Mover",
+                personalities: vec![(8, "Basic"), (11, "11-channel")],
+                personality: 1,
+                start_address: 1,
+                identifying: false,
+                muted: false,
+            }
+        }
+
+        fn footprint(&self) -> u16 {
+            self.personalities[usize::from(self.personality) - 1].0
+        }
+
+        /// The response type and parameter data of the answer to `request`.
+        fn answer(&mut self, request: &Message) -> (u8, Vec<u8>) {
+            let nack = |reason: u16| (RESPONSE_TYPE_NACK_REASON, reason.to_be_bytes().to_vec());
+            match (request.command_class, request.pid, &request.data[..]) {
+                (DISCOVERY_COMMAND, DISC_MUTE, _) => {
+                    self.muted = true;
+                    (RESPONSE_TYPE_ACK, vec![0, 0])
+                }
+                (GET_COMMAND, DEVICE_INFO, _) => {
+                    let mut info = vec![0x01, 0x00];
+                    info.extend_from_slice(&self.model_id.to_be_bytes());
+                    info.extend_from_slice(&[0x01, 0x01, 0, 0, 0, 1]);
+                    info.extend_from_slice(&self.footprint().to_be_bytes());
+                    #[allow(clippy::cast_possible_truncation)]
+                    info.extend_from_slice(&[self.personality, self.personalities.len() as u8]);
+                    info.extend_from_slice(&self.start_address.to_be_bytes());
+                    info.extend_from_slice(&[0, 0, 0]);
+                    (RESPONSE_TYPE_ACK, info)
+                }
+                (GET_COMMAND, MANUFACTURER_LABEL, _) => {
+                    (RESPONSE_TYPE_ACK, self.manufacturer.as_bytes().to_vec())
+                }
+                (GET_COMMAND, DEVICE_MODEL_DESCRIPTION, _) => {
+                    (RESPONSE_TYPE_ACK, self.model.as_bytes().to_vec())
+                }
+                (GET_COMMAND, DMX_PERSONALITY_DESCRIPTION, [index]) => {
+                    match self.personalities.get(usize::from(*index).wrapping_sub(1)) {
+                        Some((footprint, description)) => {
+                            let mut data = vec![*index];
+                            data.extend_from_slice(&footprint.to_be_bytes());
+                            data.extend_from_slice(description.as_bytes());
+                            (RESPONSE_TYPE_ACK, data)
+                        }
+                        None => nack(0x0006),
+                    }
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                (GET_COMMAND, DMX_PERSONALITY, _) => (
+                    RESPONSE_TYPE_ACK,
+                    vec![self.personality, self.personalities.len() as u8],
+                ),
+                (SET_COMMAND, DMX_PERSONALITY, [personality]) => {
+                    if (1..=self.personalities.len()).contains(&usize::from(*personality)) {
+                        self.personality = *personality;
+                        (RESPONSE_TYPE_ACK, Vec::new())
+                    } else {
+                        nack(0x0006)
+                    }
+                }
+                (GET_COMMAND, DMX_START_ADDRESS, _) => {
+                    (RESPONSE_TYPE_ACK, self.start_address.to_be_bytes().to_vec())
+                }
+                (SET_COMMAND, DMX_START_ADDRESS, [high, low]) => {
+                    self.start_address = u16::from_be_bytes([*high, *low]);
+                    (RESPONSE_TYPE_ACK, Vec::new())
+                }
+                (SET_COMMAND, IDENTIFY_DEVICE, [identify]) => {
+                    self.identifying = *identify != 0;
+                    (RESPONSE_TYPE_ACK, Vec::new())
+                }
+                _ => nack(0x0000),
+            }
+        }
+    }
+
+    /// Responders sharing a line, whose discovery answers collide the way
+    /// signals on a real line do.
+    pub(crate) struct Line {
+        pub responders: Vec<Responder>,
+    }
+
+    impl Line {
+        pub(crate) fn respond(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+            let request = Message::parse(packet).ok()?;
+
+            if request.pid == DISC_UNIQUE_BRANCH {
+                let lower = Uid::from_u64(u64_from(&request.data[..6]));
+                let upper = Uid::from_u64(u64_from(&request.data[6..12]));
+                return self
+                    .responders
+                    .iter()
+                    .filter(|r| !r.muted && (lower..=upper).contains(&r.uid))
+                    .map(|r| encode_discovery_response(r.uid))
+                    .reduce(|collided, response| {
+                        collided.iter().zip(response).map(|(a, b)| a | b).collect()
+                    });
+            }
+            if request.destination == Uid::BROADCAST {
+                if request.pid == DISC_UN_MUTE {
+                    for responder in &mut self.responders {
+                        responder.muted = false;
+                    }
+                }
+                return None;
+            }
+
+            let responder = self
+                .responders
+                .iter_mut()
+                .find(|r| r.uid == request.destination)?;
+            let (response_type, data) = responder.answer(&request);
+            Some(
+                Message {
+                    destination: request.source,
+                    source: responder.uid,
+                    transaction: request.transaction,
+                    port_or_response_type: response_type,
+                    command_class: request.command_class + 1,
+                    pid: request.pid,
+                    data,
+                }
+                .encode(),
+            )
+        }
+    }
+
+    /// The reply to `DISC_UNIQUE_BRANCH`: a preamble, a separator, then the
+    /// UID and its checksum with every byte sent twice, once with the bits of
+    /// `0xAA` set and once with those of `0x55`, so that a lone responder reads
+    /// back cleanly and colliding ones do not.
+    pub(crate) fn encode_discovery_response(uid: Uid) -> Vec<u8> {
+        let mut response = vec![0xFE; 7];
+        response.push(DISCOVERY_SEPARATOR);
+        let mut encoded = Vec::with_capacity(DISCOVERY_RESPONSE_LEN);
+        for byte in uid.to_bytes() {
+            encoded.extend_from_slice(&[byte | 0xAA, byte | 0x55]);
+        }
+        for byte in checksum(&encoded).to_be_bytes() {
+            encoded.extend_from_slice(&[byte | 0xAA, byte | 0x55]);
+        }
+        response.extend(encoded);
+        response
+    }
+
+    fn u64_from(bytes: &[u8]) -> u64 {
+        bytes
+            .iter()
+            .fold(0, |uid, byte| (uid << 8) | u64::from(*byte))
+    }
+
+    impl RdmPort for Line {
+        fn transact(&mut self, request: &[u8]) -> Result<Option<Vec<u8>>, String> {
+            Ok(self.respond(request))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulated::{Line, Responder, encode_discovery_response};
+    use super::*;
+    use dmx_engine::proto::{
+        ArtnetDmxOutput, FixtureDefinitions, Output, Patch, dmx_fixture_definition::Mode,
+    };
+
+    fn uid(device_id: u32) -> Uid {
+        Uid {
+            manufacturer_id: 0x08BD,
+            device_id,
+        }
+    }
+
+    #[test]
+    fn uids_read_back_from_text() {
+        let uid = uid(0x0000_1234);
+        assert_eq!(uid.to_string(), "08BD:00001234");
+        assert_eq!("08BD:00001234".parse::<Uid>(), Ok(uid));
+        assert_eq!(Uid::from_bytes(uid.to_bytes()), uid);
+        assert!("08BD".parse::<Uid>().is_err());
+    }
+
+    #[test]
+    fn discovery_responses_decode_unless_they_collide() {
+        let response = encode_discovery_response(uid(7));
+        assert_eq!(decode_discovery_response(&response), Some(uid(7)));
+        // A leading break byte or an echoed request is skipped.
+        let mut echoed = vec![0];
+        echoed.extend_from_slice(&response[4..]);
+        assert_eq!(decode_discovery_response(&echoed), Some(uid(7)));
+
+        let other = encode_discovery_response(uid(8));
+        let collided: Vec<u8> = response.iter().zip(&other).map(|(a, b)| a | b).collect();
+        assert_eq!(decode_discovery_response(&collided), None);
+    }
+
+    #[test]
+    fn finds_every_responder_and_reads_it() {
+        let mut line = Line {
+            responders: vec![
+                Responder::new(uid(0x10)),
+                Responder::new(uid(0x11)),
+                Responder::new(Uid {
+                    manufacturer_id: 0x4A4C,
+                    device_id: 0x10,
+                }),
+            ],
+        };
+        line.responders[1].start_address = 9;
+
+        let devices = discover_devices(&mut line).unwrap();
+        let uids: Vec<_> = devices.iter().map(|d| d.uid).collect();
+        assert_eq!(uids, [uid(0x10), uid(0x11), line.responders[2].uid]);
+
+        let device = &devices[1];
+        assert_eq!(device.manufacturer, "Generic");
+        assert_eq!(device.model, "Spot Mover");
+        assert_eq!(device.footprint, 8);
+        assert_eq!(device.start_address, Some(9));
+        assert_eq!(device.personality, 1);
+        assert_eq!(device.personalities[1].description, "11-channel");
+        assert_eq!(device.personalities[1].footprint, 11);
+    }
+
+    #[test]
+    fn sets_address_and_personality_and_identifies() {
+        let mut line = Line {
+            responders: vec![Responder::new(uid(1))],
+        };
+
+        set_start_address(&mut line, uid(1), 101).unwrap();
+        assert_eq!(start_address(&mut line, uid(1)).unwrap(), Some(101));
+        assert!(set_start_address(&mut line, uid(1), 513).is_err());
+
+        set_personality(&mut line, uid(1), 2).unwrap();
+        assert_eq!(personality(&mut line, uid(1)).unwrap(), (2, 2));
+        assert_eq!(read_device(&mut line, uid(1)).unwrap().footprint, 11);
+        let error = set_personality(&mut line, uid(1), 3).unwrap_err();
+        assert_eq!(
+            error,
+            "08BD:00000001 refused DMX_PERSONALITY: data out of range"
+        );
+
+        identify(&mut line, uid(1), true).unwrap();
+        assert!(line.responders[0].identifying);
+
+        let error = read_device(&mut line, uid(2)).unwrap_err();
+        assert_eq!(error, "08BD:00000002 did not answer DEVICE_INFO");
+    }
+
+    fn definition(
+        manufacturer: &str,
+        name: &str,
+        modes: &[(&str, u32, u32)],
+    ) -> DmxFixtureDefinition {
+        DmxFixtureDefinition {
+            manufacturer: manufacturer.to_string(),
+            name: name.to_string(),
+            modes: modes
+                .iter()
+                .map(|(mode_name, num_channels, rdm_personality)| {
+                    (
+                        (*mode_name).to_string(),
+                        Mode {
+                            name: (*mode_name).to_string(),
+                            num_channels: *num_channels,
+                            rdm_personality: *rdm_personality,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_definitions_by_id_then_name() {
+        let mut line = Line {
+            responders: vec![Responder::new(uid(1))],
+        };
+        let device = read_device(&mut line, uid(1)).unwrap();
+
+        let by_name = definition("generic", "spot-mover", &[("basic", 8, 0), ("wide", 11, 0)]);
+        let mut by_id = definition("Other", "Other", &[("first", 11, 2), ("second", 8, 1)]);
+        by_id.rdm_manufacturer_id = 0x08BD;
+        by_id.rdm_model_id = 0x0102;
+        let mut wrong_id = by_name.clone();
+        wrong_id.rdm_model_id = 0x0999;
+
+        let definitions = HashMap::from([(1, by_name.clone()), (3, wrong_id.clone())]);
+        assert_eq!(
+            match_definition(&definitions, &device),
+            Some((1, "basic".to_string()))
+        );
+        let definitions = HashMap::from([(1, by_name), (2, by_id), (3, wrong_id)]);
+        assert_eq!(
+            match_definition(&definitions, &device),
+            Some((2, "second".to_string()))
+        );
+
+        // No mode has the device's footprint.
+        let definitions =
+            HashMap::from([(4, definition("Generic", "Spot Mover", &[("wide", 11, 0)]))]);
+        assert_eq!(match_definition(&definitions, &device), None);
+    }
+
+    #[test]
+    fn patches_matched_devices_once() {
+        let mut line = Line {
+            responders: vec![Responder::new(uid(1)), Responder::new(uid(2))],
+        };
+        line.responders[1].start_address = 17;
+        line.responders[1].model = "Unknown";
+        let devices = discover_devices(&mut line).unwrap();
+
+        let mut project = Project {
+            active_patch: 1,
+            patches: HashMap::from([(
+                1,
+                Patch {
+                    outputs: HashMap::from([(
+                        5,
+                        Output {
+                            output: Some(ProtoOutput::ArtnetDmxOutput(ArtnetDmxOutput::default())),
+                            ..Default::default()
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            fixture_definitions: Some(FixtureDefinitions {
+                dmx_fixture_definitions: HashMap::from([(
+                    9,
+                    definition("Generic", "Spot Mover", &[("basic", 8, 1)]),
+                )]),
+            }),
+            ..Default::default()
+        };
+
+        let fixture_ids = add_device_fixtures(&mut project, 5, 0, &devices).unwrap();
+        assert_eq!(fixture_ids.len(), 1);
+        let Some(ProtoOutput::ArtnetDmxOutput(artnet)) = &project.patches[&1].outputs[&5].output
+        else {
+            panic!("not an Art-Net output");
+        };
+        let fixture = &artnet.fixtures[&fixture_ids[0]];
+        assert_eq!(fixture.name, "Spot Mover 1");
+        assert_eq!(fixture.fixture_definition_id, 9);
+        assert_eq!(fixture.fixture_mode, "basic");
+        assert_eq!(fixture.channel_offset, 0);
+
+        assert!(
+            add_device_fixtures(&mut project, 5, 0, &devices)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Unaddressed devices, and ones whose footprint runs past the end of
+        // the universe, are skipped.
+        let mut line = Line {
+            responders: vec![Responder::new(uid(3)), Responder::new(uid(4))],
+        };
+        line.responders[0].start_address = 0;
+        line.responders[1].start_address = 506;
+        let devices = discover_devices(&mut line).unwrap();
+        assert_eq!(devices[0].start_address, Some(0));
+        assert!(
+            add_device_fixtures(&mut project, 5, 0, &devices)
+                .unwrap()
+                .is_empty()
+        );
+        line.responders[1].start_address = 505;
+        let devices = discover_devices(&mut line).unwrap();
+        assert_eq!(
+            add_device_fixtures(&mut project, 5, 0, &devices)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
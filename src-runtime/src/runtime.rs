@@ -1,13 +1,20 @@
 use dmx_engine::beat::BeatSampler;
 use dmx_engine::project;
+use dmx_engine::proto::output::Output as ProtoOutput;
+use dmx_engine::recording::{self, FrameWriter};
+use std::net::Ipv4Addr;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
 
-use crate::artnet::ArtnetState;
+use crate::artnet::{self, ArtnetState};
+use crate::artnet_discovery::ArtnetDiscovery;
+use crate::artnet_rdm::ArtnetRdmPort;
 use crate::beat::SharedBeatSampler;
+use crate::dmx_input::{DmxInputReceiver, ReceiverConfig};
 use crate::events::EventSink;
 use crate::output_loop::OutputLoopManager;
 use crate::project_store::ProjectStore;
+use crate::rdm::RdmPort;
 use crate::sacn::SacnState;
 use crate::serial::SerialState;
 use crate::wled::WledState;
@@ -28,6 +35,7 @@ use crate::midi::MidiState;
 #[cfg(feature = "audio")]
 use crate::audio_input::AudioInputState;
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct RuntimeConfig {
     pub events: Arc<dyn EventSink>,
     /// `None` makes the runtime read-only: the project is never written back.
@@ -35,6 +43,12 @@ pub struct RuntimeConfig {
     pub enable_visualizer: bool,
     pub enable_audio: bool,
     pub enable_midi: bool,
+    pub enable_artnet_discovery: bool,
+    pub enable_dmx_input: bool,
+    /// The directed broadcast address of the Art-Net network, such as
+    /// 2.255.255.255, which polls and `ArtSync` go to. `None` uses the limited
+    /// broadcast address.
+    pub artnet_broadcast: Option<Ipv4Addr>,
 }
 
 /// Construct with [`Runtime::start`] once the project is already in the
@@ -48,6 +62,10 @@ pub struct Runtime {
     artnet: Arc<ArtnetState>,
     wled: Arc<WledState>,
     output_loops: Arc<OutputLoopManager>,
+    pub artnet_discovery: Option<Arc<ArtnetDiscovery>>,
+    dmx_input: Option<Arc<DmxInputReceiver>>,
+    /// Held for an RDM session, which owns the replies from Art-Net nodes.
+    rdm: tokio::sync::Mutex<()>,
 
     #[cfg(feature = "visualizer")]
     ddp: Arc<Mutex<DdpState>>,
@@ -101,9 +119,37 @@ impl Runtime {
         serial.start_port_watcher();
 
         let sacn = Arc::new(SacnState::new()?);
-        let artnet = Arc::new(ArtnetState::new()?);
+        let artnet_broadcast = config.artnet_broadcast.unwrap_or(Ipv4Addr::BROADCAST);
+        let artnet = Arc::new(ArtnetState::new(artnet_broadcast)?);
         let wled = Arc::new(WledState::new()?);
 
+        let artnet_discovery = if config.enable_artnet_discovery {
+            let discovery = Arc::new(ArtnetDiscovery::new(Arc::clone(&events), artnet_broadcast));
+            discovery.start_discovery();
+            Some(discovery)
+        } else {
+            None
+        };
+
+        let dmx_input = if config.enable_dmx_input {
+            let receiver = Arc::new(DmxInputReceiver::new(
+                Arc::clone(&events),
+                Arc::clone(&beat_sampler),
+                ReceiverConfig {
+                    own_sacn_cids: sacn.cids(),
+                    own_artnet_port: artnet.local_port(),
+                    ..ReceiverConfig::standard(artnet_discovery.is_none())
+                },
+            ));
+            if let Some(discovery) = &artnet_discovery {
+                discovery.forward_dmx_to(Arc::clone(&receiver));
+            }
+            receiver.start_receiver();
+            Some(receiver)
+        } else {
+            None
+        };
+
         #[cfg(feature = "visualizer")]
         let shader = if config.enable_visualizer {
             match ShaderState::new().await {
@@ -124,13 +170,13 @@ impl Runtime {
         #[cfg(feature = "visualizer")]
         let ddp = Arc::new(Mutex::new(DdpState::default()));
         #[cfg(feature = "visualizer")]
-        let display_loops = Arc::new(DisplayLoopManager::new(
+        let display_loops = Arc::new(DisplayLoopManager::new(Arc::clone(&events), shader.clone()));
+
+        let output_loops = Arc::new(OutputLoopManager::new(
             Arc::clone(&events),
-            shader.clone(),
+            dmx_input.clone(),
         ));
 
-        let output_loops = Arc::new(OutputLoopManager::new(Arc::clone(&events)));
-
         #[cfg(feature = "visualizer")]
         DisplayLoopManager::start_on_load(Arc::clone(&display_loops), Arc::clone(&ddp));
 
@@ -150,6 +196,9 @@ impl Runtime {
             artnet,
             wled,
             output_loops,
+            artnet_discovery,
+            dmx_input,
+            rdm: tokio::sync::Mutex::new(()),
             #[cfg(feature = "visualizer")]
             ddp,
             #[cfg(feature = "visualizer")]
@@ -192,6 +241,78 @@ impl Runtime {
         Ok(())
     }
 
+    /// Runs `session` against the RDM devices on `universe`, an index within
+    /// output `output_id` of the active patch. A serial output stops sending
+    /// DMX for the length of the session. Art-Net needs discovery, which
+    /// receives the replies.
+    pub async fn rdm<T, F>(&self, output_id: u64, universe: u32, session: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn RdmPort) -> Result<T, String> + Send + 'static,
+    {
+        let output = project::with_project(|project| {
+            project
+                .patches
+                .get(&project.active_patch)
+                .and_then(|patch| patch.outputs.get(&output_id))
+                .and_then(|output| output.output.clone())
+                .ok_or_else(|| format!("Output {output_id} is not in the active patch"))
+        })?;
+
+        let _session = self.rdm.lock().await;
+        let result = match output {
+            ProtoOutput::SerialDmxOutput(_) => {
+                if universe != 0 {
+                    return Err(format!("Serial output {output_id} has one universe"));
+                }
+                let serial = Arc::clone(&self.serial);
+                tokio::task::spawn_blocking(move || {
+                    serial.rdm_session(&output_id.to_string(), session)
+                })
+                .await
+            }
+            ProtoOutput::ArtnetDmxOutput(artnet) => {
+                if universe >= artnet.universe_count.max(1) {
+                    return Err(format!(
+                        "Art-Net output {output_id} has no universe {universe}"
+                    ));
+                }
+                let universe = u16::try_from(artnet.universe + universe)
+                    .ok()
+                    .filter(|universe| *universe <= 0x7FFF)
+                    .ok_or("Art-Net universe out of range")?;
+                let discovery = self
+                    .artnet_discovery
+                    .as_ref()
+                    .ok_or("RDM over Art-Net needs Art-Net discovery")?;
+                let node = artnet::destination(&artnet.ip_address)?;
+                let mut port = ArtnetRdmPort::new(
+                    node,
+                    self.artnet.broadcast(),
+                    universe,
+                    discovery.forward_rdm(),
+                )?;
+                tokio::task::spawn_blocking(move || session(&mut port)).await
+            }
+            _ => return Err(format!("Output {output_id} does not support RDM")),
+        };
+        result.map_err(|e| format!("RDM session failed: {e}"))?
+    }
+
+    /// Records the DMX received from another console on the universes the
+    /// active patch sends, handing each frame to `out`. Returns the number of
+    /// frames captured.
+    pub async fn capture_dmx_input(
+        &self,
+        fps: u32,
+        duration_ms: u64,
+        out: &mut impl FrameWriter,
+    ) -> Result<u64, String> {
+        let dmx_input = self.dmx_input.as_ref().ok_or("Capturing needs DMX input")?;
+        let streams = project::with_project(recording::capture_streams)?;
+        dmx_input.capture(streams, fps, duration_ms, out).await
+    }
+
     pub fn persist_changes(&self) -> Result<(), String> {
         self.events.project_updated();
         self.events.undo_state_changed();
@@ -219,10 +340,10 @@ impl Runtime {
     /// Stops every loop, watcher and capture thread this runtime started, then
     /// flushes any pending write.
     ///
-    /// Leaves the last rendered frame on the wire, so blackout must be set
-    /// before calling this.
+    /// Leaves the last rendered frame on the wire, except on sACN, whose
+    /// streams are terminated, so blackout must be set before calling this.
     pub async fn shutdown(&self) -> Result<(), String> {
-        self.output_loops.stop_all().await;
+        self.output_loops.stop_all(&self.sacn).await;
 
         #[cfg(feature = "visualizer")]
         self.display_loops.stop_display_loop().await;
@@ -230,6 +351,14 @@ impl Runtime {
         #[cfg(feature = "serial")]
         self.serial.stop_port_watcher();
 
+        if let Some(discovery) = &self.artnet_discovery {
+            discovery.stop_discovery();
+        }
+
+        if let Some(dmx_input) = &self.dmx_input {
+            dmx_input.stop_receiver();
+        }
+
         #[cfg(feature = "midi")]
         if let Some(midi) = &self.midi {
             midi.stop_device_watcher();
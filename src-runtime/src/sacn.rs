@@ -1,55 +1,320 @@
-use sacn::packet::ACN_SDT_MULTICAST_PORT;
+use sacn::packet::{
+    ACN_SDT_MULTICAST_PORT, AcnRootLayerProtocol, DataPacketDmpLayer, DataPacketFramingLayer,
+    E131_TERMINATE_STREAM_PACKET_COUNT, E131RootLayer, E131RootLayerData,
+};
 use sacn::source::SacnSource;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+
+use crate::util::lock_or_recover;
+
+pub(crate) const DEFAULT_SOURCE_NAME: &str = "DMX Controller App";
+
+/// Start code of standard dimmer data.
+const DMX_START_CODE: u8 = 0x00;
+
+/// How an output sends its universes.
+pub(crate) struct SacnOptions<'a> {
+    /// Empty sends each universe to its standard multicast address.
+    pub ip_address: &'a str,
+    pub priority: u8,
+    pub source_name: &'a str,
+    /// The universe whose sync packets release each frame, if the receivers
+    /// should hold data until then. Not one the output sends data on.
+    pub sync_universe: Option<u16>,
+}
+
+/// `None` is multicast.
+fn destination(ip_address: &str) -> Result<Option<SocketAddr>, String> {
+    if ip_address.is_empty() {
+        return Ok(None);
+    }
+    let ip_addr: IpAddr = ip_address
+        .parse()
+        .map_err(|e| format!("Invalid IP address '{ip_address}': {e}"))?;
+    Ok(Some(SocketAddr::new(ip_addr, ACN_SDT_MULTICAST_PORT)))
+}
+
+fn termination_packet(
+    source: &SacnSource,
+    universe: u16,
+    priority: u8,
+    sequence_number: u8,
+) -> Result<Vec<u8>, String> {
+    let cid = source
+        .cid()
+        .map_err(|e| format!("Failed to read sACN source: {e}"))?;
+    let source_name = source
+        .name()
+        .map_err(|e| format!("Failed to read sACN source: {e}"))?;
+
+    AcnRootLayerProtocol {
+        pdu: E131RootLayer {
+            cid,
+            data: E131RootLayerData::DataPacket(DataPacketFramingLayer {
+                source_name: source_name.into(),
+                priority,
+                synchronization_address: 0,
+                sequence_number,
+                preview_data: false,
+                stream_terminated: true,
+                force_synchronization: false,
+                universe,
+                data: DataPacketDmpLayer {
+                    property_values: vec![DMX_START_CODE].into(),
+                },
+            }),
+        },
+    }
+    .pack_alloc()
+    .map_err(|e| format!("Failed to encode sACN termination: {e}"))
+}
+
+#[derive(Default)]
+struct Streams {
+    /// Each universe being sent, with where it goes.
+    active: HashSet<(u16, Option<SocketAddr>)>,
+    /// The sequence number the source gives each universe's next data packet.
+    /// `SacnSource` only terminates streams over multicast, so unicast
+    /// terminations are sent from here and must carry it.
+    sequences: HashMap<u16, u8>,
+}
+
+/// Receivers tell sources apart by CID and show one name for each, so every
+/// source name is sent from a source of its own.
+struct Source {
+    sacn: SacnSource,
+    streams: Streams,
+}
 
 pub struct SacnState {
-    source: std::sync::Mutex<SacnSource>,
+    /// By source name.
+    sources: Mutex<HashMap<String, Source>>,
+    /// The CIDs of `sources`, shared with the receiver so it can ignore them.
+    cids: Arc<Mutex<HashSet<[u8; 16]>>>,
+    unicast_socket: UdpSocket,
 }
 
 impl SacnState {
     pub fn new() -> Result<Self, String> {
-        let local_addr = SocketAddr::new(
-            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-            ACN_SDT_MULTICAST_PORT + 1,
-        );
+        let unicast_socket = UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| format!("Failed to create sACN socket: {e}"))?;
 
-        let source = SacnSource::with_ip("DMX Controller App", local_addr)
-            .map_err(|e| format!("Failed to create sACN source: {e}"))?;
+        let state = SacnState {
+            sources: Mutex::new(HashMap::new()),
+            cids: Arc::new(Mutex::new(HashSet::new())),
+            unicast_socket,
+        };
+        // Fail at startup rather than on the first send if sACN can't be sent.
+        state.with_source(DEFAULT_SOURCE_NAME, |_| Ok(()))?;
+        Ok(state)
+    }
 
-        Ok(SacnState {
-            source: std::sync::Mutex::new(source),
-        })
+    /// The CIDs packets are sent under, so the receiver can ignore them. New
+    /// source names add theirs as they start sending.
+    pub(crate) fn cids(&self) -> Arc<Mutex<HashSet<[u8; 16]>>> {
+        Arc::clone(&self.cids)
+    }
+
+    /// Runs `f` on the source sending as `source_name`, creating it if this is
+    /// the first time the name is used.
+    fn with_source<T>(
+        &self,
+        source_name: &str,
+        f: impl FnOnce(&mut Source) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut sources = self
+            .sources
+            .lock()
+            .map_err(|e| format!("Failed to lock sACN sources: {e}"))?;
+
+        let source = match sources.entry(source_name.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let local_addr = SocketAddr::new(
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    ACN_SDT_MULTICAST_PORT + 1,
+                );
+                let sacn = SacnSource::with_ip(source_name, local_addr)
+                    .map_err(|e| format!("Failed to create sACN source: {e}"))?;
+                let cid = sacn
+                    .cid()
+                    .map_err(|e| format!("Failed to read sACN source: {e}"))?;
+                lock_or_recover(&self.cids, "sACN CIDs").insert(*cid.as_bytes());
+                entry.insert(Source {
+                    sacn,
+                    streams: Streams::default(),
+                })
+            }
+        };
+        f(source)
     }
 
     pub(crate) fn output_sacn(
         &self,
         universe: u16,
-        ip_address: &str,
+        options: &SacnOptions,
         data: &[u8],
     ) -> Result<(), String> {
-        // Prepend DMX start code (0x00) to the data
+        let destination = destination(options.ip_address)?;
+
         let mut dmx_data = Vec::with_capacity(513);
-        dmx_data.push(0x00); // DMX start code for standard dimmer data
+        dmx_data.push(DMX_START_CODE);
         dmx_data.extend_from_slice(data);
 
-        let mut source = self
-            .source
-            .lock()
-            .map_err(|e| format!("Failed to lock sACN source: {e}"))?;
+        self.with_source(options.source_name, |source| {
+            if source.streams.active.insert((universe, destination)) {
+                source
+                    .sacn
+                    .register_universe(universe)
+                    .map_err(|e| format!("Failed to register sACN DMX universe: {e}"))?;
+            }
+            // The source only synchronizes on universes it has registered.
+            if let Some(sync_universe) = options.sync_universe {
+                source
+                    .sacn
+                    .register_universe(sync_universe)
+                    .map_err(|e| format!("Failed to register sACN sync universe: {e}"))?;
+            }
 
-        let ip_addr: IpAddr = ip_address
-            .parse()
-            .map_err(|e| format!("Invalid IP address '{ip_address}': {e}"))?;
-        let socket_addr = SocketAddr::new(ip_addr, ACN_SDT_MULTICAST_PORT);
+            source
+                .sacn
+                .send(
+                    &[universe],
+                    &dmx_data,
+                    Some(options.priority),
+                    destination,
+                    options.sync_universe,
+                )
+                .map_err(|e| format!("Failed to send sACN DMX data: {e}"))?;
 
-        source
-            .register_universe(universe)
-            .map_err(|e| format!("Failed to register sACN DMX universe: {e}"))?;
+            let sequence = source.streams.sequences.entry(universe).or_insert(0);
+            *sequence = sequence.wrapping_add(1);
 
-        let result = source.send(&[universe], &dmx_data, Some(100), Some(socket_addr), None);
+            Ok(())
+        })
+    }
+
+    /// Releases the data held by receivers synchronizing on `sync_universe`.
+    pub(crate) fn sync_sacn(
+        &self,
+        sync_universe: u16,
+        ip_address: &str,
+        source_name: &str,
+    ) -> Result<(), String> {
+        let destination = destination(ip_address)?;
+
+        self.with_source(source_name, |source| {
+            source
+                .sacn
+                .send_sync_packet(sync_universe, destination)
+                .map_err(|e| format!("Failed to send sACN sync packet: {e}"))
+        })
+    }
+
+    /// Tells the receivers of `universe` that this source has stopped, so they
+    /// move on at once rather than holding the last frame until they time out.
+    /// Does nothing if the universe is not being sent as `options` describe.
+    pub(crate) fn terminate_sacn(
+        &self,
+        universe: u16,
+        options: &SacnOptions,
+    ) -> Result<(), String> {
+        let destination = destination(options.ip_address)?;
+
+        self.with_source(options.source_name, |source| {
+            let streams = &mut source.streams;
+            if !streams.active.remove(&(universe, destination)) {
+                return Ok(());
+            }
+
+            let Some(destination) = destination else {
+                source
+                    .sacn
+                    .terminate_stream(universe, DMX_START_CODE)
+                    .map_err(|e| format!("Failed to terminate sACN universe {universe}: {e}"))?;
+                let sequence = streams.sequences.entry(universe).or_insert(0);
+                #[allow(clippy::cast_possible_truncation)]
+                let sent = E131_TERMINATE_STREAM_PACKET_COUNT as u8;
+                *sequence = sequence.wrapping_add(sent);
+
+                // Terminating deregisters the universe, which other outputs may
+                // still be sending to.
+                if streams.active.iter().any(|(u, _)| *u == universe) {
+                    source
+                        .sacn
+                        .register_universe(universe)
+                        .map_err(|e| format!("Failed to register sACN DMX universe: {e}"))?;
+                }
+                return Ok(());
+            };
+
+            let sequence = streams.sequences.get(&universe).copied().unwrap_or(0);
+            let packet = termination_packet(&source.sacn, universe, options.priority, sequence)?;
+            for _ in 0..E131_TERMINATE_STREAM_PACKET_COUNT {
+                self.unicast_socket
+                    .send_to(&packet, destination)
+                    .map_err(|e| format!("Failed to terminate sACN universe {universe}: {e}"))?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_addresses_multicast() {
+        assert_eq!(destination("").unwrap(), None);
+        assert_eq!(
+            destination("10.0.0.1").unwrap(),
+            Some(SocketAddr::from(([10, 0, 0, 1], ACN_SDT_MULTICAST_PORT)))
+        );
+        assert!(destination("not an address").is_err());
+    }
+
+    #[test]
+    fn terminations_carry_the_stream_terminated_flag() {
+        let source = SacnSource::with_ip(
+            DEFAULT_SOURCE_NAME,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+        )
+        .unwrap();
+
+        let packet = termination_packet(&source, 7, 150, 42).unwrap();
+        let parsed = AcnRootLayerProtocol::parse(&packet).unwrap();
+        assert_eq!(parsed.pdu.cid, source.cid().unwrap());
+        let E131RootLayerData::DataPacket(data) = parsed.pdu.data else {
+            panic!("not a data packet");
+        };
+        assert!(data.stream_terminated);
+        assert_eq!(data.universe, 7);
+        assert_eq!(data.sequence_number, 42);
+        assert_eq!(data.priority, 150);
+        assert_eq!(data.source_name, DEFAULT_SOURCE_NAME);
+    }
 
-        result.map_err(|e| format!("Failed to send sACN DMX data: {e}"))?;
+    #[test]
+    fn each_source_name_sends_under_its_own_cid() {
+        let state = SacnState::new().unwrap();
+        let send = |source_name| {
+            let options = SacnOptions {
+                ip_address: "127.0.0.1",
+                priority: 150,
+                source_name,
+                sync_universe: Some(9),
+            };
+            state.output_sacn(1, &options, &[0; 512]).unwrap();
+            state.sync_sacn(9, "127.0.0.1", source_name).unwrap();
+        };
+        send(DEFAULT_SOURCE_NAME);
+        send("Upstage");
+        send("Upstage");
 
-        Ok(())
+        assert_eq!(lock_or_recover(&state.cids, "sACN CIDs").len(), 2);
     }
 }
@@ -1,15 +1,33 @@
+use crate::rdm::RdmPort;
 use crate::util::lock_or_recover;
 use dmx_engine::project;
 use dmx_engine::proto::SerialDmxOutput;
 use dmx_engine::proto::output::Output as ProtoOutput;
 use open_dmx::DMXSerial;
-use serialport::available_ports;
-use std::collections::HashMap;
+use serialport::{ClearBuffer, SerialPort, StopBits, available_ports};
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Read, Write};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// DMX and RDM share the line's bit rate.
+const DMX_BAUD_RATE: u32 = 250_000;
+/// E1.20 allows a controller's break to run from 176 to 352 microseconds.
+const RDM_BREAK: Duration = Duration::from_micros(176);
+const RDM_MARK_AFTER_BREAK: Duration = Duration::from_micros(12);
+/// How long the line stays quiet before a response is taken as complete. USB
+/// adapters deliver bytes in batches, so this is well over E1.20's 2.8 ms.
+const RDM_RESPONSE_TIMEOUT: Duration = Duration::from_millis(30);
+/// An output's sender thread only lets go of the port once it notices the
+/// connection has been dropped.
+const RDM_OPEN_ATTEMPTS: u32 = 10;
 
 #[derive(Default)]
 pub struct SerialState {
     dmx_ports: std::sync::Mutex<HashMap<String, (String, DMXSerial)>>, // output_id -> (port_name, connection)
+    /// Outputs whose port is lent to an RDM session, which send no DMX until
+    /// it ends.
+    rdm_outputs: std::sync::Mutex<HashSet<String>>,
     watcher_cancel_tx: std::sync::Mutex<Option<tokio::sync::watch::Sender<bool>>>,
 }
 
@@ -120,10 +138,43 @@ impl SerialState {
                     .map_err(|_| "DMX device disconnected".to_string())?;
                 Ok(())
             }
+            None if lock_or_recover(&self.rdm_outputs, "RDM outputs").contains(output_id) => Ok(()),
             None => Err("Output not bound to any port".to_string()),
         }
     }
 
+    /// Pauses the DMX of `output_id` and runs `session` on its port, then
+    /// resumes it.
+    pub(crate) fn rdm_session<T>(
+        &self,
+        output_id: &str,
+        session: impl FnOnce(&mut dyn RdmPort) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let port_name = {
+            let mut ports = self
+                .dmx_ports
+                .lock()
+                .map_err(|e| format!("Failed to lock DMX ports: {e}"))?;
+            let (port_name, connection) = ports
+                .remove(output_id)
+                .ok_or("Output not bound to any port")?;
+            // Ends the sender thread, which closes the port.
+            drop(connection);
+            lock_or_recover(&self.rdm_outputs, "RDM outputs").insert(output_id.to_string());
+            port_name
+        };
+
+        let result = SerialRdmPort::open(&port_name).and_then(|mut port| session(&mut port));
+
+        // Stays marked until DMX is bound again so the port watcher does not
+        // open the port in between.
+        if let Err(e) = self.try_open_port(output_id, &port_name) {
+            log::error!("Failed to resume DMX on '{port_name}' after RDM: {e}");
+        }
+        lock_or_recover(&self.rdm_outputs, "RDM outputs").remove(output_id);
+        result
+    }
+
     /// Attempt to open a port for the given output
     pub fn try_open_port(&self, output_id: &str, port_name: &str) -> Result<(), String> {
         match DMXSerial::open_sync(port_name) {
@@ -241,7 +292,18 @@ impl SerialState {
 
         // Process each serial output outside the project lock
         for (output_id_str, desired_port_option) in serial_outputs {
-            let current_port = self.get_bound_port_name(&output_id_str);
+            let current_port = {
+                // Checked under the port lock, which an RDM session holds
+                // while it takes the port over.
+                let ports = lock_or_recover(&self.dmx_ports, "DMX ports");
+                // Its port is rebound when the RDM session ends.
+                if lock_or_recover(&self.rdm_outputs, "RDM outputs").contains(&output_id_str) {
+                    continue;
+                }
+                ports
+                    .get(&output_id_str)
+                    .map(|(port_name, _)| port_name.clone())
+            };
 
             // An empty `last_port` means the same thing as an absent one, so
             // both collapse to None and every arm below binds what it needs.
@@ -305,6 +367,62 @@ impl SerialState {
     }
 }
 
+/// A serial port driven directly, rather than by `open_dmx`, so RDM responses
+/// can be read back between requests.
+struct SerialRdmPort {
+    port: Box<dyn SerialPort>,
+}
+
+impl SerialRdmPort {
+    fn open(port_name: &str) -> Result<Self, String> {
+        let mut attempt = 1;
+        loop {
+            match serialport::new(port_name, DMX_BAUD_RATE)
+                .stop_bits(StopBits::Two)
+                .timeout(RDM_RESPONSE_TIMEOUT)
+                .open()
+            {
+                Ok(port) => return Ok(SerialRdmPort { port }),
+                Err(_) if attempt < RDM_OPEN_ATTEMPTS => {
+                    attempt += 1;
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => return Err(format!("Failed to open '{port_name}' for RDM: {e}")),
+            }
+        }
+    }
+}
+
+impl RdmPort for SerialRdmPort {
+    /// Adapters that cannot turn the line around to receive never hear a
+    /// response, so nothing is found through them.
+    fn transact(&mut self, request: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let io_error = |e: &dyn std::fmt::Display| format!("RDM serial port failed: {e}");
+
+        self.port
+            .clear(ClearBuffer::Input)
+            .map_err(|e| io_error(&e))?;
+        self.port.set_break().map_err(|e| io_error(&e))?;
+        std::thread::sleep(RDM_BREAK);
+        self.port.clear_break().map_err(|e| io_error(&e))?;
+        std::thread::sleep(RDM_MARK_AFTER_BREAK);
+        self.port.write_all(request).map_err(|e| io_error(&e))?;
+        self.port.flush().map_err(|e| io_error(&e))?;
+
+        let mut reply = Vec::new();
+        let mut buffer = [0u8; 512];
+        loop {
+            match self.port.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => reply.extend_from_slice(&buffer[..len]),
+                Err(e) if e.kind() == ErrorKind::TimedOut => break,
+                Err(e) => return Err(io_error(&e)),
+            }
+        }
+        Ok((!reply.is_empty()).then_some(reply))
+    }
+}
+
 pub fn list_ports() -> Result<Vec<String>, String> {
     match available_ports() {
         Ok(ports) => {
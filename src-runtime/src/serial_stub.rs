@@ -15,6 +15,15 @@ impl SerialState {
         Ok(())
     }
 
+    #[allow(clippy::unused_self)]
+    pub(crate) fn rdm_session<T>(
+        &self,
+        _output_id: &str,
+        _session: impl FnOnce(&mut dyn crate::rdm::RdmPort) -> Result<T, String>,
+    ) -> Result<T, String> {
+        Err("Serial support is not built in".to_string())
+    }
+
     pub fn try_close_port(&self, _output_id: &str) -> Result<(), String> {
         Ok(())
     }
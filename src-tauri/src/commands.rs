@@ -1,6 +1,7 @@
 use crate::event_sink;
 use dmx_engine::beat::{set_bpm as engine_set_bpm, set_first_beat as engine_set_first_beat};
 use dmx_engine::project;
+use dmx_runtime::artnet_discovery::{self, ArtnetNode};
 use dmx_runtime::runtime::Runtime;
 use dmx_runtime::util::now_ms;
 use std::collections::HashMap;
@@ -105,3 +106,57 @@ pub async fn disconnect_midi(
     dmx_runtime::midi::disconnect_midi(midi, &device_name);
     Ok(())
 }
+
+/// Art-Net nodes currently answering polls.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn list_artnet_nodes(runtime: State<'_, Arc<Runtime>>) -> Result<Vec<ArtnetNode>, String> {
+    let discovery = runtime
+        .artnet_discovery
+        .as_ref()
+        .ok_or("Art-Net discovery is not enabled")?;
+    Ok(discovery.nodes())
+}
+
+/// Whether a node is answering at the address of each Art-Net output.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_artnet_output_status(
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<HashMap<String, bool>, String> {
+    let discovery = runtime
+        .artnet_discovery
+        .as_ref()
+        .ok_or("Art-Net discovery is not enabled")?;
+    Ok(discovery
+        .output_status()
+        .into_iter()
+        .map(|(output_id, alive)| (output_id.to_string(), alive))
+        .collect())
+}
+
+/// Creates an output for each port of a discovered node that no output sends
+/// to yet. Returns the IDs of the new outputs.
+#[tauri::command]
+pub async fn create_artnet_outputs(
+    ip_address: String,
+    bind_index: u8,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<Vec<String>, String> {
+    let node = runtime
+        .artnet_discovery
+        .as_ref()
+        .ok_or("Art-Net discovery is not enabled")?
+        .nodes()
+        .into_iter()
+        .find(|n| n.ip_address == ip_address && n.bind_index == bind_index)
+        .ok_or_else(|| format!("No Art-Net node at {ip_address}"))?;
+
+    let description = format!("Create Art-Net outputs for {}.", node.short_name);
+    let output_ids = project::save(&description, true, |p| {
+        artnet_discovery::add_node_outputs(p, &node)
+    })?;
+
+    runtime.finalize_project_modification().await?;
+    Ok(output_ids.iter().map(u64::to_string).collect())
+}
@@ -1,6 +1,7 @@
 use dmx_engine::audio::AudioAnalysis;
 use dmx_engine::project::{self, UndoState};
 use dmx_engine::proto::{DisplayBuffer, WledRenderTarget};
+use dmx_runtime::artnet_discovery::ArtnetNode;
 use dmx_runtime::events::EventSink;
 use prost::Message;
 use serde::Serialize;
@@ -62,6 +63,12 @@ struct MidiConnectionStatusEvent {
     connected: bool,
 }
 
+#[derive(Clone, Serialize)]
+struct ArtnetOutputStatusEvent {
+    output_id: String,
+    alive: bool,
+}
+
 #[derive(Clone, Serialize)]
 struct AudioInputDevice {
     name: String,
@@ -254,4 +261,18 @@ impl EventSink for TauriEventSink {
     fn audio_analysis(&self, analysis: &AudioAnalysis) {
         self.emit("audio-input-analysis", analysis);
     }
+
+    fn artnet_nodes(&self, nodes: &[ArtnetNode]) {
+        self.emit("artnet-nodes", nodes);
+    }
+
+    fn artnet_output_status(&self, output_id: u64, alive: bool) {
+        self.emit(
+            "artnet-output-status",
+            ArtnetOutputStatusEvent {
+                output_id: output_id.to_string(),
+                alive,
+            },
+        );
+    }
 }
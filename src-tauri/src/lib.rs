@@ -3,7 +3,10 @@ mod commands;
 mod event_sink;
 #[cfg(desktop)]
 mod mcp;
+mod programmer;
 mod project;
+mod rdm;
+mod recording;
 mod render;
 
 use dmx_runtime::events::EventSink;
@@ -78,6 +81,7 @@ pub fn run() {
 
             let store = Arc::new(DiskProjectStore::new(&app_data_dir));
             project_store::load_into_engine(store.as_ref()).map_err(to_setup_error)?;
+            recording::load_recordings(app.handle()).map_err(to_setup_error)?;
 
             let events: Arc<dyn EventSink> =
                 Arc::new(event_sink::TauriEventSink::new(app.handle().clone()));
@@ -88,6 +92,9 @@ pub fn run() {
                 enable_visualizer: true,
                 enable_audio: true,
                 enable_midi: true,
+                enable_artnet_discovery: true,
+                enable_dmx_input: true,
+                artnet_broadcast: None,
             }))
             .map_err(to_setup_error)?;
 
@@ -132,20 +139,40 @@ pub fn run() {
             project::undo_project,
             project::redo_project,
             project::get_undo_state,
+            project::validate_project,
             project::request_update,
             project::toggle_tile,
             project::delete_visualizer,
             project::export_project,
             project::import_project,
             project::new_project,
+            programmer::programmer_get,
+            programmer::programmer_select,
+            programmer::programmer_set_state,
+            programmer::programmer_clear,
+            programmer::programmer_record_to_tile,
+            programmer::programmer_record_to_cue,
             cas::import_audio_file,
             cas::read_cas_blob,
             render::render_dmx,
             render::set_render_mode,
+            recording::capture_dmx_recording,
+            recording::pause_playback,
+            recording::resume_playback,
+            recording::seek_playback,
+            recording::set_playback_speed,
             commands::compile_visualizer,
             commands::get_builtin_visualizers,
             #[cfg(desktop)]
             commands::list_ports,
+            commands::list_artnet_nodes,
+            commands::get_artnet_output_status,
+            commands::create_artnet_outputs,
+            rdm::discover_rdm_devices,
+            rdm::set_rdm_start_address,
+            rdm::set_rdm_personality,
+            rdm::identify_rdm_device,
+            rdm::patch_rdm_devices,
             #[cfg(desktop)]
             mcp::bridge::mcp_frontend_response,
             commands::frontend_ready_for_update,
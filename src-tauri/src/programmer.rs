@@ -0,0 +1,91 @@
+use dmx_engine::programmer;
+use dmx_engine::project;
+use dmx_engine::proto::{FixtureState, programmer::Selection};
+use dmx_runtime::runtime::Runtime;
+use prost::Message;
+use std::sync::Arc;
+use tauri::State;
+
+// The programmer is live state, not part of the project: changing it neither
+// adds an undo entry nor saves. These commands return the encoded
+// `Programmer` so the webview can show it.
+
+/// Returns the encoded `Programmer`.
+#[tauri::command]
+pub fn programmer_get() -> Result<Vec<u8>, String> {
+    programmer::with_programmer(|programmer| Ok(programmer.encode_to_vec()))
+}
+
+/// Replaces the programmer's selection with the targets of an encoded
+/// `Programmer.Selection`.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn programmer_select(selection_binary: Vec<u8>) -> Result<Vec<u8>, String> {
+    let selection = Selection::decode(&selection_binary[..])
+        .map_err(|e| format!("Failed to decode selection: {e}"))?;
+
+    programmer::with_programmer(|programmer| {
+        programmer::select(programmer, selection.targets);
+        Ok(programmer.encode_to_vec())
+    })
+}
+
+/// Sets the attributes of an encoded `FixtureState` on the selection.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn programmer_set_state(state_binary: Vec<u8>) -> Result<Vec<u8>, String> {
+    let state = FixtureState::decode(&state_binary[..])
+        .map_err(|e| format!("Failed to decode fixture state: {e}"))?;
+
+    programmer::with_programmer(|programmer| {
+        programmer::set_state(programmer, &state)?;
+        Ok(programmer.encode_to_vec())
+    })
+}
+
+/// Clears the programmer's values and selection.
+#[tauri::command]
+pub fn programmer_clear() -> Result<Vec<u8>, String> {
+    programmer::with_programmer(|programmer| {
+        programmer::clear(programmer);
+        Ok(programmer.encode_to_vec())
+    })
+}
+
+/// Records the programmer into a scene tile.
+#[tauri::command]
+pub async fn programmer_record_to_tile(
+    scene_id: String,
+    tile_id: String,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<(), String> {
+    let scene_id: u64 = scene_id.parse().map_err(|_| "Invalid scene_id")?;
+    let tile_id: u64 = tile_id.parse().map_err(|_| "Invalid tile_id")?;
+
+    project::save("Record programmer to tile.", true, |p| {
+        programmer::with_programmer(|programmer| {
+            programmer::record_to_tile(p, programmer, scene_id, tile_id)
+        })
+    })?;
+
+    runtime.finalize_project_modification().await
+}
+
+/// Records the programmer into a cue.
+#[tauri::command]
+pub async fn programmer_record_to_cue(
+    cue_list_id: String,
+    cue_id: String,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<(), String> {
+    let cue_list_id: u64 = cue_list_id.parse().map_err(|_| "Invalid cue_list_id")?;
+    let cue_id: u64 = cue_id.parse().map_err(|_| "Invalid cue_id")?;
+
+    project::save("Record programmer to cue.", true, |p| {
+        programmer::with_programmer(|programmer| {
+            programmer::record_to_cue(p, programmer, cue_list_id, cue_id)
+        })
+    })?;
+
+    runtime.finalize_project_modification().await
+}
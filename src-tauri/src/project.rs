@@ -1,6 +1,8 @@
 use dmx_engine::project;
 use dmx_engine::proto::FatProject;
+use dmx_engine::recording;
 use dmx_engine::tile::toggle_tile as engine_toggle_tile;
+use dmx_engine::validate::{Finding, validate};
 use dmx_engine::visualizer::utils as visualizer_utils;
 use dmx_runtime::runtime::Runtime;
 use dmx_runtime::util::now_ms;
@@ -52,6 +54,12 @@ pub async fn redo_project(runtime: State<'_, Arc<Runtime>>) -> Result<(), String
     runtime.finalize_project_modification().await
 }
 
+/// Checks the current project for structural problems.
+#[tauri::command]
+pub fn validate_project() -> Result<Vec<Finding>, String> {
+    project::with_project(|project| Ok(validate(project)))
+}
+
 /// Returns the current undo/redo availability state.
 #[tauri::command]
 pub fn get_undo_state() -> Result<UndoStatePayload, String> {
@@ -85,9 +93,12 @@ pub async fn export_project(app: AppHandle) -> Result<bool, String> {
 
     // Get CAS entries
     let mut cas = HashMap::new();
-    for track in project.tracks.values() {
-        let digest = &track.digest;
-
+    let digests = project
+        .tracks
+        .values()
+        .map(|t| &t.digest)
+        .chain(project.recordings.values().map(|r| &r.digest));
+    for digest in digests {
         let blob = read_cas_bytes(&app, digest)?;
         cas.insert(digest.clone(), blob);
     }
@@ -152,9 +163,9 @@ pub async fn import_project(
         .map_err(|e| format!("Failed to decode project: {e}"))?;
 
     // Load CAS entries onto filesystem
-    for (expected_digest, bytes) in fat_project.cas {
-        let actual_digest = write_cas_bytes(&app, &bytes)?;
-        if actual_digest != expected_digest {
+    for (expected_digest, bytes) in &fat_project.cas {
+        let actual_digest = write_cas_bytes(&app, bytes)?;
+        if actual_digest != *expected_digest {
             return Err(format!(
                 "CAS digest mismatch: expected {expected_digest}, got {actual_digest}"
             ));
@@ -163,6 +174,11 @@ pub async fn import_project(
 
     // Load the project into the engine
     if let Some(project) = fat_project.project {
+        for dmx_recording in project.recordings.values() {
+            if let Some(bytes) = fat_project.cas.get(&dmx_recording.digest) {
+                recording::load(&dmx_recording.digest, bytes)?;
+            }
+        }
         project::load(project)?;
     } else {
         return Err("Could not load fat project, `project` field not set!".to_string());
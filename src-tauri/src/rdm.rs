@@ -0,0 +1,126 @@
+use dmx_engine::project;
+use dmx_runtime::rdm::{self, RdmDevice, Uid};
+use dmx_runtime::runtime::Runtime;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::State;
+
+/// A discovered device and the fixture definition and mode it matches, if
+/// any.
+#[derive(Clone, Serialize)]
+pub struct DiscoveredRdmDevice {
+    #[serde(flatten)]
+    device: RdmDevice,
+    fixture_definition_id: Option<String>,
+    fixture_mode: Option<String>,
+}
+
+fn parse_output_id(output_id: &str) -> Result<u64, String> {
+    output_id
+        .parse()
+        .map_err(|_| "Invalid output_id".to_string())
+}
+
+/// Finds the RDM devices on a universe of an output.
+#[tauri::command]
+pub async fn discover_rdm_devices(
+    output_id: String,
+    universe: u32,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<Vec<DiscoveredRdmDevice>, String> {
+    let output_id = parse_output_id(&output_id)?;
+    let devices = runtime
+        .rdm(output_id, universe, |port| rdm::discover_devices(port))
+        .await?;
+
+    let definitions = project::with_project(|p| {
+        Ok(p.fixture_definitions
+            .as_ref()
+            .map(|d| d.dmx_fixture_definitions.clone())
+            .unwrap_or_default())
+    })?;
+    Ok(devices
+        .into_iter()
+        .map(|device| {
+            let matched = rdm::match_definition(&definitions, &device);
+            DiscoveredRdmDevice {
+                fixture_definition_id: matched.as_ref().map(|(id, _)| id.to_string()),
+                fixture_mode: matched.map(|(_, mode)| mode),
+                device,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn set_rdm_start_address(
+    output_id: String,
+    universe: u32,
+    uid: String,
+    start_address: u16,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<(), String> {
+    let output_id = parse_output_id(&output_id)?;
+    let uid: Uid = uid.parse()?;
+    runtime
+        .rdm(output_id, universe, move |port| {
+            rdm::set_start_address(port, uid, start_address)
+        })
+        .await
+}
+
+#[tauri::command]
+pub async fn set_rdm_personality(
+    output_id: String,
+    universe: u32,
+    uid: String,
+    personality: u8,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<(), String> {
+    let output_id = parse_output_id(&output_id)?;
+    let uid: Uid = uid.parse()?;
+    runtime
+        .rdm(output_id, universe, move |port| {
+            rdm::set_personality(port, uid, personality)
+        })
+        .await
+}
+
+/// Turns a device's identify mode, usually a flashing light, on or off.
+#[tauri::command]
+pub async fn identify_rdm_device(
+    output_id: String,
+    universe: u32,
+    uid: String,
+    identify: bool,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<(), String> {
+    let output_id = parse_output_id(&output_id)?;
+    let uid: Uid = uid.parse()?;
+    runtime
+        .rdm(output_id, universe, move |port| {
+            rdm::identify(port, uid, identify)
+        })
+        .await
+}
+
+/// Discovers the devices on a universe of an output and patches a fixture for
+/// each one that matches a definition. Returns the IDs of the new fixtures.
+#[tauri::command]
+pub async fn patch_rdm_devices(
+    output_id: String,
+    universe: u32,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<Vec<String>, String> {
+    let output_id = parse_output_id(&output_id)?;
+    let devices = runtime
+        .rdm(output_id, universe, |port| rdm::discover_devices(port))
+        .await?;
+
+    let fixture_ids = project::save("Patch fixtures found over RDM.", true, |p| {
+        rdm::add_device_fixtures(p, output_id, universe, &devices)
+    })?;
+
+    runtime.finalize_project_modification().await?;
+    Ok(fixture_ids.iter().map(u64::to_string).collect())
+}
@@ -0,0 +1,93 @@
+use crate::cas::{read_cas_bytes, write_cas_bytes};
+use dmx_engine::project;
+use dmx_engine::proto::{
+    DmxRecording, RenderMode,
+    render_mode::{Mode, RecordingPlayback},
+};
+use dmx_engine::recording;
+use dmx_engine::render::{playback, render::RENDER_MODE_REF};
+use dmx_runtime::runtime::Runtime;
+use dmx_runtime::util::now_ms;
+use prost::Message;
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+/// Decodes the frame logs of the loaded project's recordings from the CAS, so
+/// they can be played back.
+pub fn load_recordings(app: &AppHandle) -> Result<(), String> {
+    let recordings = project::with_project(|p| Ok(p.recordings.clone()))?;
+    for dmx_recording in recordings.values() {
+        let loaded = read_cas_bytes(app, &dmx_recording.digest)
+            .and_then(|bytes| recording::load(&dmx_recording.digest, &bytes));
+        if let Err(e) = loaded {
+            log::warn!("Failed to load recording \"{}\": {e}", dmx_recording.name);
+        }
+    }
+    Ok(())
+}
+
+/// Records the DMX received from another console for `duration_ms` and adds
+/// it to the project as a recording. Returns the new recording's ID.
+#[tauri::command]
+pub async fn capture_dmx_recording(
+    name: String,
+    fps: u32,
+    duration_ms: u64,
+    app: AppHandle,
+    runtime: State<'_, Arc<Runtime>>,
+) -> Result<String, String> {
+    // Captures go to the CAS as a whole, so the frame log is built in memory.
+    let mut writer = recording::FrameLogWriter::new(Vec::new());
+    runtime
+        .capture_dmx_input(fps, duration_ms, &mut writer)
+        .await?;
+    let bytes = writer.into_inner();
+    let digest = write_cas_bytes(&app, &bytes)?;
+    recording::load(&digest, &bytes)?;
+
+    let recording_id = project::save("Capture DMX recording.", true, |p| {
+        let recording_id = p.recordings.keys().max().map_or(1, |id| id + 1);
+        p.recordings
+            .insert(recording_id, DmxRecording { name, digest });
+        Ok(recording_id)
+    })?;
+
+    runtime.finalize_project_modification().await?;
+    Ok(recording_id.to_string())
+}
+
+/// Applies `control` to the recording playing back, returning the encoded
+/// render mode so the caller stays in step.
+fn control_playback(control: impl FnOnce(&mut RecordingPlayback, u64)) -> Result<Vec<u8>, String> {
+    let mut render_mode = RENDER_MODE_REF
+        .lock()
+        .map_err(|e| format!("Failed to lock render mode: {e}"))?;
+    let RenderMode {
+        mode: Some(Mode::RecordingPlayback(recording_playback)),
+    } = &mut *render_mode
+    else {
+        return Err("No recording is playing back".to_string());
+    };
+    control(recording_playback, now_ms());
+    Ok(render_mode.encode_to_vec())
+}
+
+#[tauri::command]
+pub fn pause_playback() -> Result<Vec<u8>, String> {
+    control_playback(playback::pause)
+}
+
+#[tauri::command]
+pub fn resume_playback() -> Result<Vec<u8>, String> {
+    control_playback(playback::resume)
+}
+
+#[tauri::command]
+pub fn seek_playback(position_ms: u32) -> Result<Vec<u8>, String> {
+    control_playback(|p, system_t| playback::seek(p, position_ms, system_t))
+}
+
+#[tauri::command]
+pub fn set_playback_speed(speed: f64) -> Result<Vec<u8>, String> {
+    control_playback(|p, system_t| playback::set_speed(p, speed, system_t))
+}
@@ -11,9 +11,9 @@ pub fn render_dmx(output_id: String, system_t: u64, frame: u32) -> Result<Vec<u8
         .parse::<u64>()
         .map_err(|e| format!("Error parsing output id: {e}"))?;
 
-    let universe = render::render_dmx(oid, system_t, frame).map_err(|e| e.to_string())?;
+    let rendered = render::render_dmx(oid, system_t, frame, None).map_err(|e| e.to_string())?;
 
-    Ok(universe.to_vec())
+    Ok(rendered.frame.as_flattened().to_vec())
 }
 
 #[tauri::command]